@@ -0,0 +1,250 @@
+//! A [`tracing_subscriber::Layer`] that writes riff-dag-tui JSONL.
+//!
+//! Every span becomes a node (label = span name, span = target, tags = level
+//! plus recorded fields), every parent/child relation and every
+//! `follows_from` becomes an edge. Point `riff-dag-tui --input <file> --follow`
+//! at the output to watch a service's spans as they open.
+//!
+//! ```no_run
+//! use tracing_subscriber::prelude::*;
+//!
+//! let layer = riff_dag_tracing::RiffDagLayer::create("spans.jsonl").unwrap();
+//! tracing_subscriber::registry().with(layer).init();
+//! ```
+use std::{
+    fmt::Debug,
+    fs::File,
+    io::{self, Write},
+    path::Path,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde_json::json;
+use tracing::{
+    field::{Field, Visit},
+    span::{Attributes, Id, Record},
+    Subscriber,
+};
+use tracing_subscriber::{layer::Context, registry::LookupSpan, Layer};
+
+/// Writes one JSONL line per span (re-emitted when fields are recorded) and per edge.
+pub struct RiffDagLayer {
+    out: Mutex<Box<dyn Write + Send>>,
+    next_id: AtomicU64,
+}
+
+/// Node state kept in the span's extensions.
+struct SpanNode {
+    id: String,
+    tags: Vec<String>,
+    ts: String,
+}
+
+impl RiffDagLayer {
+    /// Write to any sink; each line is flushed as it is written so a
+    /// following viewer sees spans immediately.
+    pub fn new<W: Write + Send + 'static>(out: W) -> Self {
+        Self { out: Mutex::new(Box::new(out)), next_id: AtomicU64::new(1) }
+    }
+
+    /// Write to `path`, appending if it exists.
+    pub fn create(path: impl AsRef<Path>) -> io::Result<Self> {
+        let file = File::options().create(true).append(true).open(path)?;
+        Ok(Self::new(file))
+    }
+
+    fn emit(&self, line: serde_json::Value) {
+        if let Ok(mut out) = self.out.lock() {
+            // Tracing must never take the service down; a failed write just loses the line.
+            let _ = writeln!(out, "{}", line).and_then(|_| out.flush());
+        }
+    }
+
+    fn emit_node(&self, node: &SpanNode, label: &str, target: &str) {
+        self.emit(json!({
+            "type": "node",
+            "id": node.id,
+            "label": label,
+            "span": target,
+            "tags": node.tags,
+            "ts": node.ts,
+        }));
+    }
+
+    fn emit_edge(&self, from: &str, to: &str) {
+        self.emit(json!({ "type": "edge", "from": from, "to": to }));
+    }
+}
+
+/// Records fields as `name=value` tags; a field recorded again replaces
+/// its earlier value.
+struct Tags<'a>(&'a mut Vec<String>);
+
+impl Tags<'_> {
+    fn set(&mut self, field: &Field, value: String) {
+        let tag = format!("{}={}", field.name(), value);
+        let prefix = format!("{}=", field.name());
+        match self.0.iter_mut().find(|t| t.starts_with(&prefix)) {
+            Some(old) => *old = tag,
+            None => self.0.push(tag),
+        }
+    }
+}
+
+impl Visit for Tags<'_> {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.set(field, value.to_string());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+        self.set(field, format!("{:?}", value));
+    }
+}
+
+impl<S> Layer<S> for RiffDagLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else { return };
+        let meta = span.metadata();
+        let mut tags = vec![meta.level().as_str().to_lowercase()];
+        attrs.record(&mut Tags(&mut tags));
+        let node = SpanNode {
+            // Span ids are recycled once a span closes; node ids must not be.
+            id: format!("{}-{}", meta.name(), self.next_id.fetch_add(1, Ordering::Relaxed)),
+            tags,
+            ts: rfc3339_now(),
+        };
+        self.emit_node(&node, meta.name(), meta.target());
+        if let Some(parent) = span.parent() {
+            if let Some(parent_node) = parent.extensions().get::<SpanNode>() {
+                self.emit_edge(&parent_node.id, &node.id);
+            }
+        }
+        span.extensions_mut().insert(node);
+    }
+
+    fn on_record(&self, id: &Id, values: &Record<'_>, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else { return };
+        let mut ext = span.extensions_mut();
+        if let Some(node) = ext.get_mut::<SpanNode>() {
+            values.record(&mut Tags(&mut node.tags));
+            self.emit_node(node, span.metadata().name(), span.metadata().target());
+        }
+    }
+
+    fn on_follows_from(&self, id: &Id, follows: &Id, ctx: Context<'_, S>) {
+        let (Some(span), Some(cause)) = (ctx.span(id), ctx.span(follows)) else { return };
+        let (ext, cause_ext) = (span.extensions(), cause.extensions());
+        if let (Some(node), Some(cause_node)) = (ext.get::<SpanNode>(), cause_ext.get::<SpanNode>()) {
+            self.emit_edge(&cause_node.id, &node.id);
+        }
+    }
+}
+
+/// Current UTC time as `YYYY-MM-DDTHH:MM:SS.mmmZ`, matching the `ts` format of riff JSONL.
+fn rfc3339_now() -> String {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+    let secs = now.as_secs();
+    let (days, rem) = (secs / 86_400, secs % 86_400);
+    // Civil date from days since 1970-01-01 (Howard Hinnant's algorithm).
+    let z = days as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+        year,
+        month,
+        day,
+        rem / 3600,
+        rem % 3600 / 60,
+        rem % 60,
+        now.subsec_millis()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use serde_json::Value;
+    use tracing_subscriber::prelude::*;
+
+    use super::*;
+
+    /// A sink the test can read back once the layer is done with it.
+    #[derive(Clone, Default)]
+    struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Buffer {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl Buffer {
+        fn lines(&self) -> Vec<Value> {
+            let out = String::from_utf8(self.0.lock().unwrap().clone()).unwrap();
+            out.lines().map(|line| serde_json::from_str(line).unwrap()).collect()
+        }
+    }
+
+    #[test]
+    fn nested_spans_become_nodes_joined_by_edges() {
+        let buffer = Buffer::default();
+        let subscriber = tracing_subscriber::registry().with(RiffDagLayer::new(buffer.clone()));
+        tracing::subscriber::with_default(subscriber, || {
+            let outer = tracing::info_span!("outer", user = "ann", attempt = tracing::field::Empty);
+            let _entered = outer.enter();
+            let inner = tracing::debug_span!("inner");
+            inner.in_scope(|| {});
+            outer.record("attempt", 1);
+            outer.record("attempt", 2);
+            let later = tracing::info_span!("later");
+            later.follows_from(&inner);
+        });
+
+        let lines = buffer.lines();
+        let fields = |i: usize, key: &str| lines[i][key].clone();
+        assert_eq!(lines.len(), 8);
+        assert_eq!(fields(0, "type"), "node");
+        assert_eq!(fields(0, "id"), "outer-1");
+        assert_eq!(fields(0, "label"), "outer");
+        assert_eq!(fields(0, "span"), module_path!());
+        assert_eq!(fields(0, "tags"), serde_json::json!(["info", "user=ann"]));
+        assert_eq!(fields(1, "id"), "inner-2");
+        assert_eq!(fields(1, "tags"), serde_json::json!(["debug"]));
+        assert_eq!(lines[2], serde_json::json!({ "type": "edge", "from": "outer-1", "to": "inner-2" }));
+        // Recording a field re-emits the node, and recording it again replaces its tag.
+        assert_eq!(fields(3, "id"), "outer-1");
+        assert_eq!(fields(3, "tags"), serde_json::json!(["info", "user=ann", "attempt=1"]));
+        assert_eq!(fields(4, "tags"), serde_json::json!(["info", "user=ann", "attempt=2"]));
+        assert_eq!(fields(4, "ts"), fields(0, "ts"));
+        assert_eq!(fields(5, "id"), "later-3");
+        assert_eq!(lines[6], serde_json::json!({ "type": "edge", "from": "outer-1", "to": "later-3" }));
+        assert_eq!(lines[7], serde_json::json!({ "type": "edge", "from": "inner-2", "to": "later-3" }));
+    }
+
+    #[test]
+    fn timestamps_are_rfc3339_utc_with_milliseconds() {
+        let ts = rfc3339_now();
+        assert_eq!(ts.len(), "1970-01-01T00:00:00.000Z".len());
+        assert!(ts.starts_with("20") && ts.ends_with('Z'));
+        assert_eq!((&ts[4..5], &ts[10..11], &ts[19..20]), ("-", "T", "."));
+    }
+}
@@ -0,0 +1,51 @@
+//! Per-node update activity, for spotting nodes that keep being revised.
+//!
+//! Every re-emission of a node already in the graph is timestamped on
+//! arrival (the last [`KEEP`] per node). The details pane draws them as a
+//! sparkline of updates per [`BUCKET`] over the last [`BUCKETS`] buckets.
+use std::{
+    collections::{HashMap, VecDeque},
+    time::{Duration, Instant},
+};
+
+/// Updates remembered per node.
+const KEEP: usize = 64;
+/// Width of one sparkline column.
+const BUCKET: Duration = Duration::from_secs(5);
+/// Columns in the sparkline, oldest first.
+const BUCKETS: usize = 24;
+
+const BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+#[derive(Debug, Default)]
+pub struct Activity {
+    updates: HashMap<String, VecDeque<Instant>>,
+}
+
+impl Activity {
+    /// Record that `id` was emitted again.
+    pub fn touch(&mut self, id: &str) {
+        let times = self.updates.entry(id.to_string()).or_default();
+        if times.len() == KEEP {
+            times.pop_front();
+        }
+        times.push_back(Instant::now());
+    }
+
+    /// `[  ▁▃█▂] 12 updates, last 3s ago`, or `None` for a node defined once.
+    pub fn sparkline(&self, id: &str) -> Option<String> {
+        let times = self.updates.get(id)?;
+        let last = times.back()?;
+        let mut counts = [0usize; BUCKETS];
+        for t in times {
+            let bucket = (t.elapsed().as_secs() / BUCKET.as_secs()) as usize;
+            if bucket < BUCKETS {
+                counts[BUCKETS - 1 - bucket] += 1;
+            }
+        }
+        let max = counts.iter().copied().max().unwrap_or(0).max(1);
+        let bars: String = counts.iter().map(|&n| if n == 0 { ' ' } else { BARS[(n * BARS.len() - 1) / max] }).collect();
+        let total = if times.len() == KEEP { format!("{}+", KEEP) } else { times.len().to_string() };
+        Some(format!("[{}] {} updates, last {}s ago", bars, total, last.elapsed().as_secs()))
+    }
+}
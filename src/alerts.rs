@@ -0,0 +1,120 @@
+//! Structural alerts: limits on the shape of the graph that catch runaway
+//! emitters (an agent stuck in a loop keeps extending one path, a fan-out
+//! bug spawns hundreds of children) while the data is still streaming in.
+//!
+//! Set in the config's `[alerts]` table; every limit is off unless given:
+//!
+//! ```toml
+//! [alerts]
+//! max_children = 50 # distinct children of one node
+//! max_parents = 20  # distinct parents of one node
+//! max_depth = 30    # edges on the longest path from a root
+//! ```
+//!
+//! A path that is too long is reported once, at the node it ends in. Nodes
+//! on a cycle, and those only reachable through one, have no depth and are
+//! left out of the path check, unless cycles are broken (`--break-cycles`),
+//! when their back edges are ignored instead.
+use std::{collections::HashMap, fmt};
+
+use petgraph::{
+    stable_graph::NodeIndex,
+    visit::EdgeRef,
+    Direction::{Incoming, Outgoing},
+};
+
+use crate::{cycles::Cycles, GraphModel};
+
+#[derive(Debug, Default, Clone, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AlertConfig {
+    pub max_children: Option<usize>,
+    pub max_parents: Option<usize>,
+    pub max_depth: Option<usize>,
+}
+
+/// One node over one limit.
+#[derive(Debug, Clone)]
+pub struct Alert {
+    pub node: String,
+    pub message: String,
+}
+
+impl fmt::Display for Alert {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.node, self.message)
+    }
+}
+
+fn distinct(gm: &GraphModel, idx: NodeIndex, dir: petgraph::Direction) -> usize {
+    let mut nodes: Vec<NodeIndex> = gm.graph.neighbors_directed(idx, dir).collect();
+    nodes.sort();
+    nodes.dedup();
+    nodes.len()
+}
+
+/// The longest path from a root to each node, in edges, with the parent it
+/// runs through (Kahn's algorithm; nodes on or behind a cycle are missing,
+/// unless the back edges of `broken` are left out).
+pub fn depths(gm: &GraphModel, broken: Option<&Cycles>) -> HashMap<NodeIndex, (usize, Option<NodeIndex>)> {
+    let back = |a: NodeIndex, b: NodeIndex| broken.is_some_and(|c| c.is_back_edge(&gm.graph[a].id, &gm.graph[b].id));
+    let mut waiting: HashMap<NodeIndex, usize> =
+        gm.graph.node_indices().map(|n| (n, gm.graph.edges_directed(n, Incoming).filter(|e| !back(e.source(), n)).count())).collect();
+    let mut depth: HashMap<NodeIndex, (usize, Option<NodeIndex>)> = HashMap::new();
+    let mut ready: Vec<NodeIndex> = gm.stable_order().filter(|n| waiting[n] == 0).collect();
+    for &n in &ready {
+        depth.insert(n, (0, None));
+    }
+    while let Some(n) = ready.pop() {
+        let d = depth[&n].0;
+        for child in gm.graph.neighbors_directed(n, Outgoing).filter(|&child| !back(n, child)) {
+            let entry = depth.entry(child).or_insert((0, None));
+            if entry.1.is_none() || d + 1 > entry.0 {
+                *entry = (d + 1, Some(n));
+            }
+            let left = waiting.get_mut(&child).expect("every node is counted");
+            *left -= 1;
+            if *left == 0 {
+                ready.push(child);
+            }
+        }
+    }
+    // A node still waiting is on or behind a cycle; a partial depth means nothing.
+    depth.retain(|n, _| waiting[n] == 0);
+    depth
+}
+
+impl AlertConfig {
+    pub fn is_empty(&self) -> bool {
+        self.max_children.is_none() && self.max_parents.is_none() && self.max_depth.is_none()
+    }
+
+    /// Every alert in `gm`, nodes in stable order; paths are measured
+    /// without the back edges of `broken`.
+    pub fn check(&self, gm: &GraphModel, broken: Option<&Cycles>) -> Vec<Alert> {
+        let depth = self.max_depth.map(|_| depths(gm, broken));
+        let mut out = Vec::new();
+        for idx in gm.stable_order() {
+            let mut report = |message: String| out.push(Alert { node: gm.graph[idx].id.to_string(), message });
+            for (max, dir, what) in [(self.max_children, Outgoing, "children"), (self.max_parents, Incoming, "parents")] {
+                if let Some(max) = max {
+                    let n = distinct(gm, idx, dir);
+                    if n > max {
+                        report(format!("has {} {} (max {})", n, what, max));
+                    }
+                }
+            }
+            if let (Some(max), Some(depth)) = (self.max_depth, &depth) {
+                let ends_path = gm.graph.neighbors_directed(idx, Outgoing).next().is_none();
+                if let Some(&(d, _)) = depth.get(&idx).filter(|(d, _)| *d > max && ends_path) {
+                    let mut root = idx;
+                    while let Some(parent) = depth[&root].1 {
+                        root = parent;
+                    }
+                    report(format!("ends a path of {} edges from {} (max {})", d, gm.graph[root].id, max));
+                }
+            }
+        }
+        out
+    }
+}
@@ -0,0 +1,113 @@
+//! Load-time node aliasing.
+//!
+//! Some emitters mint a fresh id (often a UUID) for what is logically the
+//! same node every time they mention it. The `[aliases]` config table
+//! collapses those duplicates before they reach the graph:
+//!
+//! - `[[aliases.rewrite]]` rules rewrite ids by regex (`replace` may use
+//!   `$1`, `$name`), in order, so related ids become one;
+//! - `merge_same_label_span` folds a node into the first node seen with the
+//!   same label and span.
+//!
+//! Edges follow their endpoints to the surviving node, and the survivor
+//! lists every id folded into it in its `merged_from` metadata.
+use std::collections::HashMap;
+
+use color_eyre::eyre::{Result, WrapErr};
+use regex::Regex;
+
+use crate::EventLine;
+
+#[derive(Debug, Default, Clone, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AliasConfig {
+    pub rewrite: Vec<RewriteRule>,
+    pub merge_same_label_span: bool,
+}
+
+#[derive(Debug, Clone, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RewriteRule {
+    pub pattern: String,
+    pub replace: String,
+}
+
+/// Compiled `[aliases]` rules, shared by every tab.
+pub struct Aliases {
+    rewrites: Vec<(Regex, String)>,
+    merge_same_label_span: bool,
+}
+
+/// What one graph has folded so far.
+#[derive(Debug, Default)]
+pub struct AliasState {
+    canonical: HashMap<String, String>,              // original id -> surviving id
+    by_label_span: HashMap<(String, String), String>, // first id seen per label + span
+    merged_from: HashMap<String, Vec<String>>,        // surviving id -> ids folded into it
+}
+
+impl Aliases {
+    /// `None` when the config asks for no aliasing at all.
+    pub fn new(config: &AliasConfig) -> Result<Option<Self>> {
+        if config.rewrite.is_empty() && !config.merge_same_label_span {
+            return Ok(None);
+        }
+        let mut rewrites = Vec::new();
+        for (i, rule) in config.rewrite.iter().enumerate() {
+            let re = Regex::new(&rule.pattern).wrap_err_with(|| format!("invalid pattern in [[aliases.rewrite]] rule {}", i + 1))?;
+            rewrites.push((re, rule.replace.clone()));
+        }
+        Ok(Some(Self { rewrites, merge_same_label_span: config.merge_same_label_span }))
+    }
+
+    fn rewrite(&self, id: &str) -> String {
+        let mut id = id.to_string();
+        for (re, replace) in &self.rewrites {
+            if re.is_match(&id) {
+                id = re.replace(&id, replace.as_str()).into_owned();
+            }
+        }
+        id
+    }
+
+    /// The id `id` ends up as, for an endpoint or a node without label and span.
+    pub fn resolve(&self, state: &AliasState, id: &str) -> String {
+        state.canonical.get(id).cloned().unwrap_or_else(|| self.rewrite(id))
+    }
+
+    /// Rewrite `ev` onto surviving ids. Edges that fold into a self-loop are dropped.
+    pub fn apply(&self, state: &mut AliasState, ev: EventLine) -> Option<EventLine> {
+        match ev {
+            EventLine::Node { id, label, span, tags, ts, metadata, score, extra } => {
+                let target = match state.canonical.get(&id) {
+                    Some(known) => known.clone(),
+                    None => {
+                        let mut target = self.rewrite(&id);
+                        if let (true, Some(label)) = (self.merge_same_label_span, &label) {
+                            let key = (label.clone(), span.clone().unwrap_or_default());
+                            target = state.by_label_span.entry(key).or_insert(target).clone();
+                        }
+                        state.canonical.insert(id.clone(), target.clone());
+                        if target != id {
+                            state.merged_from.entry(target.clone()).or_default().push(id);
+                        }
+                        target
+                    }
+                };
+                let mut metadata = metadata;
+                // Re-emissions replace the node wholesale, so provenance rides on every one.
+                if let Some(from) = state.merged_from.get(&target) {
+                    metadata.get_or_insert_with(Default::default).insert("merged_from".into(), from.join(", ").into());
+                }
+                Some(EventLine::Node { id: target, label, span, tags, ts, metadata, score, extra })
+            }
+            EventLine::Edge { from, to, kind, weight, ts } => {
+                let (new_from, new_to) = (self.resolve(state, &from), self.resolve(state, &to));
+                if new_from == new_to && from != to {
+                    return None;
+                }
+                Some(EventLine::Edge { from: new_from, to: new_to, kind, weight, ts })
+            }
+        }
+    }
+}
@@ -0,0 +1,218 @@
+//! Graph assertions: schema linting for emitters.
+//!
+//! An assertions file (`--assertions`, or `assertions_file` in the config)
+//! is TOML with one `[[assert]]` table per rule. `where` picks the nodes the
+//! rule applies to (every node when omitted); the rest say what each of them
+//! must satisfy:
+//!
+//! ```toml
+//! [[assert]]
+//! name = "every tool call has exactly one response"
+//! where = { tag = "tool" }
+//! children = { where = { tag = "response" }, exactly = 1 }
+//!
+//! [[assert]]
+//! name = "llm spans record their model"
+//! where = { span = "llm" }
+//! metadata = ["model"]
+//!
+//! [[assert]]
+//! name = "no placeholder nodes"
+//! where = { label = "^TODO" }
+//! forbidden = true
+//! ```
+//!
+//! Selectors match on `tag` and `span` exactly and on `id` and `label` by
+//! regex; every field given must match. `children` and `parents` count the
+//! neighbours matching their own `where` and take `exactly`, `min` and `max`.
+use std::{fmt, fs};
+
+use color_eyre::eyre::{bail, Result, WrapErr};
+use petgraph::stable_graph::NodeIndex;
+use regex::Regex;
+
+use crate::{GraphModel, NodeData};
+
+#[derive(Debug, Default, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct AssertionsFile {
+    #[serde(rename = "assert")]
+    rules: Vec<RuleConfig>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RuleConfig {
+    name: String,
+    #[serde(rename = "where", default)]
+    select: SelectorConfig,
+    #[serde(default)]
+    forbidden: bool,
+    #[serde(default)]
+    metadata: Vec<String>,
+    children: Option<CountConfig>,
+    parents: Option<CountConfig>,
+}
+
+#[derive(Debug, Default, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct SelectorConfig {
+    tag: Option<String>,
+    span: Option<String>,
+    id: Option<String>,
+    label: Option<String>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CountConfig {
+    #[serde(rename = "where", default)]
+    select: SelectorConfig,
+    exactly: Option<usize>,
+    min: Option<usize>,
+    max: Option<usize>,
+}
+
+struct Selector {
+    tag: Option<String>,
+    span: Option<String>,
+    id: Option<Regex>,
+    label: Option<Regex>,
+}
+
+struct Count {
+    select: Selector,
+    min: usize,
+    max: Option<usize>,
+}
+
+struct Rule {
+    name: String,
+    select: Selector,
+    forbidden: bool,
+    metadata: Vec<String>,
+    children: Option<Count>,
+    parents: Option<Count>,
+}
+
+/// The compiled rules of an assertions file.
+pub struct Assertions {
+    rules: Vec<Rule>,
+}
+
+/// One node breaking one rule.
+#[derive(Debug, Clone)]
+pub struct Violation {
+    pub rule: String,
+    pub node: String,
+    pub message: String,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}: {}", self.rule, self.node, self.message)
+    }
+}
+
+impl Selector {
+    fn new(cfg: SelectorConfig, rule: &str) -> Result<Self> {
+        let regex = |field: &str, pattern: Option<String>| {
+            pattern
+                .map(|p| Regex::new(&p).wrap_err_with(|| format!("invalid `{}` pattern in assertion `{}`", field, rule)))
+                .transpose()
+        };
+        Ok(Self { tag: cfg.tag, span: cfg.span, id: regex("id", cfg.id)?, label: regex("label", cfg.label)? })
+    }
+
+    fn matches(&self, nd: &NodeData) -> bool {
+        self.tag.as_ref().is_none_or(|t| nd.tags.iter().any(|tag| **tag == **t))
+            && self.span.as_ref().is_none_or(|s| *nd.span == **s)
+            && self.id.as_ref().is_none_or(|re| re.is_match(&nd.id))
+            && self.label.as_ref().is_none_or(|re| re.is_match(&nd.label))
+    }
+}
+
+impl Count {
+    fn new(cfg: CountConfig, rule: &str) -> Result<Self> {
+        let (min, max) = match (cfg.exactly, cfg.min, cfg.max) {
+            (Some(n), None, None) => (n, Some(n)),
+            (Some(_), _, _) => bail!("assertion `{}` combines `exactly` with `min`/`max`", rule),
+            (None, None, None) => bail!("assertion `{}` needs `exactly`, `min` or `max` in its count", rule),
+            (None, min, max) => (min.unwrap_or(0), max),
+        };
+        Ok(Self { select: Selector::new(cfg.select, rule)?, min, max })
+    }
+
+    fn expected(&self) -> String {
+        match (self.min, self.max) {
+            (min, Some(max)) if min == max => format!("exactly {}", min),
+            (0, Some(max)) => format!("at most {}", max),
+            (min, None) => format!("at least {}", min),
+            (min, Some(max)) => format!("between {} and {}", min, max),
+        }
+    }
+
+    /// How many distinct `nodes` match (parallel edges count once).
+    fn matching(&self, gm: &GraphModel, mut nodes: Vec<NodeIndex>) -> usize {
+        nodes.sort();
+        nodes.dedup();
+        nodes.into_iter().filter(|&n| self.select.matches(&gm.graph[n])).count()
+    }
+
+    /// A message when `n` matching neighbours break the count.
+    fn check(&self, n: usize, what: &str) -> Option<String> {
+        (n < self.min || self.max.is_some_and(|max| n > max))
+            .then(|| format!("has {} matching {}, expected {}", n, what, self.expected()))
+    }
+}
+
+impl Assertions {
+    pub fn load(path: &str) -> Result<Self> {
+        let text = fs::read_to_string(path).wrap_err_with(|| format!("failed to read assertions {}", path))?;
+        let file: AssertionsFile = toml::from_str(&text).wrap_err_with(|| format!("invalid assertions {}", path))?;
+        let mut rules = Vec::new();
+        for rule in file.rules {
+            let name = rule.name;
+            rules.push(Rule {
+                select: Selector::new(rule.select, &name)?,
+                forbidden: rule.forbidden,
+                metadata: rule.metadata,
+                children: rule.children.map(|c| Count::new(c, &name)).transpose()?,
+                parents: rule.parents.map(|c| Count::new(c, &name)).transpose()?,
+                name,
+            });
+        }
+        Ok(Self { rules })
+    }
+
+    /// Every violation in `gm`, rule by rule, nodes in stable order.
+    pub fn check(&self, gm: &GraphModel) -> Vec<Violation> {
+        let mut out = Vec::new();
+        for rule in &self.rules {
+            for idx in gm.stable_order() {
+                let nd = &gm.graph[idx];
+                if !rule.select.matches(nd) {
+                    continue;
+                }
+                let mut report = |message: String| out.push(Violation { rule: rule.name.clone(), node: nd.id.to_string(), message });
+                if rule.forbidden {
+                    report("matches a forbidden selector".to_string());
+                }
+                for key in rule.metadata.iter().filter(|k| !nd.metadata.contains_key(*k)) {
+                    report(format!("missing metadata `{}`", key));
+                }
+                if let Some(count) = &rule.children {
+                    if let Some(msg) = count.check(count.matching(gm, gm.children_of(idx)), "children") {
+                        report(msg);
+                    }
+                }
+                if let Some(count) = &rule.parents {
+                    if let Some(msg) = count.check(count.matching(gm, gm.parents_of(idx)), "parents") {
+                        report(msg);
+                    }
+                }
+            }
+        }
+        out
+    }
+}
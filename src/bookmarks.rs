@@ -0,0 +1,81 @@
+//! Bookmarks: nodes marked with `b` to come back to, listed with `B`.
+//!
+//! When the graph is read from a single local file, the bookmarked ids are
+//! kept beside it in `FILE.bookmarks`, one per line, so they are there the
+//! next time the file is opened (and can be handed to a teammate with it).
+//! Any other input keeps them for the session only. Ids are kept whether or
+//! not the node is in the graph yet, as a file being followed may not have
+//! reached it.
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+#[derive(Debug, Default)]
+pub struct Bookmarks {
+    ids: Vec<String>,      // in the order bookmarked
+    file: Option<PathBuf>, // the sidecar they are saved to, if any
+}
+
+impl Bookmarks {
+    /// The bookmarks saved for `input`, when it is a local file; an
+    /// unreadable sidecar is an error, a missing one just means none.
+    pub fn load(input: &str) -> io::Result<Self> {
+        let path = Path::new(input);
+        if !path.is_file() {
+            return Ok(Self::default());
+        }
+        let mut file = path.as_os_str().to_owned();
+        file.push(".bookmarks");
+        let file = PathBuf::from(file);
+        let ids = match fs::read_to_string(&file) {
+            Ok(text) => text.lines().map(str::trim).filter(|l| !l.is_empty()).map(str::to_string).collect(),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Vec::new(),
+            Err(err) => return Err(err),
+        };
+        Ok(Self { ids, file: Some(file) })
+    }
+
+    pub fn ids(&self) -> &[String] {
+        &self.ids
+    }
+
+    pub fn contains(&self, id: &str) -> bool {
+        self.ids.iter().any(|b| b == id)
+    }
+
+    /// Bookmark `id`, or remove its bookmark; true when it was added. The
+    /// change is kept in memory even if saving it fails.
+    pub fn toggle(&mut self, id: &str) -> (bool, io::Result<()>) {
+        let added = match self.ids.iter().position(|b| b == id) {
+            Some(i) => {
+                self.ids.remove(i);
+                false
+            }
+            None => {
+                self.ids.push(id.to_string());
+                true
+            }
+        };
+        (added, self.save())
+    }
+
+    /// Where they are saved, for the notice.
+    pub fn file(&self) -> Option<&Path> {
+        self.file.as_deref()
+    }
+
+    /// Write the sidecar, or remove it once there is nothing left in it.
+    fn save(&self) -> io::Result<()> {
+        let Some(file) = &self.file else { return Ok(()) };
+        if self.ids.is_empty() {
+            return match fs::remove_file(file) {
+                Err(err) if err.kind() != io::ErrorKind::NotFound => Err(err),
+                _ => Ok(()),
+            };
+        }
+        let mut text = self.ids.join("\n");
+        text.push('\n');
+        fs::write(file, text)
+    }
+}
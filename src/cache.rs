@@ -0,0 +1,141 @@
+//! On-disk graph snapshots for instant reopen.
+//!
+//! After an input file has been fully parsed, the resulting graph is written
+//! as a bincode snapshot under `$XDG_CACHE_HOME/riff-dag-tui/` (falling back
+//! to `~/.cache/riff-dag-tui/`). The snapshot records the canonical input
+//! path, mtime and size; reopening the same unchanged file restores the graph
+//! from the snapshot instead of re-parsing it.
+use std::{
+    collections::hash_map::DefaultHasher,
+    fs::{self, File},
+    hash::{Hash, Hasher},
+    io::{BufReader, BufWriter},
+    path::PathBuf,
+    sync::Arc,
+    time::UNIX_EPOCH,
+};
+
+use color_eyre::eyre::{eyre, Result, WrapErr};
+
+use crate::duplicate::Conflict;
+use crate::{EdgeData, GraphModel, NodeData, Payload};
+
+/// Bump whenever `NodeData`, `EdgeData`, `Conflict` or the snapshot layout changes.
+const FORMAT_VERSION: u32 = 9;
+
+/// Identity of an input file at the time it was read.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct CacheKey {
+    path: PathBuf,
+    mtime_ns: u128,
+    size: u64,
+}
+
+impl CacheKey {
+    /// Key for the file at `path` as it is right now.
+    pub fn for_input(path: &str) -> Option<Self> {
+        let path = fs::canonicalize(path).ok()?;
+        let meta = fs::metadata(&path).ok()?;
+        if !meta.is_file() {
+            return None; // pipes and devices have no stable contents to snapshot
+        }
+        let mtime_ns = meta.modified().ok()?.duration_since(UNIX_EPOCH).ok()?.as_nanos();
+        Some(Self { path, mtime_ns, size: meta.len() })
+    }
+
+    fn snapshot_path(&self) -> Option<PathBuf> {
+        let base = std::env::var_os("XDG_CACHE_HOME")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|h| PathBuf::from(h).join(".cache")))?;
+        let mut hasher = DefaultHasher::new();
+        self.path.hash(&mut hasher);
+        Some(base.join("riff-dag-tui").join(format!("{:016x}.bin", hasher.finish())))
+    }
+}
+
+#[derive(serde::Deserialize)]
+struct Snapshot {
+    version: u32,
+    key: CacheKey,
+    events: u64,
+    nodes: Vec<NodeData>,
+    payloads: Vec<Option<Arc<str>>>, // each node's event as received, which `NodeData` doesn't serialize
+    edges: Vec<(u32, u32, EdgeData)>,
+    conflicts: Vec<Conflict>,
+    conflicts_elided: usize,
+    warnings: Vec<String>,
+}
+
+#[derive(serde::Serialize)]
+struct SnapshotRef<'a> {
+    version: u32,
+    key: &'a CacheKey,
+    events: u64,
+    nodes: Vec<&'a NodeData>,
+    payloads: Vec<&'a Option<Arc<str>>>,
+    edges: Vec<(u32, u32, &'a EdgeData)>,
+    conflicts: &'a [Conflict],
+    conflicts_elided: usize,
+    warnings: &'a [String],
+}
+
+/// A graph restored from disk, plus what the original load reported.
+pub struct Restored {
+    pub gm: GraphModel,
+    pub events: u64,
+    pub warnings: Vec<String>,
+}
+
+/// Restore the snapshot for `key`, if one exists and still matches the file.
+pub fn load(key: &CacheKey) -> Option<Restored> {
+    let f = File::open(key.snapshot_path()?).ok()?;
+    let snap: Snapshot = bincode::deserialize_from(BufReader::new(f)).ok()?;
+    if snap.version != FORMAT_VERSION || snap.key != *key {
+        return None;
+    }
+    let mut gm = GraphModel::new();
+    let mut idx = Vec::with_capacity(snap.nodes.len());
+    for (mut nd, raw) in snap.nodes.into_iter().zip(snap.payloads) {
+        nd.raw = Payload(raw);
+        let id = nd.id.clone();
+        idx.push(gm.upsert_node(&id, nd));
+    }
+    for (a, b, data) in snap.edges {
+        let (a, b) = (*idx.get(a as usize)?, *idx.get(b as usize)?);
+        gm.edges_received = gm.edges_received.max(data.seq);
+        gm.graph.add_edge(a, b, data);
+    }
+    gm.conflicts = snap.conflicts;
+    gm.conflicts_elided = snap.conflicts_elided;
+    Some(Restored { gm, events: snap.events, warnings: snap.warnings })
+}
+
+/// Write a snapshot of `gm` for `key`, replacing any previous one.
+pub fn store(key: &CacheKey, gm: &GraphModel, events: u64, warnings: &[String]) -> Result<()> {
+    let path = key.snapshot_path().ok_or_else(|| eyre!("no cache directory (HOME unset)"))?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).wrap_err("failed to create cache directory")?;
+    }
+
+    // Node indices may have holes; store edges by position in `nodes`.
+    let mut pos = std::collections::HashMap::new();
+    let mut nodes = Vec::with_capacity(gm.graph.node_count());
+    for (i, idx) in gm.stable_order().enumerate() {
+        pos.insert(idx, i as u32);
+        nodes.push(&gm.graph[idx]);
+    }
+    let payloads = nodes.iter().map(|nd| &nd.raw.0).collect();
+    let edges = gm
+        .graph
+        .edge_indices()
+        .filter_map(|e| gm.graph.edge_endpoints(e).map(|(a, b)| (pos[&a], pos[&b], &gm.graph[e])))
+        .collect();
+    let snap = SnapshotRef { version: FORMAT_VERSION, key, events, nodes, payloads, edges, conflicts: &gm.conflicts, conflicts_elided: gm.conflicts_elided, warnings };
+
+    // Write to a temp file first so a crash never leaves a truncated snapshot.
+    let tmp = path.with_extension("tmp");
+    let f = File::create(&tmp).wrap_err("failed to create cache file")?;
+    bincode::serialize_into(BufWriter::new(f), &snap).wrap_err("failed to write cache file")?;
+    fs::rename(&tmp, &path).wrap_err("failed to move cache file into place")?;
+    Ok(())
+}
@@ -0,0 +1,45 @@
+//! Change notifications for code built on a `GraphModel`.
+//!
+//! `GraphModel::subscribe` hands out a channel that is sent a `GraphChange`
+//! for each node added, each node whose data changed (a redefinition, a tag
+//! edit, a find and replace) and each edge added, as it happens, so an
+//! embedder or a UI component can update what it shows of those alone
+//! instead of diffing snapshots. Removals come from `GraphModel::replace`,
+//! when a source resends its whole graph. With no one subscribed nothing is
+//! sent, and a subscriber that drops its receiver is forgotten.
+use std::sync::{
+    mpsc::{self, Receiver, Sender},
+    Arc,
+};
+
+/// One change to a graph. Nodes are named by id, edges by their endpoints'.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GraphChange {
+    NodeAdded(Arc<str>),
+    NodeUpdated(Arc<str>),
+    NodeRemoved(Arc<str>),
+    EdgeAdded { from: Arc<str>, to: Arc<str> },
+    EdgeRemoved { from: Arc<str>, to: Arc<str> },
+}
+
+#[derive(Debug, Default)]
+pub struct Subscribers {
+    senders: Vec<Sender<GraphChange>>,
+}
+
+impl Subscribers {
+    pub fn add(&mut self) -> Receiver<GraphChange> {
+        let (tx, rx) = mpsc::channel();
+        self.senders.push(tx);
+        rx
+    }
+
+    /// Whether anyone is listening, so changes needn't be worked out otherwise.
+    pub fn any(&self) -> bool {
+        !self.senders.is_empty()
+    }
+
+    pub fn send(&mut self, change: GraphChange) {
+        self.senders.retain(|tx| tx.send(change.clone()).is_ok());
+    }
+}
@@ -0,0 +1,107 @@
+//! `riff-dag-tui diff BEFORE AFTER`: two runs of the same pipeline in one
+//! view.
+//!
+//! Both files are read in full, then shown as a single graph: everything
+//! AFTER has, plus the nodes and edges only BEFORE had. Nodes are matched by
+//! id and marked added (`+`), removed (`-`) or changed (`~`) in the node
+//! list, where filtering and the rest work as usual; the details pane lists
+//! a changed node's fields as they were and are, and the canvas draws added
+//! and removed edges in the same colours. Edges are matched by their
+//! endpoints and kind.
+use std::collections::HashMap;
+
+use crate::{
+    revision::{self, DiffLine},
+    snapshot::{edge_counts, Change},
+    GraphModel, NodeData,
+};
+
+#[derive(Debug, Default)]
+pub struct Comparison {
+    pub title: String,                        // `before → after`
+    nodes: HashMap<String, Change>,           // every node not the same on both sides
+    before: HashMap<String, NodeData>,        // changed nodes as BEFORE had them
+    edges: HashMap<(String, String), Change>, // endpoints of edges only one side has
+    edges_added: usize,
+    edges_removed: usize,
+}
+
+impl Comparison {
+    /// The graph to show, AFTER with what only BEFORE had put back, and what
+    /// is marked in it.
+    pub fn new(before: &GraphModel, after: &GraphModel, title: String) -> (GraphModel, Self) {
+        let mut gm = after.freeze();
+        let mut cmp = Self { title, ..Self::default() };
+        for nd in after.graph.node_weights() {
+            match before.indices.get(&nd.id) {
+                None => {
+                    cmp.nodes.insert(nd.id.to_string(), Change::Added);
+                }
+                Some(&idx) if !before.graph[idx].same_fields(nd) => {
+                    cmp.nodes.insert(nd.id.to_string(), Change::Changed);
+                    cmp.before.insert(nd.id.to_string(), before.graph[idx].clone());
+                }
+                Some(_) => {}
+            }
+        }
+        for idx in before.stable_order() {
+            let nd = &before.graph[idx];
+            if !after.indices.contains_key(&nd.id) {
+                cmp.nodes.insert(nd.id.to_string(), Change::Removed);
+                gm.upsert_node(&nd.id, nd.clone());
+            }
+        }
+
+        let (old, new) = (edge_counts(before), edge_counts(after));
+        for (&(from, to, kind), &n) in &new {
+            let added = n.saturating_sub(old.get(&(from, to, kind)).copied().unwrap_or(0));
+            if added > 0 {
+                cmp.edges.insert((from.to_string(), to.to_string()), Change::Added);
+                cmp.edges_added += added;
+            }
+        }
+        let mut missing: HashMap<_, usize> =
+            old.iter().map(|(&key, &n)| (key, n.saturating_sub(new.get(&key).copied().unwrap_or(0)))).filter(|&(_, n)| n > 0).collect();
+        // Put back in the order BEFORE received them.
+        let mut edges: Vec<_> = before.graph.edge_indices().collect();
+        edges.sort_by_key(|&e| before.graph[e].seq);
+        for e in edges {
+            let (a, b) = before.graph.edge_endpoints(e).expect("edge index is live");
+            let (from, to, data) = (&*before.graph[a].id, &*before.graph[b].id, &before.graph[e]);
+            if let Some(left) = missing.get_mut(&(from, to, data.kind.as_str())).filter(|left| **left > 0) {
+                *left -= 1;
+                gm.add_edge(from, to, data.clone());
+                cmp.edges.insert((from.to_string(), to.to_string()), Change::Removed);
+                cmp.edges_removed += 1;
+            }
+        }
+        (gm, cmp)
+    }
+
+    pub fn change(&self, id: &str) -> Option<Change> {
+        self.nodes.get(id).copied()
+    }
+
+    pub fn edge_change(&self, from: &str, to: &str) -> Option<Change> {
+        self.edges.get(&(from.to_string(), to.to_string())).copied()
+    }
+
+    /// A changed node's fields, line by line, BEFORE's against AFTER's.
+    pub fn node_diff(&self, nd: &NodeData) -> Option<Vec<DiffLine>> {
+        self.before.get(&*nd.id).map(|old| revision::diff(old, nd))
+    }
+
+    /// `a.jsonl → b.jsonl: +12 ~3 -1 nodes, +20 -2 edges`
+    pub fn summary(&self) -> String {
+        let count = |c: Change| self.nodes.values().filter(|&&n| n == c).count();
+        format!(
+            "{}: +{} ~{} -{} nodes, +{} -{} edges",
+            self.title,
+            count(Change::Added),
+            count(Change::Changed),
+            count(Change::Removed),
+            self.edges_added,
+            self.edges_removed
+        )
+    }
+}
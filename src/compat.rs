@@ -0,0 +1,144 @@
+//! What to hold back on terminals that can't show the full UI: the legacy
+//! Windows console (conhost), the Linux virtual console, `vt100`-style
+//! emulators and `TERM=dumb`.
+//!
+//! On those, box drawing and the canvas's braille dots come out as
+//! garbage, 24-bit and 256-colour themes as the wrong colours, and the
+//! alternate screen and mouse reports may not be there at all. Once a frame
+//! is drawn, `degrade` rewrites it cell by cell: the UI's own glyphs become
+//! ASCII (borders `+-|`, braille `.`, `▶` `>` ...) and every colour the
+//! nearest of the 16 ANSI ones. Other text, such as labels, is left as is.
+//! `--compat` turns everything off regardless of what is detected.
+use std::env;
+
+use ratatui::{buffer::Buffer, style::Color};
+
+#[derive(Debug, Clone, Copy)]
+pub struct Compat {
+    pub ascii: bool,      // only ASCII glyphs
+    pub colors16: bool,   // only the 16 ANSI colours
+    pub mouse: bool,      // mouse reports are asked for
+    pub alt_screen: bool, // drawn on the alternate screen, leaving the shell's as it was
+}
+
+impl Default for Compat {
+    fn default() -> Self {
+        Self { ascii: false, colors16: false, mouse: true, alt_screen: true }
+    }
+}
+
+impl Compat {
+    const LIMITED: Self = Self { ascii: true, colors16: true, mouse: false, alt_screen: false };
+
+    /// What this terminal can do, from the platform and `$TERM`; everything
+    /// off when `forced`.
+    pub fn detect(forced: bool) -> Self {
+        let term = env::var("TERM").unwrap_or_default();
+        // Windows Terminal, ConEmu and editors' terminals announce themselves; conhost doesn't.
+        let conhost = cfg!(windows) && ["WT_SESSION", "TERM_PROGRAM", "ConEmuANSI"].iter().all(|v| env::var_os(v).is_none());
+        match term.as_str() {
+            _ if forced || conhost => Self::LIMITED,
+            "dumb" | "ansi" | "cons25" | "vt100" | "vt102" | "vt220" => Self::LIMITED,
+            // The console's font has box drawing, but no braille.
+            "linux" => Self { ascii: true, colors16: true, ..Self::default() },
+            _ => Self::default(),
+        }
+    }
+
+    /// Whether `degrade` has anything to do.
+    pub fn limited(&self) -> bool {
+        self.ascii || self.colors16
+    }
+
+    /// Rewrite a drawn frame for this terminal.
+    pub fn degrade(&self, buf: &mut Buffer) {
+        for cell in buf.content.iter_mut() {
+            if self.ascii && !cell.symbol().is_ascii() {
+                let symbol: String = cell.symbol().chars().map(|c| ascii(c).unwrap_or(c)).collect();
+                cell.set_symbol(&symbol);
+            }
+            if self.colors16 {
+                cell.fg = ansi(cell.fg);
+                cell.bg = ansi(cell.bg);
+            }
+        }
+    }
+}
+
+/// The ASCII stand-in for one of the UI's glyphs.
+fn ascii(c: char) -> Option<char> {
+    Some(match c {
+        '─' | '━' | '═' | '—' | '–' => '-',
+        '│' | '┃' | '║' => '|',
+        '\u{2500}'..='\u{257f}' => '+', // the rest of box drawing: corners, tees, crosses
+        '\u{2800}' => ' ',              // a braille cell without dots
+        '\u{2801}'..='\u{28ff}' => '.',
+        '▁' | '▂' => '_',
+        '▃' | '▄' | '▅' => '=',
+        '▆' | '▇' | '█' | '░' | '▒' | '▓' => '#',
+        '▶' | '▸' | '→' | '»' | '↪' => '>',
+        '←' => '<',
+        '↑' => '^',
+        '↓' => 'v',
+        '●' | '◆' | '▪' | '•' => '*',
+        '↻' => '@',
+        '·' | '…' => '.',
+        '×' => 'x',
+        '±' => '+',
+        '⚠' => '!',
+        _ => return None,
+    })
+}
+
+/// xterm's values for the 16 ANSI colours.
+const ANSI: [(Color, (u8, u8, u8)); 16] = [
+    (Color::Black, (0, 0, 0)),
+    (Color::Red, (205, 0, 0)),
+    (Color::Green, (0, 205, 0)),
+    (Color::Yellow, (205, 205, 0)),
+    (Color::Blue, (0, 0, 238)),
+    (Color::Magenta, (205, 0, 205)),
+    (Color::Cyan, (0, 205, 205)),
+    (Color::Gray, (229, 229, 229)),
+    (Color::DarkGray, (127, 127, 127)),
+    (Color::LightRed, (255, 0, 0)),
+    (Color::LightGreen, (0, 255, 0)),
+    (Color::LightYellow, (255, 255, 0)),
+    (Color::LightBlue, (92, 92, 255)),
+    (Color::LightMagenta, (255, 0, 255)),
+    (Color::LightCyan, (0, 255, 255)),
+    (Color::White, (255, 255, 255)),
+];
+
+/// `color` as red, green and blue, as xterm shows it; `None` for the
+/// terminal's own foreground or background (`Reset`).
+pub fn rgb(color: Color) -> Option<(u8, u8, u8)> {
+    Some(match color {
+        Color::Rgb(r, g, b) => (r, g, b),
+        Color::Indexed(i) if i < 16 => ANSI[i as usize].1,
+        Color::Indexed(i) if i < 232 => {
+            // The 6×6×6 cube.
+            let level = |v: u8| if v == 0 { 0 } else { 55 + 40 * v };
+            (level((i - 16) / 36), level((i - 16) / 6 % 6), level((i - 16) % 6))
+        }
+        Color::Indexed(i) => {
+            let v = 8 + 10 * (i - 232);
+            (v, v, v)
+        }
+        named => ANSI.iter().find(|c| c.0 == named)?.1,
+    })
+}
+
+/// The ANSI colour nearest `color`.
+fn ansi(color: Color) -> Color {
+    let (r, g, b) = match color {
+        Color::Indexed(i) if i < 16 => return ANSI[i as usize].0,
+        Color::Rgb(..) | Color::Indexed(_) => rgb(color).unwrap_or_default(),
+        other => return other,
+    };
+    let distance = |&(_, (r2, g2, b2)): &(Color, (u8, u8, u8))| {
+        let d = |a: u8, b: u8| (a as i32 - b as i32).pow(2);
+        d(r, r2) + d(g, g2) + d(b, b2)
+    };
+    ANSI.iter().min_by_key(|c| distance(c)).map_or(color, |c| c.0)
+}
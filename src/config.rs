@@ -0,0 +1,87 @@
+//! User configuration loaded from a TOML file.
+//!
+//! Looked up at `--config <path>` or, failing that, at
+//! `$XDG_CONFIG_HOME/riff-dag-tui/config.toml` (falling back to
+//! `~/.config/riff-dag-tui/config.toml`). A missing default file is not an
+//! error; every setting has a built-in default and CLI flags win over the file.
+use std::{fs, path::PathBuf};
+
+use color_eyre::eyre::{Result, WrapErr};
+
+use crate::alerts::AlertConfig;
+use crate::alias::AliasConfig;
+use crate::extract::ExtractRule;
+use crate::group::GroupRule;
+use crate::ingest::JournalConfig;
+use crate::redact::RedactConfig;
+use crate::theme::ThemeColors;
+
+#[derive(Debug, Default, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// UI tick while sources are live, in milliseconds (default 200).
+    pub tick_rate_ms: Option<u64>,
+    /// Poll timeout once everything is loaded and nothing animates (default 2000).
+    pub idle_tick_rate_ms: Option<u64>,
+    /// Open each file found by `--watch-dir` in its own tab instead of
+    /// merging it into the main graph (default false).
+    pub watch_dir_tabs: Option<bool>,
+    /// Draw the text DAG view's connectors with `|`, `-` and `+` instead of
+    /// box-drawing characters (default false).
+    pub ascii_connectors: Option<bool>,
+    /// Built-in colour theme: `dark`, `light` or `solarized` (default `dark`).
+    pub theme: Option<String>,
+    /// Colours replacing the theme's, by name (`[colors]` table).
+    pub colors: ThemeColors,
+    /// Credentials file for authenticated remote sources (see `--credentials`).
+    pub credentials_file: Option<String>,
+    /// Graph assertions checked on load and by `validate` (see `--assertions`).
+    pub assertions_file: Option<String>,
+    /// systemd journal source (`[journal]` table).
+    pub journal: JournalConfig,
+    /// `[[extract]]` rules used by `--format regex`.
+    pub extract: Vec<ExtractRule>,
+    /// Id rewrites and duplicate merging applied to every event (`[aliases]` table).
+    pub aliases: AliasConfig,
+    /// How nodes are named in the list, e.g. `"{ts:short} {type} {label|id}"`
+    /// (default `id · label`).
+    pub label_template: Option<String>,
+    /// Zone timestamps are shown in: `utc`, `local` or an offset like
+    /// `+05:30` (see `--time-zone`; default: as written).
+    pub time_zone: Option<String>,
+    /// strftime pattern for timestamps, or `relative` (see `--time-format`).
+    pub time_format: Option<String>,
+    /// How far either side of the selected node's `ts` `A` filters to, in
+    /// minutes (default 5).
+    pub time_window_minutes: Option<u64>,
+    /// Filters whose match counts are pinned to the status bar (see `--watch-expr`).
+    pub watches: Vec<String>,
+    /// Limits on fan-out and path length (`[alerts]` table).
+    pub alerts: AlertConfig,
+    /// What `--redact` hides in exports (`[redact]` table).
+    pub redact: RedactConfig,
+    /// `[[group]]` rules folding nodes whose ids match into one container.
+    pub group: Vec<GroupRule>,
+}
+
+fn default_path() -> Option<PathBuf> {
+    let base = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|h| PathBuf::from(h).join(".config")))?;
+    Some(base.join("riff-dag-tui").join("config.toml"))
+}
+
+impl Config {
+    /// Load `path` if given (it must exist), else the default location if present.
+    pub fn load(path: Option<&str>) -> Result<Self> {
+        let path = match path {
+            Some(p) => PathBuf::from(p),
+            None => match default_path() {
+                Some(p) if p.exists() => p,
+                _ => return Ok(Self::default()),
+            },
+        };
+        let text = fs::read_to_string(&path).wrap_err_with(|| format!("failed to read config {}", path.display()))?;
+        toml::from_str(&text).wrap_err_with(|| format!("invalid config {}", path.display()))
+    }
+}
@@ -0,0 +1,173 @@
+//! `--control`: a Unix socket for scripts and editor integrations to drive
+//! a running instance.
+//!
+//! Each line sent is a JSON-RPC 2.0 request; each gets a response line back.
+//! Methods act on the active tab:
+//!
+//! - `select {"id"}`: select the node (clearing the filter if it hides it)
+//! - `filter {"query"}`: filter the node list, as `/` does; returns the match count
+//! - `export {"path"}`: write the nodes listed, and the edges between them, as JSONL
+//! - `selection`: the selected node, or `null`
+//!
+//! Params can also be given by position (`"params": ["n1"]`). Requests are
+//! answered by the UI thread between frames.
+use std::{
+    path::PathBuf,
+    sync::mpsc::{channel, Receiver, Sender},
+    time::Duration,
+};
+
+use color_eyre::eyre::Result;
+use serde_json::{json, Value};
+
+/// How long a connection waits for the UI to answer.
+const REPLY_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The UI loop's poll timeout while a control socket is open, so requests
+/// are answered promptly even when nothing else is happening.
+pub const CONTROL_POLL: Duration = Duration::from_millis(50);
+
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const FAILED: i64 = -32000; // the method ran but could not do what was asked
+
+#[derive(Debug)]
+pub enum Request {
+    Select(String),
+    Filter(String),
+    Export(PathBuf),
+    Selection,
+}
+
+/// A request waiting for the UI thread, with the way back to its connection.
+pub struct Call {
+    pub request: Request,
+    reply: Sender<Result<Value, String>>,
+}
+
+impl Call {
+    pub fn reply(self, result: Result<Value, String>) {
+        let _ = self.reply.send(result);
+    }
+}
+
+pub struct Control {
+    calls: Receiver<Call>,
+    path: PathBuf,
+}
+
+fn error(id: &Value, code: i64, message: impl Into<String>) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "error": { "code": code, "message": message.into() } })
+}
+
+/// The string parameter `name`, given by name or as the only positional one.
+fn param(params: &Value, name: &str) -> Result<String, String> {
+    let value = match params {
+        Value::Object(map) => map.get(name),
+        Value::Array(list) => list.first(),
+        _ => None,
+    };
+    value.and_then(Value::as_str).map(str::to_string).ok_or_else(|| format!("expected string param `{}`", name))
+}
+
+fn parse(method: &str, params: &Value) -> Result<Request, (i64, String)> {
+    let invalid = |msg| (INVALID_PARAMS, msg);
+    match method {
+        "select" => param(params, "id").map(Request::Select).map_err(invalid),
+        "filter" => param(params, "query").map(Request::Filter).map_err(invalid),
+        "export" => param(params, "path").map(|p| Request::Export(PathBuf::from(p))).map_err(invalid),
+        "selection" => Ok(Request::Selection),
+        other => Err((METHOD_NOT_FOUND, format!("unknown method `{}`", other))),
+    }
+}
+
+/// Answer one request line, handing it to the UI thread through `calls`.
+fn respond(line: &str, calls: &Sender<Call>) -> Value {
+    let request: Value = match serde_json::from_str(line) {
+        Ok(v) => v,
+        Err(err) => return error(&Value::Null, PARSE_ERROR, err.to_string()),
+    };
+    let id = request.get("id").cloned().unwrap_or(Value::Null);
+    let Some(method) = request.get("method").and_then(Value::as_str) else {
+        return error(&id, INVALID_REQUEST, "missing `method`");
+    };
+    let request = match parse(method, request.get("params").unwrap_or(&Value::Null)) {
+        Ok(r) => r,
+        Err((code, message)) => return error(&id, code, message),
+    };
+    let (reply, answer) = channel();
+    if calls.send(Call { request, reply }).is_err() {
+        return error(&id, FAILED, "shutting down");
+    }
+    match answer.recv_timeout(REPLY_TIMEOUT) {
+        Ok(Ok(result)) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Ok(Err(message)) => error(&id, FAILED, message),
+        Err(_) => error(&id, FAILED, "the UI did not answer in time"),
+    }
+}
+
+impl Control {
+    /// Listen on `path`, replacing a stale socket left by an earlier run.
+    #[cfg(unix)]
+    pub fn bind(path: &str) -> Result<Self> {
+        use std::{
+            io::{BufRead, BufReader, Write},
+            os::unix::{
+                fs::FileTypeExt,
+                net::{UnixListener, UnixStream},
+            },
+            thread,
+        };
+
+        use color_eyre::eyre::{bail, WrapErr};
+
+        if let Ok(meta) = std::fs::symlink_metadata(path) {
+            // Only ever remove a socket: a mistyped path may name a file worth keeping.
+            if !meta.file_type().is_socket() {
+                bail!("control socket {}: path exists and is not a socket", path);
+            }
+            if UnixStream::connect(path).is_ok() {
+                bail!("control socket {} is in use by another instance", path);
+            }
+            std::fs::remove_file(path).wrap_err_with(|| format!("failed to remove stale control socket {}", path))?;
+        }
+        let listener = UnixListener::bind(path).wrap_err_with(|| format!("failed to bind control socket {}", path))?;
+        let (tx, calls) = channel();
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let tx: Sender<Call> = tx.clone();
+                thread::spawn(move || {
+                    let Ok(mut out) = stream.try_clone() else { return };
+                    for line in BufReader::new(stream).lines() {
+                        let Ok(line) = line else { return };
+                        if line.trim().is_empty() {
+                            continue;
+                        }
+                        if writeln!(out, "{}", respond(&line, &tx)).is_err() {
+                            return;
+                        }
+                    }
+                });
+            }
+        });
+        Ok(Self { calls, path: PathBuf::from(path) })
+    }
+
+    #[cfg(not(unix))]
+    pub fn bind(_path: &str) -> Result<Self> {
+        color_eyre::eyre::bail!("--control needs Unix domain sockets, which this platform lacks")
+    }
+
+    /// The next request waiting for an answer, if any.
+    pub fn next(&self) -> Option<Call> {
+        self.calls.try_recv().ok()
+    }
+}
+
+impl Drop for Control {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
@@ -0,0 +1,134 @@
+//! Cycles: the graph is meant to be a DAG, but nothing stops an emitter from
+//! writing an edge back to an ancestor. Every strongly connected component
+//! with more than one node (or a node with an edge to itself) is a cycle;
+//! it is found with Tarjan's algorithm whenever ingestion pauses, badged in
+//! the status bar and listed with the problems, and `y` steps through its
+//! nodes. Its edges are the ones that stay inside it.
+//!
+//! A few of those edges are picked as back edges: a minimal feedback edge
+//! set, without which the graph is a DAG. With `--break-cycles` (or `Y`)
+//! they are drawn apart and set aside by what needs a DAG, like path
+//! depths, so one bad edge doesn't leave a whole cycle without them.
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use itertools::Itertools;
+use petgraph::{algo::tarjan_scc, stable_graph::NodeIndex, visit::EdgeRef, Direction::Outgoing};
+
+use crate::GraphModel;
+
+#[derive(Debug, Default, Clone)]
+pub struct Cycles {
+    pub groups: Vec<Vec<String>>,    // node ids of each component, in stable order
+    nodes: HashSet<String>,
+    edges: HashSet<(String, String)>, // (from, to) of the edges inside a component
+    back: HashSet<(String, String)>,  // (from, to) of the feedback edges, a subset of `edges`
+}
+
+impl Cycles {
+    pub fn find(gm: &GraphModel) -> Self {
+        let position: HashMap<_, usize> = gm.stable_order().enumerate().map(|(i, idx)| (idx, i)).collect();
+        let mut cycles = Self::default();
+        let mut groups = Vec::new();
+        for mut component in tarjan_scc(&gm.graph) {
+            if component.len() == 1 && gm.graph.find_edge(component[0], component[0]).is_none() {
+                continue;
+            }
+            component.sort_by_key(|idx| position[idx]);
+            let inside: HashSet<_> = component.iter().copied().collect();
+            for &idx in &component {
+                for e in gm.graph.edges(idx).filter(|e| inside.contains(&e.target())) {
+                    cycles.edges.insert((gm.graph[idx].id.to_string(), gm.graph[e.target()].id.to_string()));
+                }
+            }
+            for (from, to) in feedback_edges(gm, &component, &inside) {
+                cycles.back.insert((gm.graph[from].id.to_string(), gm.graph[to].id.to_string()));
+            }
+            groups.push(component);
+        }
+        groups.sort_by_key(|component| position[&component[0]]);
+        cycles.groups = groups.into_iter().map(|c| c.into_iter().map(|idx| gm.graph[idx].id.to_string()).collect()).collect();
+        cycles.nodes = cycles.groups.iter().flatten().cloned().collect();
+        cycles
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.groups.is_empty()
+    }
+
+    /// Nodes on any cycle.
+    pub fn node_count(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn contains(&self, id: &str) -> bool {
+        self.nodes.contains(id)
+    }
+
+    pub fn contains_edge(&self, from: &str, to: &str) -> bool {
+        self.edges.contains(&(from.to_string(), to.to_string()))
+    }
+
+    /// Edges in the feedback edge set.
+    pub fn back_count(&self) -> usize {
+        self.back.len()
+    }
+
+    pub fn is_back_edge(&self, from: &str, to: &str) -> bool {
+        !self.back.is_empty() && self.back.contains(&(from.to_string(), to.to_string()))
+    }
+}
+
+/// A minimal set of the edges inside `component` whose removal leaves it
+/// acyclic: the back edges of a depth-first search in stable order, less
+/// every one that closes no cycle once the others are gone.
+fn feedback_edges(gm: &GraphModel, component: &[NodeIndex], inside: &HashSet<NodeIndex>) -> Vec<(NodeIndex, NodeIndex)> {
+    let children = |n: NodeIndex| gm.graph.neighbors_directed(n, Outgoing).filter(|m| inside.contains(m)).unique().collect::<Vec<_>>();
+    let mut back = Vec::new();
+    let mut on_stack: HashMap<NodeIndex, bool> = HashMap::new(); // every node visited, and whether it is still being searched
+    for &start in component {
+        if on_stack.contains_key(&start) {
+            continue;
+        }
+        on_stack.insert(start, true);
+        let mut stack = vec![(start, children(start), 0)];
+        while let Some((n, kids, next)) = stack.last_mut() {
+            let n = *n;
+            match kids.get(*next).copied() {
+                Some(m) => {
+                    *next += 1;
+                    match on_stack.get(&m) {
+                        Some(true) => back.push((n, m)),
+                        Some(false) => {}
+                        None => {
+                            on_stack.insert(m, true);
+                            stack.push((m, children(m), 0));
+                        }
+                    }
+                }
+                None => {
+                    on_stack.insert(n, false);
+                    stack.pop();
+                }
+            }
+        }
+    }
+    // Each edge put back is kept for the checks after it, so what is left is minimal.
+    let mut set: HashSet<(NodeIndex, NodeIndex)> = back.iter().copied().collect();
+    for &(from, to) in &back {
+        set.remove(&(from, to));
+        let mut seen = HashSet::from([to]);
+        let mut queue = VecDeque::from([to]);
+        while let Some(n) = queue.pop_front() {
+            for m in children(n) {
+                if !set.contains(&(n, m)) && seen.insert(m) {
+                    queue.push_back(m);
+                }
+            }
+        }
+        if seen.contains(&from) {
+            set.insert((from, to));
+        }
+    }
+    back.retain(|e| set.contains(e));
+    back
+}
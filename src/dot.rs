@@ -0,0 +1,489 @@
+//! A small Graphviz DOT reader.
+//!
+//! Covers what tools actually emit: `graph`/`digraph` (optionally `strict`),
+//! nested and anonymous subgraphs (flattened), node and edge statements with attribute
+//! lists, edge chains (`a -> b -> c`), edges to subgraphs, ports, quoted /
+//! HTML / concatenated IDs and all three comment styles. Default attribute
+//! statements (`node [...]`) apply to nodes declared after them, as in dot.
+//! [`events`] turns a parsed graph into node/edge events for `--format dot`.
+use std::collections::HashMap;
+
+use color_eyre::eyre::{bail, Result};
+
+use crate::EventLine;
+
+pub type Attrs = Vec<(String, String)>;
+
+#[derive(Debug, Default)]
+pub struct DotNode {
+    pub id: String,
+    pub attrs: Attrs,
+}
+
+impl DotNode {
+    pub fn attr(&self, key: &str) -> Option<&str> {
+        attr(&self.attrs, key)
+    }
+}
+
+#[derive(Debug)]
+pub struct DotEdge {
+    pub from: String,
+    pub to: String,
+    pub attrs: Attrs,
+}
+
+#[derive(Debug, Default)]
+pub struct DotGraph {
+    pub name: Option<String>,
+    /// Nodes in order of first appearance, including ones only named by edges.
+    pub nodes: Vec<DotNode>,
+    pub edges: Vec<DotEdge>,
+    index: HashMap<String, usize>,
+}
+
+/// Last value of `key` in `attrs` (later settings win, as in dot).
+pub fn attr<'a>(attrs: &'a Attrs, key: &str) -> Option<&'a str> {
+    attrs.iter().rev().find(|(k, _)| k == key).map(|(_, v)| v.as_str())
+}
+
+pub fn parse(text: &str) -> Result<DotGraph> {
+    let mut p = Parser { toks: tokenize(text)?, pos: 0, graph: DotGraph::default() };
+    p.graph_stmt()?;
+    Ok(p.graph)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Tok {
+    Id(String),
+    LBrace,
+    RBrace,
+    LBracket,
+    RBracket,
+    Eq,
+    Semi,
+    Comma,
+    Colon,
+    Plus,
+    Edge,
+}
+
+fn tokenize(text: &str) -> Result<Vec<Tok>> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut toks = Vec::new();
+    let mut i = 0;
+    let mut line_start = true;
+    while i < chars.len() {
+        let c = chars[i];
+        if c == '\n' {
+            line_start = true;
+            i += 1;
+            continue;
+        }
+        if c.is_whitespace() {
+            i += 1;
+            continue;
+        }
+        // `#` lines are C preprocessor output, which dot ignores.
+        if (c == '#' && line_start) || (c == '/' && chars.get(i + 1) == Some(&'/')) {
+            while i < chars.len() && chars[i] != '\n' {
+                i += 1;
+            }
+            continue;
+        }
+        line_start = false;
+        if c == '/' && chars.get(i + 1) == Some(&'*') {
+            i += 2;
+            while i < chars.len() && !(chars[i] == '*' && chars.get(i + 1) == Some(&'/')) {
+                i += 1;
+            }
+            i += 2;
+            continue;
+        }
+        let tok = match c {
+            '{' => Tok::LBrace,
+            '}' => Tok::RBrace,
+            '[' => Tok::LBracket,
+            ']' => Tok::RBracket,
+            '=' => Tok::Eq,
+            ';' => Tok::Semi,
+            ',' => Tok::Comma,
+            ':' => Tok::Colon,
+            '+' => Tok::Plus,
+            '-' if matches!(chars.get(i + 1), Some('>' | '-')) => {
+                i += 1;
+                Tok::Edge
+            }
+            '"' => {
+                let mut s = String::new();
+                i += 1;
+                while i < chars.len() && chars[i] != '"' {
+                    match (chars[i], chars.get(i + 1)) {
+                        ('\\', Some('"')) => {
+                            s.push('"');
+                            i += 1;
+                        }
+                        ('\\', Some('\n')) => i += 1, // line continuation
+                        (ch, _) => s.push(ch),
+                    }
+                    i += 1;
+                }
+                if i >= chars.len() {
+                    bail!("unterminated string in DOT input");
+                }
+                Tok::Id(s)
+            }
+            '<' => {
+                let start = i;
+                let mut depth = 0;
+                loop {
+                    match chars.get(i) {
+                        Some('<') => depth += 1,
+                        Some('>') if depth == 1 => break,
+                        Some('>') => depth -= 1,
+                        None => bail!("unterminated HTML string in DOT input"),
+                        _ => {}
+                    }
+                    i += 1;
+                }
+                Tok::Id(chars[start + 1..i].iter().collect())
+            }
+            c if is_id_char(c) || c == '-' => {
+                let start = i;
+                i += 1;
+                while i < chars.len() && is_id_char(chars[i]) {
+                    i += 1;
+                }
+                toks.push(Tok::Id(chars[start..i].iter().collect()));
+                continue;
+            }
+            _ => {
+                i += 1;
+                continue;
+            }
+        };
+        toks.push(tok);
+        i += 1;
+    }
+    // "a" + "b" is one ID.
+    let mut merged: Vec<Tok> = Vec::with_capacity(toks.len());
+    let mut iter = toks.into_iter().peekable();
+    while let Some(tok) = iter.next() {
+        if tok == Tok::Plus {
+            if let (Some(Tok::Id(prev)), Some(Tok::Id(_))) = (merged.last_mut(), iter.peek()) {
+                if let Some(Tok::Id(next)) = iter.next() {
+                    prev.push_str(&next);
+                }
+            }
+            continue;
+        }
+        merged.push(tok);
+    }
+    Ok(merged)
+}
+
+fn is_id_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '.' || !c.is_ascii()
+}
+
+struct Parser {
+    toks: Vec<Tok>,
+    pos: usize,
+    graph: DotGraph,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Tok> {
+        self.toks.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Tok> {
+        let tok = self.toks.get(self.pos).cloned();
+        self.pos += 1;
+        tok
+    }
+
+    fn eat(&mut self, tok: &Tok) -> bool {
+        if self.peek() == Some(tok) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn keyword(&self, word: &str) -> bool {
+        matches!(self.peek(), Some(Tok::Id(id)) if id.eq_ignore_ascii_case(word))
+    }
+
+    fn graph_stmt(&mut self) -> Result<()> {
+        if self.keyword("strict") {
+            self.pos += 1;
+        }
+        if !(self.keyword("graph") || self.keyword("digraph")) {
+            bail!("DOT input must start with `graph` or `digraph`");
+        }
+        self.pos += 1;
+        if let Some(Tok::Id(name)) = self.peek().cloned() {
+            self.graph.name = Some(name);
+            self.pos += 1;
+        }
+        if !self.eat(&Tok::LBrace) {
+            bail!("expected `{{` after the graph header");
+        }
+        self.stmt_list(&mut Attrs::new())?;
+        Ok(())
+    }
+
+    /// Statements up to the closing `}`; returns every node mentioned.
+    fn stmt_list(&mut self, node_defaults: &mut Attrs) -> Result<Vec<String>> {
+        let mut members = Vec::new();
+        loop {
+            match self.peek() {
+                None => bail!("unexpected end of DOT input (missing `}}`)"),
+                Some(Tok::RBrace) => {
+                    self.pos += 1;
+                    return Ok(members);
+                }
+                Some(Tok::Semi) | Some(Tok::Comma) => self.pos += 1,
+                _ => self.stmt(node_defaults, &mut members)?,
+            }
+        }
+    }
+
+    fn stmt(&mut self, node_defaults: &mut Attrs, members: &mut Vec<String>) -> Result<()> {
+        if self.keyword("graph") || self.keyword("edge") || self.keyword("node") {
+            let is_node = self.keyword("node");
+            self.pos += 1;
+            let attrs = self.attr_lists()?;
+            if is_node {
+                node_defaults.extend(attrs);
+            }
+            return Ok(());
+        }
+        // `key = value` at statement level is a graph attribute.
+        if let (Some(Tok::Id(_)), Some(Tok::Eq)) = (self.toks.get(self.pos), self.toks.get(self.pos + 1)) {
+            self.pos += 2;
+            self.next();
+            return Ok(());
+        }
+        let mut group = self.endpoint(node_defaults)?;
+        members.extend(group.iter().cloned());
+        let mut hops = Vec::new();
+        while self.eat(&Tok::Edge) {
+            let next = self.endpoint(node_defaults)?;
+            members.extend(next.iter().cloned());
+            hops.push((group, next.clone()));
+            group = next;
+        }
+        let attrs = self.attr_lists()?;
+        if hops.is_empty() {
+            // A lone node statement: attributes belong to the node(s).
+            for id in &group {
+                let idx = self.graph.index[id];
+                self.graph.nodes[idx].attrs.extend(attrs.iter().cloned());
+            }
+        }
+        for (froms, tos) in hops {
+            for from in &froms {
+                for to in &tos {
+                    self.graph.edges.push(DotEdge { from: from.clone(), to: to.clone(), attrs: attrs.clone() });
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// A node id (with optional port) or a subgraph; returns the node ids.
+    fn endpoint(&mut self, node_defaults: &Attrs) -> Result<Vec<String>> {
+        if self.keyword("subgraph") || self.peek() == Some(&Tok::LBrace) {
+            if self.keyword("subgraph") {
+                self.pos += 1;
+                if let Some(Tok::Id(_)) = self.peek() {
+                    self.pos += 1;
+                }
+            }
+            if !self.eat(&Tok::LBrace) {
+                bail!("expected `{{` after `subgraph`");
+            }
+            // Defaults set inside a subgraph stay inside it.
+            let mut defaults = node_defaults.clone();
+            return self.stmt_list(&mut defaults);
+        }
+        let Some(Tok::Id(id)) = self.next() else {
+            bail!("expected a node id in DOT input");
+        };
+        // Ports (`node:port:compass`) don't matter for the graph.
+        while self.eat(&Tok::Colon) {
+            self.next();
+        }
+        self.declare(&id, node_defaults);
+        Ok(vec![id])
+    }
+
+    fn declare(&mut self, id: &str, node_defaults: &Attrs) {
+        if !self.graph.index.contains_key(id) {
+            self.graph.index.insert(id.to_string(), self.graph.nodes.len());
+            self.graph.nodes.push(DotNode { id: id.to_string(), attrs: node_defaults.clone() });
+        }
+    }
+
+    /// Zero or more `[k = v, ...]` lists.
+    fn attr_lists(&mut self) -> Result<Attrs> {
+        let mut attrs = Attrs::new();
+        while self.eat(&Tok::LBracket) {
+            loop {
+                match self.next() {
+                    Some(Tok::RBracket) => break,
+                    Some(Tok::Comma) | Some(Tok::Semi) => {}
+                    Some(Tok::Id(key)) => {
+                        let value = if self.eat(&Tok::Eq) {
+                            match self.next() {
+                                Some(Tok::Id(v)) => v,
+                                _ => bail!("expected a value for attribute `{}`", key),
+                            }
+                        } else {
+                            "true".to_string()
+                        };
+                        attrs.push((key, value));
+                    }
+                    _ => bail!("malformed attribute list in DOT input"),
+                }
+            }
+        }
+        Ok(attrs)
+    }
+}
+
+/// Attributes that become node fields rather than metadata.
+const NODE_FIELDS: [&str; 6] = ["label", "tags", "class", "span", "ts", "score"];
+
+/// `--format dot`: every node and edge of `graph` as events, nodes first.
+/// A node's `label`, `span`, `ts` and `score` attributes fill those fields, `tags`
+/// (comma-separated) or else `class` its tags, and every other attribute
+/// goes to its metadata; an edge's `label` (or `kind`) becomes its kind,
+/// along with its `weight` and `ts`.
+pub fn events(graph: &DotGraph) -> Vec<EventLine> {
+    let mut events = Vec::new();
+    for node in &graph.nodes {
+        // `\N` is dot's placeholder for the node's own name.
+        let label = node.attr("label").filter(|l| *l != "\\N").map(str::to_string);
+        let tags = match (node.attr("tags"), node.attr("class")) {
+            (Some(tags), _) => Some(tags.split(',').map(str::trim).filter(|t| !t.is_empty()).map(str::to_string).collect()),
+            (None, Some(class)) => Some(class.split([' ', ',']).filter(|t| !t.is_empty()).map(str::to_string).collect()),
+            (None, None) => None,
+        };
+        let metadata: serde_json::Map<String, serde_json::Value> = node
+            .attrs
+            .iter()
+            .filter(|(k, _)| !NODE_FIELDS.contains(&k.as_str()))
+            .map(|(k, v)| (k.clone(), v.clone().into()))
+            .collect();
+        events.push(EventLine::Node {
+            id: node.id.clone(),
+            label,
+            span: node.attr("span").map(str::to_string),
+            tags,
+            ts: node.attr("ts").map(str::to_string),
+            metadata: (!metadata.is_empty()).then_some(metadata),
+            score: node.attr("score").and_then(|s| s.parse().ok()),
+            extra: serde_json::Map::new(),
+        });
+    }
+    for edge in &graph.edges {
+        let kind = attr(&edge.attrs, "label").or_else(|| attr(&edge.attrs, "kind"));
+        events.push(EventLine::Edge {
+            from: edge.from.clone(),
+            to: edge.to.clone(),
+            kind: kind.map(str::to_string),
+            weight: attr(&edge.attrs, "weight").and_then(|w| w.parse().ok()),
+            ts: attr(&edge.attrs, "ts").map(str::to_string),
+        });
+    }
+    events
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ids(graph: &DotGraph) -> Vec<&str> {
+        graph.nodes.iter().map(|n| n.id.as_str()).collect()
+    }
+
+    fn edges(graph: &DotGraph) -> Vec<(&str, &str)> {
+        graph.edges.iter().map(|e| (e.from.as_str(), e.to.as_str())).collect()
+    }
+
+    #[test]
+    fn chains_and_subgraph_endpoints_make_every_edge() {
+        let g = parse("strict digraph G { a -> b -> c; d -> { e f } -> g }").unwrap();
+        assert_eq!(g.name.as_deref(), Some("G"));
+        assert_eq!(ids(&g), ["a", "b", "c", "d", "e", "f", "g"]);
+        assert_eq!(edges(&g), [("a", "b"), ("b", "c"), ("d", "e"), ("d", "f"), ("e", "g"), ("f", "g")]);
+    }
+
+    #[test]
+    fn undirected_edges_and_ports() {
+        let g = parse("graph { a:p1:n -- b:s; -1.5 -- a }").unwrap();
+        assert_eq!(ids(&g), ["a", "b", "-1.5"]);
+        assert_eq!(edges(&g), [("a", "b"), ("-1.5", "a")]);
+    }
+
+    #[test]
+    fn ids_quoted_html_and_concatenated() {
+        let g = parse("digraph { \"a \\\"b\\\"\" -> <x<b>y</b>> ; \"pa\" + \"rt\"; \"long\\\nline\" }").unwrap();
+        assert_eq!(ids(&g), ["a \"b\"", "x<b>y</b>", "part", "longline"]);
+    }
+
+    #[test]
+    fn comments_of_every_style_are_skipped() {
+        let g = parse("# 1 \"file.dot\"\ndigraph { // a -> x\n a /* -> y */ -> b }").unwrap();
+        assert_eq!(ids(&g), ["a", "b"]);
+        assert_eq!(edges(&g), [("a", "b")]);
+        // `#` only starts a comment at the start of a line.
+        let g = parse("digraph { a # b\n }").unwrap();
+        assert_eq!(ids(&g), ["a", "b"]);
+    }
+
+    #[test]
+    fn node_defaults_apply_after_them_and_stay_in_their_subgraph() {
+        let g = parse("digraph { a; node [shape=box]; b; subgraph s { node [color=red]; c } d [shape=oval] }").unwrap();
+        let node = |id: &str| g.nodes.iter().find(|n| n.id == id).unwrap();
+        assert_eq!(node("a").attr("shape"), None);
+        assert_eq!(node("b").attr("shape"), Some("box"));
+        assert_eq!(node("c").attr("color"), Some("red"));
+        assert_eq!(node("d").attr("color"), None);
+        assert_eq!(node("d").attr("shape"), Some("oval"));
+    }
+
+    #[test]
+    fn attributes_and_graph_settings() {
+        let g = parse("digraph { rankdir=LR; graph [x=1]; edge [color=b]; a -> b [label=calls weight=2][ts=\"9\"]; c [hidden] }").unwrap();
+        assert_eq!(ids(&g), ["a", "b", "c"]);
+        assert_eq!(g.edges[0].attrs, [("label".to_string(), "calls".to_string()), ("weight".into(), "2".into()), ("ts".into(), "9".into())]);
+        assert_eq!(g.nodes[2].attr("hidden"), Some("true"));
+    }
+
+    #[test]
+    fn malformed_input_is_an_error() {
+        for text in ["", "a -> b", "digraph", "digraph { a -> b", "digraph { \"a }", "digraph { <a }", "digraph { a [label=] }", "digraph { a -> }"] {
+            assert!(parse(text).is_err(), "{:?}", text);
+        }
+    }
+
+    #[test]
+    fn events_fill_fields_and_keep_the_rest_as_metadata() {
+        let g = parse("digraph { a [label=\"\\N\" class=\"x y\" shape=box score=0.5]; b [tags=\"p, q\" class=z]; a -> b [kind=uses weight=3] }").unwrap();
+        let events = events(&g);
+        let EventLine::Node { id, label, tags, metadata, score, .. } = &events[0] else { panic!("{:?}", events[0]) };
+        assert_eq!((id.as_str(), label, score), ("a", &None, &Some(0.5)));
+        assert_eq!(tags.as_deref(), Some(&["x".to_string(), "y".to_string()][..]));
+        assert_eq!(metadata.as_ref().map(|m| m.len()), Some(1));
+        let EventLine::Node { tags, metadata, .. } = &events[1] else { panic!("{:?}", events[1]) };
+        assert_eq!(tags.as_deref(), Some(&["p".to_string(), "q".to_string()][..]));
+        assert_eq!(metadata, &None);
+        let EventLine::Edge { kind, weight, .. } = &events[2] else { panic!("{:?}", events[2]) };
+        assert_eq!((kind.as_deref(), *weight), (Some("uses"), Some(3.0)));
+    }
+}
@@ -0,0 +1,128 @@
+//! What happens when a node id is defined more than once (`--on-duplicate`).
+//!
+//! A redefinition conflicts when it changes a field that was already set:
+//! a different non-empty `label`, `span` or `ts`, a different `score`, a
+//! different set of tags, or a different value for a metadata key. Filling in an empty field is
+//! not a conflict; under `last`, which replaces the node wholesale, leaving
+//! out a field that was set is. Every conflict is recorded on the graph (and listed in
+//! the problems panel) whichever policy resolved it.
+use std::fmt;
+
+use clap::ValueEnum;
+
+use crate::NodeData;
+
+/// Metadata key listing the input files a node was defined in, when several
+/// are merged. The viewer keeps it, so it never conflicts.
+pub const SOURCES_KEY: &str = "sources";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DuplicatePolicy {
+    /// The latest definition replaces the node
+    #[default]
+    Last,
+    /// The first definition stays; later ones are ignored
+    First,
+    /// Fields are merged: later values win, tags and metadata are combined
+    Merge,
+    /// Conflicting redefinitions are rejected and reported as errors
+    Error,
+}
+
+/// One redefinition that changed fields already set.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct Conflict {
+    pub id: String,
+    pub at: String, // source:line of the redefinition
+    pub fields: Vec<String>,
+    pub policy: DuplicatePolicy,
+}
+
+impl fmt::Display for Conflict {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let outcome = match self.policy {
+            DuplicatePolicy::Last => "replaced",
+            DuplicatePolicy::First => "kept the first",
+            DuplicatePolicy::Merge => "merged, later values win",
+            DuplicatePolicy::Error => "rejected",
+        };
+        write!(f, "node {} redefined at {} with conflicting {} ({})", self.id, self.at, self.fields.join(", "), outcome)
+    }
+}
+
+/// The fields `incoming` would change that `existing` already set; with
+/// `clearing`, dropping a field counts as changing it.
+fn conflicting_fields(existing: &NodeData, incoming: &NodeData, clearing: bool) -> Vec<String> {
+    let mut fields = Vec::new();
+    for (name, old, new) in
+        [("label", &*existing.label, &*incoming.label), ("span", &existing.span, &incoming.span), ("ts", &existing.ts, &incoming.ts)]
+    {
+        if !old.is_empty() && (clearing || !new.is_empty()) && old != new {
+            fields.push(name.to_string());
+        }
+    }
+    if existing.score.is_some() && (clearing || incoming.score.is_some()) && existing.score != incoming.score {
+        fields.push("score".to_string());
+    }
+    let same_tags = existing.tags.iter().all(|t| incoming.tags.contains(t)) && incoming.tags.iter().all(|t| existing.tags.contains(t));
+    if !existing.tags.is_empty() && (clearing || !incoming.tags.is_empty()) && !same_tags {
+        fields.push("tags".to_string());
+    }
+    for (key, old) in existing.metadata.iter().filter(|(key, _)| *key != SOURCES_KEY) {
+        match incoming.metadata.get(key) {
+            Some(new) if new != old => fields.push(format!("metadata.{}", key)),
+            None if clearing => fields.push(format!("metadata.{}", key)),
+            _ => {}
+        }
+    }
+    for (key, old) in &existing.extra {
+        match incoming.extra.get(key) {
+            Some(new) if new != old => fields.push(key.clone()),
+            None if clearing => fields.push(key.clone()),
+            _ => {}
+        }
+    }
+    fields
+}
+
+fn merge(existing: &NodeData, incoming: NodeData) -> NodeData {
+    let pick = |old: &String, new: String| if new.is_empty() { old.clone() } else { new };
+    let mut tags = existing.tags.clone();
+    for t in incoming.tags {
+        if !tags.contains(&t) {
+            tags.push(t);
+        }
+    }
+    let mut metadata = existing.metadata.clone();
+    metadata.extend(incoming.metadata);
+    let mut extra = existing.extra.clone();
+    extra.extend(incoming.extra);
+    NodeData {
+        id: incoming.id,
+        label: pick(&existing.label, incoming.label),
+        span: if incoming.span.is_empty() { existing.span.clone() } else { incoming.span },
+        tags,
+        ts: pick(&existing.ts, incoming.ts),
+        metadata,
+        score: incoming.score.or(existing.score),
+        extra,
+        raw: incoming.raw,
+    }
+}
+
+impl DuplicatePolicy {
+    /// What the node becomes when `incoming` redefines `existing` (`None`:
+    /// it stays as it is), and the conflicting fields, if any.
+    pub fn resolve(self, existing: &NodeData, incoming: NodeData) -> (Option<NodeData>, Vec<String>) {
+        let fields = conflicting_fields(existing, &incoming, self == DuplicatePolicy::Last);
+        let node = match self {
+            DuplicatePolicy::Last => Some(incoming),
+            DuplicatePolicy::First => None,
+            DuplicatePolicy::Merge => Some(merge(existing, incoming)),
+            DuplicatePolicy::Error if fields.is_empty() => Some(merge(existing, incoming)),
+            DuplicatePolicy::Error => None,
+        };
+        (node, fields)
+    }
+}
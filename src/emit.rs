@@ -0,0 +1,109 @@
+//! `--emit-selection`: the selected node as one JSON line per selection
+//! change, for companion tools that follow along (a preview pane in another
+//! tmux window, say).
+//!
+//! Lines go to stdout (the UI then draws on the terminal directly, so stdout
+//! has to be redirected) or to a file or named pipe. Writing happens on a
+//! thread of its own: opening a pipe waits for a reader, and only the latest
+//! selection is written once one shows up, so the UI never blocks on it.
+use std::{
+    fs::OpenOptions,
+    io::{self, Write},
+    path::PathBuf,
+    str::FromStr,
+    sync::mpsc::{channel, Sender},
+    thread,
+    time::Duration,
+};
+
+use petgraph::stable_graph::NodeIndex;
+
+use crate::{GraphModel, NodeData};
+
+/// Wait before reopening a target that could not be opened or written.
+const REOPEN_AFTER: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone)]
+pub enum EmitTarget {
+    Stdout,
+    Path(PathBuf), // a file (appended to) or a named pipe
+}
+
+impl FromStr for EmitTarget {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        match s {
+            "" => Err("expected `stdout` or the path of a file or named pipe".to_string()),
+            "stdout" | "-" => Ok(EmitTarget::Stdout),
+            path => Ok(EmitTarget::Path(PathBuf::from(path))),
+        }
+    }
+}
+
+/// The selected node with its neighbours' ids.
+#[derive(serde::Serialize)]
+struct Selected<'a> {
+    #[serde(flatten)]
+    node: &'a NodeData,
+    parents: Vec<&'a str>,
+    children: Vec<&'a str>,
+}
+
+/// The node at `idx` as written for a selection: its fields plus the ids of
+/// its parents and children.
+pub fn node_json(gm: &GraphModel, idx: NodeIndex) -> serde_json::Value {
+    let ids = |nodes: Vec<NodeIndex>| nodes.into_iter().map(|n| &*gm.graph[n].id).collect();
+    let selected = Selected { node: &gm.graph[idx], parents: ids(gm.parents_of(idx)), children: ids(gm.children_of(idx)) };
+    serde_json::to_value(selected).unwrap_or_default()
+}
+
+pub struct SelectionEmitter {
+    tx: Sender<String>,
+    last: Option<String>, // id of the node last written
+}
+
+fn open(target: &EmitTarget) -> io::Result<Box<dyn Write>> {
+    match target {
+        EmitTarget::Stdout => Ok(Box::new(io::stdout())),
+        EmitTarget::Path(path) => Ok(Box::new(OpenOptions::new().append(true).create(true).open(path)?)),
+    }
+}
+
+impl SelectionEmitter {
+    pub fn spawn(target: EmitTarget) -> Self {
+        let (tx, rx) = channel::<String>();
+        thread::spawn(move || {
+            let mut out: Option<Box<dyn Write>> = None;
+            while let Ok(mut line) = rx.recv() {
+                while out.is_none() {
+                    match open(&target) {
+                        Ok(w) => out = Some(w),
+                        Err(_) => thread::sleep(REOPEN_AFTER),
+                    }
+                }
+                // Skip selections made while the pipe had no reader.
+                while let Ok(newer) = rx.try_recv() {
+                    line = newer;
+                }
+                let w = out.as_mut().expect("opened above");
+                if writeln!(w, "{}", line).and_then(|()| w.flush()).is_err() {
+                    // The reader went away; the next selection waits for another.
+                    out = None;
+                }
+            }
+        });
+        Self { tx, last: None }
+    }
+
+    /// Write the node at `idx` if it isn't the one written last.
+    pub fn selected(&mut self, gm: &GraphModel, idx: Option<NodeIndex>) {
+        let Some(idx) = idx else { return };
+        let nd = &gm.graph[idx];
+        if self.last.as_deref() == Some(&*nd.id) {
+            return;
+        }
+        let _ = self.tx.send(node_json(gm, idx).to_string());
+        self.last = Some(nd.id.to_string());
+    }
+}
@@ -0,0 +1,254 @@
+//! Writing (part of) a graph back out: as node/edge JSONL events, in the
+//! form `--input` reads, or as Graphviz DOT for rendering (`--format dot`
+//! reads it back, though the styling then shows up as metadata), or as a
+//! Markdown or org-mode outline to paste into a write-up. Each can be
+//! redacted (see `redact`).
+use std::{
+    borrow::Cow,
+    collections::{HashMap, HashSet},
+    fs::File,
+    io::{BufWriter, Write},
+    path::Path,
+};
+
+use color_eyre::eyre::{Result, WrapErr};
+use petgraph::{stable_graph::NodeIndex, visit::EdgeRef};
+use serde_json::{json, Map, Value};
+
+use crate::{classify_node_type, redact::RedactConfig, timefmt::TimeDisplay, EdgeData, GraphModel, NodeData, NodeType};
+
+/// Set fields only; empty strings and lists are left out.
+fn node_event(nd: &NodeData, time: &TimeDisplay) -> Value {
+    let mut event = Map::new();
+    event.insert("type".into(), "node".into());
+    event.insert("id".into(), nd.id.to_string().into());
+    for (key, value) in [("label", nd.label.clone()), ("span", nd.span.to_string()), ("ts", time.export(&nd.ts))] {
+        if !value.is_empty() {
+            event.insert(key.into(), value.into());
+        }
+    }
+    if !nd.tags.is_empty() {
+        event.insert("tags".into(), json!(nd.tags));
+    }
+    if !nd.metadata.is_empty() {
+        event.insert("metadata".into(), json!(nd.metadata));
+    }
+    if let Some(score) = nd.score {
+        event.insert("score".into(), json!(score));
+    }
+    for (key, value) in &nd.extra {
+        event.insert(key.clone(), value.clone());
+    }
+    Value::Object(event)
+}
+
+/// `nd` as it is written out.
+fn shown<'a>(nd: &'a NodeData, redact: Option<&RedactConfig>) -> Cow<'a, NodeData> {
+    redact.map_or(Cow::Borrowed(nd), |r| Cow::Owned(r.node(nd)))
+}
+
+/// The two ends of an edge as they are written out.
+fn ends<'a>(gm: &'a GraphModel, a: NodeIndex, b: NodeIndex, redact: Option<&RedactConfig>) -> (Cow<'a, str>, Cow<'a, str>) {
+    let (a, b) = (&*gm.graph[a].id, &*gm.graph[b].id);
+    redact.map_or((Cow::Borrowed(a), Cow::Borrowed(b)), |r| (r.id(a), r.id(b)))
+}
+
+fn edge_event(from: &str, to: &str, ed: &EdgeData, time: &TimeDisplay, redact: Option<&RedactConfig>) -> Value {
+    let mut event = Map::new();
+    event.insert("type".into(), "edge".into());
+    event.insert("from".into(), from.into());
+    event.insert("to".into(), to.into());
+    if !ed.kind.is_empty() {
+        event.insert("kind".into(), ed.kind.clone().into());
+    }
+    if let Some(weight) = ed.weight {
+        event.insert("weight".into(), weight.into());
+    }
+    let ts = redact.map_or(ed.ts.as_str(), |r| r.edge_ts(&ed.ts));
+    if !ts.is_empty() {
+        event.insert("ts".into(), time.export(ts).into());
+    }
+    Value::Object(event)
+}
+
+/// Write `nodes` (in the order given) and then every edge between two of
+/// them to `path`, timestamps moved to `time`'s zone. Returns how many nodes
+/// and edges were written.
+pub fn write_jsonl(gm: &GraphModel, nodes: &[NodeIndex], path: &Path, time: &TimeDisplay, redact: Option<&RedactConfig>) -> Result<(usize, usize)> {
+    let file = File::create(path).wrap_err_with(|| format!("failed to create {}", path.display()))?;
+    let mut out = BufWriter::new(file);
+    for &idx in nodes {
+        writeln!(out, "{}", node_event(&shown(&gm.graph[idx], redact), time))?;
+    }
+    let kept: HashSet<NodeIndex> = nodes.iter().copied().collect();
+    let mut edges = 0;
+    for &idx in nodes {
+        for e in gm.graph.edges(idx).filter(|e| kept.contains(&e.target())) {
+            let (from, to) = ends(gm, idx, e.target(), redact);
+            writeln!(out, "{}", edge_event(&from, &to, e.weight(), time, redact))?;
+            edges += 1;
+        }
+    }
+    out.flush().wrap_err_with(|| format!("failed to write {}", path.display()))?;
+    Ok((nodes.len(), edges))
+}
+
+/// Shape, outline and fill per node type, after the canvas's shapes and colours.
+fn dot_style(node_type: NodeType) -> (&'static str, &'static str, &'static str) {
+    match node_type {
+        NodeType::Prompt => ("box", "darkcyan", "lightcyan"),
+        NodeType::Response => ("ellipse", "darkgreen", "honeydew"),
+        NodeType::Tool => ("diamond", "goldenrod", "lightyellow"),
+        NodeType::Error => ("octagon", "red3", "mistyrose"),
+        NodeType::Event => ("point", "magenta3", "magenta3"),
+        NodeType::Unknown => ("box", "gray40", "white"),
+    }
+}
+
+/// A quoted DOT ID; newlines become dot's `\n` line breaks.
+fn quote(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n"))
+}
+
+fn dot_attrs(attrs: &[(&str, String)]) -> String {
+    attrs.iter().map(|(k, v)| format!("{}={}", k, quote(v))).collect::<Vec<_>>().join(", ")
+}
+
+/// Write `nodes` and every edge between two of them to `path` as a DOT
+/// digraph named `title`, styled by node type. The node fields become
+/// attributes (`label`, `span`, `ts`, `tags`, metadata and the producer's
+/// own fields) and an edge's kind
+/// its label. Returns how many nodes and edges were written.
+pub fn write_dot(gm: &GraphModel, nodes: &[NodeIndex], path: &Path, title: &str, time: &TimeDisplay, redact: Option<&RedactConfig>) -> Result<(usize, usize)> {
+    let file = File::create(path).wrap_err_with(|| format!("failed to create {}", path.display()))?;
+    let mut out = BufWriter::new(file);
+    // The title is the input's name, which is best kept out of a redacted graph too.
+    writeln!(out, "digraph {} {{", quote(if redact.is_some() { "graph" } else { title }))?;
+    writeln!(out, "  node [style=filled, fontname=Helvetica];")?;
+    writeln!(out, "  edge [fontname=Helvetica, fontsize=10];")?;
+    for &idx in nodes {
+        let nd = shown(&gm.graph[idx], redact);
+        let (shape, color, fill) = dot_style(classify_node_type(&nd.tags));
+        let mut attrs = vec![("label", if nd.label.is_empty() { nd.id.to_string() } else { nd.label.clone() })];
+        for (key, value) in [("span", nd.span.to_string()), ("ts", time.export(&nd.ts)), ("tags", nd.tags.join(","))] {
+            if !value.is_empty() {
+                attrs.push((key, value));
+            }
+        }
+        attrs.extend(nd.metadata.iter().map(|(k, v)| (k.as_str(), v.clone())));
+        // DOT attributes are text: strings as they are, anything else as JSON.
+        attrs.extend(nd.extra.iter().map(|(k, v)| (k.as_str(), v.as_str().map_or_else(|| v.to_string(), str::to_string))));
+        attrs.extend(nd.score.map(|s| ("score", s.to_string())));
+        attrs.extend([("shape", shape.to_string()), ("color", color.to_string()), ("fillcolor", fill.to_string())]);
+        writeln!(out, "  {} [{}];", quote(&nd.id), dot_attrs(&attrs))?;
+    }
+    let kept: HashSet<NodeIndex> = nodes.iter().copied().collect();
+    let mut edges = 0;
+    for &idx in nodes {
+        for e in gm.graph.edges(idx).filter(|e| kept.contains(&e.target())) {
+            let ed = e.weight();
+            let mut attrs = Vec::new();
+            if !ed.kind.is_empty() {
+                attrs.push(("label", ed.kind.clone()));
+            }
+            if let Some(weight) = ed.weight {
+                attrs.push(("weight", weight.to_string()));
+            }
+            let ts = redact.map_or(ed.ts.as_str(), |r| r.edge_ts(&ed.ts));
+            if !ts.is_empty() {
+                attrs.push(("ts", time.export(ts)));
+            }
+            let attrs = if attrs.is_empty() { String::new() } else { format!(" [{}]", dot_attrs(&attrs)) };
+            let (from, to) = ends(gm, idx, e.target(), redact);
+            writeln!(out, "  {} -> {}{};", quote(&from), quote(&to), attrs)?;
+            edges += 1;
+        }
+    }
+    writeln!(out, "}}")?;
+    out.flush().wrap_err_with(|| format!("failed to write {}", path.display()))?;
+    Ok((nodes.len(), edges))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outline {
+    Markdown,
+    Org,
+}
+
+impl Outline {
+    /// The outline a path's extension asks for: `.md`/`.markdown` or `.org`.
+    pub fn for_path(path: &Path) -> Option<Self> {
+        match path.extension().and_then(|e| e.to_str()).map(str::to_ascii_lowercase).as_deref() {
+            Some("md" | "markdown") => Some(Outline::Markdown),
+            Some("org") => Some(Outline::Org),
+            _ => None,
+        }
+    }
+
+    fn title(self, title: &str) -> String {
+        match self {
+            Outline::Markdown => format!("# {}\n", title),
+            Outline::Org => format!("#+TITLE: {}\n", title),
+        }
+    }
+
+    fn item(self, depth: usize, text: &str) -> String {
+        match self {
+            Outline::Markdown => format!("{}- {}", "  ".repeat(depth), text),
+            Outline::Org => format!("{} {}", "*".repeat(depth + 1), text),
+        }
+    }
+}
+
+/// Write `nodes` to `path` as an indented outline, depth first from each
+/// node without a parent among them (in the order given), children under
+/// their parents. A node reached again is listed as `↪ id (above)` instead
+/// of being expanded twice, and an edge back to a node on the way down as
+/// `↻ id (cycle)`. Nodes only reachable through a cycle start outlines of
+/// their own at the end. Returns how many nodes and edges were written.
+pub fn write_outline(gm: &GraphModel, nodes: &[NodeIndex], path: &Path, title: &str, style: Outline, redact: Option<&RedactConfig>) -> Result<(usize, usize)> {
+    let file = File::create(path).wrap_err_with(|| format!("failed to create {}", path.display()))?;
+    let mut out = BufWriter::new(file);
+    writeln!(out, "{}", style.title(if redact.is_some() { "graph" } else { title }))?;
+    let position: HashMap<NodeIndex, usize> = nodes.iter().enumerate().map(|(i, &idx)| (idx, i)).collect();
+    let has_parent = |idx: NodeIndex| gm.graph.neighbors_directed(idx, petgraph::Direction::Incoming).any(|p| position.contains_key(&p));
+    let roots = nodes.iter().copied().filter(|&idx| !has_parent(idx)).chain(nodes.iter().copied());
+    let text = |idx: NodeIndex| {
+        let nd = shown(&gm.graph[idx], redact);
+        let tags = if nd.tags.is_empty() { String::new() } else { format!(" [{}]", nd.tags.join(", ")) };
+        format!("{}{}", nd.display_label(), tags)
+    };
+    let mut written: HashSet<NodeIndex> = HashSet::new();
+    let mut edges = 0;
+    for root in roots {
+        if written.contains(&root) {
+            continue;
+        }
+        // (node, depth); `path` holds the nodes above the one being written.
+        let mut stack = vec![(root, 0)];
+        let mut path: Vec<NodeIndex> = Vec::new();
+        while let Some((idx, depth)) = stack.pop() {
+            path.truncate(depth);
+            if depth > 0 {
+                edges += 1;
+            }
+            let id = || shown(&gm.graph[idx], redact).id.clone();
+            if path.contains(&idx) {
+                writeln!(out, "{}", style.item(depth, &format!("↻ {} (cycle)", id())))?;
+                continue;
+            }
+            if !written.insert(idx) {
+                writeln!(out, "{}", style.item(depth, &format!("↪ {} (above)", id())))?;
+                continue;
+            }
+            writeln!(out, "{}", style.item(depth, &text(idx)))?;
+            path.push(idx);
+            let mut children: Vec<NodeIndex> = gm.graph.neighbors_directed(idx, petgraph::Direction::Outgoing).filter(|c| position.contains_key(c)).collect();
+            children.sort_by_key(|c| position[c]);
+            children.dedup();
+            stack.extend(children.into_iter().rev().map(|c| (c, depth + 1)));
+        }
+    }
+    out.flush().wrap_err_with(|| format!("failed to write {}", path.display()))?;
+    Ok((written.len(), edges))
+}
@@ -0,0 +1,81 @@
+//! Regex-based extraction of graph events from plain-text logs.
+//!
+//! With `--format regex`, every input line is matched against the
+//! `[[extract]]` rules from the config. Named capture groups say what a
+//! match means:
+//!
+//! - `id` makes a node; `label`, `span`, `ts` and `tags` (comma-separated)
+//!   fill in its fields, and `parent` adds an edge parent -> id;
+//! - `from` and `to` make an edge.
+//!
+//! Every rule is tried on every line, so node and edge rules can share
+//! lines. Lines no rule matches are skipped silently.
+use color_eyre::eyre::{bail, Result, WrapErr};
+use regex::{Captures, Regex};
+
+use crate::ingest::Adapter;
+use crate::EventLine;
+
+#[derive(Debug, Clone, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ExtractRule {
+    pub pattern: String,
+}
+
+pub struct RegexAdapter {
+    rules: Vec<Regex>,
+}
+
+impl RegexAdapter {
+    pub fn new(rules: &[ExtractRule]) -> Result<Self> {
+        if rules.is_empty() {
+            bail!("--format regex needs at least one [[extract]] rule in the config");
+        }
+        let mut compiled = Vec::new();
+        for (i, rule) in rules.iter().enumerate() {
+            let re = Regex::new(&rule.pattern).wrap_err_with(|| format!("invalid pattern in [[extract]] rule {}", i + 1))?;
+            let has = |name: &str| re.capture_names().flatten().any(|n| n == name);
+            let is_node = has("id");
+            let is_edge = has("from") && has("to");
+            if !is_node && !is_edge {
+                bail!("[[extract]] rule {} needs an `id` group or `from` and `to` groups", i + 1);
+            }
+            compiled.push(re);
+        }
+        Ok(Self { rules: compiled })
+    }
+}
+
+fn group(caps: &Captures, name: &str) -> Option<String> {
+    caps.name(name).map(|m| m.as_str().to_string()).filter(|s| !s.is_empty())
+}
+
+impl Adapter for RegexAdapter {
+    fn events(&self, line: &str) -> Result<Vec<EventLine>, String> {
+        let mut events = Vec::new();
+        for re in &self.rules {
+            let Some(caps) = re.captures(line) else { continue };
+            if let Some(id) = group(&caps, "id") {
+                let tags = group(&caps, "tags")
+                    .map(|t| t.split(',').map(str::trim).filter(|t| !t.is_empty()).map(str::to_string).collect());
+                events.push(EventLine::Node {
+                    id: id.clone(),
+                    label: group(&caps, "label"),
+                    span: group(&caps, "span"),
+                    tags,
+                    ts: group(&caps, "ts"),
+                    metadata: None,
+                    score: None,
+                    extra: serde_json::Map::new(),
+                });
+                if let Some(parent) = group(&caps, "parent") {
+                    events.push(EventLine::edge(parent, id));
+                }
+            }
+            if let (Some(from), Some(to)) = (group(&caps, "from"), group(&caps, "to")) {
+                events.push(EventLine::edge(from, to));
+            }
+        }
+        Ok(events)
+    }
+}
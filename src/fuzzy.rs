@@ -0,0 +1,154 @@
+//! Fuzzy matching for the `/` filter's `~` mode: `~plnrspn` finds
+//! `planner-span-013`, its characters in order with anything between.
+//!
+//! Of every way the characters could line up, the best scoring is taken:
+//! each matched character scores, more at the start of a word (after `-`,
+//! `_`, `:`, `/`, `.`, a space, or a lower-case letter before a capital) and
+//! more again right after the previous match, and the characters skipped
+//! between matches cost a little. Case is ignored. The list is ordered by
+//! the score, and the matched characters are highlighted.
+
+const MATCH: i64 = 16;
+const BOUNDARY: i64 = 10;
+const CONSECUTIVE: i64 = 12;
+const GAP: i64 = 1; // per character skipped between two matches
+
+/// Whether `text[j]` starts a word.
+fn boundary(text: &[char], j: usize) -> bool {
+    match j.checked_sub(1).map(|i| text[i]) {
+        None => true,
+        Some(prev) => !prev.is_alphanumeric() || (prev.is_lowercase() && text[j].is_uppercase()),
+    }
+}
+
+fn same(a: char, b: char) -> bool {
+    a == b || a.to_lowercase().eq(b.to_lowercase())
+}
+
+/// The score of the best alignment of `pattern` (whitespace ignored) in
+/// `text`, and where its characters are in `text` (by char); `None` when
+/// they aren't all there in order.
+pub fn best(pattern: &str, text: &str) -> Option<(i64, Vec<usize>)> {
+    let pattern: Vec<char> = pattern.chars().filter(|c| !c.is_whitespace()).collect();
+    let text: Vec<char> = text.chars().collect();
+    if pattern.is_empty() {
+        return Some((0, Vec::new()));
+    }
+    // Cheap rejection first: most nodes don't contain the characters at all.
+    let mut rest = text.iter();
+    if !pattern.iter().all(|&p| rest.any(|&t| same(p, t))) {
+        return None;
+    }
+    let (m, n) = (pattern.len(), text.len());
+    let own = |j: usize| MATCH + if boundary(&text, j) { BOUNDARY } else { 0 };
+    // score[i][j]: the best with pattern[i] matched at text[j], pattern[..i] before it.
+    let mut score = vec![vec![None::<i64>; n]; m];
+    for i in 0..m {
+        // The best of row i - 1 up to j - 2, less the gap from there to j.
+        let mut gapped: Option<i64> = None;
+        for j in 0..n {
+            if same(pattern[i], text[j]) {
+                score[i][j] = if i == 0 {
+                    Some(own(j))
+                } else {
+                    let consecutive = j.checked_sub(1).and_then(|k| score[i - 1][k]).map(|s| s + CONSECUTIVE);
+                    gapped.max(consecutive).map(|s| s + own(j))
+                };
+            }
+            if i > 0 && j > 0 {
+                gapped = gapped.max(score[i - 1][j - 1]).map(|s| s - GAP);
+            }
+        }
+    }
+    let (end, total) = (0..n).filter_map(|j| Some((j, score[m - 1][j]?))).max_by_key(|&(j, s)| (s, std::cmp::Reverse(j)))?;
+    // Walk back the way the best score was reached.
+    let mut positions = vec![end];
+    let mut j = end;
+    for i in (1..m).rev() {
+        let before = score[i][j].expect("on the best path") - own(j);
+        j = match j.checked_sub(1).and_then(|k| score[i - 1][k]) {
+            Some(s) if s + CONSECUTIVE == before => j - 1,
+            _ => (0..j.saturating_sub(1))
+                .find(|&k| score[i - 1][k].is_some_and(|s| s - GAP * (j - k - 1) as i64 == before))
+                .expect("some earlier match gave this score"),
+        };
+        positions.push(j);
+    }
+    positions.reverse();
+    Some((total, positions))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// What an alignment scores, added up from its positions.
+    fn score_of(text: &str, positions: &[usize]) -> i64 {
+        let text: Vec<char> = text.chars().collect();
+        let own = |j: usize| MATCH + if boundary(&text, j) { BOUNDARY } else { 0 };
+        let mut total = own(positions[0]);
+        for w in positions.windows(2) {
+            total += own(w[1]) + if w[1] == w[0] + 1 { CONSECUTIVE } else { -GAP * (w[1] - w[0] - 1) as i64 };
+        }
+        total
+    }
+
+    /// The best score over every alignment, tried one by one.
+    fn brute(pattern: &[char], text: &str, from: usize, at: &mut Vec<usize>) -> Option<i64> {
+        let Some((&p, rest)) = pattern.split_first() else { return Some(score_of(text, at)) };
+        let chars: Vec<char> = text.chars().collect();
+        (from..chars.len())
+            .filter(|&j| same(p, chars[j]))
+            .filter_map(|j| {
+                at.push(j);
+                let best = brute(rest, text, j + 1, at);
+                at.pop();
+                best
+            })
+            .max()
+    }
+
+    #[test]
+    fn finds_characters_in_order() {
+        assert!(best("plnrspn", "planner-span-013").is_some());
+        assert!(best("PLN", "planner").is_some());
+        assert_eq!(best("npl", "planner"), None);
+        assert_eq!(best("x", ""), None);
+        assert_eq!(best(" ", "anything"), Some((0, Vec::new())));
+    }
+
+    #[test]
+    fn word_starts_and_runs_score_higher() {
+        let score = |p: &str, t: &str| best(p, t).unwrap().0;
+        assert!(score("sp", "planner-span") > score("sp", "planner-xsxp"));
+        assert!(score("ab", "xabx") > score("ab", "xaxb"));
+        assert!(score("fb", "fooBar") > score("fb", "foobar"));
+    }
+
+    /// The walk back must find the path the best score came by, whatever
+    /// the repeats and ties, and never fall through to its `expect`s.
+    #[test]
+    fn positions_add_up_to_the_best_score() {
+        let cases = [
+            ("aaa", "aaaaab a"),
+            ("ab", "a-b_ab.aab"),
+            ("abc", "abxabcxxabc"),
+            ("spn", "span-spn/s-p-n"),
+            ("ss", "ßsS ss"),
+            ("éa", "Éxa éa"),
+            ("aba", "abababa"),
+            ("xy", "x y xy x-y"),
+            ("cb", "aCaBcb"),
+        ];
+        for (pattern, text) in cases {
+            let (total, positions) = best(pattern, text).unwrap();
+            let chars: Vec<char> = text.chars().collect();
+            let wanted: Vec<char> = pattern.chars().collect();
+            assert_eq!(positions.len(), wanted.len(), "{} in {}", pattern, text);
+            assert!(positions.windows(2).all(|w| w[0] < w[1]), "{} in {}: {:?}", pattern, text, positions);
+            assert!(positions.iter().zip(&wanted).all(|(&j, &p)| same(p, chars[j])), "{} in {}: {:?}", pattern, text, positions);
+            assert_eq!(score_of(text, &positions), total, "{} in {}: {:?}", pattern, text, positions);
+            assert_eq!(brute(&wanted, text, 0, &mut Vec::new()), Some(total), "{} in {}", pattern, text);
+        }
+    }
+}
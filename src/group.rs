@@ -0,0 +1,55 @@
+//! Virtual containers: nodes whose ids match a `[[group]]` rule from the
+//! config are folded into one list entry (and one shape on the canvas)
+//! until it is expanded with `G`, so a graph with thousands of `retry-N`
+//! nodes stays readable.
+//!
+//! ```toml
+//! [[group]]
+//! pattern = 'retry-\d+'
+//! name = "retries"        # shown for the container; the pattern otherwise
+//! ```
+//!
+//! A node belongs to the first rule its id matches. The filter applies to
+//! the members; a container lists as many as match.
+use color_eyre::eyre::{Result, WrapErr};
+use regex::Regex;
+
+#[derive(Debug, Clone, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct GroupRule {
+    pub pattern: String,
+    pub name: Option<String>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Groups {
+    rules: Vec<(Regex, String)>, // pattern and the name shown for it
+}
+
+impl Groups {
+    pub fn new(rules: &[GroupRule]) -> Result<Self> {
+        let mut compiled = Vec::new();
+        for (i, rule) in rules.iter().enumerate() {
+            let re = Regex::new(&rule.pattern).wrap_err_with(|| format!("invalid pattern in [[group]] rule {}", i + 1))?;
+            compiled.push((re, rule.name.clone().unwrap_or_else(|| rule.pattern.clone())));
+        }
+        Ok(Self { rules: compiled })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// The group `id` belongs to.
+    pub fn of(&self, id: &str) -> Option<usize> {
+        self.rules.iter().position(|(re, _)| re.is_match(id))
+    }
+
+    pub fn name(&self, group: usize) -> &str {
+        &self.rules[group].1
+    }
+
+    pub fn pattern(&self, group: usize) -> &str {
+        self.rules[group].0.as_str()
+    }
+}
@@ -0,0 +1,244 @@
+//! The subcommands without a UI: `validate`, `metrics`, `stats`, `query`
+//! and `preview`, and each side of `diff`.
+//!
+//! Each reads its input in full through [`load::load`], set up from the
+//! flags and config as the viewer's would be: the same aliases, duplicate
+//! policy, assertions, alerts, watches and `--break-cycles`, with secrets
+//! masked out of the warnings.
+use std::{
+    io::{self, Write},
+    sync::{atomic::Ordering, Arc},
+};
+
+use color_eyre::eyre::{bail, Result};
+use itertools::Itertools;
+use petgraph::stable_graph::NodeIndex;
+
+use crate::alerts::Alert;
+use crate::alias::Aliases;
+use crate::assertions::{Assertions, Violation};
+use crate::auth::Credentials;
+use crate::config::Config;
+use crate::cycles::Cycles;
+use crate::duplicate::DuplicatePolicy;
+use crate::ingest::{Ingest, IngestMsg};
+use crate::load::{self, Loaded};
+use crate::metrics::{Metrics, MetricsFormat};
+use crate::query::{self, Query};
+use crate::stats::{Stats, StatsFormat};
+use crate::view::{self, SortOrder};
+use crate::watch::Watches;
+use crate::{cycle_text, emit, extract, preview, spawn_input, Args, GraphModel, InputFormat, QueryFormat};
+
+/// `input` read in full, with what the checks found in it.
+pub struct Headless {
+    title: String,
+    loaded: Loaded,
+    on_duplicate: DuplicatePolicy,
+    break_cycles: bool,
+    violations: Vec<Violation>,
+    alerts: Vec<Alert>,
+    cycles: Cycles,
+    watches: Watches,
+}
+
+impl Headless {
+    /// Read `input`; `cred_warnings` are reported with its own.
+    pub fn read(input: &str, args: &Args, config: &Config, creds: &mut Credentials, cred_warnings: Vec<String>) -> Result<Self> {
+        let mut ingest = Ingest::new();
+        if args.format == InputFormat::Regex {
+            ingest.set_adapter(Arc::new(extract::RegexAdapter::new(&config.extract)?));
+        }
+        spawn_input(&ingest, input, false, args.format, creds)?;
+        let aliases = Aliases::new(&config.aliases)?;
+        let assertions = args.assertions.as_deref().or(config.assertions_file.as_deref()).map(Assertions::load).transpose()?;
+        let mut loaded = load::load(&ingest, aliases.as_ref(), args.on_duplicate, creds.secrets());
+        loaded.warnings.splice(0..0, cred_warnings.into_iter().map(|w| load::mask_secrets(w, creds.secrets())));
+        let gm = &loaded.gm;
+        let violations = assertions.map_or_else(Vec::new, |a| a.check(gm));
+        let cycles = Cycles::find(gm);
+        let alerts = if config.alerts.is_empty() { Vec::new() } else { config.alerts.check(gm, args.break_cycles.then_some(&cycles)) };
+        let mut watches = Watches::new(if args.watch_exprs.is_empty() { &config.watches } else { &args.watch_exprs });
+        watches.recount(gm, false);
+        Ok(Self {
+            title: input.to_string(),
+            loaded,
+            on_duplicate: args.on_duplicate,
+            break_cycles: args.break_cycles,
+            violations,
+            alerts,
+            cycles,
+            watches,
+        })
+    }
+
+    /// `diff`: the graph and its warnings.
+    pub fn into_side(self) -> (GraphModel, Vec<String>) {
+        (self.loaded.gm, self.loaded.warnings)
+    }
+
+    /// What `stats` and `query` print on stderr, away from their output:
+    /// warnings and assertion violations.
+    fn report_problems(&self) {
+        for w in &self.loaded.warnings {
+            eprintln!("{}", w);
+        }
+        if self.loaded.warnings_elided > 0 {
+            eprintln!("[warn] ... and {} more warnings", self.loaded.warnings_elided);
+        }
+        for v in &self.violations {
+            eprintln!("[violation] {}", v);
+        }
+    }
+}
+
+/// `validate`: report everything reading the input found.
+pub fn validate(h: Headless) -> Result<()> {
+    for w in &h.loaded.warnings {
+        println!("{}", w);
+    }
+    if h.loaded.warnings_elided > 0 {
+        println!("[warn] ... and {} more warnings", h.loaded.warnings_elided);
+    }
+    for v in &h.violations {
+        println!("[violation] {}", v);
+    }
+    // Alerts are reported but, being heuristics, don't fail validation.
+    for a in &h.alerts {
+        println!("[alert] {}", a);
+    }
+    // Nor do cycles, which earlier versions accepted without a word.
+    for ids in &h.cycles.groups {
+        println!("{}", cycle_text(ids));
+    }
+    let gm = &h.loaded.gm;
+    for c in &gm.conflicts {
+        println!("[conflict] {}", c);
+    }
+    if gm.conflicts_elided > 0 {
+        println!("[conflict] ... and {} more conflicts", gm.conflicts_elided);
+    }
+    let warnings = h.loaded.warning_count();
+    let conflicts = gm.conflicts.len() + gm.conflicts_elided;
+    println!("{}: {} nodes, {} violations, {} conflicts, {} warnings", h.title, gm.graph.node_count(), h.violations.len(), conflicts, warnings);
+    // Conflicts resolved by a policy are reported, but only fail under `error`.
+    let failed_conflicts = h.on_duplicate == DuplicatePolicy::Error && conflicts > 0;
+    if warnings > 0 || !h.violations.is_empty() || failed_conflicts {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+/// `metrics`: print the numbers on stdout.
+pub fn metrics(h: Headless, format: MetricsFormat) -> Result<()> {
+    let gm = &h.loaded.gm;
+    let mut m = Metrics::measure(gm, h.break_cycles);
+    m.warnings = h.loaded.warning_count();
+    m.conflicts = gm.conflicts.len() + gm.conflicts_elided;
+    m.violations = h.violations.len();
+    m.watches = h.watches.counts().map(|(q, n)| (q.to_string(), n.unwrap_or(0))).collect();
+    let mut out = io::stdout().lock();
+    piped(match format {
+        MetricsFormat::Json => writeln!(out, "{}", m.json(&h.title)),
+        MetricsFormat::Prometheus => write!(out, "{}", m.prometheus(&h.title)),
+    })
+}
+
+/// `stats`: print the graph's shape on stdout (warnings and violations go
+/// to stderr).
+pub fn stats(h: Headless, format: StatsFormat) -> Result<()> {
+    h.report_problems();
+    let stats = Stats::measure(&h.loaded.gm, h.break_cycles);
+    let mut out = io::stdout().lock();
+    piped(match format {
+        StatsFormat::Text => writeln!(out, "{}", stats.lines().join("\n")),
+        StatsFormat::Json => writeln!(out, "{}", stats.json(&h.title)),
+    })
+}
+
+/// Output cut short because the reader went away (`| head`) is not an
+/// error for a command meant to be piped.
+fn piped(written: io::Result<()>) -> Result<()> {
+    match written {
+        Err(err) if err.kind() == io::ErrorKind::BrokenPipe => Ok(()),
+        other => Ok(other?),
+    }
+}
+
+/// `query`: filter as the node list does, and print the matches on stdout
+/// (warnings and violations go to stderr). A filter with an unmatched
+/// parenthesis is refused; one that matches nothing gets the status bar's
+/// "did you mean" on stderr, if there is one, and exit status 1.
+pub fn query(h: Headless, filter: &str, format: QueryFormat, neighbors: bool) -> Result<()> {
+    let q = Query::parse(filter.trim());
+    if let Some(error) = q.error() {
+        bail!("invalid filter `{}`: {}", filter, error);
+    }
+    h.report_problems();
+    let gm = &h.loaded.gm;
+    let matched = view::matching(gm, &q, SortOrder::Stable, |_| true);
+    if matched.is_empty() {
+        if let Some(hint) = query::did_you_mean(&q.did_you_mean(gm)) {
+            eprintln!("[hint] no match, {}", hint);
+            std::process::exit(1);
+        }
+    }
+    piped(write_matches(gm, &matched, format, neighbors))
+}
+
+fn write_matches(gm: &GraphModel, matched: &[NodeIndex], format: QueryFormat, neighbors: bool) -> io::Result<()> {
+    let json = |idx: NodeIndex| if neighbors { emit::node_json(gm, idx) } else { serde_json::to_value(&gm.graph[idx]).unwrap_or_default() };
+    let mut out = io::stdout().lock();
+    match format {
+        QueryFormat::Json => writeln!(out, "{}", serde_json::Value::Array(matched.iter().map(|&idx| json(idx)).collect()))?,
+        QueryFormat::Jsonl => {
+            for &idx in matched {
+                writeln!(out, "{}", json(idx))?;
+            }
+        }
+        QueryFormat::Text => {
+            for &idx in matched {
+                write!(out, "{}", gm.graph[idx].display_label())?;
+                if neighbors {
+                    let ids = |nodes: Vec<NodeIndex>| nodes.iter().map(|&n| gm.graph[n].id.to_string()).join(", ");
+                    write!(out, "  (parents: {}; children: {})", ids(gm.parents_of(idx)), ids(gm.children_of(idx)))?;
+                }
+                writeln!(out)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// `preview`: tally events as they arrive instead of applying them.
+pub fn preview(input: &str, ingest: &Ingest) -> Result<()> {
+    let mut summary = preview::Preview::default();
+    let mut malformed = Vec::new();
+    loop {
+        let live = ingest.stats().live.load(Ordering::Relaxed);
+        let (msgs, backlog) = ingest.drain(load::BUDGET);
+        if msgs.is_empty() && live == 0 {
+            break;
+        }
+        for msg in msgs {
+            match msg {
+                IngestMsg::Event(ev, origin) => summary.add(&ev, origin.raw.as_deref()),
+                IngestMsg::Snapshot { events, .. } => events.iter().for_each(|ev| summary.add(ev, None)),
+                IngestMsg::Warning(w) => malformed.push(w),
+                IngestMsg::SourceOpened { .. } => {}
+            }
+        }
+        if !backlog {
+            std::thread::sleep(load::POLL);
+        }
+    }
+    println!("{}:", input);
+    for line in summary.report() {
+        println!("  {}", line);
+    }
+    println!("  malformed lines: {}", ingest.stats().malformed.load(Ordering::Relaxed));
+    for w in malformed.iter().take(5) {
+        println!("    {}", w);
+    }
+    Ok(())
+}
@@ -0,0 +1,88 @@
+//! Streaming HTTP(S) input.
+//!
+//! The response body is handed to the regular JSONL pump as it arrives, so
+//! artifacts in object storage load like local files. If the connection drops
+//! mid-body, the request is retried with a `Range` header starting at the
+//! last byte read; a server that ignores ranges resends the whole body and
+//! the already-seen prefix is skipped instead.
+use std::{
+    io::{self, Read},
+    thread,
+    time::Duration,
+};
+
+use color_eyre::eyre::{eyre, Result};
+
+/// Reconnect attempts before a broken body is reported as a read error.
+const MAX_RESUMES: u32 = 5;
+
+type Body = Box<dyn Read + Send + Sync + 'static>;
+
+/// A response body that transparently resumes after connection failures.
+pub struct ResumingBody {
+    agent: ureq::Agent,
+    url: String,
+    auth: Option<String>, // Authorization header value
+    body: Body,
+    offset: u64, // bytes handed out so far
+    resumes: u32,
+}
+
+impl ResumingBody {
+    pub fn open(url: &str, auth: Option<String>) -> Result<Self> {
+        let agent = ureq::AgentBuilder::new().build();
+        let body = request(&agent, url, auth.as_deref(), 0)?.ok_or_else(|| eyre!("{}: empty response", url))?;
+        Ok(Self { agent, url: url.to_string(), auth, body, offset: 0, resumes: 0 })
+    }
+}
+
+/// GET `url` from byte `offset` on. `None` means there is nothing past `offset`.
+fn request(agent: &ureq::Agent, url: &str, auth: Option<&str>, offset: u64) -> Result<Option<Body>> {
+    let mut req = agent.get(url);
+    if let Some(auth) = auth {
+        req = req.set("Authorization", auth);
+    }
+    if offset > 0 {
+        req = req.set("Range", &format!("bytes={}-", offset));
+    }
+    let resp = match req.call() {
+        Ok(resp) => resp,
+        Err(ureq::Error::Status(416, _)) if offset > 0 => return Ok(None),
+        Err(err) => return Err(eyre!("GET {} failed: {}", url, err)),
+    };
+    let partial = resp.status() == 206;
+    let mut body = resp.into_reader();
+    if offset > 0 && !partial {
+        // Range ignored: skip what we already delivered.
+        let skipped = io::copy(&mut (&mut body).take(offset), &mut io::sink())?;
+        if skipped < offset {
+            return Ok(None);
+        }
+    }
+    Ok(Some(body))
+}
+
+impl Read for ResumingBody {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            let err = match self.body.read(buf) {
+                Ok(n) => {
+                    self.offset += n as u64;
+                    return Ok(n);
+                }
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                Err(err) => err,
+            };
+            if self.resumes >= MAX_RESUMES {
+                return Err(err);
+            }
+            self.resumes += 1;
+            thread::sleep(Duration::from_millis(250 << self.resumes));
+            match request(&self.agent, &self.url, self.auth.as_deref(), self.offset) {
+                Ok(Some(body)) => self.body = body,
+                Ok(None) => return Ok(0),
+                Err(_) => continue, // counts as another attempt on the next failed read
+            }
+        }
+    }
+}
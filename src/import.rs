@@ -0,0 +1,21 @@
+//! Importers: build a graph from another tool's view of a project instead of
+//! a JSONL stream (`riff-dag-tui cargo`, `riff-dag-tui git`, ...). Each one returns the events to
+//! feed through the normal ingest path, so the result can be filtered and
+//! explored like any other graph.
+pub mod airflow;
+pub mod build;
+pub mod cargo;
+pub mod dbt;
+pub mod git;
+pub mod k8s;
+pub mod nix;
+pub mod npm;
+pub mod terraform;
+
+use crate::EventLine;
+
+/// A finished import: the tab title plus the graph as events, nodes first.
+pub struct Imported {
+    pub title: String,
+    pub events: Vec<EventLine>,
+}
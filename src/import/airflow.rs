@@ -0,0 +1,60 @@
+//! `riff-dag-tui airflow`: task dependencies of a serialized Airflow DAG.
+//!
+//! Reads either the serialized form Airflow stores for the webserver
+//! (`{"dag": {"tasks": [...]}}`, as in the `serialized_dag` table) or the
+//! REST API's `GET /dags/{dag_id}/tasks` response. Each task is a node
+//! (task id as id, DAG id as span, operator as tag) with an edge to each of
+//! its `downstream_task_ids`.
+use std::fs;
+
+use color_eyre::eyre::{bail, Result, WrapErr};
+use serde_json::{Map, Value};
+
+use super::Imported;
+use crate::EventLine;
+
+pub fn import(path: &str) -> Result<Imported> {
+    let text = fs::read_to_string(path).wrap_err_with(|| format!("failed to read {}", path))?;
+    let doc: Value = serde_json::from_str(&text).wrap_err_with(|| format!("invalid Airflow DAG {}", path))?;
+    let dag = if doc["dag"].is_object() { &doc["dag"] } else { &doc };
+    let Some(tasks) = dag["tasks"].as_array() else {
+        bail!("{} has no `tasks` list (expected a serialized DAG or a /dags/{{id}}/tasks response)", path);
+    };
+    let dag_id = dag["_dag_id"].as_str().or(dag["dag_id"].as_str()).unwrap_or("dag").to_string();
+
+    let mut events = Vec::new();
+    let mut edges = Vec::new();
+    for task in tasks {
+        // Airflow 2.x wraps each serialized operator as {"__type": ..., "__var": {...}}.
+        let task = if task["__var"].is_object() { &task["__var"] } else { task };
+        let Some(id) = task["task_id"].as_str() else { continue };
+        let operator = task["_task_type"].as_str().or(task["class_ref"]["class_name"].as_str()).or(task["task_type"].as_str());
+        let mut metadata = Map::new();
+        for key in ["owner", "pool", "queue", "trigger_rule", "retries", "_task_module"] {
+            match &task[key] {
+                Value::Null => {}
+                Value::String(s) => {
+                    metadata.insert(key.trim_start_matches('_').into(), s.clone().into());
+                }
+                other => {
+                    metadata.insert(key.trim_start_matches('_').into(), other.to_string().into());
+                }
+            }
+        }
+        events.push(EventLine::Node {
+            id: id.to_string(),
+            label: None,
+            span: Some(dag_id.clone()),
+            tags: operator.map(|o| vec![o.to_string()]),
+            ts: None,
+            metadata: Some(metadata),
+            score: None,
+            extra: serde_json::Map::new(),
+        });
+        for down in task["downstream_task_ids"].as_array().into_iter().flatten().filter_map(Value::as_str) {
+            edges.push(EventLine::edge(id.to_string(), down.to_string()));
+        }
+    }
+    events.extend(edges);
+    Ok(Imported { title: format!("airflow: {}", dag_id), events })
+}
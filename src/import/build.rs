@@ -0,0 +1,336 @@
+//! `riff-dag-tui build`: the target dependency graph of a Ninja or Make build.
+//!
+//! `build.ninja` files are parsed directly (with `include`/`subninja` and
+//! top-level variable expansion); Makefiles are read through GNU Make's own
+//! database (`make -pRrq`), and a saved `make -p` dump works too. Every
+//! target and input becomes a node and every dependency an edge input ->
+//! target. Produced targets have span `target` and their rule as a tag,
+//! leaves have span `source`.
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use color_eyre::eyre::{bail, Result, WrapErr};
+use serde_json::Map;
+
+use super::Imported;
+use crate::EventLine;
+
+/// What the graph knows about one target.
+#[derive(Default)]
+struct Target {
+    rule: Option<String>,
+    inputs: Vec<String>,
+    metadata: Map<String, serde_json::Value>,
+}
+
+pub fn import(path: &str) -> Result<Imported> {
+    let path = Path::new(path);
+    let file = if path.is_dir() {
+        ["build.ninja", "GNUmakefile", "makefile", "Makefile"]
+            .iter()
+            .map(|name| path.join(name))
+            .find(|p| p.is_file())
+            .ok_or_else(|| color_eyre::eyre::eyre!("no build.ninja or Makefile in {}", path.display()))?
+    } else {
+        path.to_path_buf()
+    };
+    let targets = if file.extension().is_some_and(|e| e == "ninja") {
+        parse_ninja(&file)?
+    } else {
+        let text = fs::read_to_string(&file).wrap_err_with(|| format!("failed to read {}", file.display()))?;
+        if text.starts_with("# GNU Make") || text.starts_with("# make") {
+            parse_make_db(&text)
+        } else {
+            parse_make_db(&make_database(&file)?)
+        }
+    };
+    Ok(Imported { title: format!("build: {}", file.display()), events: to_events(targets) })
+}
+
+fn to_events(targets: BTreeMap<String, Target>) -> Vec<EventLine> {
+    let mut events = Vec::new();
+    let mut leaves = Vec::new();
+    for (name, target) in &targets {
+        for input in &target.inputs {
+            if !targets.contains_key(input) && !leaves.contains(input) {
+                leaves.push(input.clone());
+            }
+        }
+        events.push(EventLine::Node {
+            id: name.clone(),
+            label: None, // the path is the id; a label would only repeat it
+            span: Some("target".to_string()),
+            tags: target.rule.clone().map(|r| vec![r]),
+            ts: None,
+            metadata: (!target.metadata.is_empty()).then(|| target.metadata.clone()),
+            score: None,
+            extra: serde_json::Map::new(),
+        });
+    }
+    for leaf in leaves {
+        events.push(EventLine::Node {
+            id: leaf,
+            label: None,
+            span: Some("source".to_string()),
+            tags: None,
+            ts: None,
+            metadata: None,
+            score: None,
+            extra: serde_json::Map::new(),
+        });
+    }
+    for (name, target) in targets {
+        for input in target.inputs {
+            events.push(EventLine::edge(input, name.clone()));
+        }
+    }
+    events
+}
+
+// --- Ninja ---
+
+/// Logical lines: `$` + newline joins with the next line, comments dropped.
+fn ninja_lines(text: &str) -> Vec<String> {
+    let mut out = Vec::new();
+    let mut cur = String::new();
+    for raw in text.lines() {
+        let line = if cur.is_empty() { raw } else { raw.trim_start() };
+        if let Some(stripped) = line.strip_suffix('$').filter(|l| !l.ends_with('$')) {
+            cur.push_str(stripped);
+            continue;
+        }
+        cur.push_str(line);
+        if !cur.trim_start().starts_with('#') && !cur.trim().is_empty() {
+            out.push(std::mem::take(&mut cur));
+        }
+        cur.clear();
+    }
+    out
+}
+
+/// Expand `$var` / `${var}` and the `$ `, `$:`, `$$` escapes.
+fn ninja_expand(s: &str, vars: &HashMap<String, String>) -> String {
+    let mut out = String::new();
+    let mut chars = s.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '$' {
+            out.push(c);
+            continue;
+        }
+        match chars.peek().copied() {
+            Some(e @ (' ' | ':' | '$')) => {
+                out.push(e);
+                chars.next();
+            }
+            Some('{') => {
+                chars.next();
+                let name: String = chars.by_ref().take_while(|&c| c != '}').collect();
+                out.push_str(vars.get(&name).map(String::as_str).unwrap_or_default());
+            }
+            Some(c) if c.is_ascii_alphanumeric() || c == '_' || c == '-' => {
+                let mut name = String::new();
+                while let Some(&c) = chars.peek().filter(|c| c.is_ascii_alphanumeric() || **c == '_' || **c == '-') {
+                    name.push(c);
+                    chars.next();
+                }
+                out.push_str(vars.get(&name).map(String::as_str).unwrap_or_default());
+            }
+            _ => {}
+        }
+    }
+    out
+}
+
+/// Split on unescaped spaces; `$ ` stays part of the path.
+fn ninja_paths(s: &str, vars: &HashMap<String, String>) -> Vec<String> {
+    let mut parts = Vec::new();
+    let mut cur = String::new();
+    let mut escaped = false;
+    for c in s.chars() {
+        if c == ' ' && !escaped {
+            if !cur.is_empty() {
+                parts.push(ninja_expand(&std::mem::take(&mut cur), vars));
+            }
+            continue;
+        }
+        escaped = c == '$' && !escaped;
+        cur.push(c);
+    }
+    if !cur.is_empty() {
+        parts.push(ninja_expand(&cur, vars));
+    }
+    parts
+}
+
+/// Index of the `:` that ends the outputs (escaped `$:` doesn't count).
+fn unescaped_colon(s: &str) -> Option<usize> {
+    let mut escaped = false;
+    for (i, c) in s.char_indices() {
+        if c == ':' && !escaped {
+            return Some(i);
+        }
+        escaped = c == '$' && !escaped;
+    }
+    None
+}
+
+fn parse_ninja(file: &Path) -> Result<BTreeMap<String, Target>> {
+    let mut targets = BTreeMap::new();
+    let mut vars = HashMap::new();
+    let mut commands = HashMap::new();
+    read_ninja(file, &mut vars, &mut commands, &mut targets)?;
+    Ok(targets)
+}
+
+fn read_ninja(
+    file: &Path,
+    vars: &mut HashMap<String, String>,
+    commands: &mut HashMap<String, String>,
+    targets: &mut BTreeMap<String, Target>,
+) -> Result<()> {
+    let text = fs::read_to_string(file).wrap_err_with(|| format!("failed to read {}", file.display()))?;
+    let dir = file.parent().map(Path::to_path_buf).unwrap_or_default();
+    let mut rule: Option<String> = None; // rule whose bindings are being read
+    let mut building: Vec<String> = Vec::new(); // outputs whose bindings are being read
+    for line in ninja_lines(&text) {
+        let indented = line.starts_with(' ') || line.starts_with('\t');
+        let line = line.trim();
+        if indented {
+            if let Some((key, value)) = line.split_once('=') {
+                let (key, value) = (key.trim(), value.trim());
+                match &rule {
+                    Some(r) if key == "command" => {
+                        commands.insert(r.clone(), value.to_string());
+                    }
+                    Some(_) => {}
+                    None if key == "description" || key == "pool" => {
+                        for out in &building {
+                            if let Some(t) = targets.get_mut(out) {
+                                t.metadata.insert(key.into(), ninja_expand(value, vars).into());
+                            }
+                        }
+                    }
+                    None => {}
+                }
+            }
+            continue;
+        }
+        rule = None;
+        building.clear();
+        let (keyword, rest) = line.split_once(' ').unwrap_or((line, ""));
+        match keyword {
+            "rule" => rule = Some(rest.trim().to_string()),
+            "build" => {
+                let Some(colon) = unescaped_colon(rest) else { continue };
+                let (outs, deps) = (&rest[..colon], &rest[colon + 1..]);
+                // Implicit outputs (after `|`) are targets like any other.
+                let outs: Vec<String> = ninja_paths(outs, vars).into_iter().filter(|o| o != "|").collect();
+                let mut deps = ninja_paths(deps, vars).into_iter();
+                let rule_name = deps.next().unwrap_or_default();
+                let inputs: Vec<String> = deps.filter(|d| d != "|" && d != "||" && d != "|@").collect();
+                for out in &outs {
+                    let target = targets.entry(out.clone()).or_default();
+                    target.rule = Some(rule_name.clone());
+                    target.inputs.extend(inputs.iter().cloned());
+                    if let Some(cmd) = commands.get(&rule_name) {
+                        target.metadata.insert("command".into(), cmd.clone().into());
+                    }
+                }
+                building = outs;
+            }
+            "include" | "subninja" => {
+                let inc: PathBuf = dir.join(ninja_expand(rest.trim(), vars));
+                read_ninja(&inc, vars, commands, targets)?;
+            }
+            "default" | "pool" => {}
+            _ => {
+                if let Some((key, value)) = line.split_once('=') {
+                    let value = ninja_expand(value.trim(), vars);
+                    vars.insert(key.trim().to_string(), value);
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+// --- Make ---
+
+/// GNU Make's database for `makefile`, without running any recipe.
+fn make_database(makefile: &Path) -> Result<String> {
+    let dir = makefile.parent().filter(|d| !d.as_os_str().is_empty()).unwrap_or(Path::new("."));
+    let out = Command::new("make")
+        .arg("-pRrq")
+        .arg("-f")
+        .arg(makefile.file_name().unwrap_or(makefile.as_os_str()))
+        .current_dir(dir)
+        .output()
+        .wrap_err("failed to run `make -p`")?;
+    // -q exits 1 when targets are out of date; only 2 is an error.
+    if out.status.code() == Some(2) && out.stdout.is_empty() {
+        bail!("`make -p` failed:\n{}", String::from_utf8_lossy(&out.stderr).trim());
+    }
+    Ok(String::from_utf8_lossy(&out.stdout).into_owned())
+}
+
+/// Rules from the `# Files` section of a `make -p` dump.
+fn parse_make_db(text: &str) -> BTreeMap<String, Target> {
+    let mut targets: BTreeMap<String, Target> = BTreeMap::new();
+    let mut in_files = false;
+    let mut skip_next = false;
+    let mut last: Vec<String> = Vec::new();
+    let mut phony = Vec::new();
+    for line in text.lines() {
+        if line.starts_with("# Files") {
+            in_files = true;
+            continue;
+        }
+        if line.starts_with("# files hash-table stats") || line.starts_with("# VPATH Search Paths") {
+            in_files = false;
+        }
+        if !in_files {
+            continue;
+        }
+        if line == "# Not a target:" {
+            skip_next = true;
+            continue;
+        }
+        if let Some(rest) = line.strip_prefix("#  recipe to execute (from ") {
+            let from = rest.trim_end_matches("):").trim_end_matches(')');
+            for t in &last {
+                if let Some(target) = targets.get_mut(t) {
+                    target.metadata.insert("recipe".into(), from.into());
+                }
+            }
+            continue;
+        }
+        if line.starts_with('#') || line.starts_with('\t') || line.trim().is_empty() {
+            continue;
+        }
+        let Some((names, prereqs)) = line.split_once(':').filter(|(_, p)| !p.starts_with('=')) else { continue };
+        if std::mem::take(&mut skip_next) {
+            continue;
+        }
+        let prereqs = prereqs.trim_start_matches(':'); // double-colon rules
+        if names.trim() == ".PHONY" {
+            phony.extend(prereqs.split_whitespace().map(str::to_string));
+            continue;
+        }
+        last = names.split_whitespace().filter(|n| !n.starts_with('.') && !n.contains('%')).map(str::to_string).collect();
+        let inputs: Vec<String> = prereqs.split_whitespace().filter(|p| *p != "|").map(str::to_string).collect();
+        for name in &last {
+            let target = targets.entry(name.clone()).or_default();
+            target.inputs.extend(inputs.iter().cloned());
+        }
+    }
+    for name in phony {
+        if let Some(target) = targets.get_mut(&name) {
+            target.rule = Some("phony".to_string());
+        }
+    }
+    targets
+}
@@ -0,0 +1,100 @@
+//! `riff-dag-tui cargo`: the crate dependency graph from `cargo metadata`.
+//!
+//! One node per resolved package (`name@version`), one edge from each
+//! package to every dependency it resolves to. The span says where a crate
+//! comes from (`workspace`, `crates.io`, `git`, `path`), the tags are its
+//! enabled features, and version, source, license and description are kept
+//! as metadata.
+use std::{collections::HashMap, process::Command};
+
+use color_eyre::eyre::{bail, eyre, Result, WrapErr};
+use serde_json::{Map, Value};
+
+use super::Imported;
+use crate::EventLine;
+
+pub fn import(manifest_path: Option<&str>) -> Result<Imported> {
+    let mut cmd = Command::new(std::env::var_os("CARGO").unwrap_or_else(|| "cargo".into()));
+    cmd.args(["metadata", "--format-version", "1"]);
+    if let Some(path) = manifest_path {
+        cmd.arg("--manifest-path").arg(path);
+    }
+    let out = cmd.output().wrap_err("failed to run `cargo metadata`")?;
+    if !out.status.success() {
+        bail!("`cargo metadata` failed:\n{}", String::from_utf8_lossy(&out.stderr).trim());
+    }
+    let meta: Value = serde_json::from_slice(&out.stdout).wrap_err("unexpected `cargo metadata` output")?;
+    from_metadata(&meta)
+}
+
+fn from_metadata(meta: &Value) -> Result<Imported> {
+    let str_of = |v: &Value, key: &str| v.get(key).and_then(Value::as_str).unwrap_or_default().to_string();
+    let members: Vec<&str> = meta["workspace_members"].as_array().into_iter().flatten().filter_map(Value::as_str).collect();
+    let resolve = meta["resolve"].as_object().ok_or_else(|| eyre!("`cargo metadata` output has no resolve graph"))?;
+    let resolved: HashMap<&str, &Value> = resolve["nodes"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|n| Some((n["id"].as_str()?, n)))
+        .collect();
+
+    let mut ids = HashMap::new(); // package id -> node id
+    let mut events = Vec::new();
+    for pkg in meta["packages"].as_array().into_iter().flatten() {
+        let pkg_id = pkg["id"].as_str().unwrap_or_default();
+        let Some(res) = resolved.get(pkg_id) else { continue }; // not part of this build
+        let (name, version) = (str_of(pkg, "name"), str_of(pkg, "version"));
+        let id = format!("{}@{}", name, version);
+        let source = str_of(pkg, "source");
+        let origin = if members.contains(&pkg_id) {
+            "workspace"
+        } else if source.starts_with("registry+https://github.com/rust-lang/crates.io-index") || source.starts_with("sparse+https://index.crates.io") {
+            "crates.io"
+        } else if source.starts_with("git+") {
+            "git"
+        } else if source.is_empty() {
+            "path"
+        } else {
+            "registry"
+        };
+        let features: Vec<String> = res["features"].as_array().into_iter().flatten().filter_map(|f| f.as_str().map(str::to_string)).collect();
+
+        let mut metadata = Map::new();
+        metadata.insert("version".into(), version.into());
+        for key in ["source", "license", "description", "rust_version"] {
+            let value = str_of(pkg, key);
+            if !value.is_empty() {
+                metadata.insert(key.into(), value.into());
+            }
+        }
+        if !features.is_empty() {
+            metadata.insert("features".into(), features.join(", ").into());
+        }
+        events.push(EventLine::Node {
+            id: id.clone(),
+            label: Some(name),
+            span: Some(origin.to_string()),
+            tags: (!features.is_empty()).then_some(features),
+            ts: None,
+            metadata: Some(metadata),
+            score: None,
+            extra: serde_json::Map::new(),
+        });
+        ids.insert(pkg_id, id);
+    }
+
+    let mut edges = Vec::new();
+    for (pkg_id, res) in &resolved {
+        let Some(from) = ids.get(pkg_id) else { continue };
+        for dep in res["deps"].as_array().into_iter().flatten() {
+            if let Some(to) = dep["pkg"].as_str().and_then(|d| ids.get(d)) {
+                edges.push(EventLine::edge(from.clone(), to.clone()));
+            }
+        }
+    }
+    events.extend(edges);
+
+    let root = resolve["root"].as_str().and_then(|r| ids.get(r)).cloned();
+    let title = format!("cargo: {}", root.unwrap_or_else(|| "workspace".to_string()));
+    Ok(Imported { title, events })
+}
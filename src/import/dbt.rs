@@ -0,0 +1,62 @@
+//! `riff-dag-tui dbt`: model lineage from a dbt `manifest.json`.
+//!
+//! Every model, seed, snapshot, source, exposure and metric is a node
+//! (`unique_id` as id, resource type as span, dbt tags as tags) and every
+//! `depends_on` entry an edge upstream -> downstream, so a node's parents are
+//! its upstream lineage. Tests are left out unless asked for; they tend to
+//! outnumber everything else.
+use std::fs;
+
+use color_eyre::eyre::{Result, WrapErr};
+use serde_json::{Map, Value};
+
+use super::Imported;
+use crate::EventLine;
+
+pub fn import(path: &str, with_tests: bool) -> Result<Imported> {
+    let text = fs::read_to_string(path).wrap_err_with(|| format!("failed to read {}", path))?;
+    let manifest: Value = serde_json::from_str(&text).wrap_err_with(|| format!("invalid dbt manifest {}", path))?;
+
+    let mut events = Vec::new();
+    let mut edges = Vec::new();
+    for section in ["nodes", "sources", "exposures", "metrics", "semantic_models", "saved_queries"] {
+        let Some(entries) = manifest[section].as_object() else { continue };
+        for (id, node) in entries {
+            let kind = node["resource_type"].as_str().unwrap_or(section);
+            if kind == "test" && !with_tests {
+                continue;
+            }
+            let tags: Vec<String> = node["tags"].as_array().into_iter().flatten().filter_map(|t| t.as_str().map(str::to_string)).collect();
+            let mut metadata = Map::new();
+            for (key, value) in [
+                ("package", &node["package_name"]),
+                ("database", &node["database"]),
+                ("schema", &node["schema"]),
+                ("materialized", &node["config"]["materialized"]),
+                ("path", &node["original_file_path"]),
+                ("description", &node["description"]),
+            ] {
+                if let Some(v) = value.as_str().filter(|v| !v.is_empty()) {
+                    metadata.insert(key.into(), v.into());
+                }
+            }
+            events.push(EventLine::Node {
+                id: id.clone(),
+                label: node["name"].as_str().map(str::to_string),
+                span: Some(kind.to_string()),
+                tags: (!tags.is_empty()).then_some(tags),
+                ts: None,
+                metadata: Some(metadata),
+                score: None,
+                extra: serde_json::Map::new(),
+            });
+            for upstream in node["depends_on"]["nodes"].as_array().into_iter().flatten().filter_map(Value::as_str) {
+                edges.push(EventLine::edge(upstream.to_string(), id.clone()));
+            }
+        }
+    }
+    events.extend(edges);
+
+    let project = manifest["metadata"]["project_name"].as_str().unwrap_or(path);
+    Ok(Imported { title: format!("dbt: {}", project), events })
+}
@@ -0,0 +1,75 @@
+//! `riff-dag-tui git`: the commit graph from `git log`.
+//!
+//! One node per commit (abbreviated hash as id, subject as label, author
+//! date as ts), one edge from each parent to its child, so history reads
+//! top-down from old to new. Branch and tag names become tags and the span
+//! is the author, which makes "everything Alice touched" one filter away.
+use std::{collections::HashSet, path::Path, process::Command};
+
+use color_eyre::eyre::{bail, Result, WrapErr};
+use serde_json::Map;
+
+use super::Imported;
+use crate::EventLine;
+
+/// Fields of one `git log` record, separated by US (0x1f).
+const FORMAT: &str = "--format=%h%x1f%p%x1f%an%x1f%ae%x1f%aI%x1f%s%x1f%D%x1f%H";
+
+pub fn import(repo: &str, max_count: usize, all: bool) -> Result<Imported> {
+    let mut cmd = Command::new("git");
+    cmd.arg("-C").arg(repo).args(["log", FORMAT, "--topo-order"]);
+    cmd.arg(format!("--max-count={}", max_count));
+    if all {
+        cmd.arg("--all");
+    }
+    let out = cmd.output().wrap_err("failed to run `git log`")?;
+    if !out.status.success() {
+        bail!("`git log` failed:\n{}", String::from_utf8_lossy(&out.stderr).trim());
+    }
+    let text = String::from_utf8_lossy(&out.stdout);
+
+    let mut nodes = Vec::new();
+    let mut parents = Vec::new();
+    let mut seen = HashSet::new();
+    for record in text.lines() {
+        let f: Vec<&str> = record.split('\x1f').collect();
+        let [short, parent_list, author, email, date, subject, refs, full] = f[..] else { continue };
+        let tags: Vec<String> = refs
+            .split(", ")
+            .filter(|r| !r.is_empty())
+            .map(|r| r.trim_start_matches("HEAD -> ").trim_start_matches("tag: ").to_string())
+            .collect();
+        let mut metadata = Map::new();
+        metadata.insert("commit".into(), full.into());
+        metadata.insert("author".into(), format!("{} <{}>", author, email).into());
+        nodes.push(EventLine::Node {
+            id: short.to_string(),
+            label: Some(subject.to_string()),
+            span: Some(author.to_string()),
+            tags: (!tags.is_empty()).then_some(tags),
+            ts: Some(date.to_string()),
+            metadata: Some(metadata),
+            score: None,
+            extra: serde_json::Map::new(),
+        });
+        seen.insert(short.to_string());
+        for parent in parent_list.split(' ').filter(|p| !p.is_empty()) {
+            parents.push((parent.to_string(), short.to_string()));
+        }
+    }
+    // Parents past --max-count aren't loaded; dropping their edges quietly
+    // beats one warning per boundary commit.
+    let edges = parents
+        .into_iter()
+        .filter(|(parent, _)| seen.contains(parent))
+        .map(|(from, to)| EventLine::edge(from, to));
+    let mut events = nodes;
+    events.extend(edges);
+
+    let name = Path::new(repo)
+        .canonicalize()
+        .ok()
+        .and_then(|p| p.file_name().map(|n| n.to_string_lossy().into_owned()))
+        .unwrap_or_else(|| repo.to_string());
+    Ok(Imported { title: format!("git: {}", name), events })
+}
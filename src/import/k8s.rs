@@ -0,0 +1,198 @@
+//! `riff-dag-tui k8s`: the ownership graph of Kubernetes workloads, from
+//! `ownerReferences` (Deployment -> ReplicaSet -> Pod, CronJob -> Job -> Pod, ...).
+//!
+//! Objects come either from saved `kubectl get -o json` dumps or from
+//! querying the cluster with `kubectl`, which is re-run periodically so the
+//! tab follows rollouts and rescheduled pods. Nodes are `Kind/namespace/name`
+//! with the kind and namespace as tags and the `app.kubernetes.io/name` (or
+//! `app`) label as span; edges point from an owner to what it owns. Owners
+//! that weren't part of the query (custom controllers, say) still get a node.
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    process::Command,
+};
+
+use color_eyre::eyre::{bail, Result, WrapErr};
+use serde_json::{Map, Value};
+
+use super::Imported;
+use crate::EventLine;
+
+/// The workload kinds queried from a live cluster.
+const KINDS: &str = "deployments,replicasets,statefulsets,daemonsets,cronjobs,jobs,pods";
+
+/// Which namespaces to query; `None` is kubectl's current namespace.
+#[derive(Debug, Clone)]
+pub struct Query {
+    pub namespace: Option<String>,
+    pub all_namespaces: bool,
+    pub context: Option<String>,
+}
+
+impl Query {
+    pub fn title(&self) -> String {
+        let scope = match (&self.namespace, self.all_namespaces) {
+            (_, true) => "all namespaces".to_string(),
+            (Some(ns), false) => ns.clone(),
+            (None, false) => "current namespace".to_string(),
+        };
+        match &self.context {
+            Some(ctx) => format!("k8s: {} ({})", ctx, scope),
+            None => format!("k8s: {}", scope),
+        }
+    }
+
+    /// Run `kubectl get` once and build the graph from its output.
+    pub fn run(&self) -> Result<Vec<EventLine>> {
+        let mut cmd = Command::new("kubectl");
+        cmd.args(["get", KINDS, "--output=json"]);
+        if let Some(ctx) = &self.context {
+            cmd.arg("--context").arg(ctx);
+        }
+        if self.all_namespaces {
+            cmd.arg("--all-namespaces");
+        } else if let Some(ns) = &self.namespace {
+            cmd.arg("--namespace").arg(ns);
+        }
+        let out = cmd.output().wrap_err("failed to run `kubectl get`")?;
+        if !out.status.success() {
+            bail!("`kubectl get` failed:\n{}", String::from_utf8_lossy(&out.stderr).trim());
+        }
+        Ok(from_objects(&objects(&String::from_utf8_lossy(&out.stdout))?))
+    }
+}
+
+/// Read saved `kubectl get -o json` output (several documents may be concatenated).
+pub fn import_dumps(paths: &[String]) -> Result<Imported> {
+    let mut items = Vec::new();
+    for path in paths {
+        let text = fs::read_to_string(path).wrap_err_with(|| format!("failed to read {}", path))?;
+        items.extend(objects(&text).wrap_err_with(|| format!("invalid kubectl JSON {}", path))?);
+    }
+    Ok(Imported { title: format!("k8s: {}", paths.join(", ")), events: from_objects(&items) })
+}
+
+/// Every object in `text`, unwrapping `List`s.
+fn objects(text: &str) -> Result<Vec<Value>> {
+    let mut items = Vec::new();
+    for doc in serde_json::Deserializer::from_str(text).into_iter::<Value>() {
+        let mut doc = doc?;
+        match doc.get_mut("items").map(Value::take) {
+            Some(Value::Array(list)) => items.extend(list),
+            _ if doc["metadata"].is_object() => items.push(doc),
+            _ => bail!("JSON is not `kubectl get -o json` output"),
+        }
+    }
+    Ok(items)
+}
+
+fn object_id(kind: &str, namespace: Option<&str>, name: &str) -> String {
+    match namespace {
+        Some(ns) => format!("{}/{}/{}", kind, ns, name),
+        None => format!("{}/{}", kind, name),
+    }
+}
+
+fn from_objects(items: &[Value]) -> Vec<EventLine> {
+    let mut events = Vec::new();
+    let mut by_uid = HashMap::new();
+    let mut ids = HashSet::new();
+    for obj in items {
+        let meta = &obj["metadata"];
+        let (Some(kind), Some(name)) = (obj["kind"].as_str(), meta["name"].as_str()) else { continue };
+        let namespace = meta["namespace"].as_str();
+        let id = object_id(kind, namespace, name);
+        if !ids.insert(id.clone()) {
+            continue;
+        }
+        if let Some(uid) = meta["uid"].as_str() {
+            by_uid.insert(uid.to_string(), id.clone());
+        }
+        let labels = &meta["labels"];
+        let app = labels["app.kubernetes.io/name"].as_str().or_else(|| labels["app"].as_str());
+        let mut tags = vec![kind.to_string()];
+        tags.extend(namespace.map(str::to_string));
+        events.push(EventLine::Node {
+            id: id.clone(),
+            label: Some(name.to_string()),
+            span: app.or(namespace).map(str::to_string),
+            tags: Some(tags),
+            ts: meta["creationTimestamp"].as_str().map(str::to_string),
+            metadata: Some(status(obj)),
+            score: None,
+            extra: serde_json::Map::new(),
+        });
+    }
+
+    let mut edges = Vec::new();
+    let mut linked = HashSet::new(); // objects repeated across dumps are linked once
+    for obj in items {
+        let meta = &obj["metadata"];
+        let (Some(kind), Some(name)) = (obj["kind"].as_str(), meta["name"].as_str()) else { continue };
+        let namespace = meta["namespace"].as_str();
+        let id = object_id(kind, namespace, name);
+        if !linked.insert(id.clone()) {
+            continue;
+        }
+        for owner in meta["ownerReferences"].as_array().into_iter().flatten() {
+            let (Some(owner_kind), Some(owner_name)) = (owner["kind"].as_str(), owner["name"].as_str()) else { continue };
+            let owner_id = match owner["uid"].as_str().and_then(|uid| by_uid.get(uid)) {
+                Some(known) => known.clone(),
+                None => {
+                    // Owners are always in the same namespace (or cluster-scoped).
+                    let stub = object_id(owner_kind, namespace, owner_name);
+                    if ids.insert(stub.clone()) {
+                        let mut tags = vec![owner_kind.to_string()];
+                        tags.extend(namespace.map(str::to_string));
+                        events.push(EventLine::Node {
+                            id: stub.clone(),
+                            label: Some(owner_name.to_string()),
+                            span: namespace.map(str::to_string),
+                            tags: Some(tags),
+                            ts: None,
+                            metadata: None,
+                            score: None,
+                            extra: serde_json::Map::new(),
+                        });
+                    }
+                    stub
+                }
+            };
+            edges.push(EventLine::edge(owner_id, id.clone()));
+        }
+    }
+    events.extend(edges);
+    events
+}
+
+/// The fields worth a glance in the details pane.
+fn status(obj: &Value) -> Map<String, Value> {
+    let mut m = Map::new();
+    if let Some(uid) = obj["metadata"]["uid"].as_str() {
+        m.insert("uid".into(), uid.into());
+    }
+    let status = &obj["status"];
+    if let Some(phase) = status["phase"].as_str() {
+        m.insert("phase".into(), phase.into());
+    }
+    if let Some(node) = obj["spec"]["nodeName"].as_str() {
+        m.insert("node".into(), node.into());
+    }
+    if let Some(statuses) = status["containerStatuses"].as_array() {
+        let restarts: u64 = statuses.iter().filter_map(|c| c["restartCount"].as_u64()).sum();
+        let ready = statuses.iter().filter(|c| c["ready"] == true).count();
+        m.insert("ready".into(), format!("{}/{}", ready, statuses.len()).into());
+        m.insert("restarts".into(), restarts.into());
+    } else if let Some(desired) = obj["spec"]["replicas"].as_u64() {
+        let ready = status["readyReplicas"].as_u64().unwrap_or(0);
+        m.insert("ready".into(), format!("{}/{}", ready, desired).into());
+    } else if let Some(desired) = status["desiredNumberScheduled"].as_u64() {
+        let ready = status["numberReady"].as_u64().unwrap_or(0);
+        m.insert("ready".into(), format!("{}/{}", ready, desired).into());
+    }
+    if let Some(schedule) = obj["spec"]["schedule"].as_str() {
+        m.insert("schedule".into(), schedule.into());
+    }
+    m
+}
@@ -0,0 +1,185 @@
+//! `riff-dag-tui nix`: a Nix closure as a graph.
+//!
+//! Reads `nix path-info --json --recursive --closure-size` output (store
+//! paths with their NAR and closure sizes), `nix derivation show --recursive`
+//! output (the build-time derivation graph) or the DOT graph from
+//! `nix-store --query --graph`. Given an installable or store path instead
+//! of a file, it runs `nix path-info` (or `nix derivation show` with
+//! `--derivation`) itself. Nodes are store path base names (`<hash>-<name>`)
+//! labelled with the name; edges point from a reference to the path that
+//! uses it, so a path's parents are its dependencies.
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs,
+    path::Path,
+    process::Command,
+};
+
+use color_eyre::eyre::{bail, Result, WrapErr};
+use serde_json::{Map, Value};
+
+use super::Imported;
+use crate::{dot, EventLine};
+
+const STORE_DIR: &str = "/nix/store/";
+
+pub fn import(target: &str, derivation: bool) -> Result<Imported> {
+    let from_file = !target.starts_with(STORE_DIR) && Path::new(target).is_file();
+    let text = if from_file {
+        fs::read_to_string(target).wrap_err_with(|| format!("failed to read {}", target))?
+    } else {
+        query(target, derivation)?
+    };
+    let events = if text.trim_start().starts_with(['{', '[']) {
+        let doc: Value = serde_json::from_str(&text).wrap_err_with(|| format!("invalid Nix JSON for {}", target))?;
+        from_json(&doc)?
+    } else {
+        from_dot(&dot::parse(&text).wrap_err_with(|| format!("invalid `nix-store --query --graph` output {}", target))?)
+    };
+    Ok(Imported { title: format!("nix: {}", target), events })
+}
+
+/// Run `nix` for an installable (`nixpkgs#hello`, `./result`, a store path).
+fn query(target: &str, derivation: bool) -> Result<String> {
+    let mut cmd = Command::new("nix");
+    cmd.args(["--extra-experimental-features", "nix-command flakes"]);
+    if derivation {
+        cmd.args(["derivation", "show", "--recursive"]);
+    } else {
+        cmd.args(["path-info", "--json", "--recursive", "--closure-size"]);
+    }
+    let out = cmd.arg(target).output().wrap_err("failed to run `nix`")?;
+    if !out.status.success() {
+        bail!("`nix` failed:\n{}", String::from_utf8_lossy(&out.stderr).trim());
+    }
+    Ok(String::from_utf8_lossy(&out.stdout).into_owned())
+}
+
+/// `/nix/store/<hash>-<name>` -> `<hash>-<name>`.
+fn base_name(path: &str) -> &str {
+    path.trim_end_matches('/').rsplit('/').next().unwrap_or(path)
+}
+
+/// `<hash>-<name>` -> `<name>` (store hashes are 32 characters).
+fn store_name(base: &str) -> &str {
+    match base.get(32..) {
+        Some(rest) if rest.starts_with('-') => &rest[1..],
+        _ => base,
+    }
+}
+
+#[derive(Default)]
+struct StorePath {
+    span: &'static str,
+    tags: Vec<String>,
+    metadata: Map<String, Value>,
+    references: BTreeSet<String>,
+}
+
+fn from_json(doc: &Value) -> Result<Vec<EventLine>> {
+    let doc = if doc["derivations"].is_object() { &doc["derivations"] } else { doc };
+    // path-info emitted a list of {"path": ...} before Nix 2.19 and a map keyed by path since.
+    let entries: Vec<(&str, &Value)> = match doc {
+        Value::Array(list) => list.iter().filter_map(|v| Some((v["path"].as_str()?, v))).collect(),
+        Value::Object(map) => map.iter().map(|(k, v)| (k.as_str(), v)).collect(),
+        _ => bail!("expected `nix path-info --json` or `nix derivation show` output"),
+    };
+    let is_drv = entries.iter().any(|(_, v)| v.get("inputDrvs").is_some() || v.get("builder").is_some());
+    let mut paths: BTreeMap<String, StorePath> = BTreeMap::new();
+    for (path, info) in entries {
+        if info.is_null() {
+            continue; // path-info reports paths that aren't valid as null
+        }
+        let mut node = StorePath::default();
+        node.metadata.insert("path".into(), format!("{}{}", STORE_DIR, base_name(path)).into());
+        if is_drv {
+            node.span = "derivation";
+            if let Some(system) = info["system"].as_str() {
+                node.tags.push(system.to_string());
+            }
+            for (output, out) in info["outputs"].as_object().into_iter().flatten() {
+                node.tags.push(output.clone());
+                if let Some(p) = out["path"].as_str() {
+                    node.metadata.insert(format!("output.{}", output), p.into());
+                }
+            }
+            for key in ["pname", "version"] {
+                if let Some(v) = info["env"][key].as_str() {
+                    node.metadata.insert(key.into(), v.into());
+                }
+            }
+            if let Some(inputs) = info["inputDrvs"].as_object() {
+                node.references.extend(inputs.keys().map(|k| base_name(k).to_string()));
+            }
+            for src in info["inputSrcs"].as_array().into_iter().flatten().filter_map(Value::as_str) {
+                node.references.insert(base_name(src).to_string());
+            }
+        } else {
+            node.span = "path";
+            for (key, name) in [("narSize", "nar_size"), ("closureSize", "closure_size")] {
+                if let Some(size) = info[key].as_u64() {
+                    node.metadata.insert(name.into(), size.into());
+                }
+            }
+            if let Some(deriver) = info["deriver"].as_str() {
+                node.metadata.insert("deriver".into(), deriver.into());
+            }
+            if info["ca"].is_string() {
+                node.tags.push("content-addressed".to_string());
+            }
+            for r in info["references"].as_array().into_iter().flatten().filter_map(Value::as_str) {
+                node.references.insert(base_name(r).to_string());
+            }
+        }
+        paths.insert(base_name(path).to_string(), node);
+    }
+    // Plain sources (`inputSrcs`) have no entry of their own.
+    let missing: BTreeSet<String> = paths.values().flat_map(|p| p.references.iter()).filter(|r| !paths.contains_key(*r)).cloned().collect();
+    for src in missing {
+        let mut node = StorePath { span: "source", ..StorePath::default() };
+        node.metadata.insert("path".into(), format!("{}{}", STORE_DIR, src).into());
+        paths.insert(src, node);
+    }
+
+    let mut events = Vec::new();
+    let mut edges = Vec::new();
+    for (id, node) in paths {
+        for r in &node.references {
+            if *r != id {
+                edges.push(EventLine::edge(r.clone(), id.clone()));
+            }
+        }
+        events.push(EventLine::Node {
+            label: Some(store_name(&id).to_string()),
+            id,
+            span: Some(node.span.to_string()),
+            tags: (!node.tags.is_empty()).then_some(node.tags),
+            ts: None,
+            metadata: Some(node.metadata),
+            score: None,
+            extra: serde_json::Map::new(),
+        });
+    }
+    events.extend(edges);
+    Ok(events)
+}
+
+fn from_dot(graph: &dot::DotGraph) -> Vec<EventLine> {
+    let mut events: Vec<EventLine> = graph
+        .nodes
+        .iter()
+        .map(|node| EventLine::Node {
+            id: node.id.clone(),
+            label: Some(node.attr("label").unwrap_or(store_name(&node.id)).to_string()),
+            span: Some(if node.id.ends_with(".drv") { "derivation" } else { "path" }.to_string()),
+            tags: None,
+            ts: None,
+            metadata: None,
+            score: None,
+            extra: serde_json::Map::new(),
+        })
+        .collect();
+    // nix-store draws each edge from a reference to its referrer already.
+    events.extend(graph.edges.iter().filter(|e| e.from != e.to).map(|e| EventLine::edge(e.from.clone(), e.to.clone())));
+    events
+}
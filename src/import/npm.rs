@@ -0,0 +1,319 @@
+//! `riff-dag-tui npm`: the package graph of a `package-lock.json` or `yarn.lock`.
+//!
+//! Lockfile versions 1–3 of npm are read (nested `dependencies` in v1, the
+//! flat `packages` map since v2, resolved with Node's lookup rules), as are
+//! classic `yarn.lock` files and Yarn 2+ YAML lockfiles. Every installed
+//! package becomes one `name@version` node with its version, resolved URL and
+//! integrity as metadata; edges point from a dependency to the package that
+//! requires it. Dev, optional and peer dependencies are tagged as such.
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    fs,
+    path::{Path, PathBuf},
+};
+
+use color_eyre::eyre::{eyre, Result, WrapErr};
+use serde_json::{Map, Value};
+
+use super::Imported;
+use crate::EventLine;
+
+/// One package as the graph shows it.
+#[derive(Default)]
+struct Package {
+    span: &'static str,
+    tags: BTreeSet<String>,
+    metadata: Map<String, Value>,
+    /// `(name, range, kind)` of every requirement, unresolved.
+    requires: Vec<(String, String, &'static str)>,
+}
+
+pub fn import(path: &str) -> Result<Imported> {
+    let path = Path::new(path);
+    let file: PathBuf = if path.is_dir() {
+        ["package-lock.json", "npm-shrinkwrap.json", "yarn.lock"]
+            .iter()
+            .map(|name| path.join(name))
+            .find(|p| p.is_file())
+            .ok_or_else(|| eyre!("no package-lock.json or yarn.lock in {}", path.display()))?
+    } else {
+        path.to_path_buf()
+    };
+    let text = fs::read_to_string(&file).wrap_err_with(|| format!("failed to read {}", file.display()))?;
+    // The project's own requirements are only complete in package.json.
+    let manifest = file.parent().map(|d| d.join("package.json")).and_then(|p| fs::read_to_string(p).ok());
+    let manifest: Option<Value> = manifest.and_then(|m| serde_json::from_str(&m).ok());
+    let events = if file.file_name().is_some_and(|n| n == "yarn.lock") || !text.trim_start().starts_with('{') {
+        from_yarn(&text, manifest.as_ref())
+    } else {
+        let doc: Value = serde_json::from_str(&text).wrap_err_with(|| format!("invalid lockfile {}", file.display()))?;
+        from_npm(&doc, manifest.as_ref())
+    };
+    Ok(Imported { title: format!("npm: {}", file.display()), events })
+}
+
+/// Requirements listed in a package.json-shaped object; `prod_field` is
+/// `dependencies`, or `requires` in a version 1 lockfile.
+fn requirements(v: &Value, prod_field: &str) -> Vec<(String, String, &'static str)> {
+    let mut out = Vec::new();
+    for (field, kind) in [(prod_field, "prod"), ("devDependencies", "dev"), ("optionalDependencies", "optional"), ("peerDependencies", "peer")] {
+        for (name, range) in v[field].as_object().into_iter().flatten() {
+            out.push((name.clone(), range.as_str().unwrap_or_default().to_string(), kind));
+        }
+    }
+    out
+}
+
+fn package(version: &str, info: &Value, span: &'static str) -> Package {
+    let mut p = Package { span, ..Package::default() };
+    p.metadata.insert("version".into(), version.into());
+    for key in ["resolved", "integrity", "license"] {
+        if let Some(v) = info[key].as_str() {
+            p.metadata.insert(key.into(), v.into());
+        }
+    }
+    for flag in ["dev", "optional", "peer"] {
+        if info[flag] == true {
+            p.tags.insert(flag.to_string());
+        }
+    }
+    p
+}
+
+/// `node_modules/a/node_modules/@s/b` -> `@s/b`.
+fn module_name(location: &str) -> &str {
+    match location.rfind("node_modules/") {
+        Some(i) => &location[i + "node_modules/".len()..],
+        None => location.rsplit('/').next().unwrap_or(location),
+    }
+}
+
+fn from_npm(doc: &Value, manifest: Option<&Value>) -> Vec<EventLine> {
+    // location (`node_modules/a`, or a nesting path for v1) -> (id, package)
+    let mut locations: BTreeMap<String, (String, Package)> = BTreeMap::new();
+    if let Some(packages) = doc["packages"].as_object() {
+        let mut links = HashMap::new();
+        for (loc, info) in packages {
+            if info["link"] == true {
+                if let Some(target) = info["resolved"].as_str() {
+                    links.insert(loc.clone(), target.to_string());
+                }
+                continue;
+            }
+            let name = info["name"].as_str().map(str::to_string).unwrap_or_else(|| {
+                if loc.is_empty() { doc["name"].as_str().unwrap_or("root").to_string() } else { module_name(loc).to_string() }
+            });
+            let version = info["version"].as_str().unwrap_or("0.0.0");
+            let span = if loc.is_empty() { "root" } else if loc.contains("node_modules/") { "package" } else { "workspace" };
+            let mut p = package(version, info, span);
+            p.requires = requirements(info, "dependencies");
+            locations.insert(loc.clone(), (format!("{}@{}", name, version), p));
+        }
+        // Workspace symlinks resolve to the linked folder's package.
+        for (loc, target) in links {
+            if let Some((id, _)) = locations.get(&target) {
+                let id = id.clone();
+                locations.insert(loc, (id, Package::default()));
+            }
+        }
+    } else {
+        // lockfileVersion 1: nested `dependencies`, where `requires` maps names to ranges.
+        let name = doc["name"].as_str().unwrap_or("root");
+        let version = doc["version"].as_str().unwrap_or("0.0.0");
+        let mut root = Package { span: "root", ..Package::default() };
+        root.metadata.insert("version".into(), version.into());
+        let mut stack = vec![(String::new(), doc)];
+        while let Some((loc, node)) = stack.pop() {
+            for (dep, info) in node["dependencies"].as_object().into_iter().flatten() {
+                let dep_loc = if loc.is_empty() { format!("node_modules/{}", dep) } else { format!("{}/node_modules/{}", loc, dep) };
+                let version = info["version"].as_str().unwrap_or("0.0.0");
+                let mut p = package(version, info, "package");
+                p.requires = requirements(info, "requires");
+                locations.insert(dep_loc.clone(), (format!("{}@{}", dep, version), p));
+                stack.push((dep_loc, info));
+            }
+        }
+        if let Some(manifest) = manifest {
+            root.requires = requirements(manifest, "dependencies");
+        } else {
+            // Without package.json, guess: hoisted packages nothing else requires.
+            let required: BTreeSet<&str> = locations.values().flat_map(|(_, p)| p.requires.iter().map(|(n, _, _)| n.as_str())).collect();
+            for (loc, (_, p)) in &locations {
+                let name = module_name(loc);
+                if !loc.contains("/node_modules/") && !required.contains(name) {
+                    root.requires.push((name.to_string(), String::new(), if p.tags.contains("dev") { "dev" } else { "prod" }));
+                }
+            }
+        }
+        locations.insert(String::new(), (format!("{}@{}", name, version), root));
+    }
+
+    // Node's lookup: the nearest `node_modules/<name>` walking up from the requirer.
+    let resolve = |from: &str, dep: &str| -> Option<&String> {
+        let mut dir = from.to_string();
+        loop {
+            let candidate = if dir.is_empty() { format!("node_modules/{}", dep) } else { format!("{}/node_modules/{}", dir, dep) };
+            if let Some((id, _)) = locations.get(&candidate) {
+                return Some(id);
+            }
+            if dir.is_empty() {
+                return None;
+            }
+            dir = match dir.rfind("/node_modules/") {
+                Some(i) => dir[..i].to_string(),
+                None => String::new(),
+            };
+        }
+    };
+    let mut edges = BTreeSet::new();
+    for (loc, (id, p)) in &locations {
+        for (dep, _, kind) in &p.requires {
+            if let Some(dep_id) = resolve(loc, dep) {
+                if dep_id != id {
+                    edges.insert((dep_id.clone(), id.clone(), *kind));
+                }
+            }
+        }
+    }
+    let packages: BTreeMap<String, Package> = locations.into_values().filter(|(_, p)| !p.span.is_empty()).collect();
+    to_events(packages, edges)
+}
+
+fn to_events(packages: BTreeMap<String, Package>, edges: BTreeSet<(String, String, &'static str)>) -> Vec<EventLine> {
+    let mut events: Vec<EventLine> = packages
+        .into_iter()
+        .map(|(id, p)| EventLine::Node {
+            id,
+            label: None,
+            span: Some(p.span.to_string()),
+            tags: (!p.tags.is_empty()).then(|| p.tags.into_iter().collect()),
+            ts: None,
+            metadata: Some(p.metadata),
+            score: None,
+            extra: serde_json::Map::new(),
+        })
+        .collect();
+    let mut seen = BTreeSet::new();
+    for (from, to, _) in edges {
+        if seen.insert((from.clone(), to.clone())) {
+            events.push(EventLine::edge(from, to));
+        }
+    }
+    events
+}
+
+/// `"@scope/pkg@^1.0.0"` -> (`@scope/pkg`, `^1.0.0`).
+fn split_spec(spec: &str) -> Option<(&str, &str)> {
+    let spec = spec.trim().trim_matches('"');
+    let at = spec.get(1..)?.find('@')? + 1;
+    Some((&spec[..at], &spec[at + 1..]))
+}
+
+/// `key value`, `key "value"` or `key: value` (Yarn 2+).
+fn yarn_pair(line: &str) -> Option<(String, String)> {
+    let line = line.trim();
+    let (key, value) = if let Some(rest) = line.strip_prefix('"') {
+        let end = rest.find('"')?;
+        (&rest[..end], rest[end + 1..].trim_start_matches(':'))
+    } else {
+        match line.split_once([' ', ':']) {
+            Some((k, v)) => (k, v),
+            None => (line, ""),
+        }
+    };
+    Some((key.to_string(), value.trim().trim_matches('"').to_string()))
+}
+
+fn from_yarn(text: &str, manifest: Option<&Value>) -> Vec<EventLine> {
+    let mut by_spec: HashMap<String, String> = HashMap::new(); // `name@range` -> id
+    let mut packages: BTreeMap<String, Package> = BTreeMap::new();
+    let mut requires: Vec<(String, String, String, &'static str)> = Vec::new(); // (id, name, range, kind)
+
+    let mut entries: Vec<(Vec<String>, Vec<&str>)> = Vec::new();
+    for line in text.lines() {
+        if line.trim_start().starts_with('#') || line.trim().is_empty() {
+            continue;
+        }
+        if !line.starts_with(' ') {
+            let specs = line.trim_end_matches(':').split(", ").map(|s| s.trim().trim_matches('"').to_string()).collect();
+            entries.push((specs, Vec::new()));
+        } else if let Some((_, body)) = entries.last_mut() {
+            body.push(line);
+        }
+    }
+    for (specs, body) in entries {
+        if specs.iter().any(|s| s == "__metadata") {
+            continue;
+        }
+        let Some((name, _)) = specs.first().and_then(|s| split_spec(s)) else { continue };
+        let name = name.to_string();
+        let mut fields = HashMap::new();
+        let mut section: Option<&'static str> = None;
+        let mut deps = Vec::new();
+        for line in body {
+            let nested = line.starts_with("    ");
+            let Some((key, value)) = yarn_pair(line) else { continue };
+            if nested {
+                if let Some(kind) = section {
+                    deps.push((key, value, kind));
+                }
+                continue;
+            }
+            section = match key.as_str() {
+                "dependencies" => Some("prod"),
+                "optionalDependencies" => Some("optional"),
+                "peerDependencies" => Some("peer"),
+                _ => None,
+            };
+            fields.insert(key, value);
+        }
+        let version = fields.get("version").cloned().unwrap_or_else(|| "0.0.0".to_string());
+        let id = format!("{}@{}", name, version);
+        let workspace = specs.iter().any(|s| s.contains("@workspace:"));
+        let mut p = package(&version, &Value::Null, if workspace { "workspace" } else { "package" });
+        // Yarn 2+ has `resolution` and `checksum` where classic Yarn has `resolved` and `integrity`.
+        for (key, meta) in [("resolved", "resolved"), ("resolution", "resolved"), ("integrity", "integrity"), ("checksum", "integrity")] {
+            if let Some(v) = fields.get(key).filter(|v| !v.is_empty()) {
+                p.metadata.insert(meta.into(), v.clone().into());
+            }
+        }
+        for spec in &specs {
+            by_spec.insert(spec.clone(), id.clone());
+        }
+        for (dep, range, kind) in deps {
+            requires.push((id.clone(), dep, range, kind));
+        }
+        packages.entry(id).or_insert(p);
+    }
+
+    // yarn.lock has no entry for the project itself; take it from package.json.
+    if let Some(manifest) = manifest.filter(|_| !packages.values().any(|p| p.span == "workspace")) {
+        let version = manifest["version"].as_str().unwrap_or("0.0.0");
+        let id = format!("{}@{}", manifest["name"].as_str().unwrap_or("root"), version);
+        let mut root = Package { span: "root", ..Package::default() };
+        root.metadata.insert("version".into(), version.into());
+        for (dep, range, kind) in requirements(manifest, "dependencies") {
+            requires.push((id.clone(), dep, range, kind));
+        }
+        packages.insert(id, root);
+    }
+
+    let mut edges = BTreeSet::new();
+    for (id, dep, range, kind) in requires {
+        let target = by_spec.get(&format!("{}@{}", dep, range)).or_else(|| by_spec.get(&format!("{}@npm:{}", dep, range)));
+        if let Some(target) = target.filter(|t| **t != id) {
+            edges.insert((target.clone(), id, kind));
+        }
+    }
+    // Tag packages only reached through dev / optional / peer edges.
+    let mut kinds: HashMap<&str, BTreeSet<&str>> = HashMap::new();
+    for (from, _, kind) in &edges {
+        kinds.entry(from.as_str()).or_default().insert(kind);
+    }
+    for (id, p) in packages.iter_mut() {
+        if let Some(k) = kinds.get(id.as_str()).filter(|k| !k.contains("prod")) {
+            p.tags.extend(k.iter().map(|s| s.to_string()));
+        }
+    }
+    to_events(packages, edges)
+}
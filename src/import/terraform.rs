@@ -0,0 +1,201 @@
+//! `riff-dag-tui terraform`: resource dependencies from `terraform graph`
+//! DOT output, `terraform show -json`, or a raw `terraform.tfstate`.
+//!
+//! Nodes are resource addresses (`module.net.aws_vpc.main`) with the
+//! provider as span and the resource type as tag (plus `data` and the
+//! module path where they apply). Edges point from a dependency to what
+//! depends on it, so a resource's parents are what it needs. Instances of a
+//! counted resource collapse into one node; Terraform's own bookkeeping
+//! nodes (root, close and expand markers) are dropped.
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+};
+
+use color_eyre::eyre::{bail, Result, WrapErr};
+use serde_json::{Map, Value};
+
+use super::Imported;
+use crate::{dot, EventLine};
+
+pub fn import(path: &str) -> Result<Imported> {
+    let text = fs::read_to_string(path).wrap_err_with(|| format!("failed to read {}", path))?;
+    let events = if text.trim_start().starts_with('{') {
+        let doc: Value = serde_json::from_str(&text).wrap_err_with(|| format!("invalid Terraform JSON {}", path))?;
+        from_state(&doc)?
+    } else {
+        from_dot(&dot::parse(&text).wrap_err_with(|| format!("invalid `terraform graph` output {}", path))?)
+    };
+    Ok(Imported { title: format!("terraform: {}", path), events })
+}
+
+/// `[root] module.x.aws_vpc.main (expand)` -> `module.x.aws_vpc.main`;
+/// `None` for nodes that only exist to order Terraform's walk.
+fn clean_dot_id(id: &str) -> Option<String> {
+    let id = id.trim_start_matches("[root] ");
+    if id == "root" || id.ends_with("(close)") || id.starts_with("meta.") {
+        return None;
+    }
+    Some(id.trim_end_matches(" (expand)").to_string())
+}
+
+fn from_dot(graph: &dot::DotGraph) -> Vec<EventLine> {
+    let mut events = Vec::new();
+    let mut ids = HashMap::new(); // DOT node id -> address
+    let mut emitted = HashSet::new();
+    for node in &graph.nodes {
+        // Older releases wrap addresses ("[root] x (expand)") and keep the
+        // plain address in the label.
+        let Some(id) = clean_dot_id(&node.id) else { continue };
+        let id = node.attr("label").filter(|l| id.contains(*l)).map(str::to_string).unwrap_or(id);
+        if emitted.insert(id.clone()) {
+            events.push(resource_node(&id, None, Map::new()));
+        }
+        ids.insert(node.id.clone(), id);
+    }
+    for edge in &graph.edges {
+        // `a -> b` in terraform graph means a depends on b.
+        if let (Some(from), Some(to)) = (ids.get(&edge.to), ids.get(&edge.from)) {
+            if from != to {
+                events.push(EventLine::edge(from.clone(), to.clone()));
+            }
+        }
+    }
+    events
+}
+
+/// Drop a trailing instance key: `aws_instance.web[0]` -> `aws_instance.web`.
+fn base_address(addr: &str) -> &str {
+    match addr.strip_suffix(']').and_then(|a| a.rfind('[').map(|i| &addr[..i])) {
+        Some(base) => base,
+        None => addr,
+    }
+}
+
+fn from_state(doc: &Value) -> Result<Vec<EventLine>> {
+    let mut nodes: Vec<(String, Option<String>, Map<String, Value>)> = Vec::new();
+    let mut deps: Vec<(String, String)> = Vec::new();
+    let mut seen = HashMap::new();
+    let mut add = |addr: &str, provider: Option<&str>, metadata: Map<String, Value>, depends: Vec<String>| {
+        let base = base_address(addr).to_string();
+        let count = seen.entry(base.clone()).or_insert(0);
+        *count += 1;
+        if *count == 1 {
+            nodes.push((base.clone(), provider.map(str::to_string), metadata));
+        }
+        for d in depends {
+            deps.push((base_address(&d).to_string(), base.clone()));
+        }
+    };
+
+    if let Some(root) = doc["values"]["root_module"].as_object() {
+        // `terraform show -json`
+        let mut modules = vec![root];
+        while let Some(module) = modules.pop() {
+            for r in module.get("resources").and_then(Value::as_array).into_iter().flatten() {
+                let Some(addr) = r["address"].as_str() else { continue };
+                let mut metadata = Map::new();
+                if let Some(id) = r["values"]["id"].as_str() {
+                    metadata.insert("id".into(), id.into());
+                }
+                let depends = r["depends_on"].as_array().into_iter().flatten().filter_map(|d| d.as_str().map(str::to_string)).collect();
+                add(addr, r["provider_name"].as_str(), metadata, depends);
+            }
+            modules.extend(module.get("child_modules").and_then(Value::as_array).into_iter().flatten().filter_map(Value::as_object));
+        }
+    } else if let Some(resources) = doc["resources"].as_array() {
+        // terraform.tfstate (format version 4)
+        for r in resources {
+            let (Some(kind), Some(name)) = (r["type"].as_str(), r["name"].as_str()) else { continue };
+            let mut addr = if r["mode"] == "data" { format!("data.{}.{}", kind, name) } else { format!("{}.{}", kind, name) };
+            if let Some(module) = r["module"].as_str() {
+                addr = format!("{}.{}", module, addr);
+            }
+            let instances = r["instances"].as_array().map(Vec::len).unwrap_or(0);
+            let mut metadata = Map::new();
+            metadata.insert("instances".into(), instances.to_string().into());
+            if let Some(id) = r["instances"][0]["attributes"]["id"].as_str() {
+                metadata.insert("id".into(), id.into());
+            }
+            let depends = r["instances"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .flat_map(|i| i["dependencies"].as_array().into_iter().flatten())
+                .filter_map(|d| d.as_str().map(str::to_string))
+                .collect();
+            // `provider["registry.terraform.io/hashicorp/aws"]` -> the quoted source.
+            let provider = r["provider"].as_str().map(|p| p.split('"').nth(1).unwrap_or(p).to_string());
+            add(&addr, provider.as_deref(), metadata, depends);
+        }
+    } else {
+        bail!("JSON is neither `terraform show -json` output nor a terraform.tfstate");
+    }
+
+    let known: HashSet<&str> = nodes.iter().map(|(id, _, _)| id.as_str()).collect();
+    let mut edges: Vec<(String, String)> = deps.into_iter().filter(|(from, to)| known.contains(from.as_str()) && from != to).collect();
+    edges.sort();
+    edges.dedup();
+    let mut events: Vec<EventLine> = nodes.iter().map(|(id, provider, meta)| resource_node(id, provider.as_deref(), meta.clone())).collect();
+    events.extend(edges.into_iter().map(|(from, to)| EventLine::edge(from, to)));
+    Ok(events)
+}
+
+/// Type a resource address: span is the provider, tags the resource type,
+/// `data` for data sources, and the module path for module resources.
+fn resource_node(addr: &str, provider_source: Option<&str>, mut metadata: Map<String, Value>) -> EventLine {
+    if let Some(rest) = addr.split_once("provider[").map(|(_, r)| r) {
+        // `provider["registry.terraform.io/hashicorp/aws"]`, possibly inside a module.
+        let source = rest.split('"').nth(1).unwrap_or(rest);
+        return EventLine::Node {
+            id: addr.to_string(),
+            label: None,
+            span: Some(source.rsplit('/').next().unwrap_or(source).to_string()),
+            tags: Some(vec!["provider".to_string()]),
+            ts: None,
+            metadata: None,
+            score: None,
+            extra: serde_json::Map::new(),
+        };
+    }
+    let mut parts: Vec<&str> = addr.split('.').collect();
+    let mut module = Vec::new();
+    while parts.len() > 2 && parts[0] == "module" {
+        module.push(format!("module.{}", parts[1]));
+        parts.drain(..2);
+    }
+    let mut tags = Vec::new();
+    let (span, label) = match parts.as_slice() {
+        ["data", kind, name, ..] => {
+            tags.push(kind.to_string());
+            tags.push("data".to_string());
+            (kind.split('_').next().unwrap_or(kind).to_string(), format!("{}.{}", kind, name))
+        }
+        [kind @ ("var" | "local" | "output"), name, ..] => {
+            tags.push(kind.to_string());
+            ("terraform".to_string(), format!("{}.{}", kind, name))
+        }
+        [kind, name, ..] => {
+            tags.push(kind.to_string());
+            (kind.split('_').next().unwrap_or(kind).to_string(), format!("{}.{}", kind, name))
+        }
+        _ => ("terraform".to_string(), addr.to_string()),
+    };
+    tags.extend(module.iter().cloned());
+    if let Some(source) = provider_source {
+        metadata.insert("provider".into(), source.into());
+    }
+    if !module.is_empty() {
+        metadata.insert("module".into(), module.join(".").into());
+    }
+    EventLine::Node {
+        id: addr.to_string(),
+        label: (label != addr).then_some(label),
+        span: Some(span),
+        tags: Some(tags),
+        ts: None,
+        metadata: (!metadata.is_empty()).then_some(metadata),
+        score: None,
+        extra: serde_json::Map::new(),
+    }
+}
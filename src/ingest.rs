@@ -0,0 +1,625 @@
+//! Background ingestion.
+//!
+//! Every source (file, file tail, named pipe, stdin, watched directory, TCP
+//! or TLS socket) runs on its
+//! own worker thread,
+//! parses JSONL lines into `EventLine`s and hands them to the UI thread over
+//! a bounded channel. File sources block when the channel is full
+//! (backpressure); socket sources drop instead, so a slow UI never stalls a
+//! remote emitter. Every drop is counted in `IngestStats`.
+use std::{
+    collections::HashSet,
+    fs::{self, File},
+    io::{self, BufRead, BufReader, Read, Seek},
+    net::{TcpListener, TcpStream},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
+        mpsc::{self, Receiver, SyncSender, TrySendError},
+        Arc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use color_eyre::eyre::{bail, Result, WrapErr};
+
+use crate::auth::{Credential, Credentials};
+use crate::EventLine;
+
+/// Source name of `-`, the standard input.
+pub const STDIN: &str = "stdin";
+
+/// Capacity of the channel between ingest workers and the UI thread.
+const CHANNEL_CAPACITY: usize = 4096;
+
+/// How long a tailing source sleeps after hitting EOF before reading again.
+const FOLLOW_POLL: Duration = Duration::from_millis(250);
+
+/// How often a watched directory is rescanned for new files.
+const WATCH_POLL: Duration = Duration::from_secs(1);
+
+/// Set to tell a tailing reader to stop once it reaches EOF.
+type StopFlag = Arc<AtomicBool>;
+
+/// First four bytes of every zstd frame.
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+
+/// Where an event came from: source name and 1-based line number.
+#[derive(Debug, Clone)]
+pub struct Origin {
+    pub source: Arc<str>,
+    pub line: usize,
+    pub raw: Option<Arc<str>>, // the line as read: a node's raw view, a skipped edge's log line
+}
+
+#[derive(Debug)]
+pub enum IngestMsg {
+    Event(EventLine, Origin),
+    /// A watched directory produced a new file; sent before any of its events.
+    SourceOpened { source: Arc<str>, own_tab: bool },
+    /// The whole current graph of `source`, replacing whatever it sent before.
+    Snapshot { source: Arc<str>, events: Vec<EventLine> },
+    Warning(String),
+}
+
+/// Counters shared between the workers and the UI thread.
+#[derive(Debug, Default)]
+pub struct IngestStats {
+    pub received: AtomicU64,  // lines parsed into events
+    pub malformed: AtomicU64, // lines that failed to parse
+    pub dropped: AtomicU64,   // events discarded because the channel was full
+    pub live: AtomicUsize,    // sources still running
+}
+
+/// Which journal entries to read and which of their fields become node data.
+/// Entries without the id field are ignored.
+#[derive(Debug, Default, Clone, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct JournalConfig {
+    /// Units to follow; `--journal-unit` replaces this list.
+    pub units: Vec<String>,
+    /// Passed to `journalctl --since` (e.g. "-1h", "today").
+    pub since: Option<String>,
+    /// Node id field (default RIFF_DAG_ID).
+    pub id_field: Option<String>,
+    /// Label field (default MESSAGE).
+    pub label_field: Option<String>,
+    /// Span field (default RIFF_DAG_SPAN).
+    pub span_field: Option<String>,
+    /// Comma-separated tags (default RIFF_DAG_TAGS).
+    pub tags_field: Option<String>,
+    /// Timestamp field (default RIFF_DAG_TS).
+    pub ts_field: Option<String>,
+    /// Comma-separated parent ids; each becomes an edge parent -> node
+    /// (default RIFF_DAG_PARENTS).
+    pub parents_field: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Delivery {
+    Block,
+    DropWhenFull,
+}
+
+/// Turns lines of some other format into events. `Ok` with no events means
+/// the line carries nothing for the graph; `Err` counts it as malformed.
+pub trait Adapter: Send + Sync {
+    fn events(&self, line: &str) -> Result<Vec<EventLine>, String>;
+}
+
+#[derive(Clone)]
+struct Sink {
+    tx: SyncSender<IngestMsg>,
+    stats: Arc<IngestStats>,
+    delivery: Delivery,
+    adapter: Option<Arc<dyn Adapter>>, // None: lines are riff JSONL
+}
+
+impl Sink {
+    /// Returns false once the UI side has gone away. Warnings always wait
+    /// for room, so `--error-log` sees every one even from a socket.
+    fn send(&self, msg: IngestMsg) -> bool {
+        match self.delivery {
+            Delivery::Block => self.tx.send(msg).is_ok(),
+            Delivery::DropWhenFull if matches!(msg, IngestMsg::Warning(_)) => self.tx.send(msg).is_ok(),
+            Delivery::DropWhenFull => match self.tx.try_send(msg) {
+                Ok(()) => true,
+                Err(TrySendError::Full(_)) => {
+                    self.stats.dropped.fetch_add(1, Ordering::Relaxed);
+                    true
+                }
+                Err(TrySendError::Disconnected(_)) => false,
+            },
+        }
+    }
+
+    fn handle_line(&self, source: &Arc<str>, line_no: usize, line: &str) -> bool {
+        let line = line.trim_end_matches(['\n', '\r']);
+        if line.trim().is_empty() {
+            return true;
+        }
+        let parsed = match &self.adapter {
+            Some(adapter) => adapter.events(line).map_err(|err| format!("unusable line at {}:{}: {}", source, line_no, err)),
+            None => serde_json::from_str::<EventLine>(line)
+                .map(|ev| vec![ev])
+                .map_err(|err| format!("bad JSON at {}:{}: {}", source, line_no, err)),
+        };
+        match parsed {
+            Ok(events) => {
+                let raw: Arc<str> = Arc::from(line);
+                for ev in events {
+                    self.stats.received.fetch_add(1, Ordering::Relaxed);
+                    let origin = Origin { source: source.clone(), line: line_no, raw: Some(raw.clone()) };
+                    if !self.send(IngestMsg::Event(ev, origin)) {
+                        return false;
+                    }
+                }
+                true
+            }
+            Err(err) => {
+                self.stats.malformed.fetch_add(1, Ordering::Relaxed);
+                self.send(IngestMsg::Warning(format!("[warn] {} (content: {})", err, line)))
+            }
+        }
+    }
+}
+
+/// Decrements the live-source counter when a worker exits, however it exits.
+struct LiveGuard(Arc<IngestStats>);
+
+impl LiveGuard {
+    fn new(stats: &Arc<IngestStats>) -> Self {
+        stats.live.fetch_add(1, Ordering::Relaxed);
+        Self(stats.clone())
+    }
+}
+
+impl Drop for LiveGuard {
+    fn drop(&mut self) {
+        self.0.live.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// Read `reader` line by line until EOF. With `follow`, keep waiting for
+/// more data at EOF until the flag is set.
+fn pump<R: BufRead>(sink: &Sink, source: &Arc<str>, mut reader: R, follow: Option<StopFlag>) -> io::Result<()> {
+    let mut buf = String::new();
+    let mut line_no = 0;
+    loop {
+        let n = reader.read_line(&mut buf)?;
+        if n == 0 {
+            match &follow {
+                Some(stop) if !stop.load(Ordering::Relaxed) => {
+                    thread::sleep(FOLLOW_POLL);
+                    continue;
+                }
+                _ => break,
+            }
+        }
+        if follow.is_some() && !buf.ends_with('\n') {
+            // Writer is mid-line; wait for the rest before parsing.
+            continue;
+        }
+        line_no += 1;
+        if !sink.handle_line(source, line_no, &buf) {
+            break;
+        }
+        buf.clear();
+    }
+    if !buf.is_empty() {
+        line_no += 1;
+        sink.handle_line(source, line_no, &buf);
+    }
+    Ok(())
+}
+
+/// Owner of the channel; the UI thread drains it every tick.
+pub struct Ingest {
+    tx: SyncSender<IngestMsg>,
+    rx: Receiver<IngestMsg>,
+    stats: Arc<IngestStats>,
+    adapter: Option<Arc<dyn Adapter>>, // line format for sources spawned from now on
+}
+
+impl Default for Ingest {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Ingest {
+    pub fn new() -> Self {
+        let (tx, rx) = mpsc::sync_channel(CHANNEL_CAPACITY);
+        Self { tx, rx, stats: Arc::new(IngestStats::default()), adapter: None }
+    }
+
+    /// Parse lines of subsequently spawned sources with `adapter` instead of as JSONL.
+    pub fn set_adapter(&mut self, adapter: Arc<dyn Adapter>) {
+        self.adapter = Some(adapter);
+    }
+
+    pub fn stats(&self) -> &IngestStats {
+        &self.stats
+    }
+
+    fn sink(&self, delivery: Delivery) -> Sink {
+        Sink { tx: self.tx.clone(), stats: self.stats.clone(), delivery, adapter: self.adapter.clone() }
+    }
+
+    /// Open `--input`: a file path, or an http(s) URL. Returns the name to
+    /// display for the source (URLs lose any embedded credentials).
+    pub fn spawn_input(&self, input: &str, follow: bool, creds: &mut Credentials) -> Result<String> {
+        if input == "-" {
+            // Read to the end of the pipe; --follow changes nothing for a stream.
+            spawn_reader(&self.sink(Delivery::Block), STDIN, BufReader::new(io::stdin()), None);
+            return Ok(STDIN.to_string());
+        }
+        if input.starts_with("http://") || input.starts_with("https://") {
+            if follow {
+                bail!("--follow is not supported for URL inputs");
+            }
+            let (url, cred) = creds.resolve(input);
+            self.spawn_url(&url, cred)?;
+            return Ok(url);
+        }
+        if is_fifo(Path::new(input)) {
+            self.spawn_fifo(input);
+        } else {
+            self.spawn_file(input, follow)?;
+        }
+        Ok(input.to_string())
+    }
+
+    /// Stream a JSONL document over HTTP(S), resuming after dropped connections.
+    #[cfg(feature = "http")]
+    fn spawn_url(&self, url: &str, cred: Option<Credential>) -> Result<()> {
+        let body = crate::http::ResumingBody::open(url, cred.map(|c| c.header()))?;
+        spawn_reader(&self.sink(Delivery::Block), url, BufReader::new(body), None);
+        Ok(())
+    }
+
+    #[cfg(not(feature = "http"))]
+    fn spawn_url(&self, _url: &str, _cred: Option<Credential>) -> Result<()> {
+        bail!("URL inputs need riff-dag-tui built with the `http` feature")
+    }
+
+    /// Load (or, with `follow`, tail) a JSONL file.
+    fn spawn_file(&self, path: &str, follow: bool) -> Result<()> {
+        let sink = self.sink(Delivery::Block);
+        let (reader, follow) = open_file(&sink, Path::new(path), follow.then(StopFlag::default)).wrap_err("failed to open input file")?;
+        spawn_reader(&sink, path, reader, follow);
+        Ok(())
+    }
+
+    /// Read a named pipe across writers: when one writer closes its end the
+    /// pipe is reopened, which waits for the next one. Opening happens on the
+    /// worker thread, so the UI comes up before anyone has connected.
+    fn spawn_fifo(&self, path: &str) {
+        let sink = self.sink(Delivery::Block);
+        let source: Arc<str> = path.into();
+        let guard = LiveGuard::new(&self.stats);
+        thread::spawn(move || {
+            let _guard = guard;
+            loop {
+                let result = File::open(&*source).and_then(|f| pump(&sink, &source, BufReader::new(f), None));
+                if let Err(err) = result {
+                    if !sink.send(IngestMsg::Warning(format!("[warn] read error on {}: {}", source, err))) {
+                        return;
+                    }
+                    thread::sleep(FOLLOW_POLL);
+                }
+            }
+        });
+    }
+
+    /// Stream every `.jsonl` file in `dir`, now and as new ones appear. The
+    /// newest file is tailed until a newer one shows up, which suits emitters
+    /// that rotate output per run. With `own_tabs`, each file is announced so
+    /// the UI can give it a tab of its own.
+    pub fn spawn_watch_dir(&self, dir: &str, own_tabs: bool) -> Result<()> {
+        let dir = PathBuf::from(dir);
+        fs::read_dir(&dir).wrap_err_with(|| format!("failed to read watch directory {}", dir.display()))?;
+        let sink = self.sink(Delivery::Block);
+        let guard = LiveGuard::new(&self.stats);
+        thread::spawn(move || {
+            let _guard = guard;
+            let mut seen: HashSet<PathBuf> = HashSet::new();
+            let mut tailing: Option<StopFlag> = None;
+            loop {
+                let mut fresh = match list_jsonl(&dir) {
+                    Ok(files) => files.into_iter().filter(|p| !seen.contains(p)).collect::<Vec<_>>(),
+                    Err(err) => {
+                        sink.send(IngestMsg::Warning(format!("[warn] cannot scan {}: {}", dir.display(), err)));
+                        Vec::new()
+                    }
+                };
+                if let Some(newest) = fresh.pop() {
+                    let older = fresh.into_iter().map(|p| (p, None));
+                    let stop = StopFlag::default();
+                    for (path, follow) in older.chain(std::iter::once((newest, Some(stop.clone())))) {
+                        seen.insert(path.clone());
+                        let name = path.to_string_lossy().into_owned();
+                        match open_file(&sink, &path, follow) {
+                            Ok((reader, follow)) => {
+                                if !sink.send(IngestMsg::SourceOpened { source: name.as_str().into(), own_tab: own_tabs }) {
+                                    return;
+                                }
+                                spawn_reader(&sink, &name, reader, follow);
+                            }
+                            Err(err) => {
+                                sink.send(IngestMsg::Warning(format!("[warn] cannot open {}: {}", name, err)));
+                            }
+                        }
+                    }
+                    // The previous newest file is finished once a newer one exists.
+                    if let Some(prev) = tailing.replace(stop) {
+                        prev.store(true, Ordering::Relaxed);
+                    }
+                }
+                thread::sleep(WATCH_POLL);
+            }
+        });
+        Ok(())
+    }
+
+    /// Follow the systemd journal for `units`, turning entries into events.
+    #[cfg(feature = "journald")]
+    pub fn spawn_journal(&self, cfg: &JournalConfig, units: &[String]) -> Result<()> {
+        let mut child = crate::journal::command(cfg, units).spawn().wrap_err("failed to run journalctl")?;
+        let stdout = child.stdout.take().expect("journalctl stdout is piped");
+        let adapter: Arc<dyn Adapter> = Arc::new(crate::journal::JournalAdapter::new(cfg));
+        let sink = Sink { adapter: Some(adapter), ..self.sink(Delivery::Block) };
+        spawn_reader(&sink, &format!("journal:{}", units.join(",")), BufReader::new(stdout), None);
+        Ok(())
+    }
+
+    /// Feed events that were built up front (importers), numbered as if
+    /// they were lines of `name`.
+    pub fn spawn_events(&self, name: &str, events: Vec<EventLine>) {
+        let sink = self.sink(Delivery::Block);
+        let source: Arc<str> = name.into();
+        let guard = LiveGuard::new(&self.stats);
+        thread::spawn(move || {
+            let _guard = guard;
+            for (i, ev) in events.into_iter().enumerate() {
+                sink.stats.received.fetch_add(1, Ordering::Relaxed);
+                let origin = Origin { source: source.clone(), line: i + 1, raw: None };
+                if !sink.send(IngestMsg::Event(ev, origin)) {
+                    return;
+                }
+            }
+        });
+    }
+
+    /// Keep `name` in sync with a source that can only be polled: `first` is
+    /// shown right away, then `refresh` runs every `interval` and each
+    /// result that differs replaces the graph (so deleted objects disappear).
+    pub fn spawn_poll<F>(&self, name: &str, first: Vec<EventLine>, interval: Duration, refresh: F)
+    where
+        F: Fn() -> Result<Vec<EventLine>> + Send + 'static,
+    {
+        let sink = self.sink(Delivery::Block);
+        let source: Arc<str> = name.into();
+        let guard = LiveGuard::new(&self.stats);
+        thread::spawn(move || {
+            let _guard = guard;
+            let mut shown = first;
+            loop {
+                sink.stats.received.fetch_add(shown.len() as u64, Ordering::Relaxed);
+                if !sink.send(IngestMsg::Snapshot { source: source.clone(), events: shown.clone() }) {
+                    return;
+                }
+                loop {
+                    thread::sleep(interval);
+                    match refresh() {
+                        Ok(events) if events != shown => {
+                            shown = events;
+                            break;
+                        }
+                        Ok(_) => {}
+                        Err(err) => {
+                            if !sink.send(IngestMsg::Warning(format!("[warn] refreshing {} failed: {:#}", source, err))) {
+                                return;
+                            }
+                        }
+                    }
+                }
+            }
+        });
+    }
+
+    /// Feed a static in-memory document (the embedded sample).
+    pub fn spawn_static(&self, name: &str, data: &'static str) {
+        spawn_reader(&self.sink(Delivery::Block), name, BufReader::new(data.as_bytes()), None);
+    }
+
+    /// Accept connections on `addr`; each connection streams JSONL events.
+    pub fn spawn_listen(&self, addr: &str) -> Result<()> {
+        self.accept_loop(addr, |sink, stream| spawn_stream(sink, peer_name("tcp", &stream), stream))
+    }
+
+    /// Like `spawn_listen`, but every connection must complete a TLS handshake.
+    #[cfg(feature = "tls")]
+    pub fn spawn_listen_tls(&self, addr: &str, config: Arc<rustls::ServerConfig>) -> Result<()> {
+        self.accept_loop(addr, move |sink, stream| match crate::tls::accept(&config) {
+            Ok(conn) => spawn_stream(sink, peer_name("tls", &stream), rustls::StreamOwned::new(conn, stream)),
+            Err(err) => {
+                sink.send(IngestMsg::Warning(format!("[warn] {:#}", err)));
+            }
+        })
+    }
+
+    fn accept_loop<F>(&self, addr: &str, on_conn: F) -> Result<()>
+    where
+        F: Fn(&Sink, TcpStream) + Send + 'static,
+    {
+        let listener = TcpListener::bind(addr).wrap_err_with(|| format!("failed to listen on {}", addr))?;
+        let sink = self.sink(Delivery::DropWhenFull);
+        let guard = LiveGuard::new(&self.stats);
+        thread::spawn(move || {
+            let _guard = guard;
+            for conn in listener.incoming() {
+                match conn {
+                    Ok(stream) => on_conn(&sink, stream),
+                    Err(err) => {
+                        sink.send(IngestMsg::Warning(format!("[warn] accept failed: {}", err)));
+                    }
+                }
+            }
+        });
+        Ok(())
+    }
+
+    /// Connect to `addr` and read its JSONL event stream until it closes.
+    pub fn spawn_connect(&self, addr: &str) -> Result<()> {
+        let stream = TcpStream::connect(addr).wrap_err_with(|| format!("failed to connect to {}", addr))?;
+        spawn_stream(&self.sink(Delivery::DropWhenFull), peer_name("tcp", &stream), stream);
+        Ok(())
+    }
+
+    /// Like `spawn_connect`, verifying the server certificate first.
+    #[cfg(feature = "tls")]
+    pub fn spawn_connect_tls(&self, addr: &str, config: Arc<rustls::ClientConfig>) -> Result<()> {
+        let stream = TcpStream::connect(addr).wrap_err_with(|| format!("failed to connect to {}", addr))?;
+        let conn = crate::tls::connect(&config, addr)?;
+        spawn_stream(&self.sink(Delivery::DropWhenFull), peer_name("tls", &stream), rustls::StreamOwned::new(conn, stream));
+        Ok(())
+    }
+
+    /// Pull whatever has arrived, stopping early once `budget` is spent so a
+    /// busy source can't starve input handling. The flag is true when the
+    /// budget ran out, i.e. more messages are probably waiting.
+    pub fn drain(&self, budget: Duration) -> (Vec<IngestMsg>, bool) {
+        let start = Instant::now();
+        let mut out = Vec::new();
+        while let Ok(msg) = self.rx.try_recv() {
+            out.push(msg);
+            if start.elapsed() >= budget {
+                return (out, true);
+            }
+        }
+        (out, false)
+    }
+}
+
+fn spawn_reader<R: BufRead + Send + 'static>(sink: &Sink, name: &str, reader: R, follow: Option<StopFlag>) {
+    let sink = sink.clone();
+    let source: Arc<str> = name.into();
+    let guard = LiveGuard::new(&sink.stats);
+    thread::spawn(move || {
+        let _guard = guard;
+        if let Err(err) = pump(&sink, &source, reader, follow) {
+            sink.send(IngestMsg::Warning(format!("[warn] read error on {}: {}", source, err)));
+        }
+    });
+}
+
+type SourceReader = Box<dyn BufRead + Send>;
+
+/// Open a file for `pump`, returning the reader and the follow flag `pump`
+/// should use. zstd files (`.zst` or starting with the zstd magic) are
+/// decompressed on the fly. Tailed files are read through `Tail`, so a
+/// decoder sees each block as soon as the writer has flushed it and a
+/// truncated file is read again from the start.
+fn open_file(sink: &Sink, path: &Path, follow: Option<StopFlag>) -> io::Result<(SourceReader, Option<StopFlag>)> {
+    let mut file = File::open(path)?;
+    let mut magic = [0u8; 4];
+    let compressed = path.extension().is_some_and(|e| e == "zst")
+        || (file.read_exact(&mut magic).is_ok() && magic == ZSTD_MAGIC);
+    file.rewind()?;
+    let tail = |file, stop| Tail { file, stop, sink: sink.clone(), source: path.to_string_lossy().into() };
+    match (compressed, follow) {
+        (false, None) => Ok((Box::new(BufReader::new(file)), None)),
+        // `Tail` waits at EOF itself, so `pump` needn't.
+        (false, Some(stop)) => Ok((Box::new(BufReader::new(tail(file, stop))), None)),
+        (true, Some(stop)) => Ok((zstd_reader(tail(file, stop))?, None)),
+        (true, None) => Ok((zstd_reader(file)?, None)),
+    }
+}
+
+#[cfg(feature = "zstd")]
+fn zstd_reader<R: Read + Send + 'static>(inner: R) -> io::Result<SourceReader> {
+    Ok(Box::new(BufReader::new(zstd::Decoder::new(inner)?)))
+}
+
+#[cfg(not(feature = "zstd"))]
+fn zstd_reader<R: Read + Send + 'static>(_inner: R) -> io::Result<SourceReader> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "zstd input needs riff-dag-tui built with the `zstd` feature"))
+}
+
+/// A file that is still being written. Reads wait at EOF until more bytes
+/// arrive or `stop` is set, so a decoder never mistakes a half-written frame
+/// for a truncated one. A file that shrinks under the reader (truncated by
+/// `> file` or a copy-truncate rotation) is read again from the start.
+struct Tail {
+    file: File,
+    stop: StopFlag,
+    sink: Sink,
+    source: Arc<str>,
+}
+
+impl Read for Tail {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            let n = self.file.read(buf)?;
+            if n > 0 || buf.is_empty() || self.stop.load(Ordering::Relaxed) {
+                return Ok(n);
+            }
+            if self.file.metadata()?.len() < self.file.stream_position()? {
+                self.file.rewind()?;
+                let warning = format!("[warn] {} was truncated; reading it again from the start", self.source);
+                if !self.sink.send(IngestMsg::Warning(warning)) {
+                    return Ok(0);
+                }
+                continue;
+            }
+            thread::sleep(FOLLOW_POLL);
+        }
+    }
+}
+
+#[cfg(unix)]
+fn is_fifo(path: &Path) -> bool {
+    use std::os::unix::fs::FileTypeExt;
+    fs::metadata(path).is_ok_and(|m| m.file_type().is_fifo())
+}
+
+#[cfg(not(unix))]
+fn is_fifo(_path: &Path) -> bool {
+    false
+}
+
+/// Source name for a socket: `<scheme>:<peer address>`.
+fn peer_name(scheme: &str, stream: &TcpStream) -> Arc<str> {
+    match stream.peer_addr() {
+        Ok(addr) => format!("{}:{}", scheme, addr).into(),
+        Err(_) => scheme.into(),
+    }
+}
+
+fn spawn_stream<S: Read + Send + 'static>(sink: &Sink, name: Arc<str>, stream: S) {
+    let sink = sink.clone();
+    let guard = LiveGuard::new(&sink.stats);
+    thread::spawn(move || {
+        let _guard = guard;
+        if let Err(err) = pump(&sink, &name, BufReader::new(stream), None) {
+            sink.send(IngestMsg::Warning(format!("[warn] read error on {}: {}", name, err)));
+        }
+    });
+}
+
+/// `.jsonl` (and `.jsonl.zst`) files in `dir`, oldest first (by mtime, then name).
+pub fn list_jsonl(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        let name = path.file_name().map(|n| n.to_string_lossy()).unwrap_or_default();
+        if path.is_file() && (name.ends_with(".jsonl") || name.ends_with(".jsonl.zst")) {
+            let mtime = fs::metadata(&path).and_then(|m| m.modified()).ok();
+            files.push((mtime, path));
+        }
+    }
+    files.sort();
+    Ok(files.into_iter().map(|(_, p)| p).collect())
+}
@@ -0,0 +1,85 @@
+//! Interned strings. Spans and tags repeat across a graph (one can be on
+//! hundreds of thousands of nodes), so each distinct one is kept once, in a
+//! pool shared by every graph and snapshot, and the nodes hold an `Arc` to
+//! it, so a million nodes tagged `retry` share one `retry`. Ids are unique
+//! within a graph but not across them: snapshots, revisions, the cache and
+//! every tab showing a node share its id too.
+//!
+//! A string no node holds any more is dropped from the pool the next time
+//! its shard has doubled, so closed tabs and replaced graphs don't leave
+//! their strings behind.
+//!
+//! The pool is split into shards by hash, each behind its own lock, so the
+//! ingest workers interning at once seldom wait for each other.
+use std::{
+    collections::HashSet,
+    hash::{BuildHasher, BuildHasherDefault, DefaultHasher},
+    sync::{Arc, Mutex, OnceLock},
+};
+
+/// Shard size below which it isn't swept.
+const SWEEP_FROM: usize = 1024;
+
+/// Independently locked parts of the pool.
+const SHARDS: usize = 32;
+
+#[derive(Debug, Default)]
+struct Pool {
+    strings: HashSet<Arc<str>>,
+    swept: usize, // how many were left by the last sweep
+}
+
+static POOL: OnceLock<[Mutex<Pool>; SHARDS]> = OnceLock::new();
+
+/// The pooled copy of `s`, added to the pool if it isn't there yet.
+pub fn intern(s: &str) -> Arc<str> {
+    let shard = BuildHasherDefault::<DefaultHasher>::default().hash_one(s) as usize % SHARDS;
+    let shards = POOL.get_or_init(|| std::array::from_fn(|_| Mutex::default()));
+    let mut pool = shards[shard].lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(pooled) = pool.strings.get(s) {
+        return Arc::clone(pooled);
+    }
+    if pool.strings.len() >= SWEEP_FROM.max(2 * pool.swept) {
+        // Held by the pool alone: nothing uses it.
+        pool.strings.retain(|s| Arc::strong_count(s) > 1);
+        pool.swept = pool.strings.len();
+    }
+    let pooled: Arc<str> = Arc::from(s);
+    pool.strings.insert(Arc::clone(&pooled));
+    pooled
+}
+
+/// `intern` for each of `strings`.
+pub fn intern_all<S: AsRef<str>>(strings: impl IntoIterator<Item = S>) -> Vec<Arc<str>> {
+    strings.into_iter().map(|s| intern(s.as_ref())).collect()
+}
+
+/// Serde for an interned field: written as a plain string, pooled when read.
+pub mod one {
+    use std::sync::Arc;
+
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(s: &Arc<str>, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(s)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Arc<str>, D::Error> {
+        Ok(super::intern(&String::deserialize(deserializer)?))
+    }
+}
+
+/// Serde for a list of interned strings.
+pub mod many {
+    use std::sync::Arc;
+
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(strings: &[Arc<str>], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(strings.iter().map(|s| &**s))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<Arc<str>>, D::Error> {
+        Ok(super::intern_all(Vec::<String>::deserialize(deserializer)?))
+    }
+}
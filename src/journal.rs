@@ -0,0 +1,89 @@
+//! systemd journal source.
+//!
+//! Runs `journalctl -f -o json` for the configured units and maps each
+//! entry's fields onto a node (plus one edge per listed parent). Services can
+//! log DAG events with e.g. `sd_journal_send("RIFF_DAG_ID=...", ...)`; which
+//! fields are read is configurable under `[journal]`.
+use std::process::{Command, Stdio};
+
+use serde_json::{Map, Value};
+
+use crate::ingest::JournalConfig;
+use crate::ingest::Adapter;
+use crate::EventLine;
+
+pub fn command(cfg: &JournalConfig, units: &[String]) -> Command {
+    let mut cmd = Command::new("journalctl");
+    cmd.args(["--follow", "--no-tail", "--output=json"]);
+    for unit in units {
+        cmd.arg("--unit").arg(unit);
+    }
+    if let Some(since) = &cfg.since {
+        cmd.arg("--since").arg(since);
+    }
+    cmd.stdin(Stdio::null()).stdout(Stdio::piped()).stderr(Stdio::null());
+    cmd
+}
+
+pub struct JournalAdapter {
+    id: String,
+    label: String,
+    span: String,
+    tags: String,
+    ts: String,
+    parents: String,
+}
+
+impl JournalAdapter {
+    pub fn new(cfg: &JournalConfig) -> Self {
+        let field = |f: &Option<String>, default: &str| f.clone().unwrap_or_else(|| default.to_string());
+        Self {
+            id: field(&cfg.id_field, "RIFF_DAG_ID"),
+            label: field(&cfg.label_field, "MESSAGE"),
+            span: field(&cfg.span_field, "RIFF_DAG_SPAN"),
+            tags: field(&cfg.tags_field, "RIFF_DAG_TAGS"),
+            ts: field(&cfg.ts_field, "RIFF_DAG_TS"),
+            parents: field(&cfg.parents_field, "RIFF_DAG_PARENTS"),
+        }
+    }
+}
+
+/// Journal fields are strings, or arrays of strings when a field was logged
+/// more than once; binary fields (byte arrays) are skipped.
+fn text(entry: &Map<String, Value>, field: &str) -> Option<String> {
+    match entry.get(field)? {
+        Value::String(s) => Some(s.clone()),
+        Value::Array(values) => values.iter().rev().find_map(|v| v.as_str().map(str::to_string)),
+        _ => None,
+    }
+}
+
+fn list(entry: &Map<String, Value>, field: &str) -> Vec<String> {
+    text(entry, field)
+        .map(|s| s.split(',').map(str::trim).filter(|p| !p.is_empty()).map(str::to_string).collect())
+        .unwrap_or_default()
+}
+
+impl Adapter for JournalAdapter {
+    fn events(&self, line: &str) -> Result<Vec<EventLine>, String> {
+        let entry: Map<String, Value> = serde_json::from_str(line).map_err(|err| format!("bad journal entry: {}", err))?;
+        let Some(id) = text(&entry, &self.id) else {
+            return Ok(Vec::new());
+        };
+        let tags = list(&entry, &self.tags);
+        let mut events = vec![EventLine::Node {
+            id: id.clone(),
+            label: text(&entry, &self.label),
+            span: text(&entry, &self.span),
+            tags: (!tags.is_empty()).then_some(tags),
+            ts: text(&entry, &self.ts),
+            metadata: None,
+            score: None,
+            extra: serde_json::Map::new(),
+        }];
+        for parent in list(&entry, &self.parents) {
+            events.push(EventLine::edge(parent, id.clone()));
+        }
+        Ok(events)
+    }
+}
@@ -0,0 +1,139 @@
+//! The text DAG view's connectors, drawn like `git log --graph`: one node
+//! per row, top to bottom, each edge running down its own lane from the
+//! node it leaves to the one it enters. A node with several children forks
+//! new lanes off to its right on the row below it; lanes waiting for the
+//! same node merge into the leftmost of them on the row above it. Only edges
+//! running down the given order are drawn, so an edge back up a cycle is
+//! left out.
+use std::collections::HashMap;
+
+use petgraph::stable_graph::NodeIndex;
+use petgraph::Direction::Outgoing;
+
+use crate::GraphModel;
+
+/// The characters a graph is drawn with.
+pub struct Glyphs {
+    pub node: char,
+    vertical: char,
+    horizontal: char,
+    branch: char,    // a lane carrying on down with a fork or merge off to its right
+    fork: char,      // a lane forked off on the way to another further right
+    fork_end: char,  // the rightmost lane forked off
+    merge: char,     // a lane merging on the way to another further right
+    merge_end: char, // the rightmost lane merging
+    cross: char,     // a fork or merge passing over a lane
+}
+
+pub const UNICODE: Glyphs = Glyphs {
+    node: '●',
+    vertical: '│',
+    horizontal: '─',
+    branch: '├',
+    fork: '┬',
+    fork_end: '╮',
+    merge: '┴',
+    merge_end: '╯',
+    cross: '┼',
+};
+
+/// For terminals without box drawing (`ascii_connectors` in the config).
+pub const ASCII: Glyphs = Glyphs {
+    node: '*',
+    vertical: '|',
+    horizontal: '-',
+    branch: '+',
+    fork: '+',
+    fork_end: '.',
+    merge: '+',
+    merge_end: '\'',
+    cross: '+',
+};
+
+/// A row of the drawing: a node with the lanes beside it, or a row of
+/// forks or merges between nodes.
+pub enum Row {
+    Node(String, NodeIndex),
+    Edges(String),
+}
+
+/// Draw `nodes`, in the order given (each once), with the edges among them.
+pub fn render(gm: &GraphModel, nodes: &[NodeIndex], glyphs: &Glyphs) -> Vec<Row> {
+    let position: HashMap<NodeIndex, usize> = nodes.iter().enumerate().map(|(i, &idx)| (idx, i)).collect();
+    let mut lanes: Vec<Option<NodeIndex>> = Vec::new(); // the node each lane runs down to
+    let mut rows = Vec::new();
+    for (i, &idx) in nodes.iter().enumerate() {
+        let waiting: Vec<usize> = (0..lanes.len()).filter(|&l| lanes[l] == Some(idx)).collect();
+        let col = match waiting.first() {
+            Some(&l) => l,
+            None => free_lane(&mut lanes, 0),
+        };
+        if let [_, .., last] = waiting[..] {
+            rows.push(Row::Edges(junction(&lanes, col, last, |l| waiting.contains(&l), glyphs.merge, glyphs.merge_end, glyphs)));
+            for &l in &waiting[1..] {
+                lanes[l] = None;
+            }
+        }
+        lanes[col] = Some(idx);
+        let line: String = lanes
+            .iter()
+            .enumerate()
+            .map(|(l, lane)| match lane {
+                _ if l == col => glyphs.node,
+                Some(_) => glyphs.vertical,
+                None => ' ',
+            })
+            .flat_map(|c| [c, ' '])
+            .collect();
+        rows.push(Row::Node(line.trim_end().to_string(), idx));
+
+        let mut children: Vec<NodeIndex> = gm.graph.neighbors_directed(idx, Outgoing).filter(|c| position.get(c).is_some_and(|&p| p > i)).collect();
+        children.sort_by_key(|c| position[c]);
+        children.dedup();
+        lanes[col] = children.first().copied();
+        if children.len() > 1 {
+            let forked: Vec<usize> = children[1..].iter().map(|&c| {
+                let l = free_lane(&mut lanes, col + 1);
+                lanes[l] = Some(c);
+                l
+            }).collect();
+            let last = *forked.iter().max().expect("forked a lane");
+            rows.push(Row::Edges(junction(&lanes, col, last, |l| forked.contains(&l), glyphs.fork, glyphs.fork_end, glyphs)));
+        }
+        while lanes.last() == Some(&None) {
+            lanes.pop();
+        }
+    }
+    rows
+}
+
+/// The first lane from `from` on that runs nowhere, added if there is none.
+fn free_lane(lanes: &mut Vec<Option<NodeIndex>>, from: usize) -> usize {
+    match (from..lanes.len()).find(|&l| lanes[l].is_none()) {
+        Some(l) => l,
+        None => {
+            lanes.resize(lanes.len().max(from) + 1, None);
+            lanes.len() - 1
+        }
+    }
+}
+
+/// A row joining lane `col` to the lanes right of it that `joined` picks,
+/// the rightmost of which is `last`.
+fn junction(lanes: &[Option<NodeIndex>], col: usize, last: usize, joined: impl Fn(usize) -> bool, mid: char, end: char, glyphs: &Glyphs) -> String {
+    let mut line = String::new();
+    for (l, lane) in lanes.iter().enumerate() {
+        let c = match lane {
+            _ if l == col => glyphs.branch,
+            _ if l == last => end,
+            _ if joined(l) => mid,
+            Some(_) if l > col && l < last => glyphs.cross,
+            None if l > col && l < last => glyphs.horizontal,
+            Some(_) => glyphs.vertical,
+            None => ' ',
+        };
+        line.push(c);
+        line.push(if l >= col && l < last { glyphs.horizontal } else { ' ' });
+    }
+    line.trim_end().to_string()
+}
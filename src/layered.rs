@@ -0,0 +1,263 @@
+//! The canvas's layered layout, after Sugiyama: ranks, then an order within
+//! each rank, then rows.
+//!
+//! A node starts at its hop from the selection (parents left of it at
+//! negative ranks, children right at positive ones) and is pushed further
+//! out until every edge on its side runs at least one rank away from the
+//! selection, so two parents joined by an edge land in different columns.
+//! An edge spanning several ranks gets an invisible stand-in at each rank it
+//! passes, so it is ordered like any other. Each rank is then ordered by the
+//! mean position of its neighbours in the rank before it, sweeping right and
+//! back left, and the order with the fewest crossings is kept. Last, every
+//! node is given the row nearest its neighbours' mean that keeps it clear of
+//! the node above it, the selection staying in the middle; a rank too tall
+//! for the pane zigzags across up to `STAGGER` columns. Past `MAX_ORDERED`
+//! vertices, ranks keep the order their nodes were met in.
+use std::collections::{HashMap, HashSet};
+
+use itertools::Itertools;
+use petgraph::stable_graph::NodeIndex;
+
+use crate::{neighborhood_layers, quality::NODE_RADIUS, GraphModel};
+
+/// Canvas rows a rank may use, centered on the selection.
+const HEIGHT: f64 = 46.0;
+
+/// Row distance between neighbours in a rank, when there is room for it.
+const GAP: f64 = 2.0 * NODE_RADIUS + 1.0;
+
+/// Columns a tall rank may zigzag across, and how far apart they sit.
+const STAGGER: usize = 3;
+const STAGGER_GAP: f64 = 4.0;
+
+/// Ordering sweeps, alternately right and left.
+const SWEEPS: usize = 8;
+
+/// Vertices (nodes and stand-ins) beyond which ranks are not ordered.
+const MAX_ORDERED: usize = 5000;
+
+/// A node, or an edge's stand-in at a rank it passes.
+struct Vertex {
+    node: Option<NodeIndex>,
+    rank: i32,
+    before: Vec<usize>, // neighbours one rank left
+    after: Vec<usize>,  // neighbours one rank right
+}
+
+/// Every node within `depth` hops of `center` with its hop, negative for
+/// parents, the selection first.
+pub fn hops(gm: &GraphModel, center: NodeIndex, depth: usize) -> Vec<(NodeIndex, i32)> {
+    let (parents_layers, children_layers) = neighborhood_layers(gm, center, depth);
+    let mut seen: Vec<(NodeIndex, i32)> = vec![(center, 0)];
+    let mut met: HashSet<NodeIndex> = HashSet::from([center]);
+    for (hop, layer) in parents_layers.iter().enumerate().map(|(i, l)| (-(i as i32) - 1, l)).chain(children_layers.iter().enumerate().map(|(i, l)| (i as i32 + 1, l))) {
+        for &n in layer {
+            // On a cycle, a node can be both a parent and a child; it stays where it was met first.
+            if met.insert(n) {
+                seen.push((n, hop));
+            }
+        }
+    }
+    seen
+}
+
+pub fn layout(gm: &GraphModel, center: NodeIndex, depth: usize) -> HashMap<NodeIndex, (f64, f64)> {
+    let seen = hops(gm, center, depth);
+    let mut rank: HashMap<NodeIndex, i32> = seen.iter().copied().collect();
+    let edges: Vec<(NodeIndex, NodeIndex)> = seen
+        .iter()
+        .flat_map(|&(a, _)| gm.graph.neighbors(a).filter(move |&b| b != a).map(move |b| (a, b)))
+        .filter(|(_, b)| rank.contains_key(b))
+        .sorted()
+        .dedup()
+        .collect();
+
+    // Ranks: push a parent left of the parents it points at, a child right of
+    // the children pointing at it. Edges back across the selection are left be.
+    for _ in 0..seen.len() {
+        let mut moved = false;
+        for &(a, b) in &edges {
+            let (ra, rb) = (rank[&a], rank[&b]);
+            if ra < 0 && rb <= 0 && ra > rb - 1 {
+                rank.insert(a, rb - 1);
+                moved = true;
+            } else if ra >= 0 && rb > 0 && rb < ra + 1 {
+                rank.insert(b, ra + 1);
+                moved = true;
+            }
+        }
+        if !moved {
+            break;
+        }
+    }
+
+    let mut vertices: Vec<Vertex> = seen.iter().map(|&(n, _)| Vertex { node: Some(n), rank: rank[&n], before: Vec::new(), after: Vec::new() }).collect();
+    let vertex: HashMap<NodeIndex, usize> = seen.iter().enumerate().map(|(i, &(n, _))| (n, i)).collect();
+    for &(a, b) in &edges {
+        let (mut from, to) = match (vertex[&a], vertex[&b]) {
+            (a, b) if vertices[a].rank < vertices[b].rank => (a, b),
+            (a, b) if vertices[a].rank > vertices[b].rank => (b, a),
+            _ => continue,
+        };
+        while vertices[from].rank + 1 < vertices[to].rank {
+            let r = vertices[from].rank + 1;
+            vertices.push(Vertex { node: None, rank: r, before: vec![from], after: Vec::new() });
+            let stand_in = vertices.len() - 1;
+            vertices[from].after.push(stand_in);
+            from = stand_in;
+        }
+        vertices[from].after.push(to);
+        vertices[to].before.push(from);
+    }
+
+    let first = vertices.iter().map(|v| v.rank).min().unwrap_or(0);
+    let last = vertices.iter().map(|v| v.rank).max().unwrap_or(0);
+    let mut ranks: Vec<Vec<usize>> = vec![Vec::new(); (last - first) as usize + 1];
+    for (i, v) in vertices.iter().enumerate() {
+        ranks[(v.rank - first) as usize].push(i);
+    }
+
+    if vertices.len() <= MAX_ORDERED {
+        order(&vertices, &mut ranks);
+    }
+    let rows = rows(&vertices, &ranks, vertex[&center]);
+
+    let step = (40.0 / first.abs().max(last).max(1) as f64).min(20.0);
+    let at = positions(&vertices, &ranks);
+    vertices
+        .iter()
+        .enumerate()
+        .zip(rows)
+        .filter_map(|((i, v), y)| {
+            // A rank too tall to keep its shapes apart zigzags across a few columns.
+            let size = ranks[(v.rank - first) as usize].len();
+            let columns = ((2.0 * NODE_RADIUS / gap(size)).ceil() as usize).clamp(1, STAGGER);
+            let x = 50.0 + v.rank as f64 * step + ((at[i] % columns) as f64 - (columns - 1) as f64 / 2.0) * STAGGER_GAP;
+            v.node.map(|n| (n, (x, y)))
+        })
+        .collect()
+}
+
+/// Row distance between neighbours in a rank of `n`.
+fn gap(n: usize) -> f64 {
+    if n > 1 { (HEIGHT / (n - 1) as f64).min(GAP) } else { GAP }
+}
+
+/// Where each vertex sits in its rank.
+fn positions(vertices: &[Vertex], ranks: &[Vec<usize>]) -> Vec<usize> {
+    let mut at = vec![0; vertices.len()];
+    for rank in ranks {
+        for (i, &v) in rank.iter().enumerate() {
+            at[v] = i;
+        }
+    }
+    at
+}
+
+/// Pairs of edges that cross between neighbouring ranks: per rank, the
+/// links sorted by their left end, counting for each how many already seen
+/// end lower on the right (an accumulator tree over the right rank).
+fn crossings(vertices: &[Vertex], ranks: &[Vec<usize>]) -> usize {
+    let at = positions(vertices, ranks);
+    let at = &at;
+    ranks
+        .iter()
+        .zip(ranks.iter().skip(1))
+        .map(|(rank, next)| {
+            let links: Vec<(usize, usize)> = rank.iter().flat_map(|&v| vertices[v].after.iter().map(move |&w| (at[v], at[w]))).sorted().collect();
+            // tree[i] sums the right ends at positions (i - (i & -i), i], one-based.
+            let mut tree = vec![0usize; next.len() + 1];
+            let mut crossed = 0;
+            for (seen, &(_, right)) in links.iter().enumerate() {
+                // Links with the same left end come before in right-end order, so
+                // "ends strictly lower" never counts them.
+                let (mut i, mut at_or_above) = (right + 1, 0);
+                while i > 0 {
+                    at_or_above += tree[i];
+                    i &= i - 1;
+                }
+                crossed += seen - at_or_above;
+                let mut i = right + 1;
+                while i < tree.len() {
+                    tree[i] += 1;
+                    i += i & i.wrapping_neg();
+                }
+            }
+            crossed
+        })
+        .sum()
+}
+
+/// Order each rank by the barycenter of its neighbours in the rank swept
+/// from, keeping the best order found.
+fn order(vertices: &[Vertex], ranks: &mut [Vec<usize>]) {
+    let mut best = (crossings(vertices, ranks), ranks.to_vec());
+    for sweep in 0..SWEEPS {
+        let rightward = sweep % 2 == 0;
+        let sequence: Vec<usize> = if rightward { (1..ranks.len()).collect() } else { (0..ranks.len().saturating_sub(1)).rev().collect() };
+        for r in sequence {
+            let at = positions(vertices, ranks);
+            let keyed: Vec<(f64, usize)> = ranks[r]
+                .iter()
+                .enumerate()
+                .map(|(i, &v)| {
+                    let near = if rightward { &vertices[v].before } else { &vertices[v].after };
+                    // Without neighbours that way, a vertex keeps its place.
+                    let key = if near.is_empty() { i as f64 } else { near.iter().map(|&w| at[w] as f64).sum::<f64>() / near.len() as f64 };
+                    (key, v)
+                })
+                .collect();
+            ranks[r] = keyed.into_iter().sorted_by(|a, b| a.0.total_cmp(&b.0)).map(|(_, v)| v).collect();
+        }
+        let count = crossings(vertices, ranks);
+        if count < best.0 {
+            best = (count, ranks.to_vec());
+        }
+    }
+    ranks.clone_from_slice(&best.1);
+}
+
+/// The row of each vertex: as near its neighbours' mean as the rank's order
+/// and spacing allow, within `HEIGHT`, with `center` at 25.
+fn rows(vertices: &[Vertex], ranks: &[Vec<usize>], center: usize) -> Vec<f64> {
+    let mut y = vec![25.0; vertices.len()];
+    for rank in ranks {
+        for (i, &v) in rank.iter().enumerate() {
+            y[v] = 25.0 + (i as f64 - (rank.len() - 1) as f64 / 2.0) * gap(rank.len());
+        }
+    }
+    for pass in 0..4 {
+        let rightward = pass % 2 == 0;
+        let sequence: Vec<usize> = if rightward { (0..ranks.len()).collect() } else { (0..ranks.len()).rev().collect() };
+        for r in sequence {
+            let rank = &ranks[r];
+            if rank.is_empty() {
+                continue;
+            }
+            let wanted: Vec<f64> = rank
+                .iter()
+                .map(|&v| {
+                    let near = if rightward { &vertices[v].before } else { &vertices[v].after };
+                    if near.is_empty() { y[v] } else { near.iter().map(|&w| y[w]).sum::<f64>() / near.len() as f64 }
+                })
+                .collect();
+            // Packed top down, then moved as one to sit where they are wanted on average.
+            let mut packed = wanted.clone();
+            for i in 1..packed.len() {
+                packed[i] = packed[i].max(packed[i - 1] + gap(rank.len()));
+            }
+            let mean = |ys: &[f64]| ys.iter().sum::<f64>() / ys.len() as f64;
+            let mut shift = mean(&wanted) - mean(&packed);
+            let (top, bottom) = (packed[0], packed[packed.len() - 1]);
+            shift = shift.max(25.0 - HEIGHT / 2.0 - top).min(25.0 + HEIGHT / 2.0 - bottom);
+            if let Some(i) = rank.iter().position(|&v| v == center) {
+                shift = 25.0 - packed[i];
+            }
+            for (&v, p) in rank.iter().zip(packed) {
+                y[v] = p + shift;
+            }
+        }
+    }
+    y
+}
+
@@ -0,0 +1,901 @@
+//! The graph behind `riff-dag-tui`, for tools of your own: parse riff JSONL
+//! events, build the DAG they describe, and read it from any of the
+//! viewer's sources.
+//!
+//! - [`EventLine`] is one line of input, a node or an edge.
+//! - [`GraphModel`] is the graph built from them, [`NodeData`] on its nodes
+//!   and [`EdgeData`] on its edges, with the lookups the viewer navigates by
+//!   (`parents_of`, `lineage`, `shortest_path`, `roots` ...).
+//! - [`apply_event`] adds one event the way the viewer does: a redefined
+//!   node is resolved by a [`duplicate::DuplicatePolicy`], and an edge to a
+//!   node not seen yet waits for it.
+//! - [`ingest::Ingest`] reads files, tails, pipes, stdin, sockets, URLs and
+//!   journals on background threads, with [`import`] and [`extract`] turning
+//!   other formats into events; [`load::load`] applies all they deliver,
+//!   and [`GraphModel::from_jsonl`] reads one JSONL stream on the calling
+//!   thread.
+//! - [`query::Query`] is the filter language of `/` and `query`, and
+//!   [`view::matching`] lists the nodes one matches as the node list does.
+//!
+//! ```no_run
+//! use std::{fs::File, io::BufReader};
+//!
+//! let file = BufReader::new(File::open("run.jsonl")?);
+//! let (gm, warnings) = riff_dag_tui::GraphModel::from_jsonl(file, "run.jsonl")?;
+//! for idx in gm.roots() {
+//!     println!("{} ({} children)", gm.graph[idx].display_label(), gm.children_of(idx).len());
+//! }
+//! warnings.iter().for_each(|w| eprintln!("{}", w));
+//! # Ok::<(), std::io::Error>(())
+//! ```
+pub mod activity;
+pub mod alias;
+pub mod auth;
+pub mod cache;
+pub mod change;
+pub mod dot;
+pub mod duplicate;
+pub mod extract;
+pub mod fuzzy;
+#[cfg(feature = "http")]
+pub mod http;
+pub mod import;
+pub mod ingest;
+pub mod intern;
+#[cfg(feature = "journald")]
+pub mod journal;
+pub mod load;
+pub mod query;
+pub mod replace;
+pub mod revision;
+pub mod suggest;
+pub mod timefmt;
+#[cfg(feature = "tls")]
+pub mod tls;
+pub mod view;
+
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    io::{self, BufRead},
+    sync::Arc,
+};
+
+use itertools::Itertools;
+use petgraph::stable_graph::{EdgeIndex, NodeIndex, StableDiGraph};
+use petgraph::visit::EdgeRef;
+use petgraph::Direction::{Incoming, Outgoing};
+
+pub use crate::change::GraphChange;
+
+use crate::alias::Aliases;
+use crate::duplicate::{Conflict, DuplicatePolicy, SOURCES_KEY};
+use crate::ingest::Origin;
+
+/// Warnings (and conflicts) kept in memory; older ones are counted only.
+pub const MAX_WARNINGS: usize = 1000;
+
+/// One line of riff JSONL: `{"type": "node", ...}` or `{"type": "edge", ...}`.
+#[derive(Debug, Clone, PartialEq, serde::Deserialize)]
+#[serde(tag = "type")]
+pub enum EventLine {
+    #[serde(rename = "node")]
+    Node {
+        id: String,
+        label: Option<String>,
+        span: Option<String>,
+        tags: Option<Vec<String>>,
+        ts: Option<String>,
+        metadata: Option<serde_json::Map<String, serde_json::Value>>,
+        #[serde(alias = "confidence")]
+        score: Option<f64>,
+        /// Fields of the producer's own, like `duration_ms` or `model`:
+        /// kept on the node as they are and listed in its details.
+        #[serde(flatten)]
+        extra: serde_json::Map<String, serde_json::Value>,
+    },
+    #[serde(rename = "edge")]
+    Edge {
+        from: String,
+        to: String,
+        kind: Option<String>,
+        weight: Option<f64>,
+        ts: Option<String>,
+    },
+}
+
+impl EventLine {
+    /// An edge with nothing but its endpoints.
+    pub fn edge(from: impl Into<String>, to: impl Into<String>) -> Self {
+        EventLine::Edge { from: from.into(), to: to.into(), kind: None, weight: None, ts: None }
+    }
+}
+
+/// A node as the graph holds it, absent fields empty and metadata values
+/// as strings.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct NodeData {
+    #[serde(with = "intern::one")]
+    pub id: Arc<str>,
+    pub label: String,
+    #[serde(with = "intern::one")]
+    pub span: Arc<str>,
+    #[serde(with = "intern::many")]
+    pub tags: Vec<Arc<str>>,
+    pub ts: String,
+    pub metadata: BTreeMap<String, String>,
+    pub score: Option<f64>, // relevance or confidence, usually 0 to 1
+    #[serde(with = "json_values")]
+    pub extra: BTreeMap<String, serde_json::Value>, // fields of the producer's own, as they were given
+    #[serde(skip)]
+    pub raw: Payload,       // the line that defined it, as read
+}
+
+impl NodeData {
+    /// Whether the two have the same fields, however their lines were
+    /// written.
+    pub fn same_fields(&self, other: &NodeData) -> bool {
+        self.id == other.id
+            && self.label == other.label
+            && self.span == other.span
+            && self.tags == other.tags
+            && self.ts == other.ts
+            && self.metadata == other.metadata
+            && self.score == other.score
+            && self.extra == other.extra
+    }
+}
+
+/// Serde for `NodeData::extra`: a map of JSON values where the format can
+/// hold one, and each value as JSON text where it can't (bincode, for the
+/// graph cache, can't read a value back without knowing its type).
+mod json_values {
+    use std::collections::BTreeMap;
+
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+    use serde_json::Value;
+
+    pub fn serialize<S: Serializer>(values: &BTreeMap<String, Value>, serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            values.serialize(serializer)
+        } else {
+            serializer.collect_map(values.iter().map(|(k, v)| (k, v.to_string())))
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<BTreeMap<String, Value>, D::Error> {
+        if deserializer.is_human_readable() {
+            return BTreeMap::deserialize(deserializer);
+        }
+        BTreeMap::<String, String>::deserialize(deserializer)?
+            .into_iter()
+            .map(|(k, v)| serde_json::from_str(&v).map(|v| (k, v)).map_err(serde::de::Error::custom))
+            .collect()
+    }
+}
+
+/// The line a node event was read from, as it was read. It isn't
+/// serialized with the node: the graph cache keeps it apart, and JSON
+/// output has the fields already.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Payload(pub Option<Arc<str>>);
+
+/// An edge as the graph holds it, with where it was read.
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+pub struct EdgeData {
+    pub kind: String,
+    pub weight: Option<f64>,
+    pub ts: String,
+    pub seq: u64,     // arrival order among the graph's edges, from 1
+    pub at: String,   // source:line
+    pub waited: bool, // arrived before one of its nodes
+}
+
+/// An edge that arrived before one of its endpoints, held until the node
+/// shows up.
+#[derive(Debug)]
+pub struct PendingEdge {
+    pub from: String,
+    pub to: String,
+    pub data: EdgeData,
+    pub dedupe: bool, // added only if no edge joins the two nodes yet (alias folding)
+    pub raw: String,
+}
+
+impl PendingEdge {
+    /// The warning an edge that never resolved is reported with.
+    pub fn warning(&self) -> String {
+        format!("[warn] edge references missing node(s) at {}: {} -> {} (content: {})", self.data.at, self.from, self.to, self.raw)
+    }
+}
+
+impl NodeData {
+    pub fn display_label(&self) -> String {
+        if self.label.is_empty() {
+            self.id.to_string()
+        } else {
+            format!("{} · {}", self.id, self.label)
+        }
+    }
+
+    /// The lowercased text a filter or watch expression is matched against.
+    pub fn haystack(&self) -> String {
+        format!(
+            "{} {} {} {} {}",
+            self.id,
+            self.label,
+            self.span,
+            self.tags.join(" "),
+            self.metadata.values().join(" ")
+        )
+        .to_lowercase()
+    }
+}
+
+/// The DAG built from events: the graph, nodes by id, and what applying the
+/// events left behind (conflicts, revisions, edges still waiting).
+#[derive(Debug, Default)]
+pub struct GraphModel {
+    pub graph: StableDiGraph<NodeData, EdgeData>,
+    // map id -> node index
+    pub indices: HashMap<Arc<str>, NodeIndex>,
+    // (ts, id) -> node index: the stable order nodes are listed and exported in
+    ordered: BTreeMap<(String, Arc<str>), NodeIndex>,
+    pub conflicts: Vec<Conflict>,   // redefinitions that changed fields, up to MAX_WARNINGS
+    pub conflicts_elided: usize,
+    pub activity: activity::Activity, // when re-emitted nodes were updated
+    pub revisions: revision::Revisions, // the versions redefinitions replaced
+    pub aliases: alias::AliasState, // ids folded by the `[aliases]` rules
+    pending: BTreeMap<String, Vec<PendingEdge>>, // edges by the missing node they wait for
+    pub pending_count: usize,
+    pub edges_received: u64, // edge events applied so far, parked or not
+    changes: change::Subscribers, // told of every node and edge added, changed or removed
+}
+
+impl GraphModel {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Read a JSONL stream of events into a new graph, as the viewer reads a
+    /// file (redefinitions resolved by `DuplicatePolicy::Last`). Returns the
+    /// graph with a warning per line that didn't parse and per edge that
+    /// never found its nodes.
+    pub fn from_jsonl(reader: impl BufRead, source: &str) -> io::Result<(Self, Vec<String>)> {
+        let mut gm = Self::new();
+        let mut warnings = Vec::new();
+        let source: Arc<str> = Arc::from(source);
+        for (i, line) in reader.lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            match serde_json::from_str::<EventLine>(&line) {
+                Ok(ev) => {
+                    let origin = Origin { source: source.clone(), line: i + 1, raw: Some(Arc::from(line.as_str())) };
+                    warnings.extend(apply_event(&mut gm, ev, &origin, None, DuplicatePolicy::Last, false));
+                }
+                Err(err) => warnings.push(format!("[warn] bad JSON at {}:{}: {} (content: {})", source, i + 1, err, line)),
+            }
+        }
+        warnings.extend(gm.take_unresolved());
+        Ok((gm, warnings))
+    }
+    /// A graph of `events` alone, numbered as lines of `source`: what a
+    /// source resending its whole graph now has. Returns it with a warning
+    /// per event skipped and per edge that never found its nodes.
+    pub fn from_events(source: &Arc<str>, events: Vec<EventLine>, aliases: Option<&Aliases>, policy: DuplicatePolicy) -> (Self, Vec<String>) {
+        let mut gm = Self::new();
+        let mut warnings = Vec::new();
+        for (i, ev) in events.into_iter().enumerate() {
+            let origin = Origin { source: source.clone(), line: i + 1, raw: None };
+            warnings.extend(apply_event(&mut gm, ev, &origin, aliases, policy, false));
+        }
+        warnings.extend(gm.take_unresolved());
+        (gm, warnings)
+    }
+    /// A channel told of every change to this graph from now on (see `change`).
+    pub fn subscribe(&mut self) -> std::sync::mpsc::Receiver<GraphChange> {
+        self.changes.add()
+    }
+    pub fn upsert_node(&mut self, id: &str, nd: NodeData) -> NodeIndex {
+        if let Some(idx) = self.indices.get(id) {
+            let idx = *idx;
+            if let Some(node) = self.graph.node_weight_mut(idx) {
+                if node.ts != nd.ts {
+                    self.ordered.remove(&(std::mem::take(&mut node.ts), Arc::clone(&node.id)));
+                    self.ordered.insert((nd.ts.clone(), Arc::clone(&nd.id)), idx);
+                }
+                if !node.same_fields(&nd) {
+                    self.changes.send(GraphChange::NodeUpdated(Arc::clone(&nd.id)));
+                }
+                *node = nd;
+            }
+            idx
+        } else {
+            let key = (nd.ts.clone(), Arc::clone(&nd.id));
+            let idx = self.graph.add_node(nd);
+            self.indices.insert(Arc::clone(&key.1), idx);
+            self.ordered.insert(key, idx);
+            self.changes.send(GraphChange::NodeAdded(Arc::clone(&self.graph[idx].id)));
+            idx
+        }
+    }
+    /// The node `id`, added with every other field empty if it isn't there.
+    pub fn ensure_node_id(&mut self, id: &str) -> NodeIndex {
+        if let Some(idx) = self.indices.get(id) {
+            *idx
+        } else {
+            let nd = NodeData {
+                id: intern::intern(id),
+                label: String::new(),
+                span: intern::intern(""),
+                tags: Vec::new(),
+                ts: String::new(),
+                metadata: BTreeMap::new(),
+                score: None,
+                extra: BTreeMap::new(),
+                raw: Payload::default(),
+            };
+            self.upsert_node(id, nd)
+        }
+    }
+    /// Every node by timestamp, then id, so the same data always comes out in
+    /// the same order however it was streamed in. Nodes without a timestamp
+    /// come first.
+    pub fn stable_order(&self) -> impl Iterator<Item = NodeIndex> + '_ {
+        self.ordered.values().copied()
+    }
+    pub fn add_edge(&mut self, from: &str, to: &str, data: EdgeData) {
+        if let (Some(&a), Some(&b)) = (self.indices.get(from), self.indices.get(to)) {
+            self.join(a, b, data);
+        }
+    }
+    fn join(&mut self, a: NodeIndex, b: NodeIndex, data: EdgeData) {
+        self.graph.add_edge(a, b, data);
+        if self.changes.any() {
+            self.changes.send(GraphChange::EdgeAdded { from: Arc::clone(&self.graph[a].id), to: Arc::clone(&self.graph[b].id) });
+        }
+    }
+    /// Add `edge` if both its endpoints exist, or hold it until they do.
+    pub fn add_or_park(&mut self, edge: PendingEdge) {
+        let (a, b) = (self.indices.get(edge.from.as_str()).copied(), self.indices.get(edge.to.as_str()).copied());
+        match (a, b) {
+            (Some(a), Some(b)) => {
+                if !edge.dedupe || self.graph.find_edge(a, b).is_none() {
+                    self.join(a, b, edge.data);
+                }
+            }
+            (None, _) => {
+                self.pending_count += 1;
+                let mut edge = edge;
+                edge.data.waited = true;
+                self.pending.entry(edge.from.clone()).or_default().push(edge);
+            }
+            (_, None) => {
+                self.pending_count += 1;
+                let mut edge = edge;
+                edge.data.waited = true;
+                self.pending.entry(edge.to.clone()).or_default().push(edge);
+            }
+        }
+    }
+    /// Retry the edges that were waiting for node `id`.
+    pub fn resolve_pending(&mut self, id: &str) {
+        if let Some(edges) = self.pending.remove(id) {
+            self.pending_count -= edges.len();
+            for edge in edges {
+                self.add_or_park(edge);
+            }
+        }
+    }
+    /// Retry the edges that were waiting for node `id`, now that `aliases`
+    /// have folded it into another: they wait under the id they named, so
+    /// their endpoints are resolved again first. Those that fold into a
+    /// self-loop are dropped, as `Aliases::apply` drops them.
+    pub fn resolve_folded(&mut self, id: &str, aliases: &Aliases) {
+        if let Some(edges) = self.pending.remove(id) {
+            self.pending_count -= edges.len();
+            for mut edge in edges {
+                let (from, to) = (aliases.resolve(&self.aliases, &edge.from), aliases.resolve(&self.aliases, &edge.to));
+                if from == to && edge.from != edge.to {
+                    continue;
+                }
+                (edge.from, edge.to) = (from, to);
+                self.add_or_park(edge);
+            }
+        }
+    }
+    /// Edges still waiting, with the node each waits for.
+    pub fn pending_edges(&self) -> impl Iterator<Item = (&str, &PendingEdge)> {
+        self.pending.iter().flat_map(|(id, edges)| edges.iter().map(move |e| (id.as_str(), e)))
+    }
+    /// Give up on the waiting edges, returning their warnings.
+    pub fn take_unresolved(&mut self) -> Vec<String> {
+        self.pending_count = 0;
+        std::mem::take(&mut self.pending).into_values().flatten().map(|e| e.warning()).collect()
+    }
+    /// Give node `id` the tag `tag`; false when it had it already.
+    pub fn add_tag(&mut self, id: &str, tag: &str) -> bool {
+        let Some(&idx) = self.indices.get(id) else { return false };
+        let tags = &mut self.graph[idx].tags;
+        if tags.iter().any(|t| **t == *tag) {
+            return false;
+        }
+        tags.push(intern::intern(tag));
+        self.changes.send(GraphChange::NodeUpdated(Arc::clone(&self.graph[idx].id)));
+        true
+    }
+    /// Rename tag `old` to `new` on every node, or with `None` remove it.
+    /// Returns how many nodes had it.
+    pub fn rename_tag(&mut self, old: &str, new: Option<&str>) -> usize {
+        let mut changed = Vec::new();
+        for nd in self.graph.node_weights_mut() {
+            let Some(pos) = nd.tags.iter().position(|t| **t == *old) else { continue };
+            changed.push(Arc::clone(&nd.id));
+            match new {
+                Some(new) if !nd.tags.iter().any(|t| **t == *new) => nd.tags[pos] = intern::intern(new),
+                _ => {
+                    nd.tags.remove(pos);
+                }
+            }
+        }
+        let count = changed.len();
+        for id in changed {
+            self.changes.send(GraphChange::NodeUpdated(id));
+        }
+        count
+    }
+    /// Replace `re` with `with` in every label and tag. Returns how many
+    /// nodes changed.
+    pub fn replace_text(&mut self, re: &regex::Regex, with: &str) -> usize {
+        let mut changed = Vec::new();
+        for nd in self.graph.node_weights_mut() {
+            if let Some(new) = replace::rewrite(re, with, nd) {
+                *nd = new;
+                changed.push(Arc::clone(&nd.id));
+            }
+        }
+        let count = changed.len();
+        for id in changed {
+            self.changes.send(GraphChange::NodeUpdated(id));
+        }
+        count
+    }
+    /// Take `new`'s nodes and edges in place of these (a source resending its
+    /// whole graph), telling subscribers what was removed, added and changed.
+    /// The subscribers stay; the rest of `new` (conflicts, revisions ...)
+    /// replaces this graph's.
+    pub fn replace(&mut self, new: GraphModel) {
+        let mut changes = Vec::new();
+        if self.changes.any() {
+            let (old_edges, new_edges) = (self.edge_ids(), new.edge_ids());
+            for ((from, to), n) in &old_edges {
+                let kept = new_edges.get(&(from.clone(), to.clone())).copied().unwrap_or(0);
+                changes.extend((kept..*n).map(|_| GraphChange::EdgeRemoved { from: from.clone(), to: to.clone() }));
+            }
+            let removed = self.stable_order().map(|idx| &self.graph[idx].id).filter(|id| !new.indices.contains_key(*id));
+            changes.extend(removed.map(|id| GraphChange::NodeRemoved(id.clone())));
+            for idx in new.stable_order() {
+                let id = &new.graph[idx].id;
+                match self.indices.get(id) {
+                    None => changes.push(GraphChange::NodeAdded(id.clone())),
+                    Some(&old) if !self.graph[old].same_fields(&new.graph[idx]) => changes.push(GraphChange::NodeUpdated(id.clone())),
+                    Some(_) => {}
+                }
+            }
+            for ((from, to), n) in &new_edges {
+                let had = old_edges.get(&(from.clone(), to.clone())).copied().unwrap_or(0);
+                changes.extend((had..*n).map(|_| GraphChange::EdgeAdded { from: from.clone(), to: to.clone() }));
+            }
+        }
+        let changes_to = std::mem::take(&mut self.changes);
+        *self = new;
+        self.changes = changes_to;
+        for change in changes {
+            self.changes.send(change);
+        }
+    }
+    /// How many edges join each pair of node ids.
+    fn edge_ids(&self) -> BTreeMap<(Arc<str>, Arc<str>), usize> {
+        let mut counts = BTreeMap::new();
+        for e in self.graph.edge_indices() {
+            if let Some((a, b)) = self.graph.edge_endpoints(e) {
+                *counts.entry((Arc::clone(&self.graph[a].id), Arc::clone(&self.graph[b].id))).or_default() += 1;
+            }
+        }
+        counts
+    }
+    /// Edges into `idx`, then edges out of it (the order the details pane lists them).
+    pub fn incident_edges(&self, idx: NodeIndex) -> Vec<EdgeIndex> {
+        let incoming = self.graph.edges_directed(idx, Incoming).map(|e| e.id());
+        let outgoing = self.graph.edges_directed(idx, Outgoing).map(|e| e.id());
+        incoming.chain(outgoing).collect()
+    }
+    pub fn parents_of(&self, idx: NodeIndex) -> Vec<NodeIndex> {
+        self.graph.neighbors_directed(idx, Incoming).collect()
+    }
+    pub fn children_of(&self, idx: NodeIndex) -> Vec<NodeIndex> {
+        self.graph.neighbors_directed(idx, Outgoing).collect()
+    }
+    /// Every node reachable from `idx` against (`Incoming`) or along
+    /// (`Outgoing`) the edges, by hop distance: index 0 holds the nodes one
+    /// hop away. Each node appears once, at its shortest distance.
+    pub fn lineage(&self, idx: NodeIndex, dir: petgraph::Direction) -> Vec<Vec<NodeIndex>> {
+        let mut seen: HashSet<NodeIndex> = HashSet::from([idx]);
+        let mut levels: Vec<Vec<NodeIndex>> = Vec::new();
+        let mut frontier = vec![idx];
+        while !frontier.is_empty() {
+            let next: Vec<NodeIndex> = frontier
+                .iter()
+                .flat_map(|&n| self.graph.neighbors_directed(n, dir))
+                .filter(|&n| seen.insert(n))
+                .collect();
+            if !next.is_empty() {
+                levels.push(next.clone());
+            }
+            frontier = next;
+        }
+        levels
+    }
+    /// The fewest edges leading from `from` to `to`, both included.
+    pub fn shortest_path(&self, from: NodeIndex, to: NodeIndex) -> Option<Vec<NodeIndex>> {
+        let mut came_from: HashMap<NodeIndex, NodeIndex> = HashMap::new();
+        let mut queue = std::collections::VecDeque::from([from]);
+        while let Some(n) = queue.pop_front() {
+            if n == to {
+                let mut path = vec![to];
+                while let Some(&prev) = path.last().and_then(|last| came_from.get(last)) {
+                    path.push(prev);
+                }
+                path.reverse();
+                return Some(path);
+            }
+            for next in self.graph.neighbors_directed(n, Outgoing) {
+                if next != from && !came_from.contains_key(&next) {
+                    came_from.insert(next, n);
+                    queue.push_back(next);
+                }
+            }
+        }
+        None
+    }
+    /// `idx` with every ancestor and descendant it has.
+    pub fn closure(&self, idx: NodeIndex) -> HashSet<NodeIndex> {
+        let mut closure = HashSet::from([idx]);
+        closure.extend(self.lineage(idx, Incoming).into_iter().flatten());
+        closure.extend(self.lineage(idx, Outgoing).into_iter().flatten());
+        closure
+    }
+    /// A copy of the nodes within `hops` edges of `idx`, whichever way the
+    /// edges run, and the edges between them.
+    pub fn neighborhood(&self, idx: NodeIndex, hops: usize) -> GraphModel {
+        let mut kept = HashSet::from([idx]);
+        let mut frontier = vec![idx];
+        for _ in 0..hops {
+            frontier = frontier.iter().flat_map(|&n| self.graph.neighbors_undirected(n)).filter(|&n| kept.insert(n)).collect();
+        }
+        let mut gm = GraphModel::new();
+        for n in self.stable_order().filter(|n| kept.contains(n)) {
+            gm.upsert_node(&self.graph[n].id, self.graph[n].clone());
+        }
+        for e in self.graph.edge_indices() {
+            if let Some((a, b)) = self.graph.edge_endpoints(e).filter(|(a, b)| kept.contains(a) && kept.contains(b)) {
+                gm.add_edge(&self.graph[a].id, &self.graph[b].id, self.graph[e].clone());
+            }
+        }
+        gm
+    }
+    /// A copy of the nodes and edges; conflicts, activity, revisions and
+    /// alias state stay with this graph.
+    pub fn freeze(&self) -> GraphModel {
+        GraphModel { graph: self.graph.clone(), indices: self.indices.clone(), ordered: self.ordered.clone(), ..GraphModel::default() }
+    }
+    pub fn degree(&self, idx: NodeIndex) -> (usize, usize) {
+        (self.parents_of(idx).len(), self.children_of(idx).len())
+    }
+    /// Likely entry points: nodes without parents, earliest timestamp first
+    /// (untimestamped ones last), then the ones with most children.
+    pub fn roots(&self) -> Vec<NodeIndex> {
+        let mut roots: Vec<NodeIndex> = self.stable_order().filter(|&idx| self.graph.neighbors_directed(idx, Incoming).next().is_none()).collect();
+        // Stable, so equal keys keep the (ts, id) order.
+        roots.sort_by(|&a, &b| {
+            let (x, y) = (&self.graph[a], &self.graph[b]);
+            (x.ts.is_empty(), &x.ts)
+                .cmp(&(y.ts.is_empty(), &y.ts))
+                .then_with(|| self.children_of(b).len().cmp(&self.children_of(a).len()))
+        });
+        roots
+    }
+}
+
+/// Apply one parsed event to the graph; returns a warning when it had to be
+/// skipped. Edges naming a node not seen yet wait in `pending` for it. With
+/// `tag_source`, the node's `sources` metadata gains the event's source.
+pub fn apply_event(
+    gm: &mut GraphModel,
+    ev: EventLine,
+    origin: &Origin,
+    aliases: Option<&Aliases>,
+    policy: DuplicatePolicy,
+    tag_source: bool,
+) -> Option<String> {
+    let named = match &ev {
+        EventLine::Node { id, .. } => Some(id.clone()),
+        EventLine::Edge { .. } => None,
+    };
+    let ev = match aliases {
+        Some(aliases) => aliases.apply(&mut gm.aliases, ev)?,
+        None => ev,
+    };
+    match ev {
+        EventLine::Node { id, label, span, tags, ts, metadata, score, extra } => {
+            let metadata = metadata
+                .unwrap_or_default()
+                .into_iter()
+                .map(|(k, v)| match v {
+                    serde_json::Value::String(s) => (k, s),
+                    other => (k, other.to_string()),
+                })
+                .collect();
+            let nd = NodeData {
+                id: intern::intern(&id),
+                label: label.unwrap_or_default(),
+                span: intern::intern(&span.unwrap_or_default()),
+                tags: intern::intern_all(tags.unwrap_or_default()),
+                ts: ts.unwrap_or_default(),
+                metadata,
+                score,
+                extra: extra.into_iter().collect(),
+                raw: Payload(origin.raw.clone()),
+            };
+            let known = gm.indices.contains_key(id.as_str());
+            // Taken before a redefinition can replace the node.
+            let sources = tag_source.then(|| {
+                let listed = gm.indices.get(id.as_str()).and_then(|&idx| gm.graph[idx].metadata.get(SOURCES_KEY));
+                let mut sources: Vec<&str> = listed.map_or_else(Vec::new, |s| s.split(", ").collect());
+                if !sources.contains(&&*origin.source) {
+                    sources.push(&origin.source);
+                }
+                sources.join(", ")
+            });
+            let before = gm.indices.get(id.as_str()).map(|&idx| gm.graph[idx].clone());
+            let nd = match gm.indices.get(id.as_str()) {
+                Some(&idx) => {
+                    gm.activity.touch(&id);
+                    let (nd, fields) = policy.resolve(&gm.graph[idx], nd);
+                    if !fields.is_empty() {
+                        if gm.conflicts.len() < MAX_WARNINGS {
+                            let at = format!("{}:{}", origin.source, origin.line);
+                            gm.conflicts.push(Conflict { id: id.clone(), at, fields, policy });
+                        } else {
+                            gm.conflicts_elided += 1;
+                        }
+                    }
+                    nd
+                }
+                None => Some(nd),
+            };
+            match (nd, sources) {
+                (Some(mut nd), sources) => {
+                    if let Some(sources) = sources {
+                        nd.metadata.insert(SOURCES_KEY.to_string(), sources);
+                    }
+                    gm.upsert_node(&id, nd);
+                    if !known {
+                        gm.resolve_pending(&id);
+                    }
+                }
+                // The node is kept as it was, but still lists this source.
+                (None, Some(sources)) => {
+                    let idx = gm.indices[id.as_str()];
+                    if gm.graph[idx].metadata.insert(SOURCES_KEY.to_string(), sources.clone()).as_ref() != Some(&sources) {
+                        gm.changes.send(GraphChange::NodeUpdated(Arc::clone(&gm.graph[idx].id)));
+                    }
+                }
+                (None, None) => {}
+            }
+            // Edges that named the node before it was folded wait under that name.
+            if let (Some(aliases), Some(named)) = (aliases, named.filter(|named| *named != id)) {
+                gm.resolve_folded(&named, aliases);
+            }
+            if let (Some(before), Some(&idx)) = (before, gm.indices.get(id.as_str())) {
+                if !before.same_fields(&gm.graph[idx]) {
+                    gm.revisions.record(before);
+                }
+            }
+            None
+        }
+        EventLine::Edge { from, to, kind, weight, ts } => {
+            gm.edges_received += 1;
+            gm.add_or_park(PendingEdge {
+                from,
+                to,
+                data: EdgeData {
+                    kind: kind.unwrap_or_default(),
+                    weight,
+                    ts: ts.unwrap_or_default(),
+                    seq: gm.edges_received,
+                    at: format!("{}:{}", origin.source, origin.line),
+                    waited: false,
+                },
+                // Merged nodes would otherwise collect one parallel edge per alias.
+                dedupe: aliases.is_some(),
+                raw: origin.raw.as_deref().unwrap_or("").to_string(),
+            });
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(json: &str) -> EventLine {
+        serde_json::from_str(json).unwrap()
+    }
+
+    fn apply(gm: &mut GraphModel, events: &[&str], policy: DuplicatePolicy) {
+        let source: Arc<str> = Arc::from("test.jsonl");
+        for (i, json) in events.iter().enumerate() {
+            let origin = Origin { source: source.clone(), line: i + 1, raw: None };
+            assert_eq!(apply_event(gm, event(json), &origin, None, policy, false), None);
+        }
+    }
+
+    fn label(gm: &GraphModel, id: &str) -> String {
+        gm.graph[gm.indices[id]].label.clone()
+    }
+
+    fn id(name: &str) -> Arc<str> {
+        Arc::from(name)
+    }
+
+    #[test]
+    fn an_edge_before_its_nodes_waits_for_them() {
+        let mut gm = GraphModel::new();
+        apply(&mut gm, &[r#"{"type":"edge","from":"a","to":"b"}"#, r#"{"type":"node","id":"a"}"#], DuplicatePolicy::Last);
+        assert_eq!(gm.graph.edge_count(), 0);
+        assert_eq!(gm.pending_count, 1);
+        assert_eq!(gm.pending_edges().map(|(waits_for, _)| waits_for).collect::<Vec<_>>(), ["b"]);
+
+        apply(&mut gm, &[r#"{"type":"node","id":"b"}"#], DuplicatePolicy::Last);
+        assert_eq!(gm.pending_count, 0);
+        assert_eq!(gm.pending_edges().count(), 0);
+        let e = gm.graph.find_edge(gm.indices["a"], gm.indices["b"]).expect("edge resolved");
+        assert!(gm.graph[e].waited);
+        assert!(gm.take_unresolved().is_empty());
+    }
+
+    #[test]
+    fn an_edge_whose_node_never_comes_is_warned_about() {
+        let (gm, warnings) = GraphModel::from_events(&id("test.jsonl"), vec![EventLine::edge("a", "b")], None, DuplicatePolicy::Last);
+        assert_eq!(gm.graph.edge_count(), 0);
+        assert_eq!(gm.pending_count, 0);
+        assert_eq!(warnings.len(), 1);
+    }
+
+    #[test]
+    fn duplicate_policies_decide_what_a_redefinition_keeps() {
+        let first = r#"{"type":"node","id":"a","label":"one","tags":["x"]}"#;
+        let second = r#"{"type":"node","id":"a","label":"two","tags":["y"]}"#;
+        let cases = [
+            (DuplicatePolicy::Last, "two", vec!["y"]),
+            (DuplicatePolicy::First, "one", vec!["x"]),
+            (DuplicatePolicy::Merge, "two", vec!["x", "y"]),
+            (DuplicatePolicy::Error, "one", vec!["x"]),
+        ];
+        for (policy, kept, tags) in cases {
+            let mut gm = GraphModel::new();
+            apply(&mut gm, &[first, second], policy);
+            assert_eq!(label(&gm, "a"), kept, "{:?}", policy);
+            let mut got: Vec<&str> = gm.graph[gm.indices["a"]].tags.iter().map(|t| &**t).collect();
+            got.sort();
+            assert_eq!(got, tags, "{:?}", policy);
+            assert_eq!(gm.conflicts.len(), 1, "{:?}", policy);
+            assert_eq!((gm.conflicts[0].at.as_str(), gm.conflicts[0].policy), ("test.jsonl:2", policy));
+            assert_eq!(gm.conflicts[0].fields, ["label", "tags"]);
+        }
+    }
+
+    #[test]
+    fn a_redefinition_that_only_adds_fields_is_no_conflict() {
+        let mut gm = GraphModel::new();
+        apply(&mut gm, &[r#"{"type":"node","id":"a","label":"one"}"#, r#"{"type":"node","id":"a","span":"s"}"#], DuplicatePolicy::Error);
+        assert!(gm.conflicts.is_empty());
+        let node = &gm.graph[gm.indices["a"]];
+        assert_eq!((node.label.as_str(), &*node.span), ("one", "s"));
+    }
+
+    #[test]
+    fn subscribers_hear_of_nodes_and_edges_as_they_change() {
+        let mut gm = GraphModel::new();
+        let rx = gm.subscribe();
+        apply(
+            &mut gm,
+            &[
+                r#"{"type":"node","id":"a","label":"one"}"#,
+                r#"{"type":"edge","from":"a","to":"b"}"#,
+                r#"{"type":"node","id":"b"}"#,
+                r#"{"type":"node","id":"a","label":"one"}"#,
+                r#"{"type":"node","id":"a","label":"two"}"#,
+            ],
+            DuplicatePolicy::Last,
+        );
+        assert_eq!(
+            rx.try_iter().collect::<Vec<_>>(),
+            [
+                GraphChange::NodeAdded(id("a")),
+                GraphChange::NodeAdded(id("b")),
+                GraphChange::EdgeAdded { from: id("a"), to: id("b") },
+                GraphChange::NodeUpdated(id("a")),
+            ]
+        );
+        drop(rx);
+        apply(&mut gm, &[r#"{"type":"node","id":"c"}"#], DuplicatePolicy::Last);
+        assert!(!gm.changes.any());
+    }
+
+    #[test]
+    fn replace_reports_what_the_new_graph_changed() {
+        let source = id("test.jsonl");
+        let (mut gm, _) = GraphModel::from_events(
+            &source,
+            vec![event(r#"{"type":"node","id":"a"}"#), event(r#"{"type":"node","id":"b"}"#), EventLine::edge("a", "b")],
+            None,
+            DuplicatePolicy::Last,
+        );
+        let rx = gm.subscribe();
+        let (new, _) = GraphModel::from_events(
+            &source,
+            vec![event(r#"{"type":"node","id":"a","label":"one"}"#), event(r#"{"type":"node","id":"c"}"#), EventLine::edge("a", "c")],
+            None,
+            DuplicatePolicy::Last,
+        );
+        gm.replace(new);
+        assert_eq!(
+            rx.try_iter().collect::<Vec<_>>(),
+            [
+                GraphChange::EdgeRemoved { from: id("a"), to: id("b") },
+                GraphChange::NodeRemoved(id("b")),
+                GraphChange::NodeUpdated(id("a")),
+                GraphChange::NodeAdded(id("c")),
+                GraphChange::EdgeAdded { from: id("a"), to: id("c") },
+            ]
+        );
+        assert!(gm.indices.contains_key("c") && !gm.indices.contains_key("b"));
+        // The subscription outlives the graph it was taken on.
+        gm.upsert_node("d", gm.graph[gm.indices["c"]].clone());
+        assert_eq!(rx.try_iter().count(), 1);
+    }
+
+    #[test]
+    fn lineage_and_shortest_path_follow_the_edges() {
+        let mut gm = GraphModel::new();
+        let events = [("a", "b"), ("b", "c"), ("a", "d"), ("d", "c"), ("c", "e")].map(|(from, to)| EventLine::edge(from, to));
+        for n in ["a", "b", "c", "d", "e"] {
+            gm.ensure_node_id(n);
+        }
+        let origin = Origin { source: id("test.jsonl"), line: 1, raw: None };
+        for ev in events {
+            apply_event(&mut gm, ev, &origin, None, DuplicatePolicy::Last, false);
+        }
+        let ids = |nodes: &[NodeIndex]| nodes.iter().map(|&n| gm.graph[n].id.to_string()).sorted().collect::<Vec<_>>();
+        let levels = gm.lineage(gm.indices["a"], Outgoing);
+        assert_eq!(levels.iter().map(|level| ids(level)).collect::<Vec<_>>(), [vec!["b", "d"], vec!["c"], vec!["e"]]);
+        assert_eq!(gm.lineage(gm.indices["c"], Incoming).len(), 2);
+        let path = gm.shortest_path(gm.indices["a"], gm.indices["e"]).expect("a reaches e");
+        assert_eq!(path.len(), 4);
+        assert_eq!((path[0], path[3]), (gm.indices["a"], gm.indices["e"]));
+        assert_eq!(gm.shortest_path(gm.indices["e"], gm.indices["a"]), None);
+    }
+
+    #[test]
+    fn ensure_node_id_adds_a_bare_node_once() {
+        let mut gm = GraphModel::new();
+        let idx = gm.ensure_node_id("a");
+        assert_eq!(&*gm.graph[idx].id, "a");
+        assert!(gm.graph[idx].label.is_empty() && gm.graph[idx].tags.is_empty());
+        assert_eq!(gm.ensure_node_id("a"), idx);
+        assert_eq!(gm.graph.node_count(), 1);
+    }
+}
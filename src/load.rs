@@ -0,0 +1,97 @@
+//! Reading every source of an [`Ingest`] into one graph, on the calling
+//! thread and without a UI, as the viewer's `validate`, `metrics`, `stats`,
+//! `query` and `diff` do.
+//!
+//! ```no_run
+//! use riff_dag_tui::{auth::Credentials, duplicate::DuplicatePolicy, ingest::Ingest, load};
+//!
+//! let ingest = Ingest::new();
+//! ingest.spawn_input("run.jsonl", false, &mut Credentials::default())?;
+//! let loaded = load::load(&ingest, None, DuplicatePolicy::Last, &[]);
+//! println!("{} nodes, {} warnings", loaded.gm.graph.node_count(), loaded.warning_count());
+//! # Ok::<(), color_eyre::eyre::Report>(())
+//! ```
+use std::{sync::atomic::Ordering, thread, time::Duration};
+
+use crate::{
+    alias::Aliases,
+    apply_event,
+    duplicate::DuplicatePolicy,
+    ingest::{Ingest, IngestMsg},
+    GraphModel, MAX_WARNINGS,
+};
+
+/// Time spent applying events between checks on the sources.
+pub const BUDGET: Duration = Duration::from_millis(30);
+
+/// Wait between drains while the sources are still sending.
+pub const POLL: Duration = Duration::from_millis(10);
+
+/// A graph read in full, with what reading it gave to report.
+#[derive(Debug, Default)]
+pub struct Loaded {
+    pub gm: GraphModel,
+    pub warnings: Vec<String>,   // the first `MAX_WARNINGS`, secrets masked
+    pub warnings_elided: usize, // the rest, only counted
+}
+
+impl Loaded {
+    pub fn warning_count(&self) -> usize {
+        self.warnings.len() + self.warnings_elided
+    }
+
+    fn warn(&mut self, w: String, secrets: &[String]) {
+        if self.warnings.len() < MAX_WARNINGS {
+            self.warnings.push(mask_secrets(w, secrets));
+        } else {
+            self.warnings_elided += 1;
+        }
+    }
+}
+
+/// Apply everything `ingest` delivers until every source is done, then give
+/// up on the edges still waiting for a node. A source resending its whole
+/// graph replaces what it sent before.
+pub fn load(ingest: &Ingest, aliases: Option<&Aliases>, policy: DuplicatePolicy, secrets: &[String]) -> Loaded {
+    let mut loaded = Loaded::default();
+    loop {
+        // Read before draining: a source sends all it has before it stops counting as live.
+        let live = ingest.stats().live.load(Ordering::Relaxed);
+        let (msgs, backlog) = ingest.drain(BUDGET);
+        if msgs.is_empty() && live == 0 {
+            break;
+        }
+        for msg in msgs {
+            let warnings = match msg {
+                IngestMsg::Event(ev, origin) => apply_event(&mut loaded.gm, ev, &origin, aliases, policy, false).into_iter().collect(),
+                IngestMsg::Snapshot { source, events } => {
+                    let (gm, warnings) = GraphModel::from_events(&source, events, aliases, policy);
+                    loaded.gm.replace(gm);
+                    warnings
+                }
+                IngestMsg::Warning(w) => vec![w],
+                IngestMsg::SourceOpened { .. } => Vec::new(),
+            };
+            for w in warnings {
+                loaded.warn(w, secrets);
+            }
+        }
+        if !backlog {
+            thread::sleep(POLL);
+        }
+    }
+    for w in loaded.gm.take_unresolved() {
+        loaded.warn(w, secrets);
+    }
+    loaded
+}
+
+/// `w` with every one of `secrets` in it replaced by `***`.
+pub fn mask_secrets(mut w: String, secrets: &[String]) -> String {
+    for secret in secrets {
+        if w.contains(secret.as_str()) {
+            w = w.replace(secret.as_str(), "***");
+        }
+    }
+    w
+}
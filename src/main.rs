@@ -3,127 +3,412 @@
 //! - Left pane: Node list (filterable)
 //! - Top-right: Node details for selection
 //! - Bottom-right: Layered DAG textual view (depth-limited)
+//!
+//! The graph itself, its events and the sources they are read from live in
+//! the `riff_dag_tui` library; this is the front-end drawn over it.
+mod alerts;
+mod assertions;
+mod bookmarks;
+mod compare;
+mod compat;
+mod config;
+mod control;
+mod cycles;
+mod emit;
+mod export;
+mod group;
+mod headless;
+mod lanes;
+mod layered;
+mod metrics;
+mod palette;
+mod payload;
+mod pattern;
+mod preview;
+mod quality;
+mod redact;
+mod sample;
+mod snapshot;
+mod stats;
+mod tag_edit;
+mod template;
+mod theme;
+mod tutorial;
+mod watch;
+
 use std::{
-    collections::{HashMap, VecDeque},
-    fs::File,
-    io::{self, BufRead, BufReader},
-    time::{Duration, Instant},
+    collections::{BTreeMap, HashMap, HashSet},
+    fs::{File, OpenOptions},
+    io::{self, IsTerminal, Write},
+    path::Path,
+    sync::{atomic::Ordering, Arc},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
 };
 
-use clap::Parser;
+use clap::{Parser, Subcommand, ValueEnum};
 use color_eyre::eyre::{eyre, Result, WrapErr};
 use crossterm::{
-    event::{self, Event, KeyCode, KeyEvent, KeyModifiers},
+    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEvent, KeyModifiers, MouseButton, MouseEvent, MouseEventKind},
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
 use itertools::Itertools;
-use petgraph::stable_graph::{NodeIndex, StableDiGraph};
+use petgraph::stable_graph::{EdgeIndex, NodeIndex};
+use petgraph::visit::EdgeRef;
 use petgraph::Direction::{Incoming, Outgoing};
 use ratatui::{
     backend::CrosstermBackend,
-    layout::{Constraint, Direction, Layout, Rect},
+    layout::{Constraint, Direction, Layout, Margin, Rect},
     style::{Color, Modifier, Style},
     text::{Line, Span},
     widgets::{Block, Borders, canvas::Canvas, List, ListItem, ListState, Paragraph, Wrap, Clear},
     Terminal,
 };
 
-#[derive(Debug, Clone, serde::Deserialize)]
-#[serde(tag = "type")]
-enum EventLine {
-    #[serde(rename = "node")]
-    Node {
-        id: String,
-        label: Option<String>,
-        span: Option<String>,
-        tags: Option<Vec<String>>,
-        ts: Option<String>,
-    },
-    #[serde(rename = "edge")]
-    Edge { from: String, to: String },
+use riff_dag_tui::{alias, auth, cache, dot, duplicate, extract, fuzzy, import, ingest, load, query, replace, revision, suggest, timefmt, view};
+#[cfg(feature = "tls")]
+use riff_dag_tui::tls;
+use riff_dag_tui::{apply_event, EdgeData, EventLine, GraphModel, NodeData, Payload, MAX_WARNINGS};
+
+use crate::alerts::{Alert, AlertConfig};
+use crate::alias::Aliases;
+use crate::assertions::{Assertions, Violation};
+use crate::auth::Credentials;
+use crate::bookmarks::Bookmarks;
+use crate::cache::CacheKey;
+use crate::compat::Compat;
+use crate::config::Config;
+use crate::control::{Call, Control, Request};
+use crate::compare::Comparison;
+use crate::cycles::Cycles;
+use crate::duplicate::{DuplicatePolicy, SOURCES_KEY};
+use crate::emit::{EmitTarget, SelectionEmitter};
+use crate::group::Groups;
+use crate::headless::Headless;
+use crate::metrics::MetricsFormat;
+use crate::palette::Palette;
+use crate::ingest::{Ingest, IngestMsg};
+use crate::sample::{Sample, Sampled};
+use crate::snapshot::{Change, Diff, Snapshot};
+use crate::stats::{Stats, StatsFormat};
+use crate::tag_edit::{TagAction, TagPrompt};
+use crate::tutorial::{Scenario, Tutorial};
+use crate::pattern::Pattern;
+use crate::quality::{Algorithm, Quality};
+use crate::query::Query;
+use crate::view::SortOrder;
+use crate::replace::ReplacePrompt;
+use crate::template::LabelTemplate;
+use crate::theme::Theme;
+use crate::timefmt::TimeDisplay;
+use crate::watch::Watches;
+
+/// Time the UI thread may spend applying ingested events per loop iteration.
+const INGEST_BUDGET: Duration = Duration::from_millis(30);
+
+/// Poll timeout while events are flowing, so workers never sit on a full channel.
+const STREAM_POLL: Duration = Duration::from_millis(10);
+
+/// Characters `[` / `]` scroll the node list by.
+const LIST_SCROLL_STEP: usize = 8;
+
+/// Edges either way from the selection a tab opened with `T` reaches.
+const NEIGHBORHOOD_HOPS: usize = 2;
+
+/// Jumps each tab remembers for going back.
+const HISTORY_LIMIT: usize = 200;
+
+/// Canvas units between the strokes of a heavy edge.
+const EDGE_STROKE_GAP: f64 = 0.8;
+
+/// Characters of a node's name printed beside it on the canvas.
+const CANVAS_LABEL: usize = 16;
+
+/// How far out and in the canvas zooms (`-`/`+` in canvas focus), and by how much a step.
+const VIEWPORT_ZOOM: (f64, f64) = (0.25, 8.0);
+const VIEWPORT_ZOOM_STEP: f64 = 1.25;
+
+/// Nodes the lineage details list per hop; the rest are only counted.
+const LINEAGE_LIST: usize = 50;
+
+#[derive(Parser, Debug)]
+#[command(name = "riff-dag-tui")]
+#[command(about = "Three-pane DAG inspector for riff/memory spans")]
+struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+    /// Optional path (or http/https URL) of a JSONL file with node/edge
+    /// events; `-` reads standard input. Repeat it, or name a directory, to
+    /// merge several files into one graph
+    #[arg(short, long, group = "input_source")]
+    input: Vec<String>,
+    /// The same as --input, e.g. `my-producer | riff-dag-tui -`
+    #[arg(value_name = "INPUT", group = "input_source")]
+    input_arg: Vec<String>,
+    /// Keep reading the input file as it grows (like `tail -f`)
+    #[arg(short, long, requires = "input_source")]
+    follow: bool,
+    /// Accept JSONL event streams on a TCP address (e.g. 127.0.0.1:7878)
+    #[arg(long, value_name = "ADDR")]
+    listen: Option<String>,
+    /// Connect to a TCP address and read its JSONL event stream
+    #[arg(long, value_name = "ADDR")]
+    connect: Option<String>,
+    /// Append every parse warning and skipped edge to this file
+    #[arg(long, value_name = "PATH")]
+    error_log: Option<String>,
+    /// Config file (default: ~/.config/riff-dag-tui/config.toml)
+    #[arg(long, value_name = "PATH")]
+    config: Option<String>,
+    /// Show timestamps in utc, local time or at an offset like +05:30
+    /// (overrides config; default: as written)
+    #[arg(long, value_name = "ZONE")]
+    time_zone: Option<String>,
+    /// Timestamp format: a strftime pattern or `relative` (overrides config)
+    #[arg(long, value_name = "FORMAT")]
+    time_format: Option<String>,
+    /// UI tick in milliseconds while sources are live (overrides config)
+    #[arg(long, value_name = "MS")]
+    tick_rate: Option<u64>,
+    /// Always re-parse the input instead of restoring a cached snapshot
+    #[arg(long)]
+    no_cache: bool,
+    /// Show only a sample of each graph: top-degree:N, random:N or bfs:N
+    /// (`x` expands around the selection)
+    #[arg(long, value_name = "STRATEGY:N")]
+    sample: Option<Sample>,
+    /// Load every .jsonl file in this directory, including ones created later
+    #[arg(long, value_name = "DIR")]
+    watch_dir: Option<String>,
+    /// TOML file with per-host credentials for remote sources
+    #[arg(long, value_name = "PATH")]
+    credentials: Option<String>,
+    /// TOML file of graph assertions, checked on load (see `validate`)
+    #[arg(long, value_name = "PATH")]
+    assertions: Option<String>,
+    /// Like --listen, but clients must connect over TLS (needs --tls-cert and --tls-key)
+    #[arg(long, value_name = "ADDR", requires_all = ["tls_cert", "tls_key"])]
+    listen_tls: Option<String>,
+    /// Like --connect, but over TLS with certificate verification
+    #[arg(long, value_name = "ADDR")]
+    connect_tls: Option<String>,
+    /// PEM certificate chain served by --listen-tls
+    #[arg(long, value_name = "PATH", requires = "listen_tls")]
+    tls_cert: Option<String>,
+    /// PEM private key for --tls-cert
+    #[arg(long, value_name = "PATH", requires = "listen_tls")]
+    tls_key: Option<String>,
+    /// PEM CA bundle trusted by --connect-tls (default: web PKI roots)
+    #[arg(long, value_name = "PATH", requires = "connect_tls")]
+    tls_ca: Option<String>,
+    /// Follow this systemd unit's journal (repeatable; overrides [journal] units)
+    #[arg(long = "journal-unit", value_name = "UNIT")]
+    journal_units: Vec<String>,
+    /// What to do when a node id is defined again with different data
+    #[arg(long, value_enum, value_name = "POLICY", default_value_t = DuplicatePolicy::Last)]
+    on_duplicate: DuplicatePolicy,
+    /// Count the nodes matching this filter in the status bar (repeatable;
+    /// overrides `watches` in the config)
+    #[arg(long = "watch-expr", value_name = "QUERY")]
+    watch_exprs: Vec<String>,
+    /// Format of file, pipe and socket sources
+    #[arg(long, value_enum, default_value_t = InputFormat::Jsonl)]
+    format: InputFormat,
+    /// Write the selected node as a JSON line on every selection change, to
+    /// `stdout` (which must then be redirected) or a file or named pipe
+    #[arg(long, value_name = "stdout|PATH")]
+    emit_selection: Option<EmitTarget>,
+    /// Load every source without the UI, write the graph to this Graphviz DOT
+    /// file (styled by node type) and exit
+    #[arg(long, value_name = "PATH", conflicts_with_all = ["follow", "listen", "listen_tls", "watch_dir"])]
+    export_dot: Option<String>,
+    /// Replace labels, spans and metadata with hashes in every export
+    /// (--export-dot, `X`, the control socket), by the config's [redact] rules
+    #[arg(long)]
+    redact: bool,
+    /// Accept JSON-RPC requests (select, filter, export, selection) on this Unix socket
+    #[arg(long, value_name = "PATH")]
+    control: Option<String>,
+    /// For the legacy Windows console and other limited terminals: ASCII
+    /// glyphs, 16 colours, no mouse and no alternate screen (detected for
+    /// conhost, TERM=linux, vt100 and the like)
+    #[arg(long)]
+    compat: bool,
+    /// Set aside a minimal set of back edges so cycles don't block what needs
+    /// a DAG (path depths in alerts, metrics and stats); `Y` toggles it
+    #[arg(long)]
+    break_cycles: bool,
 }
 
-#[derive(Debug, Clone)]
-struct NodeData {
-    id: String,
-    label: String,
-    span: String,
-    tags: Vec<String>,
-    ts: String,
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Show the crate dependency graph from `cargo metadata`
+    Cargo {
+        /// Cargo.toml of the package or workspace (default: search from the current directory)
+        #[arg(long, value_name = "PATH")]
+        manifest_path: Option<String>,
+    },
+    /// Show a repository's commit graph from `git log`
+    Git {
+        /// Repository to read (default: the current directory)
+        #[arg(default_value = ".")]
+        repo: String,
+        /// Newest commits to load
+        #[arg(short = 'n', long, default_value_t = 5000)]
+        max_count: usize,
+        /// Include every branch and tag, not just HEAD's history
+        #[arg(long)]
+        all: bool,
+    },
+    /// Show a Ninja or Make build's target dependency graph
+    Build {
+        /// build.ninja, Makefile, saved `make -p` output, or a directory containing one
+        #[arg(default_value = ".")]
+        path: String,
+    },
+    /// Show model lineage from a dbt manifest
+    Dbt {
+        #[arg(default_value = "target/manifest.json")]
+        manifest: String,
+        /// Include data tests as nodes
+        #[arg(long)]
+        tests: bool,
+    },
+    /// Show task dependencies of a serialized Airflow DAG (JSON)
+    Airflow { path: String },
+    /// Show resource dependencies from `terraform graph` output, `terraform show -json` or a tfstate
+    Terraform { path: String },
+    /// Show a Nix closure from `nix path-info`, `nix derivation show` or `nix-store --query --graph`
+    Nix {
+        /// Saved JSON or DOT output, or an installable / store path to query
+        target: String,
+        /// Query the build-time derivation graph instead of the runtime closure
+        #[arg(long)]
+        derivation: bool,
+    },
+    /// Show the package graph of a package-lock.json or yarn.lock
+    Npm {
+        /// Lockfile, or a directory containing one
+        #[arg(default_value = ".")]
+        path: String,
+    },
+    /// Show Kubernetes workload ownership (Deployment -> ReplicaSet -> Pod), refreshed live
+    K8s {
+        /// Saved `kubectl get -o json` output to read instead of querying the cluster
+        dumps: Vec<String>,
+        /// Namespace to query (default: the kubectl context's namespace)
+        #[arg(short, long, conflicts_with = "all_namespaces")]
+        namespace: Option<String>,
+        /// Query every namespace
+        #[arg(short = 'A', long)]
+        all_namespaces: bool,
+        /// kubectl context to use
+        #[arg(long)]
+        context: Option<String>,
+        /// Seconds between cluster queries
+        #[arg(long, value_name = "SECS", default_value_t = 5)]
+        refresh: u64,
+    },
+    /// Load a file without the UI, check it against --assertions and list every
+    /// problem (exits non-zero if there are any)
+    Validate {
+        /// File or URL to check, read like --input
+        input: String,
+    },
+    /// Load a file without the UI and print its key metrics (counts, depth,
+    /// errors, watch-expression matches) for CI to record
+    Metrics {
+        /// File or URL to measure, read like --input
+        input: String,
+        /// How to print them
+        #[arg(long = "format", value_enum, default_value_t = MetricsFormat::Json)]
+        output: MetricsFormat,
+    },
+    /// Load a file without the UI and print its shape: node, edge, root and
+    /// leaf counts, depth, the widest fan-out, and nodes per type and per tag
+    Stats {
+        /// File or URL to summarize, read like --input
+        input: String,
+        /// How to print it
+        #[arg(long = "format", value_enum, default_value_t = StatsFormat::Text)]
+        output: StatsFormat,
+    },
+    /// Load a file without the UI and print the nodes a filter matches, as
+    /// `/` would list them
+    Query {
+        /// File or URL to search, read like the main --input
+        #[arg(long)]
+        input: String,
+        /// The filter, as typed after `/` (default: every node)
+        #[arg(long, value_name = "QUERY", default_value = "")]
+        filter: String,
+        /// How to print the matches
+        #[arg(long = "format", value_enum, default_value_t = QueryFormat::Json)]
+        output: QueryFormat,
+        /// List each match's parents and children too
+        #[arg(long)]
+        neighbors: bool,
+    },
+    /// Compare two files, such as two runs of the same pipeline: AFTER is
+    /// shown with what only BEFORE had, nodes marked added, removed or changed
+    Diff {
+        /// The earlier file or URL, read like --input
+        before: String,
+        /// The later one
+        after: String,
+    },
+    /// Learn the UI on a bundled graph, with a box walking through
+    /// filtering, lineage, the canvas and export step by step
+    Tutorial {
+        /// Which graph to learn on
+        #[arg(value_enum, default_value_t = Scenario::Agent)]
+        scenario: Scenario,
+    },
+    /// Summarize a file's schema (fields used, tags, spans, timestamp range,
+    /// memory needed) without loading it into a graph
+    Preview {
+        /// File or URL to summarize, read like --input
+        input: String,
+    },
 }
 
-impl NodeData {
-    fn display_label(&self) -> String {
-        if self.label.is_empty() {
-            self.id.clone()
-        } else {
-            format!("{} · {}", self.id, self.label)
-        }
-    }
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum InputFormat {
+    /// riff node/edge JSONL
+    Jsonl,
+    /// Plain text, matched against the config's [[extract]] rules
+    Regex,
+    /// A Graphviz DOT document (--input files only; the default for .dot and .gv)
+    Dot,
 }
 
-#[derive(Debug, Default)]
-struct GraphModel {
-    graph: StableDiGraph<NodeData, ()>,
-    // map id -> node index
-    indices: HashMap<String, NodeIndex>,
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum QueryFormat {
+    /// One JSON array of nodes
+    Json,
+    /// One JSON object per line
+    Jsonl,
+    /// `id · label` per line
+    Text,
 }
 
-impl GraphModel {
-    fn new() -> Self {
-        Self::default()
-    }
-    fn upsert_node(&mut self, id: &str, nd: NodeData) -> NodeIndex {
-        if let Some(idx) = self.indices.get(id) {
-            let idx = *idx;
-            if let Some(node) = self.graph.node_weight_mut(idx) {
-                *node = nd;
-            }
-            idx
-        } else {
-            let idx = self.graph.add_node(nd);
-            self.indices.insert(id.to_string(), idx);
-            idx
-        }
-    }
-    fn ensure_node_id(&mut self, id: &str) -> NodeIndex {
-        if let Some(idx) = self.indices.get(id) {
-            *idx
+impl InputFormat {
+    /// The format `input` is read in: `.dot` and `.gv` files are DOT unless
+    /// another format was asked for.
+    fn for_input(self, input: &str) -> Self {
+        let ext = Path::new(input).extension().and_then(|e| e.to_str());
+        if self == InputFormat::Jsonl && matches!(ext, Some("dot" | "gv")) {
+            InputFormat::Dot
         } else {
-            let nd = NodeData {
-                id: id.to_string(),
-                label: "".into(),
-                span: "".into(),
-                tags: vec![],
-                ts: "".into(),
-            };
-            self.upsert_node(id, nd)
-        }
-    }
-    fn add_edge(&mut self, from: &str, to: &str) {
-        if let (Some(&a), Some(&b)) = (self.indices.get(from), self.indices.get(to)) {
-            self.graph.add_edge(a, b, ());
+            self
         }
     }
-    fn parents_of(&self, idx: NodeIndex) -> Vec<NodeIndex> {
-        self.graph.neighbors_directed(idx, Incoming).collect()
-    }
-    fn children_of(&self, idx: NodeIndex) -> Vec<NodeIndex> {
-        self.graph.neighbors_directed(idx, Outgoing).collect()
-    }
-    fn degree(&self, idx: NodeIndex) -> (usize, usize) {
-        (self.parents_of(idx).len(), self.children_of(idx).len())
-    }
 }
 
-#[derive(Parser, Debug)]
-#[command(name = "riff-dag-tui")]
-#[command(about = "Three-pane DAG inspector for riff/memory spans")]
-struct Args {
-    /// Optional path to a JSONL file with node/edge events
-    #[arg(short, long)]
-    input: Option<String>,
+impl Args {
+    fn has_sockets(&self) -> bool {
+        self.listen.is_some() || self.connect.is_some() || self.listen_tls.is_some() || self.connect_tls.is_some()
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -136,11 +421,61 @@ enum NodeType {
     Unknown,
 }
 
+impl NodeType {
+    const ALL: [NodeType; 6] = [NodeType::Prompt, NodeType::Response, NodeType::Tool, NodeType::Error, NodeType::Event, NodeType::Unknown];
+
+    /// What `{type}` renders in a label template.
+    fn name(self) -> &'static str {
+        match self {
+            NodeType::Prompt => "prompt",
+            NodeType::Response => "response",
+            NodeType::Tool => "tool",
+            NodeType::Error => "error",
+            NodeType::Event => "event",
+            NodeType::Unknown => "",
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum Mode {
     Normal,
     Filter,
     HelpOverlay,
+    EdgeFocus, // cycling through the selected node's edges
+    Problems,  // assertion violations and warnings
+    Tags,      // every tag with its count; Enter filters by it
+    Snapshots, // the live graph and its snapshots; Enter shows one, d diffs against it
+    Roots,     // likely entry points, earliest first; Enter selects one
+    TagEdit,   // typing a tag to add to the selection, or a new name for one
+    PatternInput, // typing a structural query like `prompt -> tool -> error`
+    Matches,   // the paths the structural query found; Enter selects one's first node
+    ExportPath, // typing the file to write the listed nodes to
+    Revisions, // the selected node's earlier versions, two of them diffed
+    Menu,      // what can be done with the selected node; Enter does it
+    Replay,    // stepping through the edges in the order they arrived
+    Replace,   // typing a regex and its replacement for every label and tag, affected nodes previewed
+    Details,   // scrolling the details pane, which keeps its place per node
+    Canvas,    // panning and zooming the canvas DAG view
+    Stats,     // the active tab's counts, depth, fan-out, types and tags
+    Bookmarks, // the bookmarked nodes, in the order marked; Enter selects one
+    Goto,      // typing part of a node's id or label to jump to it, whatever the filter
+    Raw,       // the selected node's event as received, unknown fields included
+}
+
+/// An entry of the node menu (`Space`), offered only when it applies.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum MenuAction {
+    GoToChild,
+    GoToParent,
+    Lineage,
+    Revisions,
+    FilterSpan,
+    ExportNeighborhood,
+    Explore,
+    CopyId,
+    AddTag,
+    Pin,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -149,38 +484,103 @@ enum DagViewMode {
     Canvas,
 }
 
-struct App {
+/// One graph plus the view state that belongs to it.
+struct Tab {
+    title: String,
     gm: GraphModel,
     order: Vec<NodeIndex>,         // filtered display order
+    order_stale: bool,             // graph changed since `order` was computed
     list_state: ListState,
     filter_text: String,
-    mode: Mode,
-    dag_view_mode: DagViewMode,    // Text or Canvas view for DAG panel
-    last_tick: Instant,
-    tick_rate: Duration,
+    filter_hint: Option<String>,   // what is wrong with the filter, or "did you mean ..." when it matches nothing
+    narrowing: Option<(Query, Vec<NodeIndex>)>, // the last filter and the unpinned nodes it matched
+    layout: LayoutCache,           // canvas positions reused across frames
+    sampled: Option<Sampled>,      // with --sample: the full graph `gm` is drawn from
+    edge_cursor: usize,            // edge focus: position in the selection's incident edges
+    snapshots: Vec<Snapshot>,      // taken with `s`, oldest first
+    viewing: Option<usize>,        // the snapshot shown instead of the live graph
+    parked: Option<GraphModel>,    // the live graph while a snapshot is shown (unsampled tabs)
+    diff: Option<Diff>,            // the live graph against a snapshot
+    compare: Option<Comparison>,   // `diff`: the two files this tab shows at once
+    pins: Vec<String>,             // node ids kept at the top of the list, in pin order
+    closure_of: Option<String>,    // `f`: only this node's ancestors and descendants are listed
+    sort: SortOrder,               // how the matches are listed, after the pins
+    path_start: Option<String>,    // `m`: where `g` looks for a path from
+    path: Vec<String>,             // `g`: the shortest path found, start first
+    pinned: usize,                 // how many of `order` are pins
+    search: bool,                  // `H`: the filter highlights its matches instead of hiding the rest
+    found: HashSet<NodeIndex>,     // the filter's matches while `search` is on
+    start_pending: bool,           // the best root is still to be selected once loading settles
+    neighbor_cursor: (Option<NodeIndex>, usize, usize), // node, highlighted parent, highlighted child
+    link: Option<(NodeIndex, usize)>, // node, and the parent or child (parents first) focused in the details pane
+    cycles: Cycles,                // of the full graph, found when the assertions are checked
+    groups: Groups,                // `[[group]]` rules from the config
+    expanded: HashSet<usize>,      // groups listed node by node (`G`); the rest are folded
+    containers: HashMap<NodeIndex, Vec<NodeIndex>>, // list entry standing for a folded group, and the members it stands for
+    back: Vec<String>,             // ids jumped away from, most recent last
+    forward: Vec<String>,          // ids gone back from, most recent last
 }
 
-impl App {
-    fn new(gm: GraphModel) -> Self {
+impl Tab {
+    fn new(title: impl Into<String>, gm: GraphModel) -> Self {
         let mut list_state = ListState::default();
-        let order: Vec<NodeIndex> = gm.graph.node_indices().collect();
+        let order: Vec<NodeIndex> = gm.stable_order().collect();
         if !order.is_empty() {
             list_state.select(Some(0));
         }
         Self {
+            title: title.into(),
             gm,
             order,
+            order_stale: false,
             list_state,
             filter_text: String::new(),
-            mode: Mode::Normal,
-            dag_view_mode: DagViewMode::Text,
-            last_tick: Instant::now(),
-            tick_rate: Duration::from_millis(200),
+            filter_hint: None,
+            narrowing: None,
+            layout: LayoutCache::default(),
+            sampled: None,
+            edge_cursor: 0,
+            snapshots: Vec::new(),
+            viewing: None,
+            parked: None,
+            diff: None,
+            compare: None,
+            pins: Vec::new(),
+            closure_of: None,
+            sort: SortOrder::default(),
+            path_start: None,
+            path: Vec::new(),
+            pinned: 0,
+            search: false,
+            found: HashSet::new(),
+            start_pending: true,
+            neighbor_cursor: (None, 0, 0),
+            link: None,
+            cycles: Cycles::default(),
+            groups: Groups::default(),
+            expanded: HashSet::new(),
+            containers: HashMap::new(),
+            back: Vec::new(),
+            forward: Vec::new(),
         }
     }
 
-    fn selected(&self) -> Option<NodeIndex> {
-        self.list_state.selected().map(|i| self.order.get(i).copied()).flatten()
+    /// Show only a sample of the graph; it is drawn on the next frame.
+    fn with_sample(mut self, sample: Option<Sample>) -> Self {
+        if let Some(sample) = sample {
+            let full = std::mem::take(&mut self.gm);
+            self.order.clear();
+            self.list_state.select(None);
+            self.sampled = Some(Sampled::new(sample, full));
+        }
+        self
+    }
+
+    /// Fold the nodes `groups` matches into containers in the list.
+    fn with_groups(mut self, groups: Groups) -> Self {
+        self.groups = groups;
+        self.refresh_order();
+        self
     }
 
     fn with_filter(mut self, query: &str) -> Self {
@@ -188,348 +588,2852 @@ impl App {
         self
     }
 
-    fn apply_filter(&mut self, query: &str) {
-        let q = query.trim().to_lowercase();
-        self.filter_text = q.clone();
-        if q.is_empty() {
-            self.order = self.gm.graph.node_indices().collect();
-        } else {
-            self.order = self
-                .gm
-                .graph
-                .node_indices()
-                .filter(|&idx| {
-                    let nd = &self.gm.graph[idx];
-                    let hay = format!(
-                        "{} {} {} {}",
-                        nd.id,
-                        nd.label,
-                        nd.span,
-                        nd.tags.iter().cloned().collect::<Vec<_>>().join(" ")
-                    )
-                    .to_lowercase();
-                    hay.contains(&q)
-                })
-                .collect();
-        }
-        // reset selection into range
-        let len = self.order.len();
-        if len == 0 {
-            self.list_state.select(None);
-        } else {
-            let i = self.list_state.selected().unwrap_or(0).min(len - 1);
-            self.list_state.select(Some(i));
+    /// Every node loaded, whether or not it is shown.
+    fn full_graph(&self) -> &GraphModel {
+        match (&self.sampled, &self.parked) {
+            (Some(s), _) => &s.full,
+            (None, Some(live)) => live,
+            (None, None) => &self.gm,
         }
     }
 
-    fn on_up(&mut self) {
-        if self.order.is_empty() {
-            return;
+    /// The live graph, for applying events to; whatever is drawn from it is
+    /// marked stale.
+    fn live_mut(&mut self) -> &mut GraphModel {
+        if let Some(diff) = &mut self.diff {
+            diff.stale = true;
         }
-        let i = self.list_state.selected().unwrap_or(0);
-        let i = if i == 0 { self.order.len() - 1 } else { i - 1 };
-        self.list_state.select(Some(i));
-    }
-    fn on_down(&mut self) {
-        if self.order.is_empty() {
-            return;
+        match (&mut self.sampled, &mut self.parked) {
+            (Some(s), _) => {
+                s.stale = true;
+                &mut s.full
+            }
+            (None, Some(live)) => live,
+            (None, None) => {
+                // Re-filtering is O(nodes); defer it to the next frame that is drawn.
+                self.order_stale = true;
+                &mut self.gm
+            }
         }
-        let i = self.list_state.selected().unwrap_or(0);
-        let i = if i >= self.order.len() - 1 { 0 } else { i + 1 };
-        self.list_state.select(Some(i));
     }
 
-    fn toggle_dag_view(&mut self) {
-        self.dag_view_mode = match self.dag_view_mode {
-            DagViewMode::Text => DagViewMode::Canvas,
-            DagViewMode::Canvas => DagViewMode::Text,
-        };
+    /// Freeze the live graph as a new snapshot.
+    fn take_snapshot(&mut self) {
+        let snapshot = Snapshot::new(self.full_graph());
+        self.snapshots.push(snapshot);
     }
-}
 
-fn load_graph_from_jsonl(path: Option<String>) -> Result<GraphModel> {
-    let mut gm = GraphModel::new();
-
-    let reader: Box<dyn BufRead> = if let Some(p) = path {
-        let f = File::open(&p).wrap_err("failed to open input file")?;
-        Box::new(BufReader::new(f))
-    } else {
-        // Fallback to embedded sample (the same as assets/sample.jsonl).
-        let data = include_str!("../assets/sample.jsonl");
-        Box::new(BufReader::new(data.as_bytes()))
-    };
-
-    for (lineno, line) in reader.lines().enumerate() {
-        let line = line?;
-        if line.trim().is_empty() {
-            continue;
+    /// Show snapshot `i` (`None`: the live graph again). Ingestion carries
+    /// on into the live graph either way.
+    fn view_snapshot(&mut self, i: Option<usize>) {
+        if i == self.viewing {
+            return;
         }
-        match serde_json::from_str::<EventLine>(&line) {
-            Ok(EventLine::Node { id, label, span, tags, ts }) => {
-                let nd = NodeData {
-                    id: id.clone(),
-                    label: label.unwrap_or_default(),
-                    span: span.unwrap_or_default(),
-                    tags: tags.unwrap_or_default(),
-                    ts: ts.unwrap_or_default(),
-                };
-                gm.upsert_node(&id, nd);
-            }
-            Ok(EventLine::Edge { from, to }) => {
-                // Only add the edge if both endpoints exist; otherwise skip silently.
-                if gm.indices.contains_key(&from) && gm.indices.contains_key(&to) {
-                    gm.add_edge(&from, &to);
-                } else {
-                    eprintln!("[warn] edge references missing node(s) at line {}: {} -> {}", lineno + 1, from, to);
+        match i {
+            Some(i) => {
+                self.diff = None;
+                let frozen = self.snapshots[i].graph.freeze();
+                let shown = self.replace_graph(frozen);
+                if self.viewing.is_none() && self.sampled.is_none() {
+                    self.parked = Some(shown);
                 }
             }
-            Err(err) => eprintln!("[warn] bad JSON at line {}: {} (content: {})", lineno + 1, err, line),
+            None => {
+                let live = match &mut self.sampled {
+                    Some(s) => s.build(),
+                    None => self.parked.take().expect("live graph parked while viewing a snapshot"),
+                };
+                self.replace_graph(live);
+            }
         }
+        self.viewing = i;
     }
 
-    Ok(gm)
-}
-
-fn setup_terminal() -> Result<Terminal<CrosstermBackend<io::Stdout>>> {
-    enable_raw_mode()?;
-    let mut stdout = io::stdout();
-    execute!(stdout, EnterAlternateScreen)?;
-    let backend = CrosstermBackend::new(stdout);
-    let terminal = Terminal::new(backend)?;
-    Ok(terminal)
-}
-
-fn restore_terminal(mut terminal: Terminal<CrosstermBackend<io::Stdout>>) -> Result<()> {
-    disable_raw_mode()?;
-    execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
-    terminal.show_cursor()?;
-    Ok(())
-}
+    /// Mark what the live graph gained since snapshot `i` (`None`: stop diffing).
+    fn diff_against(&mut self, i: Option<usize>) {
+        self.view_snapshot(None);
+        self.diff = i.map(Diff::new);
+    }
 
-fn draw_ui(f: &mut ratatui::Frame, app: &mut App) {
-    let main_chunks = Layout::default()
-        .direction(Direction::Horizontal)
-        .constraints([Constraint::Percentage(32), Constraint::Percentage(68)].as_ref())
-        .split(f.size());
+    /// Recompute a stale diff.
+    fn refresh_diff(&mut self) {
+        if !self.diff.as_ref().is_some_and(|d| d.stale) {
+            return;
+        }
+        let Some(mut diff) = self.diff.take() else { return };
+        diff.compute(&self.snapshots[diff.against].graph, self.full_graph());
+        self.diff = Some(diff);
+    }
 
-    let right_chunks = Layout::default()
-        .direction(Direction::Vertical)
-        .constraints([Constraint::Percentage(45), Constraint::Percentage(55)].as_ref())
-        .split(main_chunks[1]);
+    /// `snapshot #2 of 3 (live continues, ...)` while a snapshot is shown.
+    fn snapshot_status(&self) -> Option<String> {
+        self.viewing.map(|i| format!("snapshot #{} of {} (live continues, S to return)", i + 1, self.snapshots.len()))
+    }
 
-    // LEFT: Node list + filter status
-    let items: Vec<ListItem> = app
-        .order
-        .iter()
-        .map(|&idx| {
-            let nd = &app.gm.graph[idx];
-            let (pin, pout) = app.gm.degree(idx);
-            let text = format!("{}  (↑{} ↓{})", nd.display_label(), pin, pout);
-            ListItem::new(text)
-        })
-        .collect();
+    /// Rebuild a stale sample, at most every `RESAMPLE_EVERY` while `busy`.
+    fn resample(&mut self, busy: bool) {
+        if self.viewing.is_some() {
+            return;
+        }
+        let Some(s) = &mut self.sampled else { return };
+        let due = s.built.is_none_or(|t| t.elapsed() >= sample::RESAMPLE_EVERY);
+        if s.stale && (!busy || due) {
+            let gm = s.build();
+            self.replace_graph(gm);
+        }
+    }
 
-    let list = List::new(items)
-        .block(Block::default().title(" Nodes ").borders(Borders::ALL))
-        .highlight_style(Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD))
-        .highlight_symbol("▶ ");
+    /// ` | sample top-degree:5000: 5000/1200000 nodes shown`, or nothing unsampled.
+    fn sample_status(&self) -> String {
+        match &self.sampled {
+            Some(s) => format!(
+                " | sample {}: {}/{} nodes shown",
+                s.sample,
+                self.gm.graph.node_count(),
+                s.full.graph.node_count()
+            ),
+            None => String::new(),
+        }
+    }
 
-    f.render_stateful_widget(list, main_chunks[0], &mut app.list_state);
+    /// The edge under the cursor in edge focus, if the selection has any edges.
+    fn focused_edge(&self) -> Option<(EdgeIndex, usize, usize)> {
+        let edges = self.gm.incident_edges(self.selected()?);
+        if edges.is_empty() {
+            return None;
+        }
+        let pos = self.edge_cursor % edges.len();
+        Some((edges[pos], pos, edges.len()))
+    }
 
-    // RIGHT TOP: Details of selected node
-    let details = if let Some(idx) = app.selected() {
-        let nd = &app.gm.graph[idx];
-        let parents = app.gm.parents_of(idx);
-        let children = app.gm.children_of(idx);
-        let parent_lines = parents
-            .iter()
-            .map(|&p| format!("  ← {}", app.gm.graph[p].display_label()))
-            .join("\n");
-        let child_lines = children
-            .iter()
-            .map(|&c| format!("  → {}", app.gm.graph[c].display_label()))
-            .join("\n");
+    fn cycle_edge(&mut self, forward: bool) {
+        if let Some((_, pos, n)) = self.focused_edge() {
+            self.edge_cursor = if forward { (pos + 1) % n } else { (pos + n - 1) % n };
+        }
+    }
 
-        let info = format!(
-            "id: {}\nlabel: {}\nspan: {}\nts: {}\ntags: {}\n\nparents:\n{}\n\nchildren:\n{}\n",
-            nd.id,
-            if nd.label.is_empty() { "(none)" } else { &nd.label },
-            if nd.span.is_empty() { "(none)" } else { &nd.span },
-            if nd.ts.is_empty() { "(n/a)" } else { &nd.ts },
-            if nd.tags.is_empty() { "(none)".to_string() } else { nd.tags.join(", ") },
-            if parent_lines.is_empty() { "(none)".to_string() } else { parent_lines },
-            if child_lines.is_empty() { "(none)".to_string() } else { child_lines },
-        );
+    /// Move the selection to the far end of the focused edge (if the filter shows it).
+    fn follow_edge(&mut self) {
+        let (Some(sel), Some((e, _, _))) = (self.selected(), self.focused_edge()) else { return };
+        let Some((a, b)) = self.gm.graph.edge_endpoints(e) else { return };
+        let other = if a == sel { b } else { a };
+        if self.order.contains(&other) {
+            let id = self.gm.graph[other].id.to_string();
+            self.jump_to_id(&id);
+            self.edge_cursor = 0;
+        }
+    }
 
-        Paragraph::new(info)
-            .block(Block::default().title(" Node Details ").borders(Borders::ALL))
-            .wrap(Wrap { trim: true })
-    } else {
-        Paragraph::new("No selection")
-            .block(Block::default().title(" Node Details ").borders(Borders::ALL))
-    };
-    f.render_widget(details, right_chunks[0]);
+    /// Pull the selected node's neighbours into the sample.
+    fn expand_selection(&mut self) {
+        let Some(id) = self.selected().map(|idx| self.gm.graph[idx].id.to_string()) else { return };
+        if let Some(s) = self.sampled.as_mut().filter(|_| self.viewing.is_none()) {
+            s.expand(&id);
+            let gm = s.build();
+            self.replace_graph(gm);
+        }
+    }
 
-    // RIGHT BOTTOM: Layered DAG text or canvas view (toggle with Tab)
-    if let Some(idx) = app.selected() {
-        match app.dag_view_mode {
-            DagViewMode::Text => {
-                let dag_text = build_layered_dag_text(&app.gm, idx, 2);
-                let dag_paragraph = Paragraph::new(dag_text)
-                    .block(Block::default().title(" DAG View (text, depth 2) ").borders(Borders::ALL))
-                    .wrap(Wrap { trim: false });
-                f.render_widget(dag_paragraph, right_chunks[1]);
-            }
-            DagViewMode::Canvas => {
-                // Canvas widget with node shapes and edges
-                let positions = layout_nodes(&app.gm, idx, 2);
-                let dag_canvas = Canvas::default()
-                    .block(Block::default().title(" DAG View (shapes, depth 2) ").borders(Borders::ALL))
-                    .x_bounds([0.0, 100.0])
-                    .y_bounds([0.0, 50.0])
-                    .paint(|ctx| {
-                        // Draw edges first (so they appear behind nodes)
-                        for (from_idx, from_pos) in &positions {
-                            for to_idx in app.gm.children_of(*from_idx) {
-                                if let Some(to_pos) = positions.get(&to_idx) {
-                                    draw_edge_line(ctx, *from_pos, *to_pos, Color::Gray);
-                                }
-                            }
-                        }
+    fn selected(&self) -> Option<NodeIndex> {
+        self.list_state.selected().and_then(|i| self.order.get(i).copied())
+    }
 
-                        // Draw nodes
-                        for (node_idx, pos) in &positions {
-                            let nd = &app.gm.graph[*node_idx];
-                            let node_type = classify_node_type(&nd.tags);
-                            let is_selected = *node_idx == idx;
-                            draw_node_shape(ctx, pos.0, pos.1, node_type, is_selected);
-                        }
-                    });
-                f.render_widget(dag_canvas, right_chunks[1]);
-            }
-        }
-    } else {
-        let empty = Paragraph::new("No selection")
-            .block(Block::default().title(" DAG View ").borders(Borders::ALL));
-        f.render_widget(empty, right_chunks[1]);
+    fn apply_filter(&mut self, query: &str) {
+        self.filter(query, false);
     }
 
-    // Status / help line overlay at bottom
-    let dag_mode_str = match app.dag_view_mode {
-        DagViewMode::Text => "text",
-        DagViewMode::Canvas => "canvas",
-    };
-    let status = match app.mode {
-        Mode::Normal => format!("Normal | / filter | c clear | Tab toggle DAG ({}) | q quit | ? help | filter: '{}'", dag_mode_str, app.filter_text),
-        Mode::Filter => format!("Filter mode — type to filter, Enter accept, Esc exit, Backspace delete | query: '{}'", app.filter_text),
-        Mode::HelpOverlay => "Help — Up/Down/j/k move · / filter · c clear filter · Tab toggle DAG view · q quit".to_string(),
-    };
-    let area = Rect {
-        x: f.size().x,
-        y: f.size().y.saturating_sub(1),
-        width: f.size().width,
-        height: 1,
-    };
-    let status_paragraph = Paragraph::new(status)
-        .style(Style::default().fg(Color::Gray))
-        .block(Block::default());
-    f.render_widget(Clear, area);
-    f.render_widget(status_paragraph, area);
-}
+    /// `apply_filter` for a key typed into the filter. When the query can
+    /// only match fewer nodes than the last one did (more of a word, another
+    /// word), just the last matches are checked again, so a key costs as
+    /// much as the matches rather than the whole graph.
+    fn narrow_filter(&mut self, query: &str) {
+        self.filter(query, true);
+    }
 
-fn build_layered_dag_text(gm: &GraphModel, center: NodeIndex, depth: usize) -> Vec<Line<'static>> {
-    // BFS layers outward (incoming = negative depth, outgoing = positive depth)
-    // We'll collect up to depth for both directions and render columns.
-    let mut parents_layers: Vec<Vec<NodeIndex>> = Vec::new();
-    let mut frontier: Vec<NodeIndex> = vec![center];
-    for _ in 0..depth {
-        let mut next = Vec::new();
-        let mut layer = Vec::new();
-        for &n in &frontier {
-            for p in gm.graph.neighbors_directed(n, Incoming) {
-                if !layer.contains(&p) {
-                    layer.push(p);
-                    next.push(p);
-                }
-            }
+    fn filter(&mut self, query: &str, narrow: bool) {
+        // Kept as typed, so a space (between `tag:x` and `span:y`) survives the next key.
+        self.filter_text = query.to_string();
+        let q = self.filter_text.trim().to_string();
+        // Pins come first whatever the filter, then everything else it matches.
+        let pinned: Vec<NodeIndex> = self.pins.iter().filter_map(|id| self.gm.indices.get(id.as_str()).copied()).collect();
+        self.pinned = pinned.len();
+        let query = Query::parse(&q);
+        let closure = self.closure_of.as_ref().and_then(|id| self.gm.indices.get(id.as_str())).map(|&idx| self.gm.closure(idx));
+        let within = |idx: NodeIndex| !pinned.contains(&idx) && closure.as_ref().is_none_or(|c| c.contains(&idx));
+        // A changed graph may match differently, so it is always scanned in full.
+        let last = self.narrowing.take().filter(|(last, _)| narrow && !self.order_stale && query.narrows(last));
+        let matched = match last {
+            Some((_, last)) => view::rematch(&self.gm, &query, last),
+            None => view::matching(&self.gm, &query, self.sort, within),
+        };
+        // Searching, everything is listed and the matches only marked.
+        let listed = if self.search && !query.is_empty() {
+            let mut all: Vec<NodeIndex> = self.gm.stable_order().filter(|&idx| within(idx)).collect();
+            self.sort.sort(&self.gm, &mut all);
+            all
+        } else {
+            matched.clone()
+        };
+        self.found = if self.search && !query.is_empty() { matched.iter().copied().collect() } else { HashSet::new() };
+        self.order = pinned.iter().copied().chain(listed).collect();
+        self.fold_groups();
+        let nothing = matched.is_empty() && !query.is_empty() && self.gm.graph.node_count() > 0;
+        self.filter_hint = match query.error() {
+            Some(error) => Some(error.to_string()),
+            None if nothing => query::did_you_mean(&query.did_you_mean(&self.gm)).map(|hint| format!("no match, {}", hint)),
+            None => None,
+        };
+        self.narrowing = Some((query, matched));
+        // reset selection into range
+        let len = self.order.len();
+        if len == 0 {
+            self.list_state.select(None);
+        } else {
+            let i = self.list_state.selected().unwrap_or(0).min(len - 1);
+            self.list_state.select(Some(i));
         }
-        if layer.is_empty() { break; }
-        parents_layers.push(layer);
-        frontier = next;
     }
 
-    let mut children_layers: Vec<Vec<NodeIndex>> = Vec::new();
-    let mut frontier: Vec<NodeIndex> = vec![center];
-    for _ in 0..depth {
-        let mut next = Vec::new();
-        let mut layer = Vec::new();
-        for &n in &frontier {
-            for c in gm.graph.neighbors_directed(n, Outgoing) {
-                if !layer.contains(&c) {
-                    layer.push(c);
-                    next.push(c);
+    /// Replace the listed members of each folded group with one entry, where
+    /// the first of them was. Pins stay as they are, and a group with a
+    /// single member listed isn't folded.
+    fn fold_groups(&mut self) {
+        self.containers.clear();
+        if self.groups.is_empty() {
+            return;
+        }
+        let mut entries: HashMap<usize, NodeIndex> = HashMap::new();
+        let mut order = Vec::with_capacity(self.order.len());
+        for (i, idx) in std::mem::take(&mut self.order).into_iter().enumerate() {
+            let group = self.groups.of(&self.gm.graph[idx].id).filter(|g| i >= self.pinned && !self.expanded.contains(g));
+            match group.map(|g| (g, entries.get(&g).copied())) {
+                Some((_, Some(entry))) => self.containers.get_mut(&entry).expect("entry has a container").push(idx),
+                Some((g, None)) => {
+                    entries.insert(g, idx);
+                    self.containers.insert(idx, vec![idx]);
+                    order.push(idx);
                 }
+                None => order.push(idx),
             }
         }
-        if layer.is_empty() { break; }
-        children_layers.push(layer);
-        frontier = next;
+        self.containers.retain(|_, members| members.len() > 1);
+        self.order = order;
     }
 
-    // Prepare columns: grand-parents ... parents | [center] | children ... grand-children
-    let mut columns: Vec<Vec<String>> = Vec::new();
+    /// The list entry `idx` is shown as: its container, if it is folded into one.
+    fn entry_of(&self, idx: NodeIndex) -> NodeIndex {
+        self.containers.iter().find(|(_, members)| members.contains(&idx)).map_or(idx, |(&entry, _)| entry)
+    }
 
-    // parents (furthest first)
-    for layer in parents_layers.iter().rev() {
-        columns.push(layer.iter().map(|&idx| label_for(gm, idx)).collect());
+    /// Fold or unfold the selected node's group, keeping it (or the
+    /// container it went into) selected. `false` if it is in no group.
+    fn toggle_group(&mut self) -> bool {
+        let Some(idx) = self.selected() else { return false };
+        let Some(group) = self.groups.of(&self.gm.graph[idx].id) else { return false };
+        if !self.expanded.remove(&group) {
+            self.expanded.insert(group);
+        }
+        self.refresh_order();
+        let entry = self.entry_of(idx);
+        if let Some(pos) = self.order.iter().position(|&i| i == entry) {
+            self.list_state.select(Some(pos));
+        }
+        true
     }
 
-    // center
-    columns.push(vec![format!("[{}]", label_for(gm, center))]);
+    /// Select the most likely entry point, once, unless a key was pressed first.
+    fn go_to_start(&mut self) {
+        self.start_pending = false;
+        if self.order_stale {
+            self.refresh_order();
+        }
+        let root = self.full_graph().roots().first().map(|&idx| self.full_graph().graph[idx].id.to_string());
+        let Some(id) = root else { return };
+        // A root folded into a container selects the container, rather than unfolding it.
+        let entry = self.gm.indices.get(id.as_str()).map(|&idx| self.entry_of(idx));
+        match entry.and_then(|entry| self.order.iter().position(|&i| i == entry)) {
+            Some(pos) => self.list_state.select(Some(pos)),
+            None => self.select_id(&id),
+        }
+    }
 
-    // children
-    for layer in children_layers.iter() {
-        columns.push(layer.iter().map(|&idx| label_for(gm, idx)).collect());
+    /// Positions of the highlighted parent and child of the selected node,
+    /// in the order the details pane lists them.
+    fn neighbor_cursor(&self) -> (usize, usize) {
+        match self.neighbor_cursor {
+            (Some(idx), parent, child) if Some(idx) == self.selected() => (parent, child),
+            _ => (0, 0),
+        }
     }
 
-    // Normalize column heights
-    let max_rows = columns.iter().map(|col| col.len()).max().unwrap_or(0).max(1);
-    for col in columns.iter_mut() {
-        while col.len() < max_rows {
-            col.push(String::new());
+    /// Move the parent (or, with `children`, the child) highlight, wrapping.
+    fn move_neighbor_cursor(&mut self, children: bool, forward: bool) {
+        let Some(idx) = self.selected() else { return };
+        let n = if children { self.gm.children_of(idx).len() } else { self.gm.parents_of(idx).len() };
+        if n == 0 {
+            return;
         }
+        let (mut parent, mut child) = self.neighbor_cursor();
+        let pos = if children { &mut child } else { &mut parent };
+        *pos = if forward { (*pos + 1) % n } else { (*pos + n - 1) % n };
+        self.neighbor_cursor = (Some(idx), parent, child);
     }
 
-    // Render as grid with connectors simplified using arrows on the center rows
-    let mut lines: Vec<Line> = Vec::new();
-    // Header
-    lines.push(Line::from(Span::styled(
-        "Layered DAG (parents ← [selected] → children)",
-        Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD),
-    )));
+    /// Select the highlighted child (or parent). The new node's highlight
+    /// points back at the node left, so the opposite jump returns to it.
+    fn jump_to_neighbor(&mut self, to_child: bool) {
+        let Some(idx) = self.selected() else { return };
+        let (parent, child) = self.neighbor_cursor();
+        let (list, pos) = if to_child { (self.gm.children_of(idx), child) } else { (self.gm.parents_of(idx), parent) };
+        let Some(&target) = list.get(pos) else { return };
+        let from = self.gm.graph[idx].id.to_string();
+        let target = self.gm.graph[target].id.to_string();
+        self.jump_to_id(&target);
+        let Some(now) = self.selected() else { return };
+        let back = if to_child { self.gm.parents_of(now) } else { self.gm.children_of(now) };
+        let back = back.iter().position(|&n| *self.gm.graph[n].id == *from).unwrap_or(0);
+        self.neighbor_cursor = if to_child { (Some(now), back, 0) } else { (Some(now), 0, back) };
+    }
 
-    // Grid
-    for row in 0..max_rows {
-        let cells = columns.iter().map(|col| format!("{: ^24}", col[row])).collect::<Vec<_>>();
-        lines.push(Line::from(cells.join("|")));
+    /// The parent or child focused in the details pane, counting the
+    /// parents first, as listed.
+    fn link(&self) -> Option<usize> {
+        self.link.filter(|&(idx, _)| Some(idx) == self.selected()).map(|(_, pos)| pos)
     }
 
-    // Legend
-    lines.push(Line::from(" "));
+    /// Focus the next (or previous) parent or child in the details pane,
+    /// wrapping; from none, the first (or last).
+    fn move_link(&mut self, forward: bool) {
+        let Some(idx) = self.selected() else { return };
+        let n = self.gm.parents_of(idx).len() + self.gm.children_of(idx).len();
+        if n == 0 {
+            return;
+        }
+        let pos = match (self.link(), forward) {
+            (None, true) => 0,
+            (None, false) => n - 1,
+            (Some(pos), true) => (pos + 1) % n,
+            (Some(pos), false) => (pos + n - 1) % n,
+        };
+        self.link = Some((idx, pos));
+    }
+
+    /// Select the focused parent or child, then focus the entry leading
+    /// back to the node left.
+    fn follow_link(&mut self) {
+        let (Some(idx), Some(pos)) = (self.selected(), self.link()) else { return };
+        let parents = self.gm.parents_of(idx).len();
+        let (parent, child) = self.neighbor_cursor();
+        let to_child = pos >= parents;
+        self.neighbor_cursor = if to_child { (Some(idx), parent, pos - parents) } else { (Some(idx), pos, child) };
+        self.jump_to_neighbor(to_child);
+        let Some(now) = self.selected().filter(|&now| now != idx) else { return };
+        let (parent, child) = self.neighbor_cursor();
+        self.link = Some((now, if to_child { parent } else { self.gm.parents_of(now).len() + child }));
+    }
+
+    /// List only the selected node's ancestors and descendants (and pins),
+    /// or everything again.
+    /// `H`: highlight the filter's matches among every node, or go back to
+    /// listing only them.
+    fn toggle_search(&mut self) {
+        self.search = !self.search;
+        self.refresh_order();
+    }
+
+    /// Whether the list entry `idx` is, or stands for, a match being highlighted.
+    fn is_found(&self, idx: NodeIndex) -> bool {
+        self.found.contains(&idx) || self.containers.get(&idx).is_some_and(|members| members.iter().any(|m| self.found.contains(m)))
+    }
+
+    /// `n`/`N`: select the next (or previous) highlighted match, wrapping
+    /// around. With `here`, a match already selected stays.
+    fn next_found(&mut self, forward: bool, here: bool) {
+        let len = self.order.len();
+        if len == 0 {
+            return;
+        }
+        let at = self.list_state.selected().unwrap_or(if forward { len.saturating_sub(1) } else { 0 });
+        let start = usize::from(!here);
+        let step = |k: usize| if forward { (at + k) % len } else { (at + len * 2 - k) % len };
+        if let Some(pos) = (start..len + start).map(step).find(|&pos| self.is_found(self.order[pos])) {
+            self.list_state.select(Some(pos));
+        }
+    }
+
+    /// `=`: list the nodes in the next `SortOrder`, keeping the selection.
+    fn cycle_sort(&mut self) {
+        self.sort = self.sort.next();
+        self.refresh_order();
+    }
+
+    fn toggle_closure(&mut self) {
+        self.closure_of = match self.closure_of.take() {
+            Some(_) => None,
+            None => self.selected().map(|idx| self.gm.graph[idx].id.to_string()),
+        };
+        self.refresh_order();
+    }
+
+    /// Mark the selected node as where `g` starts, or unmark it.
+    fn mark_path_start(&mut self) -> String {
+        let Some(id) = self.selected().map(|idx| self.gm.graph[idx].id.to_string()) else { return "Nothing selected".to_string() };
+        self.path.clear();
+        if self.path_start.as_ref() == Some(&id) {
+            self.path_start = None;
+            return format!("{} unmarked", id);
+        }
+        self.path_start = Some(id.clone());
+        format!("{} marked; select another node and press g for the shortest path", id)
+    }
+
+    /// Find the shortest path between the marked node and the selected one,
+    /// whichever way the edges run, and say how it went.
+    fn find_path(&mut self) -> (String, bool) {
+        let Some(start) = self.path_start.clone() else { return ("Mark a node with m first".to_string(), true) };
+        let (Some(&a), Some(b)) = (self.gm.indices.get(start.as_str()), self.selected()) else {
+            return (format!("{} is not in this graph", start), true);
+        };
+        let found = self.gm.shortest_path(a, b).or_else(|| self.gm.shortest_path(b, a));
+        let Some(path) = found else {
+            self.path.clear();
+            return (format!("No path joins {} and {}", start, self.gm.graph[b].id), true);
+        };
+        self.path = path.iter().map(|&idx| self.gm.graph[idx].id.to_string()).collect();
+        (format!("{} edges: {}", path.len() - 1, self.path.join(" → ")), false)
+    }
+
+    /// Pin the selected node to the top of the list, or unpin it.
+    fn toggle_pin(&mut self) {
+        let Some(id) = self.selected().map(|idx| self.gm.graph[idx].id.to_string()) else { return };
+        match self.pins.iter().position(|p| *p == id) {
+            Some(i) => {
+                self.pins.remove(i);
+            }
+            None => self.pins.push(id),
+        }
+        self.refresh_order();
+    }
+
+    /// Re-run the current filter, keeping the selected node selected.
+    fn refresh_order(&mut self) {
+        let keep = self.selected();
+        let q = self.filter_text.clone();
+        self.apply_filter(&q);
+        if let Some(pos) = keep.and_then(|k| self.order.iter().position(|&i| i == k)) {
+            self.list_state.select(Some(pos));
+        }
+        self.order_stale = false;
+    }
+
+    /// Swap in a rebuilt graph, keeping the selection on the same node id if
+    /// it survived. Returns the graph it replaced.
+    fn replace_graph(&mut self, gm: GraphModel) -> GraphModel {
+        let keep = self.selected().map(|idx| self.gm.graph[idx].id.to_string());
+        let old = std::mem::replace(&mut self.gm, gm);
+        self.layout = LayoutCache::default();
+        let q = self.filter_text.clone();
+        self.apply_filter(&q);
+        let keep = keep.and_then(|id| self.gm.indices.get(id.as_str()).copied());
+        if let Some(pos) = keep.and_then(|k| self.order.iter().position(|&i| i == k)) {
+            self.list_state.select(Some(pos));
+        }
+        self.order_stale = false;
+        old
+    }
+
+    /// Select a node clicked on the canvas, as a jump. A folded group's
+    /// shape selects its entry, rather than unfolding it.
+    fn click_node(&mut self, idx: NodeIndex) {
+        match self.order.iter().position(|&i| i == idx).filter(|_| self.containers.contains_key(&idx)) {
+            Some(pos) => self.list_state.select(Some(pos)),
+            None => {
+                let id = self.gm.graph[idx].id.to_string();
+                self.jump_to_id(&id);
+            }
+        }
+    }
+
+    /// Select `id`, pulling it into the sample or clearing the filter if need be.
+    /// Select `id` as a jump: the node left can be gone back to.
+    fn jump_to_id(&mut self, id: &str) {
+        let from = self.selected().map(|idx| self.gm.graph[idx].id.to_string());
+        self.select_id(id);
+        let now = self.selected().map(|idx| self.gm.graph[idx].id.to_string());
+        if let Some(from) = from.filter(|from| now.as_ref() != Some(from)) {
+            if self.back.last() != Some(&from) {
+                self.back.push(from);
+            }
+            if self.back.len() > HISTORY_LIMIT {
+                self.back.remove(0);
+            }
+            self.forward.clear();
+        }
+    }
+
+    /// Go back to the node jumped away from last (or, with `forward`, redo
+    /// the jump gone back from). Nodes no longer in the graph are skipped.
+    fn go_history(&mut self, forward: bool) {
+        let current = self.selected().map(|idx| self.gm.graph[idx].id.to_string());
+        loop {
+            let target = if forward { self.forward.pop() } else { self.back.pop() };
+            let Some(target) = target else { return };
+            if !self.full_graph().indices.contains_key(target.as_str()) {
+                continue;
+            }
+            if let Some(current) = current {
+                if forward { &mut self.back } else { &mut self.forward }.push(current);
+            }
+            self.select_id(&target);
+            return;
+        }
+    }
+
+    fn select_id(&mut self, id: &str) {
+        if let Some(s) = self.sampled.as_mut().filter(|_| self.viewing.is_none() && !self.gm.indices.contains_key(id)) {
+            s.expand(id);
+            let gm = s.build();
+            self.replace_graph(gm);
+        }
+        let Some(&idx) = self.gm.indices.get(id) else { return };
+        // A node folded into a container (or standing for one) is shown by unfolding its group.
+        if !self.order.contains(&idx) || self.containers.contains_key(&idx) {
+            if let Some(group) = self.groups.of(id).filter(|g| !self.expanded.contains(g)) {
+                self.expanded.insert(group);
+                let q = self.filter_text.clone();
+                self.apply_filter(&q);
+            }
+        }
+        if !self.order.contains(&idx) {
+            self.closure_of = None;
+            self.apply_filter("");
+        }
+        if let Some(pos) = self.order.iter().position(|&i| i == idx) {
+            self.list_state.select(Some(pos));
+        }
+    }
+
+    fn on_up(&mut self) {
+        if self.order.is_empty() {
+            return;
+        }
+        let i = self.list_state.selected().unwrap_or(0);
+        let i = if i == 0 { self.order.len() - 1 } else { i - 1 };
+        self.list_state.select(Some(i));
+    }
+    fn on_down(&mut self) {
+        if self.order.is_empty() {
+            return;
+        }
+        let i = self.list_state.selected().unwrap_or(0);
+        let i = if i >= self.order.len() - 1 { 0 } else { i + 1 };
+        self.list_state.select(Some(i));
+    }
+}
+
+struct App {
+    tabs: Vec<Tab>,                // tab 0 receives every source without a tab of its own
+    active: usize,
+    routes: HashMap<Arc<str>, usize>, // ingest source -> tab
+    ingest: Ingest,
+    warnings: Vec<String>,
+    warnings_elided: usize,
+    error_log: Option<File>,
+    secrets: Vec<String>,          // masked out of every warning
+    aliases: Option<Aliases>,      // `[aliases]` rules applied to every event
+    on_duplicate: DuplicatePolicy, // --on-duplicate
+    tag_sources: bool,             // several inputs are merged: note each node's files in its metadata
+    sources: Vec<Arc<str>>,        // the merged inputs in the order given (then as seen); colours follow it
+    color_by_source: bool,         // `C`: colour nodes by the input that defined them
+    sample: Option<Sample>,        // --sample, applied to every tab
+    groups: Groups,                // `[[group]]` rules, applied to every tab
+    label_template: Option<LabelTemplate>, // `label_template` from the config; `id · label` otherwise
+    time: TimeDisplay,             // --time-zone / --time-format
+    emitter: Option<SelectionEmitter>, // --emit-selection
+    control: Option<Control>,      // --control
+    watches: Watches,              // counters pinned to the status bar, for the active tab
+    assertions: Option<Assertions>, // --assertions, checked whenever ingestion pauses
+    violations: Vec<Violation>,    // of the active tab, as of the last check
+    violations_stale: bool,        // the graph changed since the last check
+    alert_config: AlertConfig,     // `[alerts]` limits, checked with the assertions
+    alerts: Vec<Alert>,
+    cycle_cursor: usize,           // `y`: the node on a cycle jumped to last
+    problems_state: ListState,     // selection in the problems panel
+    tag_cloud: Vec<(String, usize)>, // tags of the active tab, most used first
+    tags_stale: bool,              // the graph changed since `tag_cloud` was counted
+    tags_state: ListState,         // selection in the tags pane
+    tag_prompt: Option<TagPrompt>, // the tag being typed in `Mode::TagEdit`
+    snapshots_state: ListState,    // selection in the snapshots pane (0 is the live graph)
+    roots: Vec<String>,            // entry points of the active tab, best first, as of opening the roots picker
+    roots_state: ListState,        // selection in the roots picker
+    bookmarks: Bookmarks,          // `b`: nodes to come back to, saved beside a single input file
+    bookmarks_state: ListState,    // selection in the bookmarks pane
+    palette: Palette,              // `:`: what was typed and the nodes it matches
+    stats: Stats,                  // the active tab's shape, as of opening the stats pane
+    stats_scroll: u16,             // lines of the stats pane scrolled past
+    help_scroll: u16,              // rows of the help pane scrolled past
+    raw_scroll: u16,               // lines of the raw view scrolled past
+    pattern_input: String,         // the structural query, kept for the next `M`
+    pattern_error: Option<String>, // why the query typed last didn't parse
+    matches: Vec<Vec<String>>,     // node ids of each path the query found
+    matches_truncated: bool,       // the search stopped at `pattern::MAX_MATCHES`
+    matches_state: ListState,      // selection in the matches pane
+    export_input: String,          // the export path, kept for the next `X`
+    redact: Option<redact::RedactConfig>, // --redact: how exports hide what nodes say
+    revisions: Vec<(String, NodeData)>, // the selected node's versions as of opening `v`, oldest first, the current one last
+    revisions_state: ListState,    // the version diffed against the base
+    revision_base: Option<usize>,  // Space: the version to diff against (default: the one before)
+    replay: Vec<(String, String, u64)>, // from, to and seq of the shown edges in arrival order, as of opening `a`
+    replay_pos: usize,             // the edge the replay is at
+    replay_playing: bool,          // the replay advances an edge per tick
+    replace: ReplacePrompt,        // find-and-replace fields, kept for the next `F`
+    replace_preview: Vec<String>,  // what the replacement would change, a line per field
+    replace_count: usize,          // nodes it would change
+    replace_error: Option<String>, // why the pattern doesn't compile
+    menu: Vec<(MenuAction, String)>, // the node menu's entries, with their text
+    menu_state: ListState,         // selection in the node menu
+    export_nodes: Option<Vec<String>>, // what the export prompt writes instead of the listed nodes
+    clipboard: Option<String>,     // text to hand the terminal (OSC 52) after this key
+    notice: Option<(String, bool)>, // outcome of the last export (and whether it failed), until the next key
+    tutorial: Option<Tutorial>,    // `riff-dag-tui tutorial`: the walkthrough box, until closed
+    pending_cache: Option<CacheKey>, // snapshot to write once loading finishes
+    cached_events: u64,            // events covered by a restored snapshot
+    seen_live: usize,              // live source count at the last pump
+    backlog: bool,                 // last pump ran out of budget with events still queued
+    streaming: bool,               // last pump applied at least one message
+    mode: Mode,
+    dag_view_mode: DagViewMode,    // Text or Canvas view for DAG panel
+    viewport: Viewport,            // the part of the canvas shown, panned and zoomed in Mode::Canvas
+    canvas_labels: bool,           // names printed beside the canvas shapes (`Z` hides them)
+    glyphs: &'static lanes::Glyphs, // the text DAG view's connectors (`ascii_connectors` in the config)
+    theme: Theme,                  // colours of the node types and the rest of the UI (`theme` in the config)
+    compat: Compat,                // what a limited terminal can't show (`--compat`)
+    split: Option<usize>,          // the tab shown beside the active one, selection kept in step
+    list_wrap: bool,               // node list items wrap onto more lines instead of being cut off
+    break_cycles: bool,            // back edges are set aside, as if the graph were a DAG
+    lineage: bool,                 // the details pane lists all ancestors/descendants by hop
+    list_scroll: usize,            // characters scrolled off the left of unwrapped list items
+    details_scroll: HashMap<String, u16>, // lines scrolled off the top of each node's details
+    details_page: u16,             // lines the details pane showed at the last draw
+    hits: Hits,                    // what the last frame drew where, for the mouse
+    last_tick: Instant,
+    last_draw: Instant,
+    tick_rate: Duration,
+    idle_tick_rate: Duration,      // poll timeout once nothing is live
+    time_window: Duration,         // `A` filters to this either side of the selected node's `ts`
+    dirty: bool,                   // redraw needed on the next loop iteration
+}
+
+impl App {
+    fn new(tab: Tab, ingest: Ingest) -> Self {
+        Self {
+            tabs: vec![tab],
+            active: 0,
+            routes: HashMap::new(),
+            ingest,
+            warnings: Vec::new(),
+            warnings_elided: 0,
+            error_log: None,
+            secrets: Vec::new(),
+            aliases: None,
+            on_duplicate: DuplicatePolicy::Last,
+            tag_sources: false,
+            sources: Vec::new(),
+            color_by_source: false,
+            sample: None,
+            groups: Groups::default(),
+            label_template: None,
+            time: TimeDisplay::default(),
+            emitter: None,
+            control: None,
+            watches: Watches::default(),
+            assertions: None,
+            violations: Vec::new(),
+            violations_stale: true,
+            alert_config: AlertConfig::default(),
+            alerts: Vec::new(),
+            cycle_cursor: 0,
+            problems_state: ListState::default(),
+            tag_cloud: Vec::new(),
+            tags_stale: true,
+            tags_state: ListState::default(),
+            tag_prompt: None,
+            snapshots_state: ListState::default(),
+            roots: Vec::new(),
+            roots_state: ListState::default(),
+            bookmarks: Bookmarks::default(),
+            bookmarks_state: ListState::default(),
+            palette: Palette::default(),
+            stats: Stats::default(),
+            stats_scroll: 0,
+            help_scroll: 0,
+            raw_scroll: 0,
+            pattern_input: String::new(),
+            pattern_error: None,
+            matches: Vec::new(),
+            matches_truncated: false,
+            matches_state: ListState::default(),
+            export_input: "export.jsonl".to_string(),
+            redact: None,
+            revisions: Vec::new(),
+            revisions_state: ListState::default(),
+            revision_base: None,
+            replay: Vec::new(),
+            replace: ReplacePrompt::default(),
+            replace_preview: Vec::new(),
+            replace_count: 0,
+            replace_error: None,
+            replay_pos: 0,
+            replay_playing: false,
+            menu: Vec::new(),
+            menu_state: ListState::default(),
+            export_nodes: None,
+            clipboard: None,
+            notice: None,
+            tutorial: None,
+            pending_cache: None,
+            cached_events: 0,
+            seen_live: 0,
+            backlog: false,
+            streaming: false,
+            mode: Mode::Normal,
+            dag_view_mode: DagViewMode::Text,
+            viewport: Viewport::default(),
+            canvas_labels: true,
+            hits: Hits::default(),
+            glyphs: &lanes::UNICODE,
+            theme: Theme::default(),
+            compat: Compat::default(),
+            split: None,
+            list_wrap: false,
+            break_cycles: false,
+            lineage: false,
+            list_scroll: 0,
+            details_scroll: HashMap::new(),
+            details_page: 1,
+            last_tick: Instant::now(),
+            last_draw: Instant::now(),
+            tick_rate: Duration::from_millis(200),
+            idle_tick_rate: Duration::from_millis(2000),
+            time_window: Duration::from_secs(5 * 60),
+            dirty: true,
+        }
+    }
+
+    fn tab(&self) -> &Tab {
+        &self.tabs[self.active]
+    }
+
+    fn tab_mut(&mut self) -> &mut Tab {
+        &mut self.tabs[self.active]
+    }
+
+    fn switch_tab(&mut self, forward: bool) {
+        let n = self.tabs.len();
+        let previous = self.active;
+        self.active = if forward { (self.active + 1) % n } else { (self.active + n - 1) % n };
+        if self.split == Some(self.active) {
+            self.split = Some(previous);
+        }
+        if self.tab().order_stale {
+            self.tab_mut().refresh_order();
+        }
+        self.watches.reset();
+        self.violations_stale = true;
+        self.tags_stale = true;
+    }
+
+    /// Open the selected node's neighbourhood (`NEIGHBORHOOD_HOPS` edges
+    /// either way) in a tab of its own, after the others, and switch to it.
+    fn explore_selection(&mut self) {
+        let Some(idx) = self.tab().selected() else { return };
+        let id = self.tab().gm.graph[idx].id.to_string();
+        let gm = self.tab().gm.neighborhood(idx, NEIGHBORHOOD_HOPS);
+        let title = format!("{} ±{}", id, NEIGHBORHOOD_HOPS);
+        let mut tab = Tab::new(title, gm).with_groups(self.groups.clone());
+        tab.start_pending = false;
+        tab.select_id(&id);
+        self.tabs.push(tab);
+        let previous = self.active;
+        self.active = self.tabs.len() - 1;
+        if self.split == Some(self.active) {
+            self.split = Some(previous);
+        }
+        self.watches.reset();
+        self.violations_stale = true;
+        self.tags_stale = true;
+    }
+
+    /// Close the active tab if it was opened to explore a neighbourhood;
+    /// the tabs sources stream into stay.
+    fn close_tab(&mut self) {
+        let t = self.active;
+        if t == 0 || self.routes.values().any(|&r| r == t) {
+            self.notice = Some(("Only tabs opened with T can be closed".to_string(), true));
+            return;
+        }
+        self.tabs.remove(t);
+        for r in self.routes.values_mut().filter(|r| **r > t) {
+            *r -= 1;
+        }
+        self.active = t - 1;
+        self.split = match self.split {
+            Some(s) if s > t => Some(s - 1),
+            Some(s) if s < t && s != self.active => Some(s),
+            _ => None,
+        };
+        self.watches.reset();
+        self.violations_stale = true;
+        self.tags_stale = true;
+    }
+
+    /// Show the next tab beside the active one, or go back to a single tab.
+    fn toggle_split(&mut self) {
+        self.split = match self.split {
+            None if self.tabs.len() > 1 => Some((self.active + 1) % self.tabs.len()),
+            _ => None,
+        };
+    }
+
+    /// Select the active tab's selected node in the split tab too, or nothing
+    /// there if it lacks the node.
+    fn sync_split(&mut self) {
+        let Some(other) = self.split else { return };
+        let id = self.tab().selected().map(|idx| self.tab().gm.graph[idx].id.to_string());
+        let tab = &mut self.tabs[other];
+        if tab.order_stale {
+            tab.refresh_order();
+        }
+        match id {
+            Some(id) if tab.gm.indices.contains_key(id.as_str()) => tab.select_id(&id),
+            _ => tab.list_state.select(None),
+        }
+    }
+
+    /// Count every tag of the active tab's full graph, most used first.
+    fn count_tags(&mut self) {
+        self.tags_stale = false;
+        let mut counts: HashMap<&str, usize> = HashMap::new();
+        for nd in self.tabs[self.active].full_graph().graph.node_weights() {
+            for tag in &nd.tags {
+                *counts.entry(&**tag).or_default() += 1;
+            }
+        }
+        let mut cloud: Vec<(String, usize)> = counts.into_iter().map(|(t, n)| (t.to_string(), n)).collect();
+        cloud.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        self.tag_cloud = cloud;
+        let n = self.tag_cloud.len();
+        self.tags_state.select((n > 0).then(|| self.tags_state.selected().unwrap_or(0).min(n - 1)));
+    }
+
+    fn move_tag(&mut self, forward: bool) {
+        let n = self.tag_cloud.len();
+        if n == 0 {
+            return;
+        }
+        let i = self.tags_state.selected().unwrap_or(0);
+        self.tags_state.select(Some(if forward { (i + 1) % n } else { (i + n - 1) % n }));
+    }
+
+    /// Leave the tags pane filtering by the selected tag.
+    fn filter_by_tag(&mut self) {
+        if let Some((tag, _)) = self.tags_state.selected().and_then(|i| self.tag_cloud.get(i)).cloned() {
+            self.tab_mut().apply_filter(&query::tag_query(&tag));
+        }
+        self.mode = Mode::Normal;
+    }
+
+    /// The tag highlighted in the tags pane.
+    fn selected_tag(&self) -> Option<String> {
+        self.tags_state.selected().and_then(|i| self.tag_cloud.get(i)).map(|(tag, _)| tag.clone())
+    }
+
+    fn open_tag_prompt(&mut self, action: TagAction) {
+        if self.tags_stale {
+            self.count_tags();
+        }
+        self.tag_prompt = Some(TagPrompt::new(action));
+        self.mode = Mode::TagEdit;
+    }
+
+    /// Apply the tag prompt to the live graph: add the tag to the selected
+    /// node, or rename the tag on every node. Then back to where it opened.
+    fn finish_tag_prompt(&mut self) {
+        let Some(prompt) = self.tag_prompt.take() else { return };
+        let selected = self.tab().selected().map(|idx| self.tab().gm.graph[idx].id.to_string());
+        let renaming = matches!(prompt.action, TagAction::Rename(_));
+        if let Some(tag) = prompt.tag() {
+            match &prompt.action {
+                TagAction::Add => {
+                    if let Some(id) = selected {
+                        self.tab_mut().live_mut().add_tag(&id, tag);
+                    }
+                }
+                TagAction::Rename(old) if old != tag => {
+                    self.tab_mut().live_mut().rename_tag(old, Some(tag));
+                }
+                TagAction::Rename(_) => {}
+            }
+        }
+        self.after_tag_edit(renaming);
+    }
+
+    /// Remove the tag highlighted in the tags pane from every node.
+    fn delete_selected_tag(&mut self) {
+        if let Some(tag) = self.selected_tag() {
+            self.tab_mut().live_mut().rename_tag(&tag, None);
+            self.after_tag_edit(true);
+        }
+    }
+
+    fn after_tag_edit(&mut self, to_tags: bool) {
+        self.tags_stale = true;
+        self.watches.stale = true;
+        self.violations_stale = true;
+        if to_tags {
+            self.count_tags();
+            self.mode = Mode::Tags;
+        } else {
+            self.mode = Mode::Normal;
+        }
+    }
+
+    fn move_snapshot(&mut self, forward: bool) {
+        let n = self.tab().snapshots.len() + 1;
+        let i = self.snapshots_state.selected().unwrap_or(0);
+        self.snapshots_state.select(Some(if forward { (i + 1) % n } else { (i + n - 1) % n }));
+    }
+
+    /// The snapshot selected in the snapshots pane; `None` for the live graph.
+    fn selected_snapshot(&self) -> Option<usize> {
+        self.snapshots_state.selected().and_then(|i| i.checked_sub(1))
+    }
+
+    fn open_roots(&mut self) {
+        let gm = self.tabs[self.active].full_graph();
+        self.roots = gm.roots().into_iter().map(|idx| gm.graph[idx].id.to_string()).collect();
+        self.roots_state.select((!self.roots.is_empty()).then_some(0));
+        self.mode = Mode::Roots;
+    }
+
+    /// Bookmark the selected node, or remove its bookmark.
+    fn toggle_bookmark(&mut self) {
+        let tab = self.tab();
+        let Some(id) = tab.selected().map(|idx| tab.gm.graph[idx].id.to_string()) else { return };
+        let (added, saved) = self.bookmarks.toggle(&id);
+        let done = if added { "bookmarked" } else { "removed the bookmark on" };
+        self.notice = Some(match (saved, self.bookmarks.file()) {
+            (Err(err), Some(file)) => (format!("{} {}, but couldn't save {}: {}", done, id, file.display(), err), true),
+            _ => (format!("{} {} (B lists the bookmarks)", done, id), false),
+        });
+    }
+
+    fn open_bookmarks(&mut self) {
+        let n = self.bookmarks.ids().len();
+        self.bookmarks_state.select((n > 0).then(|| self.bookmarks_state.selected().unwrap_or(0).min(n - 1)));
+        self.mode = Mode::Bookmarks;
+    }
+
+    fn move_bookmark(&mut self, forward: bool) {
+        let n = self.bookmarks.ids().len();
+        if n == 0 {
+            return;
+        }
+        let i = self.bookmarks_state.selected().unwrap_or(0);
+        self.bookmarks_state.select(Some(if forward { (i + 1) % n } else { (i + n - 1) % n }));
+    }
+
+    /// `d` in the bookmarks pane: drop the one chosen.
+    fn remove_bookmark(&mut self) {
+        let Some(id) = self.bookmarks_state.selected().and_then(|i| self.bookmarks.ids().get(i)).cloned() else { return };
+        if let (_, Err(err)) = self.bookmarks.toggle(&id) {
+            self.notice = Some((format!("couldn't save the bookmarks: {}", err), true));
+        }
+        self.open_bookmarks();
+    }
+
+    /// Leave the bookmarks pane on the chosen node, if the active tab has it.
+    fn select_bookmark(&mut self) {
+        let Some(id) = self.bookmarks_state.selected().and_then(|i| self.bookmarks.ids().get(i)).cloned() else { return };
+        if !self.tab().full_graph().indices.contains_key(id.as_str()) {
+            self.notice = Some((format!("{} isn't in this tab's graph", id), true));
+            return;
+        }
+        self.tab_mut().jump_to_id(&id);
+        self.mode = Mode::Normal;
+    }
+
+    fn open_palette(&mut self) {
+        self.palette.input.clear();
+        self.palette.update(self.tabs[self.active].full_graph());
+        self.mode = Mode::Goto;
+    }
+
+    /// Leave the palette on the highlighted node, clearing the filter if it
+    /// hides it.
+    fn goto_chosen(&mut self) {
+        if let Some(id) = self.palette.chosen().map(str::to_string) {
+            self.tab_mut().jump_to_id(&id);
+        }
+        self.mode = Mode::Normal;
+    }
+
+    fn open_stats(&mut self) {
+        self.stats = Stats::measure(self.tabs[self.active].full_graph(), self.break_cycles);
+        self.stats_scroll = 0;
+        self.mode = Mode::Stats;
+    }
+
+    fn move_root(&mut self, forward: bool) {
+        let n = self.roots.len();
+        if n == 0 {
+            return;
+        }
+        let i = self.roots_state.selected().unwrap_or(0);
+        self.roots_state.select(Some(if forward { (i + 1) % n } else { (i + n - 1) % n }));
+    }
+
+    /// Leave the roots picker on the chosen root.
+    fn select_root(&mut self) {
+        if let Some(id) = self.roots_state.selected().and_then(|i| self.roots.get(i)).cloned() {
+            self.tab_mut().jump_to_id(&id);
+        }
+        self.mode = Mode::Normal;
+    }
+
+    /// Search the active tab's full graph for the structural query, or
+    /// leave the prompt open with the reason it doesn't parse.
+    fn run_pattern(&mut self) {
+        let pattern: Pattern = match self.pattern_input.parse() {
+            Ok(p) => p,
+            Err(err) => {
+                self.pattern_error = Some(err);
+                return;
+            }
+        };
+        let gm = self.tabs[self.active].full_graph();
+        let (paths, truncated) = pattern.find(gm);
+        self.matches = paths.into_iter().map(|path| path.into_iter().map(|idx| gm.graph[idx].id.to_string()).collect()).collect();
+        self.matches_truncated = truncated;
+        self.matches_state.select((!self.matches.is_empty()).then_some(0));
+        self.mode = Mode::Matches;
+    }
+
+    fn move_match(&mut self, forward: bool) {
+        let n = self.matches.len();
+        if n == 0 {
+            return;
+        }
+        let i = self.matches_state.selected().unwrap_or(0);
+        self.matches_state.select(Some(if forward { (i + 1) % n } else { (i + n - 1) % n }));
+    }
+
+    /// Leave the matches pane on the first node of the chosen path.
+    fn select_match(&mut self) {
+        if let Some(id) = self.matches_state.selected().and_then(|i| self.matches.get(i)).and_then(|path| path.first()).cloned() {
+            self.tab_mut().jump_to_id(&id);
+        }
+        self.mode = Mode::Normal;
+    }
+
+    /// Recompute what the find-and-replace prompt would change.
+    fn preview_replace(&mut self) {
+        self.replace_preview.clear();
+        self.replace_count = 0;
+        self.replace_error = None;
+        let re = match self.replace.regex() {
+            Ok(Some(re)) => re,
+            Ok(None) => return,
+            Err(err) => {
+                self.replace_error = Some(err);
+                return;
+            }
+        };
+        let gm = self.tabs[self.active].full_graph();
+        for idx in gm.stable_order() {
+            let nd = &gm.graph[idx];
+            let Some(new) = replace::rewrite(&re, &self.replace.with, nd) else { continue };
+            self.replace_count += 1;
+            if self.replace_count <= replace::PREVIEW_LIMIT {
+                self.replace_preview.extend(replace::describe(nd, &new));
+            }
+        }
+    }
+
+    /// Apply the find-and-replace prompt to the live graph.
+    fn apply_replace(&mut self) {
+        let re = match self.replace.regex() {
+            Ok(Some(re)) => re,
+            Ok(None) => return,
+            Err(err) => {
+                self.replace_error = Some(err);
+                return;
+            }
+        };
+        let with = self.replace.with.clone();
+        let changed = self.tab_mut().live_mut().replace_text(&re, &with);
+        self.notice = Some((format!("Replaced in {} nodes", changed), false));
+        self.tags_stale = true;
+        self.watches.stale = true;
+        self.violations_stale = true;
+        self.mode = Mode::Normal;
+    }
+
+    /// Step through the shown graph's edges in the order they arrived,
+    /// starting from the first.
+    fn open_replay(&mut self) {
+        let gm = &self.tab().gm;
+        let mut edges: Vec<(String, String, u64)> = gm
+            .graph
+            .edge_indices()
+            .filter_map(|e| gm.graph.edge_endpoints(e).map(|(a, b)| (gm.graph[a].id.to_string(), gm.graph[b].id.to_string(), gm.graph[e].seq)))
+            .collect();
+        if edges.is_empty() {
+            self.notice = Some(("No edges to replay".to_string(), true));
+            return;
+        }
+        edges.sort_by_key(|e| e.2);
+        self.replay = edges;
+        self.replay_pos = 0;
+        self.replay_playing = false;
+        self.mode = Mode::Replay;
+        self.show_replay_edge();
+    }
+
+    /// The edge the replay is at, if the shown graph still has it.
+    fn replay_edge(&self) -> Option<EdgeIndex> {
+        let (from, to, seq) = self.replay.get(self.replay_pos)?;
+        let gm = &self.tab().gm;
+        let (&a, &b) = (gm.indices.get(from.as_str())?, gm.indices.get(to.as_str())?);
+        gm.graph.edges_connecting(a, b).find(|e| e.weight().seq == *seq).map(|e| e.id())
+    }
+
+    /// Select the node the replayed edge comes from.
+    fn show_replay_edge(&mut self) {
+        if let Some((from, _, _)) = self.replay.get(self.replay_pos).cloned() {
+            self.tab_mut().select_id(&from);
+        }
+    }
+
+    fn step_replay(&mut self, forward: bool) {
+        let last = self.replay.len().saturating_sub(1);
+        self.replay_pos = if forward { (self.replay_pos + 1).min(last) } else { self.replay_pos.saturating_sub(1) };
+        self.show_replay_edge();
+    }
+
+    /// Advance a playing replay by one edge, stopping at the last.
+    fn replay_tick(&mut self) -> bool {
+        if self.mode != Mode::Replay || !self.replay_playing {
+            return false;
+        }
+        if self.replay_pos + 1 >= self.replay.len() {
+            self.replay_playing = false;
+        } else {
+            self.step_replay(true);
+        }
+        true
+    }
+
+    /// List the selected node's versions, the current one selected and
+    /// diffed against the one before it.
+    fn open_revisions(&mut self) {
+        let Some(idx) = self.tab().selected() else { return };
+        let tab = self.tab();
+        let nd = tab.gm.graph[idx].clone();
+        let kept = tab.full_graph().revisions.of(&nd.id);
+        if kept.is_empty() {
+            self.notice = Some((format!("{} has not been redefined", nd.id), false));
+            return;
+        }
+        self.revisions = kept
+            .into_iter()
+            .enumerate()
+            .map(|(i, rev)| (format!("rev {} — replaced {}s ago", i + 1, rev.replaced.elapsed().as_secs()), rev.node))
+            .collect();
+        self.revisions.push((format!("rev {} — current", self.revisions.len() + 1), nd));
+        self.revisions_state.select(Some(self.revisions.len() - 1));
+        self.revision_base = None;
+        self.mode = Mode::Revisions;
+    }
+
+    fn move_revision(&mut self, forward: bool) {
+        let n = self.revisions.len();
+        let i = self.revisions_state.selected().unwrap_or(0);
+        self.revisions_state.select(Some(if forward { (i + 1).min(n - 1) } else { i.saturating_sub(1) }));
+    }
+
+    /// Open the node menu with the entries that apply to the selection.
+    fn open_menu(&mut self) {
+        let Some(idx) = self.tab().selected() else { return };
+        let tab = self.tab();
+        let gm = &tab.gm;
+        let nd = &gm.graph[idx];
+        let (parent, child) = tab.neighbor_cursor();
+        let (parents, children) = (gm.parents_of(idx), gm.children_of(idx));
+        let mut menu = Vec::new();
+        if let Some(&c) = children.get(child) {
+            menu.push((MenuAction::GoToChild, format!("Go to child {}  (Enter)", gm.graph[c].id)));
+        }
+        if let Some(&p) = parents.get(parent) {
+            menu.push((MenuAction::GoToParent, format!("Go to parent {}  (Backspace)", gm.graph[p].id)));
+        }
+        let lineage = if self.lineage { "Show direct parents and children  (L)" } else { "Focus lineage: all ancestors and descendants  (L)" };
+        menu.push((MenuAction::Lineage, lineage.to_string()));
+        if !tab.full_graph().revisions.of(&nd.id).is_empty() {
+            menu.push((MenuAction::Revisions, "Diff revisions  (v)".to_string()));
+        }
+        if !nd.span.is_empty() {
+            menu.push((MenuAction::FilterSpan, format!("Filter by span {}", nd.span)));
+        }
+        let around = parents.iter().chain(&children).unique().count();
+        menu.push((MenuAction::ExportNeighborhood, format!("Export with its {} parents and children…", around)));
+        menu.push((MenuAction::Explore, format!("Open its {}-hop neighbourhood in a new tab  (T)", NEIGHBORHOOD_HOPS)));
+        menu.push((MenuAction::CopyId, "Copy id to the clipboard".to_string()));
+        menu.push((MenuAction::AddTag, "Add a tag…  (+)".to_string()));
+        let pin = if tab.pins.iter().any(|p| **p == *nd.id) { "Unpin from the top of the list  (P)" } else { "Pin to the top of the list  (P)" };
+        menu.push((MenuAction::Pin, pin.to_string()));
+        self.menu = menu;
+        self.menu_state.select(Some(0));
+        self.mode = Mode::Menu;
+    }
+
+    fn move_menu(&mut self, forward: bool) {
+        let n = self.menu.len();
+        let i = self.menu_state.selected().unwrap_or(0);
+        self.menu_state.select(Some(if forward { (i + 1) % n } else { (i + n - 1) % n }));
+    }
+
+    /// Carry out the highlighted menu entry.
+    fn run_menu(&mut self) {
+        let Some(&(action, _)) = self.menu_state.selected().and_then(|i| self.menu.get(i)) else { return };
+        self.mode = Mode::Normal;
+        let Some(idx) = self.tab().selected() else { return };
+        let id = self.tab().gm.graph[idx].id.to_string();
+        match action {
+            MenuAction::GoToChild => self.tab_mut().jump_to_neighbor(true),
+            MenuAction::GoToParent => self.tab_mut().jump_to_neighbor(false),
+            MenuAction::Lineage => self.lineage = !self.lineage,
+            MenuAction::Revisions => self.open_revisions(),
+            MenuAction::FilterSpan => {
+                let span = self.tab().gm.graph[idx].span.clone();
+                let query = if span.contains(char::is_whitespace) { format!("span:\"{}\"", span) } else { format!("span:{}", span) };
+                self.tab_mut().apply_filter(&query);
+            }
+            MenuAction::ExportNeighborhood => {
+                let gm = &self.tab().gm;
+                let mut nodes = vec![id.clone()];
+                nodes.extend(gm.parents_of(idx).into_iter().chain(gm.children_of(idx)).map(|n| gm.graph[n].id.to_string()).unique());
+                self.export_input = format!("{}-neighborhood.jsonl", id.replace(['/', '\\'], "_"));
+                self.export_nodes = Some(nodes);
+                self.mode = Mode::ExportPath;
+            }
+            MenuAction::Explore => self.explore_selection(),
+            MenuAction::CopyId => {
+                self.notice = Some((format!("copied {}", id), false));
+                self.clipboard = Some(id);
+            }
+            MenuAction::AddTag => self.open_tag_prompt(TagAction::Add),
+            MenuAction::Pin => self.tab_mut().toggle_pin(),
+        }
+    }
+
+    /// Write the nodes the active tab lists (or those the menu picked), and
+    /// the edges between them, to the typed path: as DOT for a `.dot` or
+    /// `.gv` file, JSONL otherwise.
+    fn export_listed(&mut self) {
+        let path = self.export_input.trim().to_string();
+        let picked = self.export_nodes.take();
+        self.mode = Mode::Normal;
+        if path.is_empty() {
+            return;
+        }
+        let tab = &mut self.tabs[self.active];
+        if tab.order_stale {
+            tab.refresh_order();
+        }
+        let nodes = match &picked {
+            Some(ids) => ids.iter().filter_map(|id| tab.gm.indices.get(id.as_str()).copied()).collect(),
+            None => tab.order.clone(),
+        };
+        let path_ref = Path::new(&path);
+        let redact = self.redact.as_ref();
+        let written = match (export::Outline::for_path(path_ref), InputFormat::Jsonl.for_input(&path)) {
+            (Some(style), _) => export::write_outline(&tab.gm, &nodes, path_ref, &tab.title, style, redact),
+            (None, InputFormat::Dot) => export::write_dot(&tab.gm, &nodes, path_ref, &tab.title, &self.time, redact),
+            _ => export::write_jsonl(&tab.gm, &nodes, path_ref, &self.time, redact),
+        };
+        self.notice = Some(match written {
+            Ok((nodes, edges)) => (format!("wrote {} nodes and {} edges to {}", nodes, edges, path), false),
+            Err(err) => (format!("export failed: {:#}", err), true),
+        });
+    }
+
+    /// `A`: filter to the nodes whose `ts` is within `time_window` of the
+    /// selected node's, replacing the filter.
+    fn filter_around(&mut self) {
+        let window = self.time_window.as_secs() as i64;
+        let tab = &mut self.tabs[self.active];
+        let Some(idx) = tab.selected() else { return };
+        let nd = &tab.gm.graph[idx];
+        let Some((secs, nanos)) = timefmt::parse(&nd.ts) else {
+            self.notice = Some((format!("{} has no timestamp to filter around", nd.id), true));
+            return;
+        };
+        let id = nd.id.to_string();
+        let q = format!("ts>={} ts<={}", timefmt::rfc3339(secs - window, nanos), timefmt::rfc3339(secs + window, nanos));
+        tab.apply_filter(&q);
+        tab.select_id(&id);
+    }
+
+    /// Re-evaluate the assertions and structural alerts against the active
+    /// tab's full graph, and look for cycles in it (and in the split tab).
+    fn check_assertions(&mut self) {
+        self.violations_stale = false;
+        if let Some(assertions) = &self.assertions {
+            self.violations = assertions.check(self.tab().full_graph());
+        }
+        // The split tab's nodes are marked too.
+        for t in std::iter::once(self.active).chain(self.split) {
+            self.tabs[t].cycles = Cycles::find(self.tabs[t].full_graph());
+        }
+        if !self.alert_config.is_empty() {
+            let broken = self.break_cycles.then_some(&self.tab().cycles);
+            self.alerts = self.alert_config.check(self.tab().full_graph(), broken);
+        }
+    }
+
+    fn toggle_break_cycles(&mut self) {
+        self.break_cycles = !self.break_cycles;
+        self.check_assertions();
+        let back = self.tab().cycles.back_count();
+        let notice = match (self.break_cycles, back) {
+            (true, 0) => "Breaking cycles: there are none".to_string(),
+            (true, n) => format!("Breaking cycles: {} back edges set aside", n),
+            (false, _) => "Cycles kept".to_string(),
+        };
+        self.notice = Some((notice, false));
+    }
+
+    /// Violations, structural alerts, cycles, then duplicate-id conflicts
+    /// (all with the node to jump to), then pending edges and ingest
+    /// warnings, each with the colour it is listed in.
+    fn problems(&self) -> impl Iterator<Item = (String, Option<&str>, Color)> {
+        let violations = self.violations.iter().map(|v| (v.to_string(), Some(v.node.as_str()), self.theme.bad));
+        let alerts = self.alerts.iter().map(|a| (format!("[alert] {}", a), Some(a.node.as_str()), self.theme.alert));
+        let cycles = self.tab().cycles.groups.iter().map(|ids| (cycle_text(ids), Some(ids[0].as_str()), self.theme.bad));
+        let conflicts = self.tab().full_graph().conflicts.iter().map(|c| {
+            let color = if c.policy == DuplicatePolicy::Error { self.theme.bad } else { self.theme.conflict };
+            (c.to_string(), Some(c.id.as_str()), color)
+        });
+        let pending = self.tab().full_graph().pending_edges().map(|(missing, e)| {
+            (format!("[pending] {} -> {} at {} waits for {}", e.from, e.to, e.data.at, missing), None, self.theme.accent)
+        });
+        let warnings = self.warnings.iter().map(|w| (w.clone(), None, self.theme.highlight));
+        violations.chain(alerts).chain(cycles).chain(conflicts).chain(pending).chain(warnings)
+    }
+
+    fn problem_count(&self) -> usize {
+        let gm = self.tab().full_graph();
+        self.violations.len() + self.alerts.len() + self.tab().cycles.groups.len() + gm.conflicts.len() + gm.pending_count + self.warnings.len()
+    }
+
+    fn move_problem(&mut self, forward: bool) {
+        let n = self.problem_count();
+        if n == 0 {
+            return;
+        }
+        let i = self.problems_state.selected().unwrap_or(0);
+        self.problems_state.select(Some(if forward { (i + 1) % n } else { (i + n - 1) % n }));
+    }
+
+    /// Scroll the selected node's details by `lines` (negative is up); the
+    /// end is clamped when the pane is drawn, so `i32::MAX` goes to the bottom.
+    fn scroll_details(&mut self, lines: i32) {
+        let Some(idx) = self.tab().selected() else { return };
+        let id = self.tab().gm.graph[idx].id.to_string();
+        let offset = self.details_scroll.entry(id).or_default();
+        *offset = (i32::from(*offset).saturating_add(lines)).clamp(0, i32::from(u16::MAX)) as u16;
+    }
+
+    fn toggle_group(&mut self) {
+        if !self.tab_mut().toggle_group() {
+            self.notice = Some(("The selected node is in no [[group]]".to_string(), true));
+        }
+    }
+
+    /// Select the next node on a cycle, going through each cycle in turn.
+    fn next_cycle_node(&mut self) {
+        let groups = &self.tab().cycles.groups;
+        let ids: Vec<(usize, usize)> = groups.iter().enumerate().flat_map(|(g, ids)| (0..ids.len()).map(move |n| (g, n))).collect();
+        if ids.is_empty() {
+            self.notice = Some(("No cycles".to_string(), false));
+            return;
+        }
+        let i = self.cycle_cursor % ids.len();
+        let (g, n) = ids[i];
+        let id = groups[g][n].clone();
+        self.notice = Some((format!("cycle {}/{}: node {} of {}", g + 1, groups.len(), n + 1, groups[g].len()), true));
+        self.cycle_cursor = i + 1;
+        self.tab_mut().jump_to_id(&id);
+    }
+
+    /// Leave the problems panel on the node of the selected violation or conflict.
+    fn jump_to_problem(&mut self) {
+        let target = self.problems_state.selected().and_then(|i| self.problems().nth(i)).and_then(|(_, node, _)| node.map(str::to_string));
+        if let Some(id) = target {
+            self.tab_mut().jump_to_id(&id);
+            self.mode = Mode::Normal;
+        }
+    }
+
+    /// Apply whatever the ingest workers delivered since the last call.
+    /// Returns true when the graph changed.
+    fn pump_ingest(&mut self) -> bool {
+        let live = self.ingest.stats().live.load(Ordering::Relaxed);
+        let live_changed = live != self.seen_live;
+        self.seen_live = live;
+        let (msgs, backlog) = self.ingest.drain(INGEST_BUDGET);
+        self.backlog = backlog;
+        self.streaming = !msgs.is_empty();
+        if msgs.is_empty() {
+            if live == 0 {
+                self.give_up_pending();
+                self.store_cache();
+            }
+            return live_changed;
+        }
+        for msg in msgs {
+            let warning = match msg {
+                IngestMsg::Event(ev, origin) => {
+                    if self.tag_sources && !self.sources.contains(&origin.source) {
+                        self.sources.push(origin.source.clone());
+                    }
+                    let t = self.routes.get(&origin.source).copied().unwrap_or(0);
+                    apply_event(self.tabs[t].live_mut(), ev, &origin, self.aliases.as_ref(), self.on_duplicate, self.tag_sources)
+                }
+                IngestMsg::SourceOpened { source, own_tab } => {
+                    if own_tab {
+                        let title = Path::new(&*source)
+                            .file_name()
+                            .map(|n| n.to_string_lossy().into_owned())
+                            .unwrap_or_else(|| source.to_string());
+                        // The first file takes over the main tab while it is still blank.
+                        let blank = self.tabs.len() == 1 && self.routes.is_empty() && self.tabs[0].full_graph().graph.node_count() == 0;
+                        // Later ones open filtered as the tab in view is, so a watch keeps its filter.
+                        if blank {
+                            self.tabs[0].title = title;
+                        } else {
+                            let filter = self.tab().filter_text.clone();
+                            let mut tab = Tab::new(title, GraphModel::new()).with_sample(self.sample).with_groups(self.groups.clone()).with_filter(&filter);
+                            // Its first match stays selected rather than a root the filter hides.
+                            tab.start_pending = filter.trim().is_empty();
+                            self.tabs.push(tab);
+                        }
+                        self.routes.insert(source, self.tabs.len() - 1);
+                    }
+                    None
+                }
+                IngestMsg::Snapshot { source, events } => {
+                    let t = self.routes.get(&source).copied().unwrap_or(0);
+                    let (gm, warnings) = GraphModel::from_events(&source, events, self.aliases.as_ref(), self.on_duplicate);
+                    let tab = &mut self.tabs[t];
+                    if tab.sampled.is_some() || tab.parked.is_some() {
+                        tab.live_mut().replace(gm);
+                    } else {
+                        tab.replace_graph(gm);
+                        if let Some(diff) = &mut tab.diff {
+                            diff.stale = true;
+                        }
+                    }
+                    for w in warnings {
+                        self.push_warning(w);
+                    }
+                    None
+                }
+                IngestMsg::Warning(w) => Some(w),
+            };
+            if let Some(w) = warning {
+                self.push_warning(w);
+            }
+        }
+        self.watches.stale = true;
+        self.violations_stale = true;
+        self.tags_stale = true;
+        true
+    }
+
+    /// Open (appending) the audit log that receives every warning from now on.
+    fn open_error_log(&mut self, path: &str) -> Result<()> {
+        let mut f = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .wrap_err("failed to open error log")?;
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+        writeln!(f, "# riff-dag-tui session started (unix time {})", now)?;
+        self.error_log = Some(f);
+        Ok(())
+    }
+
+    /// Once every source has finished, edges still waiting for a node never
+    /// will; report them as warnings.
+    fn give_up_pending(&mut self) {
+        for t in 0..self.tabs.len() {
+            if self.tabs[t].full_graph().pending_count > 0 {
+                for w in self.tabs[t].live_mut().take_unresolved() {
+                    self.push_warning(w);
+                }
+            }
+        }
+    }
+
+    /// Snapshot the graph once the input file has been fully applied.
+    fn store_cache(&mut self) {
+        if let Some(key) = self.pending_cache.take() {
+            let events = self.ingest.stats().received.load(Ordering::Relaxed);
+            if let Err(err) = cache::store(&key, self.tabs[0].full_graph(), events, &self.warnings) {
+                self.push_warning(format!("[warn] could not write graph cache: {:#}", err));
+            }
+        }
+    }
+
+    fn push_warning(&mut self, w: String) {
+        let w = load::mask_secrets(w, &self.secrets);
+        if let Some(f) = &mut self.error_log {
+            if let Err(err) = writeln!(f, "{}", w) {
+                // Stop logging rather than failing on every subsequent warning.
+                self.error_log = None;
+                self.push_warning(format!("[warn] error log disabled: {}", err));
+            }
+        }
+        if self.warnings.len() < MAX_WARNINGS {
+            self.warnings.push(w);
+        } else {
+            self.warnings_elided += 1;
+        }
+    }
+
+    fn ingest_status(&self) -> String {
+        let stats = self.ingest.stats();
+        let live = if stats.live.load(Ordering::Relaxed) > 0 { " (live)" } else { "" };
+        let cached = if self.cached_events > 0 { " (cached)" } else { "" };
+        format!(
+            "ingest{}{}: {} ev · {} bad · {} dropped",
+            live,
+            cached,
+            self.cached_events + stats.received.load(Ordering::Relaxed),
+            stats.malformed.load(Ordering::Relaxed),
+            stats.dropped.load(Ordering::Relaxed),
+        )
+    }
+
+    /// How long to wait for input before the next tick. Live sources need a
+    /// steady tick to pick up events; once everything is loaded and nothing
+    /// animates, only input can change the screen, so poll far less often.
+    fn poll_timeout(&self) -> Duration {
+        if self.backlog {
+            return Duration::from_secs(0);
+        }
+        let idle = self.ingest.stats().live.load(Ordering::Relaxed) == 0 && !self.replay_playing;
+        let rate = if idle { self.idle_tick_rate } else { self.tick_rate };
+        let timeout = rate.checked_sub(self.last_tick.elapsed()).unwrap_or_else(|| Duration::from_secs(0));
+        if self.streaming {
+            timeout.min(STREAM_POLL)
+        } else if self.control.is_some() {
+            timeout.min(control::CONTROL_POLL)
+        } else {
+            timeout
+        }
+    }
+
+    /// Scroll unwrapped node list items sideways, a few characters at a time.
+    fn scroll_list(&mut self, right: bool) {
+        self.list_scroll = if right { self.list_scroll + LIST_SCROLL_STEP } else { self.list_scroll.saturating_sub(LIST_SCROLL_STEP) };
+    }
+
+    /// Carry out a control socket request on the active tab.
+    fn handle_call(&mut self, call: Call) {
+        let tab = &mut self.tabs[self.active];
+        if tab.order_stale {
+            tab.refresh_order();
+        }
+        let result = match &call.request {
+            Request::Select(id) => {
+                tab.jump_to_id(id);
+                match tab.selected().filter(|&idx| *tab.gm.graph[idx].id == **id) {
+                    Some(idx) => Ok(emit::node_json(&tab.gm, idx)),
+                    None => {
+                        let ids = tab.gm.graph.node_weights().map(|nd| &*nd.id);
+                        let hint = suggest::closest(id, ids).map_or(String::new(), |near| format!(" (did you mean `{}`?)", near));
+                        Err(format!("no node `{}`{}", id, hint))
+                    }
+                }
+            }
+            Request::Filter(query) => {
+                tab.apply_filter(query);
+                Ok(serde_json::json!({ "matches": tab.order.len() }))
+            }
+            Request::Export(path) => export::write_jsonl(&tab.gm, &tab.order, path, &self.time, self.redact.as_ref())
+                .map(|(nodes, edges)| serde_json::json!({ "nodes": nodes, "edges": edges }))
+                .map_err(|err| format!("{:#}", err)),
+            Request::Selection => Ok(tab.selected().map_or(serde_json::Value::Null, |idx| emit::node_json(&tab.gm, idx))),
+        };
+        call.reply(result);
+        self.dirty = true;
+    }
+
+    fn toggle_dag_view(&mut self) {
+        self.dag_view_mode = match self.dag_view_mode {
+            DagViewMode::Text => DagViewMode::Canvas,
+            DagViewMode::Canvas => DagViewMode::Text,
+        };
+        if self.mode == Mode::Canvas {
+            self.mode = Mode::Normal;
+        }
+    }
+}
+
+/// The terminal the UI draws on: stdout, or the controlling terminal itself
+/// when stdout carries `--emit-selection stdout`.
+type Tui = Terminal<CrosstermBackend<Box<dyn Write>>>;
+
+fn setup_terminal(on_tty: bool, compat: Compat) -> Result<Tui> {
+    enable_raw_mode()?;
+    let mut out: Box<dyn Write> = if on_tty {
+        Box::new(OpenOptions::new().write(true).open("/dev/tty").wrap_err("failed to open /dev/tty")?)
+    } else {
+        Box::new(io::stdout())
+    };
+    if compat.alt_screen {
+        execute!(out, EnterAlternateScreen)?;
+    }
+    if compat.mouse {
+        execute!(out, EnableMouseCapture)?;
+    }
+    let backend = CrosstermBackend::new(out);
+    let mut terminal = Terminal::new(backend)?;
+    if !compat.alt_screen {
+        terminal.clear()?;
+    }
+    Ok(terminal)
+}
+
+fn restore_terminal(mut terminal: Tui, compat: Compat) -> Result<()> {
+    disable_raw_mode()?;
+    if compat.mouse {
+        execute!(terminal.backend_mut(), DisableMouseCapture)?;
+    }
+    if compat.alt_screen {
+        execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
+    } else {
+        // Nothing to switch back to, so the UI is wiped instead.
+        terminal.clear()?;
+    }
+    terminal.show_cursor()?;
+    Ok(())
+}
+
+fn draw_ui(f: &mut ratatui::Frame, app: &mut App) {
+    app.hits = Hits::default();
+    let theme = app.theme;
+    let main_chunks = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(32), Constraint::Percentage(68)].as_ref())
+        .split(f.size());
+
+    let right_chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Percentage(45), Constraint::Percentage(55)].as_ref())
+        .split(main_chunks[1]);
+
+    // Status / help line overlay at bottom
+    let dag_mode_str = match app.dag_view_mode {
+        DagViewMode::Text => "text",
+        DagViewMode::Canvas => "canvas",
+    };
+    let status = match app.mode {
+        Mode::Normal => format!(
+            "Normal | / filter | c clear | Tab toggle DAG ({}) | q quit | ? help | filter: '{}'{} | {}{}",
+            dag_mode_str,
+            app.tab().filter_text,
+            app.tab().filter_hint.as_ref().map_or(String::new(), |hint| format!(" ({})", hint)),
+            app.ingest_status(),
+            app.tab().sample_status()
+        ),
+        Mode::Filter => format!(
+            "Filter mode — type to filter (~ first for fuzzy), Enter accept, Esc exit, Backspace delete | query: '{}'{}",
+            app.tab().filter_text,
+            app.tab().filter_hint.as_ref().map_or(String::new(), |hint| format!(" | {}", hint))
+        ),
+        Mode::EdgeFocus => "Edge focus — Up/Down/j/k cycle edges, Enter follow edge, Esc/e back".to_string(),
+        Mode::Problems => "Problems — Up/Down/j/k move, Enter go to node, Esc/p back".to_string(),
+        Mode::Tags => "Tags — Up/Down/j/k move, Enter filter by tag, R rename everywhere, D delete everywhere, Esc/t back".to_string(),
+        Mode::TagEdit => {
+            let prompt = app.tag_prompt.as_ref().expect("tag prompt is open in TagEdit");
+            let what = match &prompt.action {
+                TagAction::Add => "Add tag".to_string(),
+                TagAction::Rename(old) => format!("Rename tag '{}'", old),
+            };
+            let suggestions = prompt.suggestions(&app.tag_cloud);
+            let suggestions = if suggestions.is_empty() { String::new() } else { format!(" | Tab: {}", suggestions.join(", ")) };
+            format!("{} — type, Tab complete, Enter apply, Esc cancel | tag: '{}'{}", what, prompt.input, suggestions)
+        }
+        Mode::Roots => "Roots — Up/Down/j/k move, Enter select, Esc/r back".to_string(),
+        Mode::Stats => "Stats — Up/Down/j/k scroll, Esc/I back".to_string(),
+        Mode::Bookmarks => "Bookmarks — Up/Down/j/k move, Enter select, d remove, Esc/B back".to_string(),
+        Mode::Raw => "Raw event — Up/Down/j/k scroll, Esc/J back".to_string(),
+        Mode::Goto => "Go to — type part of an id or label, Up/Down pick, Tab complete, Enter jump, Esc cancel".to_string(),
+        Mode::PatternInput => format!(
+            "Pattern — steps like prompt -> tool|error -> *, Enter search, Esc cancel | query: '{}'{}",
+            app.pattern_input,
+            app.pattern_error.as_ref().map_or(String::new(), |err| format!(" | {}", err))
+        ),
+        Mode::Matches => "Matches — Up/Down/j/k move, Enter select first node, Esc/M back".to_string(),
+        Mode::Revisions => "Revisions — Up/Down/j/k pick a version, Space diff against this one, Esc/v back".to_string(),
+        Mode::ExportPath => format!(
+            "Export {} nodes and their edges (JSONL, DOT for .dot/.gv, an outline for .md/.org) — type a path, Enter write, Esc cancel | path: '{}'",
+            app.export_nodes.as_ref().map_or(app.tab().order.len(), Vec::len),
+            app.export_input
+        ),
+        Mode::Replay => {
+            let (from, to, seq) = &app.replay[app.replay_pos];
+            let waited = app.replay_edge().filter(|&e| app.tab().gm.graph[e].waited).map_or("", |_| " (arrived before its nodes)");
+            format!(
+                "Replay — edge {}/{}: #{} {} -> {}{} — j/k step, Space {}, Home/End, Esc/a back",
+                app.replay_pos + 1,
+                app.replay.len(),
+                seq,
+                from,
+                to,
+                waited,
+                if app.replay_playing { "pause" } else { "play" }
+            )
+        }
+        Mode::Replace => {
+            let field = |editing: bool, name: &str, value: &str| format!("{}{}: '{}'", if editing { "▸ " } else { "" }, name, value);
+            format!(
+                "Find and replace in labels and tags (regex, $1 for groups) — Tab switch field, Enter replace, Esc cancel | {} | {}",
+                field(!app.replace.editing_with, "find", &app.replace.find),
+                field(app.replace.editing_with, "with", &app.replace.with)
+            )
+        }
+        Mode::Menu => "Node menu — Up/Down/j/k move, Enter do it, Esc/Space back".to_string(),
+        Mode::Canvas => "Canvas — h/j/k/l or arrows pan, +/- zoom, 0 reset, Z labels, Esc/z back".to_string(),
+        Mode::Details => "Details — Up/Down/j/k scroll, PageUp/PageDown by a page, Home/End, n/N focus a parent or child, Enter go to it, Esc/h/l back to the list".to_string(),
+        Mode::Snapshots => "Snapshots — Up/Down/j/k move, Enter show, d diff live against it, s take another, Esc/S back".to_string(),
+        Mode::HelpOverlay => "Help — Up/Down/j/k scroll, PageUp/PageDown by a page, Home/End, Esc/? back".to_string(),
+    };
+    let nodes_title = if app.tabs.len() > 1 {
+        format!(" Nodes — tab {}/{}: {} ", app.active + 1, app.tabs.len(), app.tab().title)
+    } else {
+        " Nodes ".to_string()
+    };
+    let nodes_title = match app.tab().viewing {
+        Some(i) => format!("{}(snapshot #{}) ", nodes_title, i + 1),
+        None => nodes_title,
+    };
+    let nodes_title = match &app.tab().closure_of {
+        Some(id) => format!("{}(lineage of {}, f for all) ", nodes_title, id),
+        None => nodes_title,
+    };
+    let nodes_title = match app.tab().found.len() {
+        0 => nodes_title,
+        n => format!("{}({} highlighted, n/N to visit) ", nodes_title, n),
+    };
+    let nodes_title = match app.tab().sort {
+        SortOrder::Stable => nodes_title,
+        sort => format!("{}(by {}, = to change) ", nodes_title, sort.name()),
+    };
+    let nodes_title = match (app.list_wrap, app.list_scroll) {
+        (true, _) => format!("{}(wrapped) ", nodes_title),
+        (false, 0) => nodes_title,
+        (false, n) => format!("{}(→{}) ", nodes_title, n),
+    };
+    // Inside the borders, less the highlight symbol.
+    let list_width = (main_chunks[0].width as usize).saturating_sub(4).max(1);
+    let fit = if app.list_wrap { Fit::Wrap(list_width) } else { Fit::Scroll(app.list_scroll) };
+    let dag_view_mode = app.dag_view_mode;
+    let template = app.label_template.clone();
+    let time = app.time.clone();
+    let name = |nd: &NodeData| template.as_ref().map_or_else(|| nd.display_label(), |t| t.render(nd, &time));
+    let sources = app.color_by_source.then(|| app.sources.clone());
+    let color = |nd: &NodeData| sources.as_deref().map(|sources| source_color(sources, nd));
+    if let Some(other) = app.split {
+        draw_split(f, app, other, &name, &color);
+        draw_status(f, app, status);
+        return;
+    }
+    // While replaying, the replayed edge is shown as if focused, and no later one is drawn.
+    let replayed = if app.mode == Mode::Replay { app.replay_edge().map(|e| (e, app.replay_pos, app.replay.len())) } else { None };
+    let arrived_by = app.replay.get(app.replay_pos).filter(|_| app.mode == Mode::Replay).map_or(u64::MAX, |e| e.2);
+    let break_cycles = app.break_cycles;
+    let tab = &mut app.tabs[app.active];
+
+    // LEFT: Node list + filter status
+    let items = node_items(tab, &name, &color, fit, None, &app.bookmarks, &theme);
+    let heights: Vec<usize> = items.iter().map(ListItem::height).collect();
+
+    let list = List::new(items)
+        .block(Block::default().title(nodes_title).borders(Borders::ALL))
+        .highlight_style(Style::default().fg(theme.highlight).add_modifier(Modifier::BOLD))
+        .highlight_symbol("▶ ");
+
+    f.render_stateful_widget(list, main_chunks[0], &mut tab.list_state);
+    app.hits.list = main_chunks[0].inner(&Margin::new(1, 1));
+    app.hits.list_rows = heights
+        .iter()
+        .enumerate()
+        .skip(tab.list_state.offset())
+        .flat_map(|(i, &h)| std::iter::repeat_n(i, h))
+        .take(usize::from(app.hits.list.height))
+        .collect();
+    app.hits.details = right_chunks[0].inner(&Margin::new(1, 1));
+
+    // RIGHT TOP: Details of selected node (or of the focused edge)
+    let focused = if app.mode == Mode::EdgeFocus { tab.focused_edge() } else { replayed };
+    let details = if let Some((e, pos, n)) = focused {
+        let (a, b) = tab.gm.graph.edge_endpoints(e).expect("focused edge exists");
+        let ed = &tab.gm.graph[e];
+        let direction = if replayed.is_some() { "in arrival order" } else if Some(b) == tab.selected() { "incoming" } else { "outgoing" };
+        let info = format!(
+            "edge {}/{} ({})\nfrom: {}\nto: {}\nkind: {}\nweight: {}\nts: {}\narrived: #{} at {}{}\n",
+            pos + 1,
+            n,
+            direction,
+            name(&tab.gm.graph[a]),
+            name(&tab.gm.graph[b]),
+            if ed.kind.is_empty() { "(none)" } else { &ed.kind },
+            ed.weight.map_or("(none)".to_string(), |w| w.to_string()),
+            if ed.ts.is_empty() { "(n/a)".to_string() } else { time.show(&ed.ts) },
+            ed.seq,
+            if ed.at.is_empty() { "(unknown)" } else { &ed.at },
+            if ed.waited { ", before its nodes (held until they arrived)" } else { "" },
+        );
+        Paragraph::new(info)
+            .block(Block::default().title(" Edge Details ").borders(Borders::ALL))
+            .wrap(Wrap { trim: true })
+    } else if let Some(idx) = tab.selected() {
+        let link = tab.link().filter(|_| app.mode == Mode::Details && !app.lineage);
+        let text = node_details(tab, idx, &name, &time, app.lineage, link);
+        let area = right_chunks[0];
+        let page = area.height.saturating_sub(2).max(1);
+        let width = area.width.saturating_sub(2);
+        let lines = wrapped_height(&text, width);
+        let mut offset = app.details_scroll.get(&*tab.gm.graph[idx].id).copied().unwrap_or(0);
+        // Scrolled just far enough for the focused link to show.
+        if let Some(at) = text.lines().position(|line| line.starts_with("▶ ")) {
+            let above = wrapped_height(&text.lines().take(at).join("\n"), width);
+            let height = wrapped_height(text.lines().nth(at).unwrap_or(""), width);
+            offset = offset.min(above).max((above + height).saturating_sub(page));
+        }
+        // Clamped here, where the pane's size is known, and kept that way.
+        let offset = offset.min(lines.saturating_sub(page));
+        if offset > 0 {
+            app.details_scroll.insert(tab.gm.graph[idx].id.to_string(), offset);
+        } else {
+            app.details_scroll.remove(&*tab.gm.graph[idx].id);
+        }
+        app.details_page = page;
+        let mut title = if app.lineage { " Node Details (lineage) ".to_string() } else { " Node Details ".to_string() };
+        if lines > page {
+            title += &format!("{}–{}/{} {}", offset + 1, (offset + page).min(lines), lines, if app.mode == Mode::Details { "" } else { "(l to scroll) " });
+        }
+        let border = if app.mode == Mode::Details { Style::default().fg(theme.highlight) } else { Style::default() };
+        let score = tab.gm.graph[idx].score;
+        let compared = tab.compare.is_some();
+        let text: Vec<Line> = text
+            .lines()
+            .map(|line| match (line.starts_with("▶ "), line.strip_prefix("score: ").zip(score)) {
+                (true, _) => Line::styled(line.to_string(), Style::default().fg(theme.highlight).add_modifier(Modifier::BOLD)),
+                (false, Some((gauge, s))) => Line::from(vec![Span::raw("score: "), Span::styled(gauge.to_string(), Style::default().fg(theme.score(s)))]),
+                _ if compared && line.starts_with("  - ") => Line::styled(line.to_string(), Style::default().fg(theme.bad)),
+                _ if compared && line.starts_with("  + ") => Line::styled(line.to_string(), Style::default().fg(theme.good)),
+                (false, None) => Line::raw(line.to_string()),
+            })
+            .collect();
+        Paragraph::new(text)
+            .block(Block::default().title(title).borders(Borders::ALL).border_style(border))
+            .wrap(Wrap { trim: true })
+            .scroll((offset, 0))
+    } else {
+        Paragraph::new("No selection")
+            .block(Block::default().title(" Node Details ").borders(Borders::ALL))
+    };
+    f.render_widget(details, right_chunks[0]);
+
+    // RIGHT BOTTOM: Layered DAG text or canvas view (toggle with Tab)
+    if let Some(idx) = tab.selected() {
+        match dag_view_mode {
+            DagViewMode::Text => {
+                let mut dag_text = build_layered_dag_text(&tab.gm, idx, 2, app.glyphs, &theme, &tab.found);
+                if !tab.path.is_empty() {
+                    let path = format!("Path ({} edges): {}", tab.path.len() - 1, tab.path.join(" → "));
+                    dag_text.push(Line::styled(path, Style::default().fg(theme.path)));
+                }
+                let dag_paragraph = Paragraph::new(dag_text)
+                    .block(Block::default().title(" DAG View (text, depth 2) ").borders(Borders::ALL))
+                    .wrap(Wrap { trim: false });
+                f.render_widget(dag_paragraph, right_chunks[1]);
+            }
+            DagViewMode::Canvas => {
+                // Canvas widget with node shapes and edges
+                tab.layout.update(&tab.gm, idx, 2);
+                let quality = tab.layout.quality;
+                let viewport = app.viewport;
+                let (positions, folded, containers) = fold_positions(tab, idx, &tab.layout.positions);
+                let positions = &positions;
+                let area = right_chunks[1];
+                let (labels, crowded) = if app.canvas_labels {
+                    let mut named: Vec<(NodeIndex, (f64, f64), String)> = positions
+                        .iter()
+                        .filter(|(n, _)| !folded.contains(n))
+                        .map(|(&n, &pos)| {
+                            let nd = &tab.gm.graph[n];
+                            let text = match tab.groups.of(&nd.id).filter(|_| containers.contains(&n)) {
+                                Some(g) => format!("▸ {}", tab.groups.name(g)),
+                                None => name(nd),
+                            };
+                            (n, pos, text)
+                        })
+                        .collect();
+                    // The selection gets the first pick of the space, then the rest in a stable order.
+                    named.sort_by_key(|(n, _, _)| (*n != idx, tab.gm.graph[*n].id.to_string()));
+                    place_labels(&named, &viewport, area.width.saturating_sub(2), area.height.saturating_sub(2))
+                } else {
+                    (Vec::new(), 0)
+                };
+                let title = format!(
+                    " DAG View (shapes, depth 2, {}: {}{}{}) ",
+                    tab.layout.algorithm.name(),
+                    match quality {
+                        Some(q) => format!("{} crossings, {} overlaps", q.crossings, q.overlaps),
+                        None => "not measured".to_string(),
+                    },
+                    if viewport == Viewport::default() { String::new() } else { format!(", zoom {:.2}×", viewport.zoom) },
+                    match (app.canvas_labels, crowded) {
+                        (false, _) => ", labels off".to_string(),
+                        (true, 0) => String::new(),
+                        (true, n) => format!(", {} labels crowded out", n),
+                    }
+                );
+                let border = if app.mode == Mode::Canvas { Style::default().fg(theme.highlight) } else { Style::default() };
+                let dag_canvas = Canvas::default()
+                    .block(Block::default().title(title).borders(Borders::ALL).border_style(border))
+                    .x_bounds(viewport.x_bounds())
+                    .y_bounds(viewport.y_bounds())
+                    .paint(|ctx| {
+                        // Draw edges first (so they appear behind nodes), the
+                        // heavier ones bolder.
+                        let mut edges = Vec::new();
+                        let arrived = |a: NodeIndex, b: NodeIndex| tab.gm.graph.edges_connecting(a, b).any(|e| e.weight().seq <= arrived_by);
+                        for (from_idx, from_pos) in positions {
+                            for to_idx in tab.gm.children_of(*from_idx).into_iter().unique() {
+                                if let Some(to_pos) = positions.get(&to_idx).filter(|_| arrived(*from_idx, to_idx)) {
+                                    edges.push((*from_pos, *to_pos, edge_strength(&tab.gm, *from_idx, to_idx)));
+                                }
+                            }
+                        }
+                        let (lightest, heaviest) = edges.iter().fold((f64::MAX, 0.0f64), |(lo, hi), e| (lo.min(e.2), hi.max(e.2)));
+                        for (from, to, strength) in edges {
+                            let (color, lines) = match (strength - lightest) / (heaviest - lightest) {
+                                t if t >= 2.0 / 3.0 => (theme.heavy_edge, 3),
+                                t if t >= 1.0 / 3.0 => (theme.medium_edge, 2),
+                                _ => (theme.muted, 1), // also when all edges weigh the same
+                            };
+                            draw_edge_line(ctx, from, to, color, lines);
+                        }
+                        // Then the edges of cycles and of the path, and the focused edge on top.
+                        for (from_idx, from_pos) in positions {
+                            for to_idx in tab.gm.children_of(*from_idx).into_iter().unique() {
+                                let (from, to) = (&tab.gm.graph[*from_idx].id, &tab.gm.graph[to_idx].id);
+                                if let Some(to_pos) = positions.get(&to_idx).filter(|_| tab.cycles.contains_edge(from, to) && arrived(*from_idx, to_idx)) {
+                                    // Broken, a cycle is drawn as a DAG plus its back edges.
+                                    match (break_cycles, tab.cycles.is_back_edge(from, to)) {
+                                        (false, _) => draw_edge_line(ctx, *from_pos, *to_pos, theme.bad, 1),
+                                        (true, true) => draw_edge_line(ctx, *from_pos, *to_pos, theme.alert, 2),
+                                        (true, false) => {}
+                                    }
+                                }
+                                match (positions.get(&to_idx), tab.compare.as_ref().and_then(|c| c.edge_change(from, to))) {
+                                    (Some(to_pos), Some(Change::Added)) => draw_edge_line(ctx, *from_pos, *to_pos, theme.good, 1),
+                                    (Some(to_pos), Some(Change::Removed)) => draw_edge_line(ctx, *from_pos, *to_pos, theme.bad, 1),
+                                    _ => {}
+                                }
+                                if let Some(to_pos) = positions.get(&to_idx).filter(|_| tab.path.windows(2).any(|w| *w[0] == **from && *w[1] == **to)) {
+                                    draw_edge_line(ctx, *from_pos, *to_pos, theme.path, 2);
+                                }
+                            }
+                        }
+                        if let Some((a, b)) = focused.and_then(|(e, _, _)| tab.gm.graph.edge_endpoints(e)) {
+                            if let (Some(from), Some(to)) = (positions.get(&a), positions.get(&b)) {
+                                draw_edge_line(ctx, *from, *to, theme.highlight, 1);
+                            }
+                        }
+
+                        // Draw nodes
+                        for (node_idx, pos) in positions.iter().filter(|(n, _)| !folded.contains(n)) {
+                            let nd = &tab.gm.graph[*node_idx];
+                            let node_type = classify_node_type(&nd.tags);
+                            let is_selected = *node_idx == idx;
+                            // Scored nodes are dimmer the lower their score.
+                            let shade = color(nd).unwrap_or(theme.node(node_type));
+                            draw_node_shape(ctx, pos.0, pos.1, node_type, is_selected, nd.score.map_or(shade, |s| theme::brightness(shade, s)));
+                            if containers.contains(node_idx) {
+                                draw_node_shape(ctx, pos.0, pos.1, NodeType::Unknown, true, theme.group);
+                            } else if tab.found.contains(node_idx) {
+                                draw_node_shape(ctx, pos.0, pos.1, NodeType::Unknown, true, theme.found);
+                            }
+                        }
+                        for (x, y, text, selected) in &labels {
+                            let style = if *selected { Style::default().fg(theme.highlight).add_modifier(Modifier::BOLD) } else { Style::default().fg(theme.muted) };
+                            ctx.print(*x, *y, Line::styled(text.clone(), style));
+                        }
+                    });
+                f.render_widget(dag_canvas, right_chunks[1]);
+                app.hits.canvas = area.inner(&Margin::new(1, 1));
+                app.hits.bounds = (viewport.x_bounds(), viewport.y_bounds());
+                app.hits.shapes = positions.iter().filter(|(n, _)| !folded.contains(n)).map(|(&n, &pos)| (n, pos)).collect();
+            }
+        }
+    } else {
+        let empty = Paragraph::new("No selection")
+            .block(Block::default().title(" DAG View ").borders(Borders::ALL));
+        f.render_widget(empty, right_chunks[1]);
+    }
+
+    draw_status(f, app, status);
+}
+
+/// The status bar along the bottom, then whichever popup the mode opens.
+fn draw_status(f: &mut ratatui::Frame, app: &mut App, status: String) {
+    let area = Rect {
+        x: f.size().x,
+        y: f.size().bottom().saturating_sub(1),
+        width: f.size().width,
+        height: 1,
+    };
+    let mut status = vec![Span::raw(status)];
+    if !app.violations.is_empty() {
+        status.push(Span::raw(" | "));
+        status.push(Span::styled(format!("{} violations (p)", app.violations.len()), Style::default().fg(app.theme.bad)));
+    }
+    if !app.alerts.is_empty() {
+        status.push(Span::raw(" | "));
+        status.push(Span::styled(format!("{} alerts (p)", app.alerts.len()), Style::default().fg(app.theme.alert)));
+    }
+    let conflicts = app.tab().full_graph().conflicts.len();
+    if conflicts > 0 {
+        status.push(Span::raw(" | "));
+        status.push(Span::styled(format!("{} conflicts (p)", conflicts), Style::default().fg(app.theme.conflict)));
+    }
+    if !app.tab().cycles.is_empty() {
+        let cycles = &app.tab().cycles;
+        let mut text = format!("⚠ {} cycles through {} nodes (y)", cycles.groups.len(), cycles.node_count());
+        if app.break_cycles {
+            text += &format!(", {} back edges set aside (Y)", cycles.back_count());
+        }
+        status.push(Span::raw(" | "));
+        status.push(Span::styled(text, Style::default().fg(app.theme.bad).add_modifier(Modifier::BOLD)));
+    }
+    if let Some(viewing) = app.tab().snapshot_status() {
+        status.push(Span::raw(" | "));
+        status.push(Span::styled(viewing, Style::default().fg(app.theme.highlight)));
+    }
+    if let Some(diff) = &app.tab().diff {
+        status.push(Span::raw(" | "));
+        status.push(Span::styled(diff.summary(), Style::default().fg(app.theme.good)));
+    }
+    if let Some(compare) = &app.tab().compare {
+        status.push(Span::raw(" | "));
+        status.push(Span::styled(compare.summary(), Style::default().fg(app.theme.changed)));
+    }
+    if let Some((notice, failed)) = &app.notice {
+        status.push(Span::raw(" | "));
+        status.push(Span::styled(notice.clone(), Style::default().fg(if *failed { app.theme.bad } else { app.theme.good })));
+    }
+    if app.color_by_source {
+        // The legend: each input's file name in its colour.
+        for (i, source) in app.sources.iter().enumerate() {
+            let name = Path::new(&**source).file_name().map_or_else(|| source.to_string(), |n| n.to_string_lossy().into_owned());
+            status.push(Span::raw(if i == 0 { " | " } else { " " }));
+            status.push(Span::styled(name, Style::default().fg(SOURCE_COLORS[i % SOURCE_COLORS.len()])));
+        }
+    }
+    status.extend(app.watches.spans());
+    let status_paragraph = Paragraph::new(Line::from(status))
+        .style(Style::default().fg(app.theme.muted))
+        .block(Block::default());
+    f.render_widget(Clear, area);
+    f.render_widget(status_paragraph, area);
+
+    match app.mode {
+        Mode::Problems => draw_problems(f, app),
+        Mode::Tags => draw_tags(f, app),
+        Mode::TagEdit if matches!(app.tag_prompt.as_ref().map(|p| &p.action), Some(TagAction::Rename(_))) => draw_tags(f, app),
+        Mode::Snapshots => draw_snapshots(f, app),
+        Mode::Roots => draw_roots(f, app),
+        Mode::Stats => draw_stats(f, app),
+        Mode::HelpOverlay => draw_help(f, app),
+        Mode::Bookmarks => draw_bookmarks(f, app),
+        Mode::Goto => draw_palette(f, app),
+        Mode::Raw => draw_raw(f, app),
+        Mode::Matches => draw_matches(f, app),
+        Mode::Revisions => draw_revisions(f, app),
+        Mode::Menu => draw_menu(f, app),
+        Mode::Replace => draw_replace(f, app),
+        _ => {}
+    }
+    if app.tutorial.is_some() {
+        draw_tutorial(f, app);
+    }
+}
+
+/// The walkthrough box, in the bottom right corner above the status line.
+fn draw_tutorial(f: &mut ratatui::Frame, app: &App) {
+    let Some(t) = &app.tutorial else { return };
+    let screen = f.size();
+    let width = screen.width.min(52);
+    let inner = width.saturating_sub(2).max(1) as usize;
+    // Rough, as words wrap early; the blank line and hint take two more.
+    let lines = (t.text().chars().count() / inner + 1) as u16 + 2;
+    let height = (lines + 3).min(screen.height.saturating_sub(1));
+    let area = Rect { x: screen.width - width, y: screen.height.saturating_sub(height + 1), width, height };
+    let text = vec![
+        Line::from(t.text().to_string()),
+        Line::from(""),
+        Line::from(Span::styled("Ctrl-n skip", Style::default().fg(app.theme.muted))),
+    ];
+    let paragraph = Paragraph::new(text).wrap(Wrap { trim: true }).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(app.theme.accent))
+            .title(t.heading()),
+    );
+    f.render_widget(Clear, area);
+    f.render_widget(paragraph, area);
+}
+
+/// How node list items longer than the list is wide are shown.
+#[derive(Clone, Copy)]
+enum Fit {
+    Wrap(usize),   // onto more lines of this many characters
+    Scroll(usize), // on one line, this many characters scrolled off the left
+}
+
+/// The node list lines of `tab`. Nodes are marked against `other` when
+/// given (only in this graph, or different there), otherwise against the
+/// snapshot the tab is diffed with; nodes on a cycle are marked `↻`
+/// and bookmarked ones `★`.
+fn node_items(
+    tab: &Tab,
+    name: &dyn Fn(&NodeData) -> String,
+    color: &dyn Fn(&NodeData) -> Option<Color>,
+    fit: Fit,
+    other: Option<&GraphModel>,
+    bookmarks: &Bookmarks,
+    theme: &Theme,
+) -> Vec<ListItem<'static>> {
+    let fuzzy = tab.filter_text.trim_start().strip_prefix('~').map(str::trim).filter(|p| !p.is_empty());
+    let matched = Style::default().fg(theme.accent).add_modifier(Modifier::BOLD | Modifier::UNDERLINED);
+    tab.order
+        .iter()
+        .enumerate()
+        .map(|(i, &idx)| {
+            let nd = &tab.gm.graph[idx];
+            let (pin, pout) = tab.gm.degree(idx);
+            let change = match other {
+                Some(other) => match other.indices.get(&nd.id) {
+                    None => Some(Change::Added),
+                    Some(&o) if !other.graph[o].same_fields(nd) => Some(Change::Changed),
+                    Some(_) => None,
+                },
+                None => tab.compare.as_ref().and_then(|c| c.change(&nd.id)).or_else(|| tab.diff.as_ref().and_then(|d| d.change(&nd.id))),
+            };
+            let (marker, style) = match change {
+                Some(Change::Added) => ("+ ", Style::default().fg(theme.good)),
+                Some(Change::Changed) => ("~ ", Style::default().fg(theme.changed)),
+                Some(Change::Removed) => ("- ", Style::default().fg(theme.bad)),
+                None if tab.path.iter().any(|p| **p == *nd.id) => ("● ", Style::default().fg(theme.path).add_modifier(Modifier::BOLD)),
+                None if tab.path_start.as_deref() == Some(&*nd.id) => ("◆ ", Style::default().fg(theme.path)),
+                None if tab.cycles.contains(&nd.id) => ("↻ ", Style::default().fg(theme.bad)),
+                None => ("", color(nd).map_or_else(Style::default, |c| Style::default().fg(c))),
+            };
+            let pinned = match (i < tab.pinned, bookmarks.contains(&nd.id)) {
+                (true, true) => "▪★ ",
+                (true, false) => "▪ ",
+                (false, true) => "★ ",
+                (false, false) => "",
+            };
+            let style = if tab.is_found(idx) { style.fg(theme.found).add_modifier(Modifier::BOLD) } else { style };
+            // The last pin is underlined to set the pins apart from the rest.
+            let style = if i + 1 == tab.pinned && tab.pinned < tab.order.len() { style.add_modifier(Modifier::UNDERLINED) } else { style };
+            let mut found = Vec::new();
+            let (text, style) = match tab.containers.get(&idx) {
+                Some(members) => {
+                    let group = tab.groups.of(&nd.id).map_or("", |g| tab.groups.name(g));
+                    (format!("{}▸ {} ({} nodes)", pinned, group, members.len()), style.fg(theme.group))
+                }
+                None => {
+                    let shown = name(nd);
+                    // The characters a `~` filter matched, where the name starts in the line.
+                    if let Some((_, at)) = fuzzy.and_then(|p| fuzzy::best(p, &shown)) {
+                        let offset = pinned.chars().count() + marker.chars().count();
+                        found = at.into_iter().map(|i| i + offset).collect();
+                    }
+                    (format!("{}{}{}  (↑{} ↓{})", pinned, marker, shown, pin, pout), style)
+                }
+            };
+            let chars: Vec<char> = text.chars().collect();
+            let line = |from: usize, to: usize| marked_line(&chars[from..to], from, &found, matched);
+            let lines: Vec<Line> = match fit {
+                Fit::Wrap(width) => (0..chars.len()).step_by(width).map(|from| line(from, (from + width).min(chars.len()))).collect(),
+                Fit::Scroll(scroll) => vec![line(scroll.min(chars.len()), chars.len())],
+            };
+            ListItem::new(lines).style(style)
+        })
+        .collect()
+}
+
+/// `chars`, which start at `from` in their line, with those at the
+/// positions in `marked` (ascending) in `style`.
+fn marked_line(chars: &[char], from: usize, marked: &[usize], style: Style) -> Line<'static> {
+    if marked.is_empty() {
+        return Line::from(chars.iter().collect::<String>());
+    }
+    let spans = chars
+        .iter()
+        .enumerate()
+        .chunk_by(|&(i, _)| marked.binary_search(&(from + i)).is_ok())
+        .into_iter()
+        .map(|(hit, run)| {
+            let text: String = run.map(|(_, c)| c).collect();
+            if hit { Span::styled(text, style) } else { Span::raw(text) }
+        })
+        .collect::<Vec<_>>();
+    Line::from(spans)
+}
+
+/// Lines `text` takes wrapped to `width` columns, near enough to bound a scroll.
+fn wrapped_height(text: &str, width: u16) -> u16 {
+    let width = usize::from(width.max(1));
+    let lines: usize = text.lines().map(|line| line.chars().count().div_ceil(width).max(1)).sum();
+    lines.min(usize::from(u16::MAX)) as u16
+}
+
+/// The details pane text for the node at `idx`.
+/// `levels` (from `GraphModel::lineage`) one hop per block, with counts, at
+/// most `LINEAGE_LIST` nodes listed per hop.
+fn lineage_lines(gm: &GraphModel, levels: &[Vec<NodeIndex>], arrow: &str, name: &dyn Fn(&NodeData) -> String) -> String {
+    levels
+        .iter()
+        .enumerate()
+        .map(|(hop, nodes)| {
+            let mut lines = format!("  hop {} ({}):", hop + 1, nodes.len());
+            for &n in nodes.iter().take(LINEAGE_LIST) {
+                lines.push_str(&format!("\n    {} {}", arrow, name(&gm.graph[n])));
+            }
+            if nodes.len() > LINEAGE_LIST {
+                lines.push_str(&format!("\n    ... {} more", nodes.len() - LINEAGE_LIST));
+            }
+            lines
+        })
+        .join("\n")
+}
+
+/// `link`: the focused parent or child, marked `▶`, parents counted first.
+fn node_details(tab: &Tab, idx: NodeIndex, name: &dyn Fn(&NodeData) -> String, time: &TimeDisplay, lineage: bool, link: Option<usize>) -> String {
+    let nd = &tab.gm.graph[idx];
+    if let (Some(members), Some(group)) = (tab.containers.get(&idx), tab.groups.of(&nd.id)) {
+        let mut text = format!(
+            "group: {}\npattern: {}\nnodes: {} (G to list them)\n",
+            tab.groups.name(group),
+            tab.groups.pattern(group),
+            members.len()
+        );
+        for &m in members.iter().take(LINEAGE_LIST) {
+            text.push_str(&format!("\n  {}", name(&tab.gm.graph[m])));
+        }
+        if members.len() > LINEAGE_LIST {
+            text.push_str(&format!("\n  ... {} more", members.len() - LINEAGE_LIST));
+        }
+        return text;
+    }
+    let (parents_title, parent_lines, children_title, child_lines) = if lineage {
+        let up = tab.gm.lineage(idx, Incoming);
+        let down = tab.gm.lineage(idx, Outgoing);
+        (
+            format!("ancestors ({})", up.iter().map(Vec::len).sum::<usize>()),
+            lineage_lines(&tab.gm, &up, "←", name),
+            format!("descendants ({})", down.iter().map(Vec::len).sum::<usize>()),
+            lineage_lines(&tab.gm, &down, "→", name),
+        )
+    } else {
+        // `»` marks where Backspace and Enter go (when there is a choice),
+        // `↻` an edge that closes a cycle.
+        let (parent, child) = tab.neighbor_cursor();
+        let parents = tab.gm.parents_of(idx);
+        let first_child = parents.len();
+        let lines = |nodes: Vec<NodeIndex>, arrow: &str, at: usize, incoming: bool, first: usize| {
+            let many = nodes.len() > 1;
+            nodes
+                .iter()
+                .enumerate()
+                .map(|(i, &n)| {
+                    let (from, to) = if incoming { (&tab.gm.graph[n].id, &nd.id) } else { (&nd.id, &tab.gm.graph[n].id) };
+                    let cycle = match (tab.cycles.is_back_edge(from, to), tab.cycles.contains_edge(from, to)) {
+                        (true, _) => "  ↻ back edge",
+                        (false, true) => "  ↻",
+                        (false, false) => "",
+                    };
+                    let mark = if link == Some(first + i) { "▶ " } else if many && i == at { "» " } else { "  " };
+                    format!("{}{} {}{}", mark, arrow, name(&tab.gm.graph[n]), cycle)
+                })
+                .join("\n")
+        };
+        let parent_lines = lines(parents, "←", parent, true, 0);
+        let child_lines = lines(tab.gm.children_of(idx), "→", child, false, first_child);
+        ("parents".to_string(), parent_lines, "children".to_string(), child_lines)
+    };
+
+    let metadata_lines = nd.metadata.iter().map(|(k, v)| format!("\n  {}: {}", k, v)).join("");
+    // Strings as they are; numbers, lists and objects as compact JSON.
+    let extra_lines = nd.extra.iter().map(|(k, v)| format!("\n  {}: {}", k, v.as_str().map_or_else(|| v.to_string(), str::to_string))).join("");
+    // Re-emissions are recorded on the full graph, whatever the sample shows.
+    let activity = tab.full_graph().activity.sparkline(&nd.id);
+    // `diff`: the fields the earlier file had otherwise.
+    let changes = tab.compare.as_ref().and_then(|c| c.node_diff(nd)).map_or(String::new(), |lines| {
+        let lines: String = lines
+            .iter()
+            .filter_map(|l| match l {
+                revision::DiffLine::Removed(l) => Some(format!("\n  - {}", l)),
+                revision::DiffLine::Added(l) => Some(format!("\n  + {}", l)),
+                revision::DiffLine::Same(_) => None,
+            })
+            .collect();
+        format!("\nchanged since before:{}", lines)
+    });
+    format!(
+        "id: {}\nlabel: {}\nspan: {}\nts: {}{}\ntags: {}{}{}{}{}\n\n{}:\n{}\n\n{}:\n{}\n",
+        nd.id,
+        if nd.label.is_empty() { "(none)" } else { &nd.label },
+        if nd.span.is_empty() { "(none)" } else { &nd.span },
+        if nd.ts.is_empty() { "(n/a)".to_string() } else { time.show(&nd.ts) },
+        nd.score.map_or(String::new(), |s| format!("\nscore: {}", score_gauge(s))),
+        if nd.tags.is_empty() { "(none)".to_string() } else { nd.tags.join(", ") },
+        activity.map_or(String::new(), |a| format!("\nactivity: {}", a)),
+        if metadata_lines.is_empty() { String::new() } else { format!("\nmetadata:{}", metadata_lines) },
+        if extra_lines.is_empty() { String::new() } else { format!("\nother fields:{}", extra_lines) },
+        changes,
+        parents_title,
+        if parent_lines.is_empty() { "(none)".to_string() } else { parent_lines },
+        children_title,
+        if child_lines.is_empty() { "(none)".to_string() } else { child_lines },
+    )
+}
+
+/// Ten cells filled in proportion to a score from 0 to 1, then the score.
+fn score_gauge(score: f64) -> String {
+    let filled = (score.clamp(0.0, 1.0) * 10.0).round() as usize;
+    format!("{}{} {}", "█".repeat(filled), "·".repeat(10 - filled), score)
+}
+
+/// The active tab and tab `other` side by side, each with its node list over
+/// the details of its selection. Nodes only one side has, or that differ
+/// between the two, are marked as in a snapshot diff.
+fn draw_split(f: &mut ratatui::Frame, app: &mut App, other: usize, name: &dyn Fn(&NodeData) -> String, color: &dyn Fn(&NodeData) -> Option<Color>) {
+    let halves = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)].as_ref())
+        .split(f.size());
+    let selected_id = app.tab().selected().map(|idx| app.tab().gm.graph[idx].id.to_string());
+    for (t, o, half) in [(app.active, other, halves[0]), (other, app.active, halves[1])] {
+        let panes = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Percentage(55), Constraint::Percentage(45)].as_ref())
+            .split(half);
+        let width = (half.width as usize).saturating_sub(4).max(1);
+        let fit = if app.list_wrap { Fit::Wrap(width) } else { Fit::Scroll(app.list_scroll) };
+        let items = node_items(&app.tabs[t], name, color, fit, Some(app.tabs[o].full_graph()), &app.bookmarks, &app.theme);
+        let tab = &app.tabs[t];
+        let border = if t == app.active { Style::default().fg(app.theme.highlight) } else { Style::default() };
+        let title = format!(" tab {}/{}: {} ", t + 1, app.tabs.len(), tab.title);
+        let list = List::new(items)
+            .block(Block::default().title(title).borders(Borders::ALL).border_style(border))
+            .highlight_style(Style::default().fg(app.theme.highlight).add_modifier(Modifier::BOLD))
+            .highlight_symbol("▶ ");
+        let details = match (tab.selected(), &selected_id) {
+            (Some(idx), _) => node_details(tab, idx, name, &app.time, app.lineage, None),
+            (None, Some(id)) => format!("{} is not in this graph", id),
+            (None, None) => "No selection".to_string(),
+        };
+        let details = Paragraph::new(details)
+            .block(Block::default().title(" Node Details ").borders(Borders::ALL))
+            .wrap(Wrap { trim: true });
+        f.render_widget(details, panes[1]);
+        f.render_stateful_widget(list, panes[0], &mut app.tabs[t].list_state);
+    }
+}
+
+/// Where the problems panel and the other panes pop up, over the middle of the screen.
+fn popup_area(screen: Rect) -> Rect {
+    Rect {
+        x: screen.x + screen.width / 10,
+        y: screen.y + screen.height / 6,
+        width: screen.width - screen.width / 5,
+        height: screen.height - screen.height / 3,
+    }
+}
+
+fn draw_tags(f: &mut ratatui::Frame, app: &mut App) {
+    let area = popup_area(f.size());
+    let width = app.tag_cloud.first().map_or(1, |(_, n)| n.to_string().len());
+    let items: Vec<ListItem> = app
+        .tag_cloud
+        .iter()
+        .map(|(tag, n)| ListItem::new(format!("{:>width$}  {}", n, tag, width = width)))
+        .collect();
+    let title = format!(" Tags — {} distinct ", app.tag_cloud.len());
+    let list = if items.is_empty() {
+        List::new(vec![ListItem::new("No tags")])
+    } else {
+        List::new(items)
+    };
+    let list = list
+        .block(Block::default().title(title).borders(Borders::ALL))
+        .highlight_style(Style::default().fg(app.theme.highlight).add_modifier(Modifier::BOLD))
+        .highlight_symbol("▶ ");
+    f.render_widget(Clear, area);
+    f.render_stateful_widget(list, area, &mut app.tags_state);
+}
+
+fn draw_roots(f: &mut ratatui::Frame, app: &mut App) {
+    let area = popup_area(f.size());
+    let gm = app.tab().full_graph();
+    let items: Vec<ListItem> = app
+        .roots
+        .iter()
+        .filter_map(|id| gm.indices.get(id.as_str()))
+        .map(|&idx| {
+            let nd = &gm.graph[idx];
+            let name = app.label_template.as_ref().map_or_else(|| nd.display_label(), |t| t.render(nd, &app.time));
+            let ts = if nd.ts.is_empty() { "(no ts)".to_string() } else { app.time.show(&nd.ts) };
+            ListItem::new(format!("{}  {}  (↓{})", ts, name, gm.children_of(idx).len()))
+        })
+        .collect();
+    let title = format!(" Roots — {} nodes without parents, earliest first ", app.roots.len());
+    let list = if items.is_empty() {
+        List::new(vec![ListItem::new("No roots (every node has a parent)")])
+    } else {
+        List::new(items)
+    };
+    let list = list
+        .block(Block::default().title(title).borders(Borders::ALL))
+        .highlight_style(Style::default().fg(app.theme.highlight).add_modifier(Modifier::BOLD))
+        .highlight_symbol("▶ ");
+    f.render_widget(Clear, area);
+    f.render_stateful_widget(list, area, &mut app.roots_state);
+}
+
+fn draw_bookmarks(f: &mut ratatui::Frame, app: &mut App) {
+    let area = popup_area(f.size());
+    let gm = app.tab().full_graph();
+    let items: Vec<ListItem> = app
+        .bookmarks
+        .ids()
+        .iter()
+        .map(|id| match gm.indices.get(id.as_str()) {
+            Some(&idx) => {
+                let nd = &gm.graph[idx];
+                let name = app.label_template.as_ref().map_or_else(|| nd.display_label(), |t| t.render(nd, &app.time));
+                ListItem::new(name)
+            }
+            None => ListItem::new(format!("{}  (not in this graph)", id)).style(Style::default().fg(app.theme.muted)),
+        })
+        .collect();
+    let saved = app.bookmarks.file().map_or_else(|| "this session only".to_string(), |p| format!("saved to {}", p.display()));
+    let title = format!(" Bookmarks — {}, {} ", app.bookmarks.ids().len(), saved);
+    let list = if items.is_empty() {
+        List::new(vec![ListItem::new("No bookmarks (b bookmarks the selected node)")])
+    } else {
+        List::new(items)
+    };
+    let list = list
+        .block(Block::default().title(title).borders(Borders::ALL))
+        .highlight_style(Style::default().fg(app.theme.highlight).add_modifier(Modifier::BOLD))
+        .highlight_symbol("▶ ");
+    f.render_widget(Clear, area);
+    f.render_stateful_widget(list, area, &mut app.bookmarks_state);
+}
+
+fn draw_palette(f: &mut ratatui::Frame, app: &mut App) {
+    let area = popup_area(f.size());
+    let matched = Style::default().fg(app.theme.accent).add_modifier(Modifier::BOLD | Modifier::UNDERLINED);
+    let p = &mut app.palette;
+    let items: Vec<ListItem> = p
+        .candidates
+        .iter()
+        .map(|c| ListItem::new(marked_line(&c.text.chars().collect::<Vec<_>>(), 0, &c.marked, matched)))
+        .collect();
+    let more = if p.total > p.candidates.len() { format!(", first {} listed", p.candidates.len()) } else { String::new() };
+    let title = format!(" Go to: {}▏ — {} nodes{} ", p.input, p.total, more);
+    let list = if items.is_empty() {
+        List::new(vec![ListItem::new("No node's id or label has these characters in order")])
+    } else {
+        List::new(items)
+    };
+    let list = list
+        .block(Block::default().title(title).borders(Borders::ALL))
+        .highlight_style(Style::default().fg(app.theme.highlight).add_modifier(Modifier::BOLD))
+        .highlight_symbol("▶ ");
+    f.render_widget(Clear, area);
+    f.render_stateful_widget(list, area, &mut p.state);
+}
+
+fn draw_stats(f: &mut ratatui::Frame, app: &mut App) {
+    let area = popup_area(f.size());
+    let lines = app.stats.lines();
+    // Clamped here, where the pane's height is known.
+    app.stats_scroll = app.stats_scroll.min((lines.len() as u16).saturating_sub(area.height.saturating_sub(2)));
+    let text: Vec<Line> = lines.into_iter().map(Line::raw).collect();
+    let title = format!(" Stats — {} ", app.tab().title);
+    let stats = Paragraph::new(text).block(Block::default().title(title).borders(Borders::ALL)).scroll((app.stats_scroll, 0));
+    f.render_widget(Clear, area);
+    f.render_widget(stats, area);
+}
+
+/// The keys of the list view, one row each, as the help pane lists them.
+const HELP: &[(&str, &str)] = &[
+    ("Up/Down/j/k", "move"),
+    ("Space", "node menu"),
+    ("/", "filter: tag:x span:y id: ts: label: meta.KEY:"),
+    ("", "  score>0.8 ts>2024-05-01T12:00, AND OR NOT ( ), ~fzy fuzzy"),
+    ("c", "clear filter"),
+    ("Tab", "toggle DAG view"),
+    ("V", "split with next tab"),
+    ("W", "wrap list"),
+    ("L", "lineage details"),
+    ("f", "list only the lineage"),
+    ("A", "filter to ±N minutes around the node"),
+    ("=", "sort the list (default, ts, id, label, in/out-degree)"),
+    ("m", "mark path start"),
+    ("g", "shortest path to here"),
+    ("C", "colour by source"),
+    ("[/]", "scroll list"),
+    ("</>", "switch tab"),
+    ("T", "open neighbourhood in a new tab"),
+    ("Ctrl-w", "close the tab"),
+    ("e", "edge focus"),
+    ("l", "scroll details (n/N, Enter follow a parent or child)"),
+    ("z", "pan/zoom canvas"),
+    ("Z", "canvas labels"),
+    ("H", "highlight matches instead of hiding the rest"),
+    ("n/N", "next/previous match"),
+    ("x", "expand sample"),
+    ("w", "watch filter"),
+    ("Enter/Backspace", "go to child/parent"),
+    ("o/O u/U", "pick child/parent"),
+    ("Ctrl-o/Alt-←", "back"),
+    ("Alt-→", "forward"),
+    ("P", "pin node"),
+    (":/Ctrl-p", "go to a node by id or label"),
+    ("b", "bookmark node"),
+    ("B", "bookmarks"),
+    ("r", "roots"),
+    ("y", "next node on a cycle"),
+    ("Y", "break cycles at back edges"),
+    ("G", "fold/unfold group"),
+    ("M", "pattern search"),
+    ("X", "export listed nodes"),
+    ("v", "revisions of a node"),
+    ("a", "replay edges in arrival order"),
+    ("F", "find and replace"),
+    ("p", "problems"),
+    ("I", "stats"),
+    ("J", "raw line of a node"),
+    ("t", "tags"),
+    ("+", "add tag"),
+    ("s", "snapshot"),
+    ("S", "snapshots"),
+    ("Ctrl-n", "next tutorial step"),
+    ("?", "this help"),
+    ("q", "quit"),
+];
+
+fn draw_help(f: &mut ratatui::Frame, app: &mut App) {
+    let area = popup_area(f.size());
+    // Clamped here, where the pane's height is known.
+    app.help_scroll = app.help_scroll.min((HELP.len() as u16).saturating_sub(area.height.saturating_sub(2)));
+    let width = HELP.iter().map(|(key, _)| key.chars().count()).max().unwrap_or(0);
+    let key_style = Style::default().fg(app.theme.accent).add_modifier(Modifier::BOLD);
+    let rows: Vec<Line> = HELP
+        .iter()
+        .map(|(key, text)| Line::from(vec![Span::styled(format!("{:<width$}  ", key, width = width), key_style), Span::raw(*text)]))
+        .collect();
+    let help = Paragraph::new(rows).block(Block::default().title(" Keys ").borders(Borders::ALL)).scroll((app.help_scroll, 0));
+    f.render_widget(Clear, area);
+    f.render_widget(help, area);
+}
+
+fn draw_raw(f: &mut ratatui::Frame, app: &mut App) {
+    let area = popup_area(f.size());
+    let tab = app.tab();
+    let Some(nd) = tab.selected().map(|idx| &tab.gm.graph[idx]) else { return };
+    let lines = match &nd.raw.0 {
+        Some(raw) => payload::lines(raw, &app.theme),
+        None => vec![Line::raw("No event was kept for this node.")],
+    };
+    // Clamped here, where the pane's size is known; long values wrap.
+    let inner = area.width.saturating_sub(2).max(1) as usize;
+    let rows: usize = lines.iter().map(|l| l.width().max(1).div_ceil(inner)).sum();
+    let scroll = app.raw_scroll.min((rows as u16).saturating_sub(area.height.saturating_sub(2)));
+    let title = format!(" Raw event — {} ", nd.id);
+    let raw = Paragraph::new(lines).block(Block::default().title(title).borders(Borders::ALL)).wrap(Wrap { trim: false }).scroll((scroll, 0));
+    app.raw_scroll = scroll;
+    f.render_widget(Clear, area);
+    f.render_widget(raw, area);
+}
+
+fn draw_matches(f: &mut ratatui::Frame, app: &mut App) {
+    let area = popup_area(f.size());
+    let gm = app.tab().full_graph();
+    let name = |id: &String| match gm.indices.get(id.as_str()) {
+        Some(&idx) => app.label_template.as_ref().map_or_else(|| gm.graph[idx].display_label(), |t| t.render(&gm.graph[idx], &app.time)),
+        None => id.clone(),
+    };
+    let items: Vec<ListItem> = app.matches.iter().map(|path| ListItem::new(path.iter().map(name).join("  →  "))).collect();
+    let title = format!(
+        " Matches for '{}' — {}{} paths ",
+        app.pattern_input.trim(),
+        if app.matches_truncated { "first " } else { "" },
+        app.matches.len()
+    );
+    let list = if items.is_empty() {
+        List::new(vec![ListItem::new("No path fits the pattern")])
+    } else {
+        List::new(items)
+    };
+    let list = list
+        .block(Block::default().title(title).borders(Borders::ALL))
+        .highlight_style(Style::default().fg(app.theme.highlight).add_modifier(Modifier::BOLD))
+        .highlight_symbol("▶ ");
+    f.render_widget(Clear, area);
+    f.render_stateful_widget(list, area, &mut app.matches_state);
+}
+
+fn draw_menu(f: &mut ratatui::Frame, app: &mut App) {
+    let screen = f.size();
+    let width = (app.menu.iter().map(|(_, text)| text.chars().count()).max().unwrap_or(0) as u16 + 6).min(screen.width);
+    let height = (app.menu.len() as u16 + 2).min(screen.height);
+    let area = Rect { x: screen.x + (screen.width - width) / 2, y: screen.y + (screen.height - height) / 2, width, height };
+    let id = app.tab().selected().map_or(String::new(), |idx| app.tab().gm.graph[idx].id.to_string());
+    let list = List::new(app.menu.iter().map(|(_, text)| ListItem::new(text.clone())).collect::<Vec<_>>())
+        .block(Block::default().title(format!(" {} ", id)).borders(Borders::ALL))
+        .highlight_style(Style::default().fg(app.theme.highlight).add_modifier(Modifier::BOLD))
+        .highlight_symbol("▶ ");
+    f.render_widget(Clear, area);
+    f.render_stateful_widget(list, area, &mut app.menu_state);
+}
+
+fn draw_replace(f: &mut ratatui::Frame, app: &mut App) {
+    let area = popup_area(f.size());
+    let (title, lines): (String, Vec<Line>) = match &app.replace_error {
+        Some(err) => (" Replace ".to_string(), err.lines().map(|l| Line::styled(l.to_string(), Style::default().fg(app.theme.bad))).collect()),
+        None if app.replace.find.is_empty() => (" Replace ".to_string(), vec![Line::from("Type a pattern to see the nodes it changes")]),
+        None => {
+            let more = app.replace_count.saturating_sub(replace::PREVIEW_LIMIT);
+            let mut lines: Vec<Line> = app.replace_preview.iter().map(|l| Line::from(l.clone())).collect();
+            if more > 0 {
+                lines.push(Line::styled(format!("... {} more nodes", more), Style::default().fg(app.theme.muted)));
+            }
+            if lines.is_empty() {
+                lines.push(Line::from("No label or tag matches"));
+            }
+            (format!(" Replace — {} nodes change ", app.replace_count), lines)
+        }
+    };
+    let preview = Paragraph::new(lines).block(Block::default().title(title).borders(Borders::ALL));
+    f.render_widget(Clear, area);
+    f.render_widget(preview, area);
+}
+
+fn draw_revisions(f: &mut ratatui::Frame, app: &mut App) {
+    let area = popup_area(f.size());
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length((app.revisions.len() as u16 + 2).min(area.height / 2)), Constraint::Min(3)].as_ref())
+        .split(area);
+    let cursor = app.revisions_state.selected().unwrap_or(0);
+    let base = app.revision_base.unwrap_or(cursor.saturating_sub(1));
+    let items: Vec<ListItem> = app
+        .revisions
+        .iter()
+        .enumerate()
+        .map(|(i, (title, _))| ListItem::new(if i == base { format!("{}  (base)", title) } else { title.clone() }))
+        .collect();
+    let id = app.revisions.last().map_or("", |(_, nd)| &*nd.id);
+    let list = List::new(items)
+        .block(Block::default().title(format!(" Revisions of {} ", id)).borders(Borders::ALL))
+        .highlight_style(Style::default().fg(app.theme.highlight).add_modifier(Modifier::BOLD))
+        .highlight_symbol("▶ ");
+    let lines: Vec<Line> = revision::diff(&app.revisions[base].1, &app.revisions[cursor].1)
+        .into_iter()
+        .map(|line| match line {
+            revision::DiffLine::Same(l) => Line::styled(format!("  {}", l), Style::default().fg(app.theme.muted)),
+            revision::DiffLine::Removed(l) => Line::styled(format!("- {}", l), Style::default().fg(app.theme.bad)),
+            revision::DiffLine::Added(l) => Line::styled(format!("+ {}", l), Style::default().fg(app.theme.good)),
+        })
+        .collect();
+    let diff = Paragraph::new(lines)
+        .block(Block::default().title(format!(" rev {} → rev {} ", base + 1, cursor + 1)).borders(Borders::ALL))
+        .wrap(Wrap { trim: false });
+    f.render_widget(Clear, area);
+    f.render_stateful_widget(list, chunks[0], &mut app.revisions_state);
+    f.render_widget(diff, chunks[1]);
+}
+
+fn draw_snapshots(f: &mut ratatui::Frame, app: &mut App) {
+    let area = popup_area(f.size());
+    let tab = &app.tabs[app.active];
+    let counts = |gm: &GraphModel| format!("{} nodes, {} edges", gm.graph.node_count(), gm.graph.edge_count());
+    let mark = |i: Option<usize>| {
+        let shown = if tab.viewing == i { "shown" } else { "" };
+        let diffed = if i.is_some() && tab.diff.as_ref().map(|d| d.against) == i { "diffed" } else { "" };
+        [shown, diffed].iter().filter(|m| !m.is_empty()).join(", ")
+    };
+    let mut items = vec![ListItem::new(format!("live          {}  {}", counts(tab.full_graph()), mark(None)))];
+    for (i, snap) in tab.snapshots.iter().enumerate() {
+        let text = format!("#{:<3} {:>12}  {}  {}", i + 1, snapshot::ago(snap.taken), counts(&snap.graph), mark(Some(i)));
+        items.push(ListItem::new(text));
+    }
+    let title = format!(" Snapshots — {} taken (s takes another) ", tab.snapshots.len());
+    let list = List::new(items)
+        .block(Block::default().title(title).borders(Borders::ALL))
+        .highlight_style(Style::default().fg(app.theme.highlight).add_modifier(Modifier::BOLD))
+        .highlight_symbol("▶ ");
+    f.render_widget(Clear, area);
+    f.render_stateful_widget(list, area, &mut app.snapshots_state);
+}
+
+/// Cycle nodes named in a problem line, at most.
+const CYCLE_LIST: usize = 8;
+
+/// `[cycle] 3 nodes: a, b, c`, for the problems panel and `validate`.
+fn cycle_text(ids: &[String]) -> String {
+    if ids.len() == 1 {
+        return format!("[cycle] {} has an edge to itself", ids[0]);
+    }
+    let more = if ids.len() > CYCLE_LIST { format!(", ... {} more", ids.len() - CYCLE_LIST) } else { String::new() };
+    format!("[cycle] {} nodes: {}{}", ids.len(), ids.iter().take(CYCLE_LIST).join(", "), more)
+}
+
+/// The problems panel.
+fn draw_problems(f: &mut ratatui::Frame, app: &mut App) {
+    let area = popup_area(f.size());
+    let items: Vec<ListItem> = app
+        .problems()
+        .map(|(text, _, color)| ListItem::new(text).style(Style::default().fg(color)))
+        .collect();
+    let title = format!(
+        " Problems — {} violations, {} alerts, {} cycles, {} conflicts, {} pending edges, {} warnings ",
+        app.violations.len(),
+        app.alerts.len(),
+        app.tab().cycles.groups.len(),
+        app.tab().full_graph().conflicts.len(),
+        app.tab().full_graph().pending_count,
+        app.warnings.len()
+    );
+    let list = if items.is_empty() {
+        List::new(vec![ListItem::new("No problems")])
+    } else {
+        List::new(items)
+    };
+    let list = list
+        .block(Block::default().title(title).borders(Borders::ALL))
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED))
+        .highlight_symbol("▶ ");
+    f.render_widget(Clear, area);
+    f.render_stateful_widget(list, area, &mut app.problems_state);
+}
+
+/// BFS outward from `center` up to `depth` hops in each direction.
+/// Returns (parent layers, child layers), nearest layer first.
+fn neighborhood_layers(gm: &GraphModel, center: NodeIndex, depth: usize) -> (Vec<Vec<NodeIndex>>, Vec<Vec<NodeIndex>>) {
+    let collect = |dir| {
+        let mut layers: Vec<Vec<NodeIndex>> = Vec::new();
+        let mut frontier: Vec<NodeIndex> = vec![center];
+        for _ in 0..depth {
+            let mut next = Vec::new();
+            let mut layer = Vec::new();
+            for &n in &frontier {
+                for m in gm.graph.neighbors_directed(n, dir) {
+                    if !layer.contains(&m) {
+                        layer.push(m);
+                        next.push(m);
+                    }
+                }
+            }
+            if layer.is_empty() { break; }
+            layers.push(layer);
+            frontier = next;
+        }
+        layers
+    };
+    (collect(Incoming), collect(Outgoing))
+}
+
+/// Grand-parents down through the selection to grand-children, a node per
+/// row, with `lanes` connectors between them.
+/// Nodes in `found` are highlighted as filter matches.
+fn build_layered_dag_text(gm: &GraphModel, center: NodeIndex, depth: usize, glyphs: &lanes::Glyphs, theme: &Theme, found: &HashSet<NodeIndex>) -> Vec<Line<'static>> {
+    let (parents_layers, children_layers) = neighborhood_layers(gm, center, depth);
+    let mut nodes: Vec<NodeIndex> = Vec::new();
+    let selected = [center];
+    let layers = parents_layers.iter().rev().map(Vec::as_slice).chain([&selected[..]]).chain(children_layers.iter().map(Vec::as_slice));
+    for &idx in layers.flatten() {
+        // On a cycle, a node can be both above and below the selection; it is drawn where it is first met.
+        if !nodes.contains(&idx) {
+            nodes.push(idx);
+        }
+    }
+
+    let mut lines: Vec<Line> = Vec::new();
     lines.push(Line::from(Span::styled(
-        "Note: This is a textual, depth-limited view. Use selection to explore neighbors.",
-        Style::default().fg(Color::Gray),
+        "Layered DAG (parents above [selected], children below)",
+        Style::default().fg(theme.accent).add_modifier(Modifier::BOLD),
     )));
+    let connector = Style::default().fg(theme.faint);
+    for row in lanes::render(gm, &nodes, glyphs) {
+        match row {
+            lanes::Row::Edges(graph) => lines.push(Line::styled(graph, connector)),
+            lanes::Row::Node(graph, idx) => {
+                // The node's own glyph stands out from the lanes passing it.
+                let (before, after) = graph.split_once(glyphs.node).unwrap_or((&graph, ""));
+                let (glyph, label) = if idx == center {
+                    (Style::default().fg(theme.highlight), Span::styled(format!("[{}]", label_for(gm, idx)), Style::default().fg(theme.highlight).add_modifier(Modifier::BOLD)))
+                } else if found.contains(&idx) {
+                    (Style::default().fg(theme.found), Span::styled(label_for(gm, idx), Style::default().fg(theme.found).add_modifier(Modifier::BOLD)))
+                } else {
+                    (Style::default().fg(theme.accent), Span::raw(label_for(gm, idx)))
+                };
+                lines.push(Line::from(vec![
+                    Span::styled(before.to_string(), connector),
+                    Span::styled(glyphs.node.to_string(), glyph),
+                    Span::styled(format!("{}  ", after), connector),
+                    label,
+                ]));
+            }
+        }
+    }
     lines
 }
 
 fn label_for(gm: &GraphModel, idx: NodeIndex) -> String {
     let nd = &gm.graph[idx];
-    let base = if nd.label.is_empty() { nd.id.clone() } else { format!("{} · {}", nd.id, nd.label) };
+    let base = if nd.label.is_empty() { nd.id.to_string() } else { format!("{} · {}", nd.id, nd.label) };
     base
 }
 
-fn classify_node_type(tags: &[String]) -> NodeType {
+fn classify_node_type(tags: &[Arc<str>]) -> NodeType {
     // Classify node type based on tags
     for tag in tags {
         let lower = tag.to_lowercase();
@@ -552,17 +3456,30 @@ fn classify_node_type(tags: &[String]) -> NodeType {
     NodeType::Unknown
 }
 
-fn draw_node_shape(ctx: &mut ratatui::widgets::canvas::Context, x: f64, y: f64, node_type: NodeType, selected: bool) {
-    use ratatui::widgets::canvas::{Points, Line};
+/// One colour per merged input, in `App::sources` order (wrapping around).
+const SOURCE_COLORS: [Color; 8] = [
+    Color::Cyan,
+    Color::Magenta,
+    Color::Green,
+    Color::Yellow,
+    Color::LightBlue,
+    Color::LightRed,
+    Color::LightGreen,
+    Color::LightMagenta,
+];
 
-    let color = match node_type {
-        NodeType::Prompt => Color::Cyan,
-        NodeType::Response => Color::Green,
-        NodeType::Tool => Color::Yellow,
-        NodeType::Error => Color::Red,
-        NodeType::Event => Color::Magenta,
-        NodeType::Unknown => Color::White,
-    };
+/// The colour of the first input that defined `nd`; gray when none did.
+fn source_color(sources: &[Arc<str>], nd: &NodeData) -> Color {
+    let first = nd.metadata.get(SOURCES_KEY).and_then(|s| s.split(", ").next());
+    match first.and_then(|first| sources.iter().position(|s| **s == *first)) {
+        Some(i) => SOURCE_COLORS[i % SOURCE_COLORS.len()],
+        None => Color::DarkGray,
+    }
+}
+
+/// `color` is the node type's colour in the theme, or its source's.
+fn draw_node_shape(ctx: &mut ratatui::widgets::canvas::Context, x: f64, y: f64, node_type: NodeType, selected: bool, color: Color) {
+    use ratatui::widgets::canvas::{Points, Line};
 
     let size = if selected { 2.0 } else { 1.5 };
 
@@ -614,81 +3531,318 @@ fn draw_node_shape(ctx: &mut ratatui::widgets::canvas::Context, x: f64, y: f64,
     }
 }
 
-fn layout_nodes(gm: &GraphModel, center: NodeIndex, depth: usize) -> HashMap<NodeIndex, (f64, f64)> {
-    // Position nodes in a layered graph: parents | [center] | children
-    let mut positions: HashMap<NodeIndex, (f64, f64)> = HashMap::new();
-
-    // Collect parent and child layers
-    let mut parents_layers: Vec<Vec<NodeIndex>> = Vec::new();
-    let mut frontier: Vec<NodeIndex> = vec![center];
-    for _ in 0..depth {
-        let mut next = Vec::new();
-        let mut layer = Vec::new();
-        for &n in &frontier {
-            for p in gm.graph.neighbors_directed(n, Incoming) {
-                if !layer.contains(&p) {
-                    layer.push(p);
-                    next.push(p);
+/// Where the last frame drew what the mouse can pick: the node list's rows,
+/// the details pane and the canvas shapes (areas inside the borders).
+#[derive(Debug, Default)]
+struct Hits {
+    list: Rect,
+    list_rows: Vec<usize>, // position in `order` of each row of `list`, top down
+    details: Rect,
+    canvas: Rect,
+    bounds: ([f64; 2], [f64; 2]), // the canvas's x and y bounds
+    shapes: Vec<(NodeIndex, (f64, f64))>,
+}
+
+impl Hits {
+    /// The shape nearest a click at `column`, `row`, if the click landed on it.
+    fn shape_at(&self, column: u16, row: u16) -> Option<NodeIndex> {
+        let ([x0, x1], [y0, y1]) = self.bounds;
+        let (w, h) = (f64::from(self.canvas.width.max(1)), f64::from(self.canvas.height.max(1)));
+        let x = x0 + (f64::from(column - self.canvas.x) + 0.5) / w * (x1 - x0);
+        let y = y1 - (f64::from(row - self.canvas.y) + 0.5) / h * (y1 - y0);
+        // Within the shape, or the cell it was drawn in when zoomed out.
+        let reach = (quality::NODE_RADIUS + 0.5).max((x1 - x0) / w).max((y1 - y0) / h);
+        self.shapes
+            .iter()
+            .map(|&(n, (sx, sy))| (n, (sx - x).hypot(sy - y)))
+            .filter(|&(_, d)| d <= reach)
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(n, _)| n)
+    }
+}
+
+fn inside(area: Rect, column: u16, row: u16) -> bool {
+    column >= area.x && column < area.x + area.width && row >= area.y && row < area.y + area.height
+}
+
+/// Where each node sits in the canvas's 100 by 50 layout space.
+type Positions = HashMap<NodeIndex, (f64, f64)>;
+
+/// Canvas positions kept across frames. While the center stays the same,
+/// nodes that arrive later are slotted in next to their already-placed
+/// neighbors instead of re-running `layered::layout`, so streaming updates
+/// don't make the whole picture jump. The layout algorithm is picked by
+/// `quality` when the center changes and kept until it does again.
+#[derive(Debug, Default)]
+struct LayoutCache {
+    center: Option<NodeIndex>,
+    depth: usize,
+    positions: HashMap<NodeIndex, (f64, f64)>,
+    placed: HashMap<NodeIndex, (i32, Vec<NodeIndex>)>, // each node's hop and parents when it was placed
+    algorithm: Algorithm,
+    quality: Option<Quality>,           // None: too many edges to measure
+    edges: Vec<(NodeIndex, NodeIndex)>, // what `quality` was measured over
+}
+
+/// The free row nearest `y` in the column at `x`, `except` not counting as
+/// taken: `y`, then `y+1`, `y-1`, `y+2`, `y-2` ...
+fn free_row(positions: &HashMap<NodeIndex, (f64, f64)>, x: f64, y: f64, except: Option<NodeIndex>) -> f64 {
+    let taken = |y: f64| positions.iter().any(|(&n, p)| Some(n) != except && (p.0 - x).abs() < 0.5 && (p.1 - y).abs() < 1.0);
+    let mut slot = y;
+    for step in 1..1000 {
+        if !taken(slot) {
+            break;
+        }
+        let off = ((step + 1) / 2) as f64;
+        slot = if step % 2 == 1 { y + off } else { y - off };
+    }
+    slot
+}
+
+impl LayoutCache {
+    fn update(&mut self, gm: &GraphModel, center: NodeIndex, depth: usize) -> &HashMap<NodeIndex, (f64, f64)> {
+        let layers = layered::hops(gm, center, depth);
+        let parents = |node: NodeIndex| gm.parents_of(node).into_iter().sorted().collect::<Vec<_>>();
+        if self.center != Some(center) || self.depth != depth || self.positions.is_empty() {
+            self.center = Some(center);
+            self.depth = depth;
+            self.positions = layered::layout(gm, center, depth);
+            self.algorithm = Algorithm::Layered;
+            self.edges = quality::edges(gm, &self.positions);
+            self.quality = Quality::measure(&self.positions, &self.edges);
+            if let Some(layered) = self.quality.filter(|q| q.is_poor(self.edges.len())) {
+                let spread = quality::spread(gm, center, depth);
+                let measured = Quality::measure(&spread, &self.edges);
+                if measured.is_some_and(|m| m.better_than(&layered)) {
+                    (self.positions, self.algorithm, self.quality) = (spread, Algorithm::Spread, measured);
                 }
             }
+            self.placed = layers.into_iter().map(|(node, layer)| (node, (layer, parents(node)))).collect();
+            return &self.positions;
         }
-        if layer.is_empty() { break; }
-        parents_layers.push(layer);
-        frontier = next;
-    }
 
-    let mut children_layers: Vec<Vec<NodeIndex>> = Vec::new();
-    let mut frontier: Vec<NodeIndex> = vec![center];
-    for _ in 0..depth {
-        let mut next = Vec::new();
-        let mut layer = Vec::new();
-        for &n in &frontier {
-            for c in gm.graph.neighbors_directed(n, Outgoing) {
-                if !layer.contains(&c) {
-                    layer.push(c);
-                    next.push(c);
+        // A node keeps its place while it stays in its layer under the same
+        // parents; one that moved layer or was rewired is placed again, as
+        // a new one is.
+        let mut positions: HashMap<NodeIndex, (f64, f64)> = HashMap::new();
+        let mut placed = HashMap::new();
+        let mut columns: HashMap<i32, f64> = HashMap::new();
+        let mut added = Vec::new();
+        for &(node, layer) in &layers {
+            let now = (layer, parents(node));
+            match self.positions.get(&node) {
+                Some(&pos) if node == center || self.placed.get(&node) == Some(&now) => {
+                    positions.insert(node, if node == center { (50.0, 25.0) } else { pos });
+                    columns.entry(layer).or_insert(pos.0);
                 }
+                _ => added.push((node, layer)),
             }
+            placed.insert(node, now);
+        }
+        self.placed = placed;
+
+        if added.is_empty() {
+            self.positions = positions;
+            self.remeasure(gm);
+            return &self.positions;
+        }
+
+        // Where a from-scratch layout would put things; used when a new node
+        // has no placed neighbors or starts a new column.
+        let fresh = match self.algorithm {
+            Algorithm::Layered => layered::layout(gm, center, depth),
+            Algorithm::Spread => quality::spread(gm, center, depth),
+        };
+        for &(node, layer) in &added {
+            // Column: wherever the rest of this layer already sits.
+            let x = *columns.entry(layer).or_insert(fresh[&node].0);
+            // Row: next to the neighbors that are already placed.
+            let ys: Vec<f64> = gm
+                .graph
+                .neighbors_undirected(node)
+                .filter_map(|n| positions.get(&n).map(|p| p.1))
+                .collect();
+            let y = if ys.is_empty() { fresh[&node].1 } else { ys.iter().sum::<f64>() / ys.len() as f64 };
+            positions.insert(node, (x, free_row(&positions, x, y, None)));
+        }
+
+        // Local relaxation: the placed neighbours of what was (re)placed
+        // move halfway towards the middle of their own neighbours, to the
+        // nearest free row; nothing further away moves.
+        let moved: HashSet<NodeIndex> = added.iter().map(|&(n, _)| n).collect();
+        let near: Vec<NodeIndex> = moved
+            .iter()
+            .flat_map(|&n| gm.graph.neighbors_undirected(n))
+            .filter(|n| *n != center && !moved.contains(n) && positions.contains_key(n))
+            .unique()
+            .sorted()
+            .collect();
+        for node in near {
+            let ys: Vec<f64> = gm.graph.neighbors_undirected(node).filter_map(|n| positions.get(&n).map(|p| p.1)).collect();
+            let (x, y) = positions[&node];
+            let target = (y + ys.iter().sum::<f64>() / ys.len() as f64) / 2.0;
+            positions.insert(node, (x, free_row(&positions, x, target, Some(node))));
         }
-        if layer.is_empty() { break; }
-        children_layers.push(layer);
-        frontier = next;
+
+        self.positions = positions;
+        self.remeasure(gm);
+        &self.positions
     }
 
-    // Position parents (reversed to show grandparents on left)
-    for (layer_idx, layer) in parents_layers.iter().rev().enumerate() {
-        let x = 10.0 + layer_idx as f64 * 15.0;
-        for (node_idx, &node) in layer.iter().enumerate() {
-            let y = 25.0 - (layer.len() as f64 / 2.0) + node_idx as f64;
-            positions.insert(node, (x, y));
+    /// Measure again if the positioned nodes or their edges changed.
+    fn remeasure(&mut self, gm: &GraphModel) {
+        let edges = quality::edges(gm, &self.positions);
+        let nodes = edges.iter().flat_map(|e| [e.0, e.1]).unique().count();
+        if edges != self.edges || nodes != self.positions.len() {
+            self.quality = Quality::measure(&self.positions, &edges);
+            self.edges = edges;
         }
     }
+}
+
+/// The part of the canvas's 100 by 50 layout space that the DAG view shows:
+/// `zoom` times magnified around (`x`, `y`).
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Viewport {
+    x: f64,
+    y: f64,
+    zoom: f64,
+}
+
+impl Default for Viewport {
+    fn default() -> Self {
+        Self { x: 50.0, y: 25.0, zoom: 1.0 }
+    }
+}
+
+impl Viewport {
+    /// Move by a tenth of what is shown, per step.
+    fn pan(&mut self, dx: f64, dy: f64) {
+        self.x += dx * 10.0 / self.zoom;
+        self.y += dy * 5.0 / self.zoom;
+    }
+
+    fn zoom_by(&mut self, factor: f64) {
+        self.zoom = (self.zoom * factor).clamp(VIEWPORT_ZOOM.0, VIEWPORT_ZOOM.1);
+    }
+
+    fn x_bounds(&self) -> [f64; 2] {
+        [self.x - 50.0 / self.zoom, self.x + 50.0 / self.zoom]
+    }
+
+    fn y_bounds(&self) -> [f64; 2] {
+        [self.y - 25.0 / self.zoom, self.y + 25.0 / self.zoom]
+    }
+}
 
-    // Position center
-    positions.insert(center, (50.0, 25.0));
+/// Canvas positions with each folded group's nodes (but never the
+/// selection) moved to where they are on average, so edges to any of them
+/// meet at one shape. Also returns the members not to draw, and the one
+/// drawn for each group.
+fn fold_positions(tab: &Tab, center: NodeIndex, positions: &Positions) -> (Positions, HashSet<NodeIndex>, HashSet<NodeIndex>) {
+    let mut moved = positions.clone();
+    let (mut folded, mut containers) = (HashSet::new(), HashSet::new());
+    if tab.groups.is_empty() {
+        return (moved, folded, containers);
+    }
+    let mut members: BTreeMap<usize, Vec<NodeIndex>> = BTreeMap::new();
+    for &n in positions.keys().filter(|&&n| n != center) {
+        if let Some(group) = tab.groups.of(&tab.gm.graph[n].id).filter(|g| !tab.expanded.contains(g)) {
+            members.entry(group).or_default().push(n);
+        }
+    }
+    for mut nodes in members.into_values().filter(|nodes| nodes.len() > 1) {
+        nodes.sort();
+        let (sx, sy) = nodes.iter().fold((0.0, 0.0), |(x, y), n| (x + positions[n].0, y + positions[n].1));
+        let mean = (sx / nodes.len() as f64, sy / nodes.len() as f64);
+        for n in &nodes {
+            moved.insert(*n, mean);
+        }
+        containers.insert(nodes[0]);
+        folded.extend(nodes.into_iter().skip(1));
+    }
+    (moved, folded, containers)
+}
 
-    // Position children
-    for (layer_idx, layer) in children_layers.iter().enumerate() {
-        let x = 70.0 + layer_idx as f64 * 15.0;
-        for (node_idx, &node) in layer.iter().enumerate() {
-            let y = 25.0 - (layer.len() as f64 / 2.0) + node_idx as f64;
-            positions.insert(node, (x, y));
+/// Where to print each node's name on a canvas of `cols` by `rows` cells
+/// showing `viewport`: beside its shape, else to its left, above or below,
+/// wherever no shape or earlier label is. Names that fit nowhere are left
+/// out and counted. `nodes` come in order of precedence, the first being the
+/// selection.
+fn place_labels(nodes: &[(NodeIndex, (f64, f64), String)], viewport: &Viewport, cols: u16, rows: u16) -> (Vec<(f64, f64, String, bool)>, usize) {
+    if cols < 2 || rows < 2 {
+        return (Vec::new(), nodes.len());
+    }
+    let (cols, rows) = (i32::from(cols), i32::from(rows));
+    let ([x0, x1], [y0, y1]) = (viewport.x_bounds(), viewport.y_bounds());
+    // Cells as `Context::print` maps them: the bounds' ends fall on the first and last cell.
+    let (sx, sy) = (f64::from(cols - 1) / (x1 - x0), f64::from(rows - 1) / (y1 - y0));
+    let cell = |(x, y): (f64, f64)| (((x - x0) * sx).round() as i32, ((y1 - y) * sy).round() as i32);
+    let mut taken = vec![false; (cols.max(0) * rows.max(0)) as usize];
+    let free = |taken: &[bool], col: i32, row: i32, len: i32| row >= 0 && row < rows && col >= 0 && col + len <= cols && (col..col + len).all(|c| !taken[(row * cols + c) as usize]);
+    // The cells a shape may cover either side of its center (a selected one is a little bigger).
+    let radius = quality::NODE_RADIUS + 0.5;
+    let (wide, high) = ((radius * sx).ceil() as i32, (radius * sy).ceil() as i32);
+    for (_, pos, _) in nodes {
+        let (col, row) = cell(*pos);
+        for (c, r) in (col - wide..=col + wide).cartesian_product(row - high..=row + high) {
+            if r >= 0 && r < rows && c >= 0 && c < cols {
+                taken[(r * cols + c) as usize] = true;
+            }
         }
     }
+    let (mut placed, mut crowded) = (Vec::new(), 0);
+    for (i, (_, pos, text)) in nodes.iter().enumerate() {
+        let text: String = if text.chars().count() > CANVAS_LABEL { text.chars().take(CANVAS_LABEL - 1).chain(['…']).collect() } else { text.clone() };
+        let len = text.chars().count() as i32;
+        let (col, row) = cell(*pos);
+        if col < 0 || col >= cols || row < 0 || row >= rows {
+            continue; // panned out of view
+        }
+        let spot = [(col + wide + 1, row), (col - wide - 1 - len, row), (col - len / 2, row - high - 1), (col - len / 2, row + high + 1)]
+            .into_iter()
+            .find(|&(c, r)| free(&taken, c, r, len));
+        let Some((c, r)) = spot else {
+            crowded += 1;
+            continue;
+        };
+        for c in c..c + len {
+            taken[(r * cols + c) as usize] = true;
+        }
+        // A quarter cell in, so rounding can't tip it into the cell before.
+        let (x, y) = (x0 + (f64::from(c) + 0.25) / sx, y1 - (f64::from(r) + 0.25) / sy);
+        placed.push((x, y, text, i == 0));
+    }
+    (placed, crowded)
+}
 
-    positions
+/// How much the edges from `a` to `b` weigh together; an edge without a
+/// weight counts as 1, so repeated edges add up too.
+fn edge_strength(gm: &GraphModel, a: NodeIndex, b: NodeIndex) -> f64 {
+    gm.graph.edges_connecting(a, b).map(|e| e.weight().weight.unwrap_or(1.0).max(0.0)).sum()
 }
 
-fn draw_edge_line(ctx: &mut ratatui::widgets::canvas::Context, from: (f64, f64), to: (f64, f64), color: Color) {
+/// An arrow from `from` to `to`, `lines` strokes thick (side by side, about
+/// a braille dot apart).
+fn draw_edge_line(ctx: &mut ratatui::widgets::canvas::Context, from: (f64, f64), to: (f64, f64), color: Color, lines: usize) {
     use ratatui::widgets::canvas::Line;
 
-    // Draw a line from source to destination
-    ctx.draw(&Line::new(from.0, from.1, to.0, to.1, color));
-
-    // Draw arrowhead
     let dx = to.0 - from.0;
     let dy = to.1 - from.1;
     let len = (dx * dx + dy * dy).sqrt();
+
+    // Draw a line from source to destination, with extra strokes offset to
+    // either side for heavier edges.
+    ctx.draw(&Line::new(from.0, from.1, to.0, to.1, color));
+    if len > 0.1 {
+        let (nx, ny) = (-dy / len * EDGE_STROKE_GAP, dx / len * EDGE_STROKE_GAP);
+        for side in [1.0, -1.0].into_iter().take(lines.saturating_sub(1)) {
+            let (ox, oy) = (nx * side, ny * side);
+            ctx.draw(&Line::new(from.0 + ox, from.1 + oy, to.0 + ox, to.1 + oy, color));
+        }
+    }
+
+    // Draw arrowhead
     if len > 0.1 {
         let ux = dx / len;
         let uy = dy / len;
@@ -703,73 +3857,872 @@ fn draw_edge_line(ctx: &mut ratatui::widgets::canvas::Context, from: (f64, f64),
     }
 }
 
+#[cfg(feature = "tls")]
+fn spawn_tls_sources(ingest: &Ingest, args: &Args) -> Result<()> {
+    if let (Some(addr), Some(cert), Some(key)) = (&args.listen_tls, &args.tls_cert, &args.tls_key) {
+        ingest.spawn_listen_tls(addr, tls::server_config(cert, key)?)?;
+    }
+    if let Some(addr) = &args.connect_tls {
+        ingest.spawn_connect_tls(addr, tls::client_config(args.tls_ca.as_deref())?)?;
+    }
+    Ok(())
+}
+
+#[cfg(not(feature = "tls"))]
+fn spawn_tls_sources(_ingest: &Ingest, args: &Args) -> Result<()> {
+    if args.listen_tls.is_some() || args.connect_tls.is_some() {
+        color_eyre::eyre::bail!("TLS sockets need riff-dag-tui built with the `tls` feature");
+    }
+    Ok(())
+}
+
+#[cfg(feature = "journald")]
+fn spawn_journal(ingest: &Ingest, config: &Config, units: &[String]) -> Result<()> {
+    ingest.spawn_journal(&config.journal, units)
+}
+
+#[cfg(not(feature = "journald"))]
+fn spawn_journal(_ingest: &Ingest, _config: &Config, _units: &[String]) -> Result<()> {
+    color_eyre::eyre::bail!("journal sources need riff-dag-tui built with the `journald` feature")
+}
+
+/// Start reading `input`: DOT documents are parsed whole, up front;
+/// everything else streams in line by line.
+fn spawn_input(ingest: &Ingest, input: &str, follow: bool, format: InputFormat, creds: &mut Credentials) -> Result<String> {
+    if format.for_input(input) != InputFormat::Dot {
+        return ingest.spawn_input(input, follow, creds);
+    }
+    if follow {
+        return Err(eyre!("--follow is not supported for DOT inputs"));
+    }
+    let text = if input == "-" {
+        io::read_to_string(io::stdin()).wrap_err("failed to read stdin")?
+    } else {
+        std::fs::read_to_string(input).wrap_err_with(|| format!("failed to read {}", input))?
+    };
+    let graph = dot::parse(&text).wrap_err_with(|| format!("invalid DOT in {}", input))?;
+    ingest.spawn_events(input, dot::events(&graph));
+    Ok(input.to_string())
+}
+
+/// `--input` values with each directory replaced by the `.jsonl` files in
+/// it, oldest first.
+fn expand_inputs(inputs: &[String]) -> Result<Vec<String>> {
+    let mut expanded = Vec::new();
+    for input in inputs {
+        if !Path::new(input).is_dir() {
+            expanded.push(input.clone());
+            continue;
+        }
+        let files = ingest::list_jsonl(Path::new(input)).wrap_err_with(|| format!("failed to read directory {}", input))?;
+        if files.is_empty() {
+            color_eyre::eyre::bail!("no .jsonl files in {}", input);
+        }
+        expanded.extend(files.into_iter().map(|p| p.to_string_lossy().into_owned()));
+    }
+    Ok(expanded)
+}
+
 fn main() -> Result<()> {
     color_eyre::install()?;
-    let args = Args::parse();
-    let gm = load_graph_from_jsonl(args.input)?;
-    let mut app = App::new(gm);
+    let mut args = Args::parse();
+    if args.input.is_empty() {
+        args.input = std::mem::take(&mut args.input_arg);
+    }
+    let inputs = expand_inputs(&args.input)?;
+    let config = Config::load(args.config.as_deref())?;
+
+    let (mut creds, cred_warnings) =
+        Credentials::load(args.credentials.as_deref().or(config.credentials_file.as_deref()))?;
+    match &args.command {
+        Some(Command::Validate { input }) => return headless::validate(Headless::read(input, &args, &config, &mut creds, cred_warnings)?),
+        Some(Command::Metrics { input, output }) => {
+            return headless::metrics(Headless::read(input, &args, &config, &mut creds, cred_warnings)?, *output);
+        }
+        Some(Command::Stats { input, output }) => return headless::stats(Headless::read(input, &args, &config, &mut creds, cred_warnings)?, *output),
+        Some(Command::Query { input, filter, output, neighbors }) => {
+            return headless::query(Headless::read(input, &args, &config, &mut creds, cred_warnings)?, filter, *output, *neighbors);
+        }
+        _ => {}
+    }
+    let mut ingest = Ingest::new();
+    if args.format == InputFormat::Regex {
+        ingest.set_adapter(Arc::new(extract::RegexAdapter::new(&config.extract)?));
+    }
+    let aliases = Aliases::new(&config.aliases)?;
+    let groups = Groups::new(&config.group)?;
+    let assertions = args.assertions.as_deref().or(config.assertions_file.as_deref()).map(Assertions::load).transpose()?;
+    if let Some(Command::Preview { input }) = &args.command {
+        spawn_input(&ingest, input, false, args.format, &mut creds)?;
+        return headless::preview(input, &ingest);
+    }
+    let mut gm = GraphModel::new();
+    let mut input_name = None;
+    let mut restored_warnings = Vec::new();
+    let mut cached_events = 0;
+    let mut pending_cache = None;
+    let mut comparison = None;
+    if let Some(Command::Diff { before, after }) = &args.command {
+        let (old, mut warnings) = load_side(before, &args, &config, &mut creds)?;
+        let (new, more) = load_side(after, &args, &config, &mut creds)?;
+        warnings.extend(more);
+        let (shown, cmp) = Comparison::new(&old, &new, format!("{} → {}", before, after));
+        gm = shown;
+        restored_warnings = warnings;
+        comparison = Some(cmp);
+    }
+    if let [path] = inputs.as_slice() {
+        // Snapshots only make sense for a file that is read once, on its own.
+        let cacheable = !args.no_cache
+            && !args.follow
+            && !args.has_sockets()
+            && args.watch_dir.is_none()
+            && args.format.for_input(path) == InputFormat::Jsonl
+            && aliases.is_none()
+            && args.on_duplicate == DuplicatePolicy::Last;
+        let key = if cacheable { CacheKey::for_input(path) } else { None };
+        match key.as_ref().and_then(cache::load) {
+            Some(restored) => {
+                gm = restored.gm;
+                restored_warnings = restored.warnings;
+                cached_events = restored.events;
+            }
+            None => {
+                input_name = Some(spawn_input(&ingest, path, args.follow, args.format, &mut creds)?);
+                pending_cache = key;
+            }
+        }
+    } else if !inputs.is_empty() {
+        for path in &inputs {
+            spawn_input(&ingest, path, args.follow, args.format, &mut creds)?;
+        }
+        input_name = Some(format!("{} files", inputs.len()));
+    }
+    if let Some(addr) = &args.listen {
+        ingest.spawn_listen(addr)?;
+    }
+    if let Some(addr) = &args.connect {
+        ingest.spawn_connect(addr)?;
+    }
+    spawn_tls_sources(&ingest, &args)?;
+    let journal_units = if args.journal_units.is_empty() { config.journal.units.clone() } else { args.journal_units.clone() };
+    if !journal_units.is_empty() {
+        spawn_journal(&ingest, &config, &journal_units)?;
+    }
+    if let Some(dir) = &args.watch_dir {
+        ingest.spawn_watch_dir(dir, config.watch_dir_tabs.unwrap_or(false))?;
+    }
+    if let Some(cmd) = &args.command {
+        input_name = Some(match cmd {
+            Command::K8s { dumps, namespace, all_namespaces, context, refresh } if dumps.is_empty() => {
+                let query = import::k8s::Query {
+                    namespace: namespace.clone(),
+                    all_namespaces: *all_namespaces,
+                    context: context.clone(),
+                };
+                // The first query runs here so a missing kubectl or cluster fails at startup.
+                let title = query.title();
+                let first = query.run()?;
+                ingest.spawn_poll(&title, first, Duration::from_secs((*refresh).max(1)), move || query.run());
+                title
+            }
+            Command::Diff { .. } => comparison.as_ref().expect("both files are loaded above").title.clone(),
+            Command::Tutorial { scenario } => {
+                ingest.spawn_static(scenario.title(), scenario.events());
+                scenario.title().to_string()
+            }
+            _ => {
+                let imported = match cmd {
+                    Command::Cargo { manifest_path } => import::cargo::import(manifest_path.as_deref())?,
+                    Command::Git { repo, max_count, all } => import::git::import(repo, *max_count, *all)?,
+                    Command::Build { path } => import::build::import(path)?,
+                    Command::Dbt { manifest, tests } => import::dbt::import(manifest, *tests)?,
+                    Command::Airflow { path } => import::airflow::import(path)?,
+                    Command::Terraform { path } => import::terraform::import(path)?,
+                    Command::Nix { target, derivation } => import::nix::import(target, *derivation)?,
+                    Command::Npm { path } => import::npm::import(path)?,
+                    Command::K8s { dumps, .. } => import::k8s::import_dumps(dumps)?,
+                    Command::Validate { .. } | Command::Preview { .. } | Command::Metrics { .. } | Command::Stats { .. } | Command::Query { .. } => {
+                        unreachable!("validate, metrics, query and preview return before any source is opened")
+                    }
+                    Command::Diff { .. } | Command::Tutorial { .. } => unreachable!("diff and tutorial are matched above"),
+                };
+                ingest.spawn_events(&imported.title, imported.events);
+                imported.title
+            }
+        });
+    }
+    let title = match (&input_name, &args.watch_dir) {
+        (Some(name), _) => name.clone(),
+        (None, _) if !inputs.is_empty() => inputs.join(", "),
+        (None, Some(dir)) => dir.clone(),
+        (None, None) if !args.has_sockets() && journal_units.is_empty() => "sample".to_string(),
+        (None, None) => "stream".to_string(),
+    };
+    let fallback = input_name.is_none()
+        && inputs.is_empty()
+        && !args.has_sockets()
+        && args.watch_dir.is_none()
+        && journal_units.is_empty();
+    if fallback {
+        // Fallback to embedded sample (the same as assets/sample.jsonl).
+        ingest.spawn_static("sample.jsonl", include_str!("../assets/sample.jsonl"));
+    }
+    let mut app = App::new(Tab::new(title, gm).with_sample(args.sample).with_groups(groups.clone()), ingest);
+    app.tabs[0].compare = comparison;
+    if let Some(Command::Tutorial { scenario }) = &args.command {
+        app.tutorial = Some(Tutorial::new(*scenario));
+        app.export_input = Tutorial::export_path();
+    } else if fallback {
+        app.notice = Some(("no input given, showing a sample graph; `riff-dag-tui tutorial` walks through the UI".to_string(), false));
+    }
+    app.sample = args.sample;
+    app.groups = groups;
+    // Before any warning is pushed, so each is masked and reaches the error log.
+    app.secrets = creds.secrets().to_vec();
+    if let Some(path) = &args.error_log {
+        app.open_error_log(path)?;
+    }
+    // Warnings restored from the cache, or from loading both sides of a diff.
+    for w in restored_warnings {
+        app.push_warning(w);
+    }
+    app.cached_events = cached_events;
+    app.pending_cache = pending_cache;
+    app.aliases = aliases;
+    app.on_duplicate = args.on_duplicate;
+    app.tag_sources = inputs.len() > 1 || (args.watch_dir.is_some() && !config.watch_dir_tabs.unwrap_or(false));
+    app.sources = inputs.iter().map(|i| Arc::from(i.as_str())).collect();
+    if let [input] = inputs.as_slice() {
+        match Bookmarks::load(input) {
+            Ok(bookmarks) => app.bookmarks = bookmarks,
+            Err(err) => app.push_warning(format!("[warn] couldn't read the bookmarks for {}: {}", input, err)),
+        }
+    }
+    app.assertions = assertions;
+    app.alert_config = config.alerts.clone();
+    app.label_template = match &config.label_template {
+        Some(t) => Some(t.parse().map_err(|err| eyre!("invalid label_template `{}`: {}", t, err))?),
+        None => None,
+    };
+    app.time = TimeDisplay::new(args.time_zone.as_deref().or(config.time_zone.as_deref()), args.time_format.as_deref().or(config.time_format.as_deref()))
+        .map_err(|err| eyre!(err))?;
+    if args.redact {
+        config.redact.check().map_err(|err| eyre!(err))?;
+        app.redact = Some(config.redact.clone());
+    }
+    app.watches = Watches::new(if args.watch_exprs.is_empty() { &config.watches } else { &args.watch_exprs });
+    for w in cred_warnings {
+        app.push_warning(w);
+    }
+    if let Some(ms) = args.tick_rate.or(config.tick_rate_ms) {
+        app.tick_rate = Duration::from_millis(ms.max(1));
+    }
+    if let Some(ms) = config.idle_tick_rate_ms {
+        app.idle_tick_rate = Duration::from_millis(ms.max(1));
+    }
+    if let Some(minutes) = config.time_window_minutes {
+        app.time_window = Duration::from_secs(minutes * 60);
+    }
+    app.compat = Compat::detect(args.compat);
+    app.break_cycles = args.break_cycles;
+    if config.ascii_connectors.unwrap_or(false) || app.compat.ascii {
+        app.glyphs = &lanes::ASCII;
+    }
+    app.theme = Theme::new(config.theme.as_deref(), &config.colors).map_err(|err| eyre!(err))?;
 
-    let mut terminal = setup_terminal()?;
+    if let Some(path) = &args.export_dot {
+        return export_dot(app, path);
+    }
+
+    let on_tty = matches!(args.emit_selection, Some(EmitTarget::Stdout));
+    if on_tty && io::stdout().is_terminal() {
+        return Err(eyre!("--emit-selection stdout needs stdout redirected to a pipe or file"));
+    }
+    app.emitter = args.emit_selection.clone().map(SelectionEmitter::spawn);
+    if let Some(path) = &args.control {
+        app.control = Some(Control::bind(path)?);
+    }
+
+    let mut terminal = setup_terminal(on_tty, app.compat)?;
     let res = run_app(&mut terminal, &mut app);
-    restore_terminal(terminal)?;
+    restore_terminal(terminal, app.compat)?;
+
+    // Warnings would garble the alternate screen, so report them afterwards.
+    for w in &app.warnings {
+        eprintln!("{}", w);
+    }
+    if app.warnings_elided > 0 {
+        eprintln!("[warn] ... and {} more warnings", app.warnings_elided);
+    }
     res
 }
 
-fn run_app(terminal: &mut Terminal<CrosstermBackend<std::io::Stdout>>, app: &mut App) -> Result<()> {
+/// `diff`: one of the two files, read in full as it would be on its own,
+/// with the warnings reading it gave.
+fn load_side(input: &str, args: &Args, config: &Config, creds: &mut Credentials) -> Result<(GraphModel, Vec<String>)> {
+    // The viewer reports the credential warnings itself.
+    Ok(Headless::read(input, args, config, creds, Vec::new())?.into_side())
+}
+
+/// Apply everything the ingest workers deliver, until every source is done.
+fn load_all(app: &mut App) {
+    loop {
+        let changed = app.pump_ingest();
+        if !changed && app.ingest.stats().live.load(Ordering::Relaxed) == 0 {
+            break;
+        }
+        if !app.backlog {
+            std::thread::sleep(STREAM_POLL);
+        }
+    }
+}
+
+/// `--export-dot`: load everything, then write the whole graph as DOT.
+fn export_dot(mut app: App, path: &str) -> Result<()> {
+    load_all(&mut app);
+    for w in &app.warnings {
+        eprintln!("{}", w);
+    }
+    let tab = app.tab();
+    let gm = tab.full_graph();
+    let nodes: Vec<NodeIndex> = gm.stable_order().collect();
+    let (nodes, edges) = export::write_dot(gm, &nodes, Path::new(path), &tab.title, &app.time, app.redact.as_ref())?;
+    eprintln!("wrote {} nodes and {} edges to {}", nodes, edges, path);
+    Ok(())
+}
+
+fn run_app(terminal: &mut Tui, app: &mut App) -> Result<()> {
     loop {
-        terminal.draw(|f| draw_ui(f, app))?;
+        if app.pump_ingest() {
+            app.dirty = true;
+        }
+        while let Some(call) = app.control.as_ref().and_then(Control::next) {
+            app.handle_call(call);
+        }
+
+        // Only redraw when input or graph state changed since the last frame;
+        // while events are flowing, redraw at most once per tick.
+        let busy = app.backlog || app.streaming;
+        if app.violations_stale && !busy {
+            app.check_assertions();
+            app.dirty = true;
+        }
+        if !busy && app.tab().start_pending && app.tab().full_graph().graph.node_count() > 0 {
+            app.tab_mut().go_to_start();
+            app.dirty = true;
+        }
+        if app.dirty && (!busy || app.last_draw.elapsed() >= app.tick_rate) {
+            app.tab_mut().resample(busy);
+            if let Some(other) = app.split {
+                app.tabs[other].resample(busy);
+            }
+            if app.tab().order_stale {
+                app.tab_mut().refresh_order();
+            }
+            app.tab_mut().refresh_diff();
+            app.sync_split();
+            let gm = app.tabs[app.active].full_graph();
+            app.watches.recount(gm, busy);
+            if app.mode == Mode::Tags && app.tags_stale {
+                app.count_tags();
+            }
+            terminal.draw(|f| {
+                draw_ui(f, app);
+                if app.compat.limited() {
+                    app.compat.degrade(f.buffer_mut());
+                }
+            })?;
+            if let Some(emitter) = &mut app.emitter {
+                let tab = &app.tabs[app.active];
+                emitter.selected(&tab.gm, tab.selected());
+            }
+            // Keep drawing until throttled counts catch up and highlights fade.
+            app.dirty = app.watches.stale || app.watches.alerting();
+            app.last_draw = Instant::now();
+        }
 
         // Input handling with periodic tick to keep UI responsive
-        let timeout = app.tick_rate.checked_sub(app.last_tick.elapsed()).unwrap_or_else(|| Duration::from_secs(0));
-        if event::poll(timeout)? {
-            if let Event::Key(key) = event::read()? {
-                if handle_key(app, key)? {
-                    break; // quit
+        if event::poll(app.poll_timeout())? {
+            match event::read()? {
+                Event::Key(key) => {
+                    app.dirty = true;
+                    if handle_key(app, key)? {
+                        break; // quit
+                    }
+                    Tutorial::advance(app);
+                    if let Some(text) = app.clipboard.take() {
+                        // OSC 52: the terminal (even over ssh) sets its clipboard.
+                        write!(terminal.backend_mut(), "\x1b]52;c;{}\x07", auth::base64(text.as_bytes()))?;
+                        terminal.backend_mut().flush()?;
+                    }
+                }
+                Event::Mouse(mouse) if handle_mouse(app, mouse) => {
+                    app.dirty = true;
+                    Tutorial::advance(app);
                 }
+                Event::Resize(_, _) => app.dirty = true,
+                _ => {}
             }
         }
         if app.last_tick.elapsed() >= app.tick_rate {
             app.last_tick = Instant::now();
+            if app.replay_tick() {
+                app.dirty = true;
+            }
         }
     }
     Ok(())
 }
 
+/// A click on a list row selects it, a click on a canvas shape selects its
+/// node, and the wheel moves the list selection (or scrolls the details).
+/// Returns whether anything changed.
+fn handle_mouse(app: &mut App, mouse: MouseEvent) -> bool {
+    if !matches!(app.mode, Mode::Normal | Mode::Details | Mode::Canvas) {
+        return false;
+    }
+    let (column, row) = (mouse.column, mouse.row);
+    let on = |area: Rect| inside(area, column, row);
+    match mouse.kind {
+        MouseEventKind::Down(MouseButton::Left) if on(app.hits.list) => {
+            let Some(&pos) = app.hits.list_rows.get(usize::from(row - app.hits.list.y)) else { return false };
+            app.tab_mut().start_pending = false;
+            app.tab_mut().list_state.select(Some(pos));
+            app.mode = Mode::Normal;
+        }
+        MouseEventKind::Down(MouseButton::Left) if on(app.hits.canvas) => {
+            let Some(idx) = app.hits.shape_at(column, row) else { return false };
+            app.tab_mut().start_pending = false;
+            app.tab_mut().click_node(idx);
+        }
+        MouseEventKind::ScrollDown | MouseEventKind::ScrollUp if on(app.hits.list) => {
+            let tab = app.tab_mut();
+            let Some(i) = tab.list_state.selected() else { return false };
+            tab.start_pending = false;
+            // Unlike j/k, the wheel stops at the ends rather than wrapping.
+            let i = if mouse.kind == MouseEventKind::ScrollDown { (i + 1).min(tab.order.len().saturating_sub(1)) } else { i.saturating_sub(1) };
+            tab.list_state.select(Some(i));
+        }
+        MouseEventKind::ScrollDown if on(app.hits.details) => app.scroll_details(1),
+        MouseEventKind::ScrollUp if on(app.hits.details) => app.scroll_details(-1),
+        _ => return false,
+    }
+    app.notice = None;
+    true
+}
+
 fn handle_key(app: &mut App, key: KeyEvent) -> Result<bool> {
+    // Once the user has started moving around, leave the selection to them.
+    app.tab_mut().start_pending = false;
+    app.notice = None;
+    if key.code == KeyCode::Char('n') && key.modifiers.contains(KeyModifiers::CONTROL) {
+        if let Some(t) = &mut app.tutorial {
+            if !t.skip() {
+                app.tutorial = None;
+            }
+            return Ok(false);
+        }
+    }
     match app.mode {
         Mode::Normal => match key.code {
             KeyCode::Char('q') => return Ok(true),
-            KeyCode::Up | KeyCode::Char('k') => app.on_up(),
-            KeyCode::Down | KeyCode::Char('j') => app.on_down(),
+            KeyCode::Char('o') if key.modifiers.contains(KeyModifiers::CONTROL) => app.tab_mut().go_history(false),
+            // Most terminals send Ctrl-i as Tab; Alt-Left/Right work everywhere.
+            KeyCode::Char('i') if key.modifiers.contains(KeyModifiers::CONTROL) => app.tab_mut().go_history(true),
+            KeyCode::Left if key.modifiers.contains(KeyModifiers::ALT) => app.tab_mut().go_history(false),
+            KeyCode::Right if key.modifiers.contains(KeyModifiers::ALT) => app.tab_mut().go_history(true),
+            KeyCode::Up | KeyCode::Char('k') => app.tab_mut().on_up(),
+            KeyCode::Down | KeyCode::Char('j') => app.tab_mut().on_down(),
             KeyCode::Char('/') => app.mode = Mode::Filter,
             KeyCode::Char('c') => {
-                app.apply_filter("");
+                app.tab_mut().apply_filter("");
+            }
+            KeyCode::Char('?') => {
+                app.help_scroll = 0;
+                app.mode = Mode::HelpOverlay;
+            }
+            KeyCode::Tab => app.toggle_dag_view(),
+            KeyCode::Char('V') => app.toggle_split(),
+            KeyCode::Char('W') => app.list_wrap = !app.list_wrap,
+            KeyCode::Char('L') => app.lineage = !app.lineage,
+            KeyCode::Char('C') if app.tag_sources => app.color_by_source = !app.color_by_source,
+            KeyCode::Char('[') => app.scroll_list(false),
+            KeyCode::Char(']') => app.scroll_list(true),
+            KeyCode::Char('>') => app.switch_tab(true),
+            KeyCode::Char('T') => app.explore_selection(),
+            KeyCode::Char('w') if key.modifiers.contains(KeyModifiers::CONTROL) => app.close_tab(),
+            KeyCode::Char('<') => app.switch_tab(false),
+            KeyCode::Char('x') => app.tab_mut().expand_selection(),
+            KeyCode::Char('y') => app.next_cycle_node(),
+            KeyCode::Char('G') => app.toggle_group(),
+            KeyCode::Char('w') => {
+                let q = app.tab().filter_text.clone();
+                app.watches.toggle(&q);
+            }
+            KeyCode::Char('n') | KeyCode::Char('N') if !app.tab().found.is_empty() => app.tab_mut().next_found(key.code == KeyCode::Char('n'), false),
+            KeyCode::Char('Z') => app.canvas_labels = !app.canvas_labels,
+            KeyCode::Char('H') => app.tab_mut().toggle_search(),
+            KeyCode::Char('z') if app.dag_view_mode == DagViewMode::Canvas && app.split.is_none() => app.mode = Mode::Canvas,
+            KeyCode::Char('l') | KeyCode::BackTab if app.split.is_none() && app.tab().selected().is_some() => app.mode = Mode::Details,
+            KeyCode::Char('e') if app.tab().focused_edge().is_some() => {
+                app.tab_mut().edge_cursor = 0;
+                app.mode = Mode::EdgeFocus;
+            }
+            KeyCode::Char('t') => {
+                app.count_tags();
+                app.mode = Mode::Tags;
+            }
+            KeyCode::Char('P') => app.tab_mut().toggle_pin(),
+            KeyCode::Char('+') if app.tab().selected().is_some() => app.open_tag_prompt(TagAction::Add),
+            KeyCode::Char('o') => app.tab_mut().move_neighbor_cursor(true, true),
+            KeyCode::Char('O') => app.tab_mut().move_neighbor_cursor(true, false),
+            KeyCode::Char('u') => app.tab_mut().move_neighbor_cursor(false, true),
+            KeyCode::Char('U') => app.tab_mut().move_neighbor_cursor(false, false),
+            KeyCode::Enter => app.tab_mut().jump_to_neighbor(true),
+            KeyCode::Backspace => app.tab_mut().jump_to_neighbor(false),
+            KeyCode::Char('r') => app.open_roots(),
+            KeyCode::Char('X') => app.mode = Mode::ExportPath,
+            KeyCode::Char('v') => app.open_revisions(),
+            KeyCode::Char('a') => app.open_replay(),
+            KeyCode::Char('f') => app.tab_mut().toggle_closure(),
+            KeyCode::Char('A') => app.filter_around(),
+            KeyCode::Char('=') => app.tab_mut().cycle_sort(),
+            KeyCode::Char('m') => {
+                let notice = app.tab_mut().mark_path_start();
+                app.notice = Some((notice, false));
+            }
+            KeyCode::Char('g') => app.notice = Some(app.tab_mut().find_path()),
+            KeyCode::Char('F') => {
+                app.preview_replace();
+                app.mode = Mode::Replace;
+            }
+            KeyCode::Char(' ') => app.open_menu(),
+            KeyCode::Char('M') => {
+                app.pattern_error = None;
+                app.mode = Mode::PatternInput;
+            }
+            KeyCode::Char('s') => app.tab_mut().take_snapshot(),
+            KeyCode::Char('S') => {
+                let current = app.tab().viewing.map_or(0, |i| i + 1);
+                app.snapshots_state.select(Some(current));
+                app.mode = Mode::Snapshots;
+            }
+            KeyCode::Char('I') => app.open_stats(),
+            KeyCode::Char('J') if app.tab().selected().is_some() => {
+                app.raw_scroll = 0;
+                app.mode = Mode::Raw;
+            }
+            KeyCode::Char('Y') => app.toggle_break_cycles(),
+            KeyCode::Char('b') => app.toggle_bookmark(),
+            KeyCode::Char(':') => app.open_palette(),
+            KeyCode::Char('p') if key.modifiers.contains(KeyModifiers::CONTROL) => app.open_palette(),
+            KeyCode::Char('B') => app.open_bookmarks(),
+            KeyCode::Char('p') => {
+                let n = app.problem_count();
+                app.problems_state.select((n > 0).then(|| app.problems_state.selected().unwrap_or(0).min(n - 1)));
+                app.mode = Mode::Problems;
+            }
+            _ => {}
+        },
+        Mode::Tags => match key.code {
+            KeyCode::Char('q') => return Ok(true),
+            KeyCode::Esc | KeyCode::Char('t') => app.mode = Mode::Normal,
+            KeyCode::Up | KeyCode::Char('k') => app.move_tag(false),
+            KeyCode::Down | KeyCode::Char('j') => app.move_tag(true),
+            KeyCode::Enter => app.filter_by_tag(),
+            KeyCode::Char('R') => {
+                if let Some(tag) = app.selected_tag() {
+                    app.open_tag_prompt(TagAction::Rename(tag));
+                }
+            }
+            KeyCode::Char('D') => app.delete_selected_tag(),
+            _ => {}
+        },
+        Mode::TagEdit => {
+            let prompt = app.tag_prompt.as_mut().expect("tag prompt is open in TagEdit");
+            match key.code {
+                KeyCode::Esc => {
+                    let back = if matches!(prompt.action, TagAction::Rename(_)) { Mode::Tags } else { Mode::Normal };
+                    app.tag_prompt = None;
+                    app.mode = back;
+                }
+                KeyCode::Enter => app.finish_tag_prompt(),
+                KeyCode::Tab => prompt.complete(&app.tag_cloud),
+                KeyCode::Backspace => prompt.pop(),
+                KeyCode::Char(ch) if !key.modifiers.contains(KeyModifiers::CONTROL) => prompt.push(ch),
+                _ => {}
+            }
+        }
+        Mode::Roots => match key.code {
+            KeyCode::Char('q') => return Ok(true),
+            KeyCode::Esc | KeyCode::Char('r') => app.mode = Mode::Normal,
+            KeyCode::Up | KeyCode::Char('k') => app.move_root(false),
+            KeyCode::Down | KeyCode::Char('j') => app.move_root(true),
+            KeyCode::Enter => app.select_root(),
+            _ => {}
+        },
+        Mode::Bookmarks => match key.code {
+            KeyCode::Char('q') => return Ok(true),
+            KeyCode::Esc | KeyCode::Char('B') => app.mode = Mode::Normal,
+            KeyCode::Up | KeyCode::Char('k') => app.move_bookmark(false),
+            KeyCode::Down | KeyCode::Char('j') => app.move_bookmark(true),
+            KeyCode::Enter => app.select_bookmark(),
+            KeyCode::Char('d') => app.remove_bookmark(),
+            _ => {}
+        },
+        Mode::Goto => {
+            let gm = app.tabs[app.active].full_graph();
+            match key.code {
+                KeyCode::Esc => app.mode = Mode::Normal,
+                KeyCode::Enter => app.goto_chosen(),
+                KeyCode::Up => app.palette.step(false),
+                KeyCode::Down => app.palette.step(true),
+                KeyCode::Tab => app.palette.complete(gm),
+                KeyCode::Backspace => app.palette.pop(gm),
+                KeyCode::Char('p') if key.modifiers.contains(KeyModifiers::CONTROL) => app.palette.step(false),
+                KeyCode::Char('n') if key.modifiers.contains(KeyModifiers::CONTROL) => app.palette.step(true),
+                KeyCode::Char(ch) if !key.modifiers.contains(KeyModifiers::CONTROL) => app.palette.push(ch, gm),
+                _ => {}
+            }
+        }
+        Mode::Raw => match key.code {
+            KeyCode::Char('q') => return Ok(true),
+            KeyCode::Esc | KeyCode::Char('J') => app.mode = Mode::Normal,
+            KeyCode::Up | KeyCode::Char('k') => app.raw_scroll = app.raw_scroll.saturating_sub(1),
+            KeyCode::Down | KeyCode::Char('j') => app.raw_scroll = app.raw_scroll.saturating_add(1),
+            _ => {}
+        },
+        Mode::Stats => match key.code {
+            KeyCode::Char('q') => return Ok(true),
+            KeyCode::Esc | KeyCode::Char('I') => app.mode = Mode::Normal,
+            KeyCode::Up | KeyCode::Char('k') => app.stats_scroll = app.stats_scroll.saturating_sub(1),
+            KeyCode::Down | KeyCode::Char('j') => app.stats_scroll = app.stats_scroll.saturating_add(1),
+            _ => {}
+        },
+        Mode::PatternInput => match key.code {
+            KeyCode::Esc => app.mode = Mode::Normal,
+            KeyCode::Enter => app.run_pattern(),
+            KeyCode::Backspace => {
+                app.pattern_error = None;
+                app.pattern_input.pop();
+            }
+            KeyCode::Char(ch) if !key.modifiers.contains(KeyModifiers::CONTROL) => {
+                app.pattern_error = None;
+                app.pattern_input.push(ch);
+            }
+            _ => {}
+        },
+        Mode::Revisions => match key.code {
+            KeyCode::Char('q') => return Ok(true),
+            KeyCode::Esc | KeyCode::Char('v') => app.mode = Mode::Normal,
+            KeyCode::Up | KeyCode::Char('k') => app.move_revision(false),
+            KeyCode::Down | KeyCode::Char('j') => app.move_revision(true),
+            KeyCode::Char(' ') => app.revision_base = app.revisions_state.selected(),
+            _ => {}
+        },
+        Mode::Replace => {
+            match key.code {
+                KeyCode::Esc => app.mode = Mode::Normal,
+                KeyCode::Enter => app.apply_replace(),
+                KeyCode::Tab => app.replace.editing_with = !app.replace.editing_with,
+                KeyCode::Backspace => app.replace.pop(),
+                KeyCode::Char(ch) if !key.modifiers.contains(KeyModifiers::CONTROL) => app.replace.push(ch),
+                _ => {}
+            }
+            if app.mode == Mode::Replace {
+                app.preview_replace();
+            }
+        }
+        Mode::Replay => match key.code {
+            KeyCode::Char('q') => return Ok(true),
+            KeyCode::Esc | KeyCode::Char('a') => app.mode = Mode::Normal,
+            KeyCode::Left | KeyCode::Up | KeyCode::Char('k') => app.step_replay(false),
+            KeyCode::Right | KeyCode::Down | KeyCode::Char('j') => app.step_replay(true),
+            KeyCode::Home => {
+                app.replay_pos = 0;
+                app.show_replay_edge();
+            }
+            KeyCode::End => {
+                app.replay_pos = app.replay.len() - 1;
+                app.show_replay_edge();
+            }
+            KeyCode::Char(' ') => app.replay_playing = !app.replay_playing,
+            _ => {}
+        },
+        Mode::Menu => match key.code {
+            KeyCode::Char('q') => return Ok(true),
+            KeyCode::Esc | KeyCode::Char(' ') => app.mode = Mode::Normal,
+            KeyCode::Up | KeyCode::Char('k') => app.move_menu(false),
+            KeyCode::Down | KeyCode::Char('j') => app.move_menu(true),
+            KeyCode::Enter => app.run_menu(),
+            _ => {}
+        },
+        Mode::ExportPath => match key.code {
+            KeyCode::Esc => {
+                app.export_nodes = None;
+                app.mode = Mode::Normal;
+            }
+            KeyCode::Enter => app.export_listed(),
+            KeyCode::Backspace => {
+                app.export_input.pop();
+            }
+            KeyCode::Char(ch) if !key.modifiers.contains(KeyModifiers::CONTROL) => app.export_input.push(ch),
+            _ => {}
+        },
+        Mode::Matches => match key.code {
+            KeyCode::Char('q') => return Ok(true),
+            KeyCode::Esc | KeyCode::Char('M') => app.mode = Mode::Normal,
+            KeyCode::Up | KeyCode::Char('k') => app.move_match(false),
+            KeyCode::Down | KeyCode::Char('j') => app.move_match(true),
+            KeyCode::Enter => app.select_match(),
+            _ => {}
+        },
+        Mode::Snapshots => match key.code {
+            KeyCode::Char('q') => return Ok(true),
+            KeyCode::Esc | KeyCode::Char('S') => app.mode = Mode::Normal,
+            KeyCode::Up | KeyCode::Char('k') => app.move_snapshot(false),
+            KeyCode::Down | KeyCode::Char('j') => app.move_snapshot(true),
+            KeyCode::Char('s') => app.tab_mut().take_snapshot(),
+            KeyCode::Enter => {
+                let i = app.selected_snapshot();
+                app.tab_mut().view_snapshot(i);
+                app.mode = Mode::Normal;
             }
-            KeyCode::Char('?') => app.mode = Mode::HelpOverlay,
+            KeyCode::Char('d') => {
+                let i = app.selected_snapshot();
+                app.tab_mut().diff_against(i);
+                app.mode = Mode::Normal;
+            }
+            _ => {}
+        },
+        Mode::Problems => match key.code {
+            KeyCode::Char('q') => return Ok(true),
+            KeyCode::Esc | KeyCode::Char('p') => app.mode = Mode::Normal,
+            KeyCode::Up | KeyCode::Char('k') => app.move_problem(false),
+            KeyCode::Down | KeyCode::Char('j') => app.move_problem(true),
+            KeyCode::Enter => app.jump_to_problem(),
+            _ => {}
+        },
+        Mode::Canvas => match key.code {
+            KeyCode::Char('q') => return Ok(true),
+            KeyCode::Esc | KeyCode::Char('z') => app.mode = Mode::Normal,
             KeyCode::Tab => app.toggle_dag_view(),
+            KeyCode::Left | KeyCode::Char('h') => app.viewport.pan(-1.0, 0.0),
+            KeyCode::Right | KeyCode::Char('l') => app.viewport.pan(1.0, 0.0),
+            KeyCode::Up | KeyCode::Char('k') => app.viewport.pan(0.0, 1.0),
+            KeyCode::Down | KeyCode::Char('j') => app.viewport.pan(0.0, -1.0),
+            KeyCode::Char('+') | KeyCode::Char('=') => app.viewport.zoom_by(VIEWPORT_ZOOM_STEP),
+            KeyCode::Char('-') => app.viewport.zoom_by(1.0 / VIEWPORT_ZOOM_STEP),
+            KeyCode::Char('0') => app.viewport = Viewport::default(),
+            KeyCode::Char('Z') => app.canvas_labels = !app.canvas_labels,
+            _ => {}
+        },
+        Mode::Details => match key.code {
+            KeyCode::Char('q') => return Ok(true),
+            KeyCode::Esc | KeyCode::Char('h') | KeyCode::Char('l') | KeyCode::Tab | KeyCode::BackTab => app.mode = Mode::Normal,
+            KeyCode::Up | KeyCode::Char('k') => app.scroll_details(-1),
+            KeyCode::Down | KeyCode::Char('j') => app.scroll_details(1),
+            KeyCode::PageUp => app.scroll_details(-i32::from(app.details_page)),
+            KeyCode::PageDown => app.scroll_details(i32::from(app.details_page)),
+            KeyCode::Home => app.scroll_details(i32::MIN),
+            KeyCode::End => app.scroll_details(i32::MAX),
+            KeyCode::Char('n') | KeyCode::Char('N') if app.lineage => {
+                app.notice = Some(("Only direct parents and children can be followed; L lists them".to_string(), true));
+            }
+            KeyCode::Char('n') => app.tab_mut().move_link(true),
+            KeyCode::Char('N') => app.tab_mut().move_link(false),
+            KeyCode::Enter if !app.lineage => app.tab_mut().follow_link(),
+            _ => {}
+        },
+        Mode::EdgeFocus => match key.code {
+            KeyCode::Char('q') => return Ok(true),
+            KeyCode::Esc | KeyCode::Char('e') => app.mode = Mode::Normal,
+            KeyCode::Up | KeyCode::Left | KeyCode::Char('k') | KeyCode::Char('h') => app.tab_mut().cycle_edge(false),
+            KeyCode::Down | KeyCode::Right | KeyCode::Char('j') | KeyCode::Char('l') => app.tab_mut().cycle_edge(true),
+            KeyCode::Enter => app.tab_mut().follow_edge(),
             _ => {}
         },
         Mode::Filter => match key.code {
             KeyCode::Esc => app.mode = Mode::Normal,
-            KeyCode::Enter => app.mode = Mode::Normal,
+            KeyCode::Enter => {
+                app.mode = Mode::Normal;
+                app.tab_mut().next_found(true, true);
+            }
             KeyCode::Backspace => {
-                app.filter_text.pop();
-                let q = app.filter_text.clone();
-                app.apply_filter(&q);
-            }
-            KeyCode::Char(ch) => {
-                if !key.modifiers.contains(KeyModifiers::CONTROL) {
-                    app.filter_text.push(ch);
-                    let q = app.filter_text.clone();
-                    app.apply_filter(&q);
-                }
+                let tab = app.tab_mut();
+                tab.filter_text.pop();
+                let q = tab.filter_text.clone();
+                tab.apply_filter(&q);
+            }
+            KeyCode::Char(ch) if !key.modifiers.contains(KeyModifiers::CONTROL) => {
+                let tab = app.tab_mut();
+                tab.filter_text.push(ch);
+                let q = tab.filter_text.clone();
+                tab.narrow_filter(&q);
             }
             _ => {}
         },
         Mode::HelpOverlay => match key.code {
+            KeyCode::Char('q') => return Ok(true),
             KeyCode::Esc | KeyCode::Char('?') => app.mode = Mode::Normal,
+            KeyCode::Up | KeyCode::Char('k') => app.help_scroll = app.help_scroll.saturating_sub(1),
+            KeyCode::Down | KeyCode::Char('j') => app.help_scroll = app.help_scroll.saturating_add(1),
+            KeyCode::PageUp => app.help_scroll = app.help_scroll.saturating_sub(LIST_SCROLL_STEP as u16),
+            KeyCode::PageDown => app.help_scroll = app.help_scroll.saturating_add(LIST_SCROLL_STEP as u16),
+            KeyCode::Home => app.help_scroll = 0,
+            KeyCode::End => app.help_scroll = u16::MAX,
             _ => {}
         },
     }
     Ok(false)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn graph(edges: &[(&str, &str)]) -> GraphModel {
+        let mut gm = GraphModel::new();
+        for &(from, to) in edges {
+            gm.ensure_node_id(from);
+            gm.ensure_node_id(to);
+            gm.add_edge(from, to, EdgeData::default());
+        }
+        gm
+    }
+
+    #[test]
+    fn streamed_nodes_leave_the_rest_where_it_was() {
+        let mut gm = graph(&[("a", "b"), ("a", "c"), ("d", "a")]);
+        let a = gm.indices["a"];
+        let mut cache = LayoutCache::default();
+        let before = cache.update(&gm, a, 2).clone();
+        gm.ensure_node_id("e");
+        gm.add_edge("a", "e", EdgeData::default());
+        let after = cache.update(&gm, a, 2).clone();
+        for id in ["a", "d"] {
+            assert_eq!(after[&gm.indices[id]], before[&gm.indices[id]], "{}", id);
+        }
+        let (b, e) = (after[&gm.indices["b"]], after[&gm.indices["e"]]);
+        assert_eq!(e.0, b.0, "a new child goes in the children's column");
+        assert!(after.iter().all(|(&n, p)| n == gm.indices["e"] || (p.0 - e.0).abs() >= 0.5 || (p.1 - e.1).abs() >= 1.0));
+    }
+
+    #[test]
+    fn a_node_that_changes_layer_is_placed_again() {
+        let mut gm = graph(&[("a", "x"), ("x", "b")]);
+        let a = gm.indices["a"];
+        let mut cache = LayoutCache::default();
+        let before = cache.update(&gm, a, 2).clone();
+        let (x, b) = (gm.indices["x"], gm.indices["b"]);
+        assert!(before[&b].0 > before[&x].0, "b starts a layer further out");
+        // A direct edge brings b into the children's layer.
+        gm.add_edge("a", "b", EdgeData::default());
+        let after = cache.update(&gm, a, 2).clone();
+        assert_eq!(after[&b].0, after[&x].0);
+        assert_ne!(after[&b], before[&b]);
+        assert!((after[&b].1 - after[&x].1).abs() >= 1.0, "b gets a row of its own");
+        assert_eq!(cache.placed[&b].0, 1);
+    }
+}
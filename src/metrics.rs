@@ -0,0 +1,104 @@
+//! `riff-dag-tui metrics`: a graph's key numbers, for a CI job to record on
+//! every run and chart or alert on.
+//!
+//! Counted over the whole graph once everything is loaded: nodes, edges,
+//! roots, leaves, the longest path from a root, error nodes (tagged like
+//! the canvas's error shape), nodes on cycles, ingest warnings, duplicate-id
+//! conflicts, assertion violations and, per watch expression (`--watch-expr`
+//! or `watches` in the config), the nodes it matches. Printed as JSON or in
+//! the Prometheus text format, ready for a pushgateway or a node_exporter
+//! textfile collector.
+use serde_json::json;
+
+use crate::{alerts, classify_node_type, cycles::Cycles, GraphModel, NodeType};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum MetricsFormat {
+    Json,
+    Prometheus,
+}
+
+#[derive(Debug, Default)]
+pub struct Metrics {
+    pub nodes: usize,
+    pub edges: usize,
+    pub roots: usize,
+    pub leaves: usize,
+    pub max_depth: usize, // edges on the longest path from a root, cycles aside unless broken
+    pub error_nodes: usize,
+    pub cycle_nodes: usize,
+    pub warnings: usize,
+    pub conflicts: usize,
+    pub violations: usize,
+    pub watches: Vec<(String, usize)>, // query, matching nodes
+}
+
+/// Name, help text and value of each plain metric, in output order.
+type Gauges = [(&'static str, &'static str, usize); 10];
+
+impl Metrics {
+    /// The counts that come from the graph alone; the caller fills in the
+    /// rest. With `break_cycles`, depths run through cycles.
+    pub fn measure(gm: &GraphModel, break_cycles: bool) -> Self {
+        let g = &gm.graph;
+        let cycles = Cycles::find(gm);
+        let (incoming, outgoing) = (petgraph::Direction::Incoming, petgraph::Direction::Outgoing);
+        Self {
+            nodes: g.node_count(),
+            edges: g.edge_count(),
+            roots: g.node_indices().filter(|&n| g.neighbors_directed(n, incoming).next().is_none()).count(),
+            leaves: g.node_indices().filter(|&n| g.neighbors_directed(n, outgoing).next().is_none()).count(),
+            max_depth: alerts::depths(gm, break_cycles.then_some(&cycles)).values().map(|&(d, _)| d).max().unwrap_or(0),
+            error_nodes: g.node_weights().filter(|nd| classify_node_type(&nd.tags) == NodeType::Error).count(),
+            cycle_nodes: cycles.node_count(),
+            ..Self::default()
+        }
+    }
+
+    fn gauges(&self) -> Gauges {
+        [
+            ("nodes", "Nodes in the graph.", self.nodes),
+            ("edges", "Edges in the graph.", self.edges),
+            ("roots", "Nodes without parents.", self.roots),
+            ("leaves", "Nodes without children.", self.leaves),
+            ("max_depth", "Edges on the longest path from a root.", self.max_depth),
+            ("error_nodes", "Nodes tagged as errors.", self.error_nodes),
+            ("cycle_nodes", "Nodes on a cycle.", self.cycle_nodes),
+            ("warnings", "Malformed lines and unresolved edges.", self.warnings),
+            ("conflicts", "Node ids redefined with different data.", self.conflicts),
+            ("violations", "Assertion violations.", self.violations),
+        ]
+    }
+
+    pub fn json(&self, input: &str) -> String {
+        let mut out = serde_json::Map::new();
+        out.insert("input".into(), json!(input));
+        for (name, _, value) in self.gauges() {
+            out.insert(name.into(), json!(value));
+        }
+        out.insert("watches".into(), self.watches.iter().map(|(q, n)| (q.clone(), json!(n))).collect::<serde_json::Map<_, _>>().into());
+        serde_json::Value::Object(out).to_string()
+    }
+
+    /// Gauges named `riff_dag_*`, each labelled with the input.
+    pub fn prometheus(&self, input: &str) -> String {
+        let input = label(input);
+        let mut out = String::new();
+        for (name, help, value) in self.gauges() {
+            out.push_str(&format!("# HELP riff_dag_{} {}\n# TYPE riff_dag_{} gauge\n", name, help, name));
+            out.push_str(&format!("riff_dag_{}{{input=\"{}\"}} {}\n", name, input, value));
+        }
+        if !self.watches.is_empty() {
+            out.push_str("# HELP riff_dag_watch_matches Nodes matching a watch expression.\n# TYPE riff_dag_watch_matches gauge\n");
+            for (query, n) in &self.watches {
+                out.push_str(&format!("riff_dag_watch_matches{{input=\"{}\",expr=\"{}\"}} {}\n", input, label(query), n));
+            }
+        }
+        out
+    }
+}
+
+/// A label value, escaped as the text format wants.
+fn label(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
@@ -0,0 +1,91 @@
+//! The goto palette (`:` or Ctrl-p): type part of a node's id or label and
+//! jump straight to it, wherever it is in the list and whatever the filter.
+//!
+//! Candidates are matched the way the `~` filter matches (see
+//! [`crate::fuzzy`]), against the id and the label separately, best first;
+//! an empty prompt lists the nodes in the default order. Tab fills the
+//! prompt with the highlighted id.
+use ratatui::widgets::ListState;
+
+use crate::{fuzzy, GraphModel};
+
+/// Candidates listed; typing more narrows them down.
+const LIMIT: usize = 200;
+
+#[derive(Debug)]
+pub struct Candidate {
+    pub id: String,
+    pub text: String,        // `id · label`, as listed
+    pub marked: Vec<usize>,  // characters of `text` the input matched
+}
+
+#[derive(Debug, Default)]
+pub struct Palette {
+    pub input: String,
+    pub candidates: Vec<Candidate>, // best first
+    pub total: usize,               // nodes that matched, listed or not
+    pub state: ListState,           // the highlighted candidate
+}
+
+impl Palette {
+    /// List the nodes of `gm` that match the input, keeping the first
+    /// highlighted.
+    pub fn update(&mut self, gm: &GraphModel) {
+        let mut found: Vec<(i64, Candidate)> = Vec::new();
+        for idx in gm.stable_order() {
+            let nd = &gm.graph[idx];
+            let (label, text) = (nd.label.as_str(), nd.display_label());
+            let on_id = fuzzy::best(&self.input, &nd.id);
+            // Label positions are moved past the id and the ` · `.
+            let offset = nd.id.chars().count() + 3;
+            let on_label = fuzzy::best(&self.input, label)
+                .filter(|_| !label.is_empty())
+                .map(|(score, at)| (score, at.into_iter().map(|i| i + offset).collect()));
+            let best = match (on_id, on_label) {
+                (Some(a), Some(b)) => Some(if b.0 > a.0 { b } else { a }),
+                (a, b) => a.or(b),
+            };
+            if let Some((score, marked)) = best {
+                found.push((score, Candidate { id: nd.id.to_string(), text, marked }));
+            }
+        }
+        // Shorter ids first among equals, so a completed id heads the list;
+        // the sort is stable, so the rest keep the default order.
+        found.sort_by_key(|(score, c)| (std::cmp::Reverse(*score), c.id.len()));
+        self.total = found.len();
+        self.candidates = found.into_iter().take(LIMIT).map(|(_, c)| c).collect();
+        self.state.select((!self.candidates.is_empty()).then_some(0));
+    }
+
+    pub fn push(&mut self, ch: char, gm: &GraphModel) {
+        self.input.push(ch);
+        self.update(gm);
+    }
+
+    pub fn pop(&mut self, gm: &GraphModel) {
+        self.input.pop();
+        self.update(gm);
+    }
+
+    pub fn step(&mut self, forward: bool) {
+        let n = self.candidates.len();
+        if n == 0 {
+            return;
+        }
+        let i = self.state.selected().unwrap_or(0);
+        self.state.select(Some(if forward { (i + 1) % n } else { (i + n - 1) % n }));
+    }
+
+    /// The highlighted node's id.
+    pub fn chosen(&self) -> Option<&str> {
+        self.state.selected().and_then(|i| self.candidates.get(i)).map(|c| c.id.as_str())
+    }
+
+    /// Tab: put the highlighted id in the prompt.
+    pub fn complete(&mut self, gm: &GraphModel) {
+        if let Some(id) = self.chosen().map(str::to_string) {
+            self.input = id;
+            self.update(gm);
+        }
+    }
+}
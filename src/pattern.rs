@@ -0,0 +1,92 @@
+//! Structural queries (`M`): a chain like `prompt -> tool -> error` finds
+//! every path whose nodes fit the steps in order, one edge per arrow.
+//!
+//! A step is a node type (prompt, response, tool, error or event, from the
+//! tags as on the canvas), a tag (whole, ignoring case) or `*` for any node;
+//! `tool|error` takes either. A path never visits a node twice, and the
+//! search stops after `MAX_MATCHES` so a dense graph can't stall the UI.
+use std::{collections::HashSet, str::FromStr};
+
+use petgraph::{stable_graph::NodeIndex, Direction::Outgoing};
+
+use crate::{classify_node_type, GraphModel, NodeData};
+
+/// Matches listed at most.
+pub const MAX_MATCHES: usize = 1000;
+
+#[derive(Debug, Clone)]
+enum Term {
+    Any,
+    Word(String), // a type name or a tag, lowercased
+}
+
+#[derive(Debug, Clone)]
+pub struct Pattern {
+    steps: Vec<Vec<Term>>, // alternatives per step
+}
+
+impl FromStr for Pattern {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        let mut steps = Vec::new();
+        for step in s.split("->") {
+            let mut terms = Vec::new();
+            for word in step.split('|') {
+                let word = word.trim();
+                match word {
+                    "" => return Err("empty step (write `*` for any node)".to_string()),
+                    "*" => terms.push(Term::Any),
+                    w if w.contains(char::is_whitespace) => return Err(format!("`{}` is not one step (separate steps with ->)", w)),
+                    w => terms.push(Term::Word(w.to_lowercase())),
+                }
+            }
+            steps.push(terms);
+        }
+        Ok(Self { steps })
+    }
+}
+
+fn fits(nd: &NodeData, terms: &[Term]) -> bool {
+    terms.iter().any(|term| match term {
+        Term::Any => true,
+        Term::Word(w) => classify_node_type(&nd.tags).name() == w || nd.tags.iter().any(|t| t.to_lowercase() == *w),
+    })
+}
+
+impl Pattern {
+    /// Every path in `gm` that fits, starting nodes in stable order, and
+    /// whether the search stopped at `MAX_MATCHES`.
+    pub fn find(&self, gm: &GraphModel) -> (Vec<Vec<NodeIndex>>, bool) {
+        let mut found = Vec::new();
+        for start in gm.stable_order().filter(|&idx| fits(&gm.graph[idx], &self.steps[0])) {
+            if !self.extend(gm, &mut vec![start], &mut found) {
+                return (found, true);
+            }
+        }
+        (found, false)
+    }
+
+    /// Extend `path` by the remaining steps; false once the limit is hit.
+    fn extend(&self, gm: &GraphModel, path: &mut Vec<NodeIndex>, found: &mut Vec<Vec<NodeIndex>>) -> bool {
+        if path.len() == self.steps.len() {
+            found.push(path.clone());
+            return found.len() < MAX_MATCHES;
+        }
+        let last = *path.last().expect("paths start with a node");
+        let mut seen = HashSet::new();
+        let terms = &self.steps[path.len()];
+        for next in gm.graph.neighbors_directed(last, Outgoing) {
+            if !seen.insert(next) || path.contains(&next) || !fits(&gm.graph[next], terms) {
+                continue;
+            }
+            path.push(next);
+            let more = self.extend(gm, path, found);
+            path.pop();
+            if !more {
+                return false;
+            }
+        }
+        true
+    }
+}
@@ -0,0 +1,90 @@
+//! The raw view (`J`): the line the selected node was read from, unknown
+//! fields and all, indented and coloured.
+//!
+//! The line is kept as it was read (see `riff_dag_tui::Payload`). JSON is
+//! laid out here a token at a time rather than parsed, so the fields stay
+//! in the order and form they were written in. Keys, strings, and numbers
+//! and literals (`true`, `false`, `null`) each get a colour of the theme.
+//! A line in some other format (`--format regex`) is shown as it is.
+use ratatui::{
+    style::{Color, Style},
+    text::{Line, Span},
+};
+
+use crate::theme::Theme;
+
+const INDENT: &str = "  ";
+
+fn closing(open: char) -> char {
+    if open == '{' {
+        '}'
+    } else {
+        ']'
+    }
+}
+
+/// `json`, one field or element per line.
+pub fn lines(json: &str, theme: &Theme) -> Vec<Line<'static>> {
+    if !json.trim_start().starts_with(['{', '[']) {
+        return vec![Line::raw(json.to_string())];
+    }
+    let span = |text: String, color: Color| Span::styled(text, Style::default().fg(color));
+    let mut out = Vec::new();
+    let mut line: Vec<Span<'static>> = Vec::new();
+    let mut depth = 0usize;
+    let mut chars = json.chars().peekable();
+    let break_line = |line: &mut Vec<Span<'static>>, out: &mut Vec<Line<'static>>, depth: usize| {
+        out.push(Line::from(std::mem::take(line)));
+        line.push(Span::raw(INDENT.repeat(depth)));
+    };
+    while let Some(c) = chars.next() {
+        match c {
+            '{' | '[' if chars.peek() == Some(&closing(c)) => {
+                chars.next();
+                line.push(span(format!("{}{}", c, closing(c)), theme.muted));
+            }
+            '{' | '[' => {
+                line.push(span(c.to_string(), theme.muted));
+                depth += 1;
+                break_line(&mut line, &mut out, depth);
+            }
+            '}' | ']' => {
+                depth = depth.saturating_sub(1);
+                break_line(&mut line, &mut out, depth);
+                line.push(span(c.to_string(), theme.muted));
+            }
+            ',' => {
+                line.push(span(c.to_string(), theme.muted));
+                break_line(&mut line, &mut out, depth);
+            }
+            ':' => line.push(span(": ".to_string(), theme.muted)),
+            '"' => {
+                let mut text = String::from('"');
+                let mut escaped = false;
+                for c in chars.by_ref() {
+                    text.push(c);
+                    match c {
+                        '"' if !escaped => break,
+                        '\\' => escaped = !escaped,
+                        _ => escaped = false,
+                    }
+                }
+                let key = chars.peek() == Some(&':');
+                line.push(span(text, if key { theme.accent } else { theme.good }));
+            }
+            c if c.is_whitespace() => {}
+            c => {
+                let mut text = c.to_string();
+                while let Some(&c) = chars.peek().filter(|c| !matches!(c, ',' | ':' | '}' | ']') && !c.is_whitespace()) {
+                    text.push(c);
+                    chars.next();
+                }
+                line.push(span(text, theme.changed));
+            }
+        }
+    }
+    if !line.is_empty() {
+        out.push(Line::from(line));
+    }
+    out
+}
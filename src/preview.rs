@@ -0,0 +1,188 @@
+//! `riff-dag-tui preview`: a schema summary of an input, without building
+//! the graph.
+//!
+//! Events are only tallied as they stream past: how often each optional
+//! field is set, the distinct tags, spans and metadata keys, the timestamp
+//! range, and roughly how much memory loading it for real would take.
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    mem::size_of,
+};
+
+use petgraph::stable_graph::NodeIndex;
+
+use crate::{EdgeData, EventLine, NodeData};
+
+/// Distinct values listed per category; the rest are only counted.
+const TOP: usize = 20;
+
+#[derive(Debug, Default)]
+pub struct Preview {
+    nodes: u64,
+    edges: u64,
+    ids: HashSet<String>,
+    node_fields: BTreeMap<&'static str, u64>,
+    edge_fields: BTreeMap<&'static str, u64>,
+    tags: HashMap<String, u64>,
+    spans: HashMap<String, u64>,
+    metadata_keys: HashMap<String, u64>,
+    first_ts: Option<String>,
+    last_ts: Option<String>,
+    bytes: u64, // estimated heap + struct size of the loaded graph
+}
+
+fn top(counts: &HashMap<String, u64>) -> String {
+    if counts.is_empty() {
+        return "none".to_string();
+    }
+    let mut sorted: Vec<(&String, &u64)> = counts.iter().collect();
+    sorted.sort_by(|a, b| b.1.cmp(a.1).then_with(|| a.0.cmp(b.0)));
+    let mut out = sorted.iter().take(TOP).map(|(k, n)| format!("{} {}", k, n)).collect::<Vec<_>>().join(", ");
+    if sorted.len() > TOP {
+        out.push_str(&format!(", ... {} more", sorted.len() - TOP));
+    }
+    out
+}
+
+/// Heap bytes behind a string of `len` bytes, with allocator rounding and bookkeeping.
+fn heap(len: usize) -> usize {
+    if len == 0 {
+        0
+    } else {
+        len.div_ceil(16) * 16 + 16
+    }
+}
+
+/// Growable tables and vectors sit between half and fully used; assume in between.
+fn slack(bytes: usize) -> usize {
+    bytes * 3 / 2
+}
+
+fn percent(n: u64, of: u64) -> u64 {
+    (n * 100).checked_div(of).unwrap_or(0)
+}
+
+fn human_bytes(bytes: u64) -> String {
+    let mut value = bytes as f64;
+    for unit in ["B", "KiB", "MiB", "GiB"] {
+        if value < 1024.0 {
+            return format!("{:.1} {}", value, unit);
+        }
+        value /= 1024.0;
+    }
+    format!("{:.1} TiB", value)
+}
+
+impl Preview {
+    fn saw_ts(&mut self, ts: &str) {
+        if self.first_ts.as_deref().is_none_or(|first| ts < first) {
+            self.first_ts = Some(ts.to_string());
+        }
+        if self.last_ts.as_deref().is_none_or(|last| ts > last) {
+            self.last_ts = Some(ts.to_string());
+        }
+    }
+
+    /// Tally `ev`, read from the line `raw` if it came from one.
+    pub fn add(&mut self, ev: &EventLine, raw: Option<&str>) {
+        match ev {
+            EventLine::Node { id, label, span, tags, ts, metadata, score, extra } => {
+                self.nodes += 1;
+                let new = self.ids.insert(id.clone());
+                let mut bytes = 0;
+                for (name, set) in [
+                    ("label", label.is_some()),
+                    ("span", span.is_some()),
+                    ("tags", tags.is_some()),
+                    ("ts", ts.is_some()),
+                    ("metadata", metadata.is_some()),
+                    ("score", score.is_some()),
+                ] {
+                    if set {
+                        *self.node_fields.entry(name).or_default() += 1;
+                    }
+                }
+                for t in tags.iter().flatten() {
+                    *self.tags.entry(t.clone()).or_default() += 1;
+                    bytes += size_of::<String>() + heap(t.len());
+                }
+                if let Some(span) = span {
+                    *self.spans.entry(span.clone()).or_default() += 1;
+                }
+                for (k, v) in metadata.iter().flatten() {
+                    *self.metadata_keys.entry(k.clone()).or_default() += 1;
+                    // BTreeMap entries, with the value as the string the graph keeps.
+                    let value = v.as_str().map_or_else(|| v.to_string().len(), str::len);
+                    bytes += slack(2 * size_of::<String>()) + heap(k.len()) + heap(value);
+                }
+                // The producer's own fields are kept as JSON values; a string's
+                // text (or roughly, a list's or object's) is on the heap.
+                for (k, v) in extra {
+                    let value = match v {
+                        serde_json::Value::String(s) => s.len(),
+                        serde_json::Value::Array(_) | serde_json::Value::Object(_) => v.to_string().len(),
+                        _ => 0,
+                    };
+                    bytes += slack(size_of::<String>() + size_of::<serde_json::Value>()) + heap(k.len()) + heap(value);
+                }
+                if let Some(ts) = ts {
+                    self.saw_ts(ts);
+                }
+                // Redefinitions replace a node rather than adding one.
+                if new {
+                    let len = |s: &Option<String>| s.as_ref().map_or(0, String::len);
+                    let (ts_len, label_len) = (len(ts), len(label));
+                    // The node itself, with its graph links.
+                    bytes += slack(size_of::<NodeData>() + 16) + heap(id.len()) + heap(label_len) + heap(len(span)) + heap(ts_len);
+                    // The id -> index map and the (ts, id) order each keep a copy of the id.
+                    bytes += slack(size_of::<String>() + size_of::<NodeIndex>() + 8) + heap(id.len());
+                    bytes += slack(2 * size_of::<String>() + size_of::<NodeIndex>()) + heap(id.len()) + heap(ts_len);
+                    // The node list: its place in the order and the line drawn for it.
+                    bytes += slack(size_of::<NodeIndex>()) + heap(id.len() + label_len + 16) + 96;
+                    // The line as read, for the raw view.
+                    bytes += heap(raw.map_or(0, str::len));
+                    self.bytes += bytes as u64;
+                }
+            }
+            EventLine::Edge { kind, weight, ts, .. } => {
+                self.edges += 1;
+                for (name, set) in [("kind", kind.is_some()), ("weight", weight.is_some()), ("ts", ts.is_some())] {
+                    if set {
+                        *self.edge_fields.entry(name).or_default() += 1;
+                    }
+                }
+                if let Some(ts) = ts {
+                    self.saw_ts(ts);
+                }
+                let strings = heap(kind.as_ref().map_or(0, String::len)) + heap(ts.as_ref().map_or(0, String::len));
+                self.bytes += (slack(size_of::<EdgeData>() + 16) + strings) as u64;
+            }
+        }
+    }
+
+    /// The summary `preview` prints, one line per finding.
+    pub fn report(&self) -> Vec<String> {
+        let mut out = vec![format!("{} node events ({} distinct ids), {} edge events", self.nodes, self.ids.len(), self.edges)];
+        let fields = |fields: &BTreeMap<&'static str, u64>, of: u64| {
+            if fields.is_empty() {
+                return "none".to_string();
+            }
+            fields.iter().map(|(name, n)| format!("{} {}%", name, percent(*n, of))).collect::<Vec<_>>().join(", ")
+        };
+        if self.nodes > 0 {
+            out.push(format!("node fields: {}", fields(&self.node_fields, self.nodes)));
+        }
+        if self.edges > 0 {
+            out.push(format!("edge fields: {}", fields(&self.edge_fields, self.edges)));
+        }
+        out.push(format!("tags ({} distinct): {}", self.tags.len(), top(&self.tags)));
+        out.push(format!("spans ({} distinct): {}", self.spans.len(), top(&self.spans)));
+        out.push(format!("metadata keys ({} distinct): {}", self.metadata_keys.len(), top(&self.metadata_keys)));
+        match (&self.first_ts, &self.last_ts) {
+            (Some(first), Some(last)) => out.push(format!("timestamps: {} .. {}", first, last)),
+            _ => out.push("timestamps: none".to_string()),
+        }
+        out.push(format!("estimated memory for a full load: ~{}", human_bytes(self.bytes)));
+        out
+    }
+}
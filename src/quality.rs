@@ -0,0 +1,164 @@
+//! Canvas layout quality: how many edges cross and how many node shapes
+//! overlap, measured whenever the canvas positions change and shown in the
+//! DAG view's title. The layered layout (`layered`) orders its columns to
+//! cross as little as it can, but its columns are as deep as the edges run;
+//! when a fresh layout still measures poorly, the spread layout (one column
+//! per hop from the selection, ordered by their neighbours' rows and spaced
+//! to fill the pane) is tried too, and whichever measures better is kept.
+//!
+//! Overlaps are found by bucketing the shapes into cells a shape wide, and
+//! crossings by sweeping the edges left to right, so only neighbours are
+//! compared. A neighbourhood with more than `MEASURE_LIMIT` edges is not
+//! measured at all (nor given the spread layout's second try): the title
+//! would be a large number either way, and the canvas redraws it often.
+use std::collections::HashMap;
+
+use itertools::Itertools;
+use petgraph::stable_graph::NodeIndex;
+
+use crate::{neighborhood_layers, GraphModel};
+
+/// Half the width of an unselected node's shape, in canvas units.
+pub const NODE_RADIUS: f64 = 1.5;
+
+/// Canvas rows the spread layout may use, centered on the selection.
+const SPREAD_HEIGHT: f64 = 46.0;
+
+/// Side-by-side columns a tall layer may be split into, and how far apart
+/// they sit, so they stay inside the layer's share of the width.
+const SPREAD_COLUMNS: usize = 3;
+const SPREAD_COLUMN_GAP: f64 = 4.0;
+
+/// Edges beyond which a layout is left unmeasured.
+pub const MEASURE_LIMIT: usize = 2000;
+
+type Point = (f64, f64);
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Algorithm {
+    #[default]
+    Layered,
+    Spread,
+}
+
+impl Algorithm {
+    pub fn name(self) -> &'static str {
+        match self {
+            Algorithm::Layered => "layered",
+            Algorithm::Spread => "spread",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Quality {
+    pub crossings: usize, // pairs of edges that cross, not counting shared ends
+    pub overlaps: usize,  // pairs of nodes whose shapes overlap
+}
+
+impl Quality {
+    /// `None` when there are more than `MEASURE_LIMIT` edges.
+    pub fn measure(positions: &HashMap<NodeIndex, (f64, f64)>, edges: &[(NodeIndex, NodeIndex)]) -> Option<Self> {
+        if edges.len() > MEASURE_LIMIT {
+            return None;
+        }
+        let size = 2.0 * NODE_RADIUS;
+        let cell = |p: (f64, f64)| ((p.0 / size).floor() as i64, (p.1 / size).floor() as i64);
+        let mut cells: HashMap<(i64, i64), Vec<(f64, f64)>> = HashMap::new();
+        for &p in positions.values() {
+            cells.entry(cell(p)).or_default().push(p);
+        }
+        // Two shapes can only overlap from the same or a neighbouring cell;
+        // each pair is counted from both ends, hence the halving.
+        let mut overlaps = 0;
+        for &a in positions.values() {
+            let (cx, cy) = cell(a);
+            overlaps += (cx - 1..=cx + 1)
+                .cartesian_product(cy - 1..=cy + 1)
+                .filter_map(|c| cells.get(&c))
+                .flatten()
+                .filter(|b| (a.0 - b.0).abs() < size && (a.1 - b.1).abs() < size)
+                .count()
+                - 1;
+        }
+        let overlaps = overlaps / 2;
+        // Left to right by each edge's leftmost end: an edge is only checked
+        // against the ones that start before it ends.
+        let segments: Vec<(Point, Point, (NodeIndex, NodeIndex))> = edges
+            .iter()
+            .map(|&e| (positions[&e.0], positions[&e.1], e))
+            .map(|(p, q, e)| if p.0 <= q.0 { (p, q, e) } else { (q, p, e) })
+            .sorted_by(|a, b| a.0 .0.total_cmp(&b.0 .0))
+            .collect();
+        let mut crossings = 0;
+        for (i, &(p0, p1, e)) in segments.iter().enumerate() {
+            crossings += segments[i + 1..]
+                .iter()
+                .take_while(|(q0, _, _)| q0.0 <= p1.0)
+                .filter(|(_, _, f)| e.0 != f.0 && e.0 != f.1 && e.1 != f.0 && e.1 != f.1)
+                .filter(|&&(q0, q1, _)| crosses((p0, p1), (q0, q1)))
+                .count();
+        }
+        Some(Self { crossings, overlaps })
+    }
+
+    /// Overlapping shapes hide nodes outright; crossings only blur the
+    /// picture once there are more of them than half the edges.
+    pub fn is_poor(&self, edges: usize) -> bool {
+        self.overlaps > 0 || self.crossings * 2 > edges
+    }
+
+    /// Which of two layouts reads better: fewer overlaps, then fewer crossings.
+    pub fn better_than(&self, other: &Quality) -> bool {
+        (self.overlaps, self.crossings) < (other.overlaps, other.crossings)
+    }
+}
+
+/// The edges between positioned nodes, each pair once, for `Quality::measure`.
+pub fn edges(gm: &GraphModel, positions: &HashMap<NodeIndex, (f64, f64)>) -> Vec<(NodeIndex, NodeIndex)> {
+    positions
+        .keys()
+        .flat_map(|&a| gm.graph.neighbors(a).filter(move |&b| b != a).map(move |b| (a, b)))
+        .filter(|(_, b)| positions.contains_key(b))
+        .sorted()
+        .dedup()
+        .collect()
+}
+
+/// Whether segments `p` and `q` properly cross (touching ends don't count).
+fn crosses(p: ((f64, f64), (f64, f64)), q: ((f64, f64), (f64, f64))) -> bool {
+    let side = |a: (f64, f64), b: (f64, f64), c: (f64, f64)| ((b.0 - a.0) * (c.1 - a.1) - (b.1 - a.1) * (c.0 - a.0)).signum();
+    let (d1, d2) = (side(q.0, q.1, p.0), side(q.0, q.1, p.1));
+    let (d3, d4) = (side(p.0, p.1, q.0), side(p.0, p.1, q.1));
+    d1 * d2 < 0.0 && d3 * d4 < 0.0
+}
+
+/// A column per hop from `center`, each ordered by the mean row of its
+/// neighbours one column nearer the selection and spaced out to fill the
+/// pane. A layer too tall to keep its shapes apart is split into up to
+/// `SPREAD_COLUMNS` columns side by side.
+pub fn spread(gm: &GraphModel, center: NodeIndex, depth: usize) -> HashMap<NodeIndex, (f64, f64)> {
+    let mut positions: HashMap<NodeIndex, (f64, f64)> = HashMap::new();
+    positions.insert(center, (50.0, 25.0));
+    let (parents_layers, children_layers) = neighborhood_layers(gm, center, depth);
+    let columns = |layers: &[Vec<NodeIndex>], first: f64, step: f64| layers.iter().enumerate().map(move |(i, layer)| (layer.clone(), first + i as f64 * step)).collect::<Vec<_>>();
+    // Nearest column first on each side, so its neighbours are already placed.
+    let depth_left = parents_layers.len() as f64;
+    for (layer, x) in columns(&parents_layers, 10.0 + (depth_left - 1.0) * 15.0, -15.0).into_iter().chain(columns(&children_layers, 70.0, 15.0)) {
+        let row = |n: NodeIndex| {
+            let ys: Vec<f64> = gm.graph.neighbors_undirected(n).filter_map(|m| positions.get(&m).map(|p| p.1)).collect();
+            if ys.is_empty() { 25.0 } else { ys.iter().sum::<f64>() / ys.len() as f64 }
+        };
+        let ordered: Vec<NodeIndex> = layer.iter().copied().sorted_by(|&a, &b| row(a).total_cmp(&row(b))).collect();
+        let fits = (SPREAD_HEIGHT / (2.0 * NODE_RADIUS)) as usize + 1;
+        let split = ordered.len().div_ceil(fits).clamp(1, SPREAD_COLUMNS);
+        let rows = ordered.len().div_ceil(split);
+        let gap = if rows > 1 { (SPREAD_HEIGHT / (rows - 1) as f64).min(2.0 * NODE_RADIUS + 1.0) } else { 0.0 };
+        for (i, node) in ordered.into_iter().enumerate() {
+            let (column, row) = (i / rows, i % rows);
+            let left = x - SPREAD_COLUMN_GAP * (split - 1) as f64 / 2.0;
+            positions.insert(node, (left + column as f64 * SPREAD_COLUMN_GAP, 25.0 + (row as f64 - (rows - 1) as f64 / 2.0) * gap));
+        }
+    }
+    positions
+}
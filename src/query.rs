@@ -0,0 +1,629 @@
+//! The `/` filter (also watch expressions and the control socket's
+//! `filter`): words like `tag:error span:planner` match only the named
+//! field, everything else is one phrase matched anywhere in the node.
+//!
+//! Fields are `id`, `label`, `span`, `ts`, `tag` (any of the node's tags),
+//! `source` (the merged inputs that defined it) and `meta.KEY`; quote a value with spaces (`label:"retry budget"`). Every
+//! part must match, each as a case-insensitive substring. A word whose
+//! prefix isn't a field (`http://...`) counts as plain text.
+//!
+//! `score` (or `confidence`) is compared instead: `score>0.8`, `score<=0.2`,
+//! `score=1`. Nodes without a score match no comparison. So is `ts`, as a
+//! point in time, with `>`, `<` and the rest: `ts>2024-05-01T12:00
+//! ts<2024-05-01T13:00` for an hour (UTC unless an offset is given; the
+//! seconds or the whole time of day may be left out). A `ts` that isn't RFC
+//! 3339 or a Unix epoch matches no comparison; `ts:` still matches the text.
+//!
+//! Words can be combined with `AND`, `OR` and `NOT` (in capitals, so the
+//! words themselves can still be searched for) and grouped in parentheses:
+//! `tag:tool AND NOT span:retry`, `(tag:error OR tag:timeout) span:db`.
+//! Words with no operator between them are one group, so `NOT` applies to
+//! all of them up to the next operator or parenthesis, and `AND` binds
+//! before `OR`. An operator with nothing after it yet is left out, so the
+//! list holds steady while an expression is typed. A parenthesis left
+//! unmatched is reported by [`Query::error`], with its column; the viewer
+//! shows it and meanwhile matches as if it weren't there, the subcommands
+//! refuse the query.
+//!
+//! A query starting with `~` is fuzzy instead (see [`crate::fuzzy`]): the
+//! rest is matched against each node's id and label, its characters in order
+//! but not necessarily together, and matches are ranked by how well they fit.
+//!
+//! When nothing matches, [`Query::did_you_mean`] offers the field, metadata
+//! key or node id a typo was likely meant to be.
+use std::collections::BTreeSet;
+
+use crate::{duplicate::SOURCES_KEY, fuzzy, suggest, timefmt, GraphModel, NodeData};
+
+/// Field names, as typed before the colon (besides `meta.KEY`).
+const FIELDS: [&str; 7] = ["id", "label", "span", "ts", "tag", "tags", "source"];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Field {
+    Id,
+    Label,
+    Span,
+    Ts,
+    Tag,
+    Source,
+    Meta(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Cmp {
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Eq,
+}
+
+impl Cmp {
+    fn holds<T: PartialOrd>(self, value: T, bound: T) -> bool {
+        match self {
+            Cmp::Lt => value < bound,
+            Cmp::Le => value <= bound,
+            Cmp::Gt => value > bound,
+            Cmp::Ge => value >= bound,
+            Cmp::Eq => value == bound,
+        }
+    }
+}
+
+/// A run of words with no operator between them; every part must match.
+#[derive(Debug, Clone, Default, PartialEq)]
+struct Terms {
+    fields: Vec<(Field, String)>,
+    scores: Vec<(Cmp, f64)>, // every one must hold for the node's score
+    times: Vec<(Cmp, (i64, u32))>, // and these for its `ts`, as seconds and nanoseconds since the epoch
+    text: String,         // the unscoped words, matched as one phrase
+    unknown: Vec<String>, // prefixes of `name:value` words that aren't fields
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Expr {
+    Terms(Terms),
+    Not(Box<Expr>),
+    And(Vec<Expr>),
+    Or(Vec<Expr>),
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Query {
+    expr: Option<Expr>,    // None for an empty query, which matches everything
+    fuzzy: Option<String>, // `~pattern`: matched fuzzily instead of `expr`
+    error: Option<String>, // the first unmatched parenthesis
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Word(String), // lowercased
+    And,
+    Or,
+    Not,
+    Open,
+    Close,
+}
+
+fn field(name: &str) -> Option<Field> {
+    Some(match name {
+        "id" => Field::Id,
+        "label" => Field::Label,
+        "span" => Field::Span,
+        "ts" => Field::Ts,
+        "tag" | "tags" => Field::Tag,
+        "source" => Field::Source,
+        other => Field::Meta(other.strip_prefix("meta.").filter(|key| !key.is_empty())?.to_string()),
+    })
+}
+
+/// The comparison and value of `NAME>VALUE` and the like, for any of `names`.
+fn bound<'a>(word: &'a str, names: &[&str]) -> Option<(Cmp, &'a str)> {
+    let rest = names.iter().find_map(|name| word.strip_prefix(name))?;
+    let ops = [(">=", Cmp::Ge), ("<=", Cmp::Le), (">", Cmp::Gt), ("<", Cmp::Lt), ("=", Cmp::Eq)];
+    ops.into_iter().find_map(|(op, cmp)| Some((cmp, rest.strip_prefix(op)?)))
+}
+
+/// `score>0.8` and the like.
+fn score_bound(word: &str) -> Option<(Cmp, f64)> {
+    let (cmp, value) = bound(word, &["score", "confidence"])?;
+    Some((cmp, value.parse().ok()?))
+}
+
+/// `ts>2024-05-01T12:00` and the like.
+fn time_bound(word: &str) -> Option<(Cmp, (i64, u32))> {
+    let (cmp, value) = bound(word, &["ts"])?;
+    Some((cmp, timefmt::parse_bound(value)?))
+}
+
+/// Split on whitespace outside double quotes, the quotes dropped, and
+/// parentheses at either end of a word split off. A `)` is kept when the
+/// word opens as many itself, as in `f(x)`. Each token comes with the
+/// column (in chars, from 0) it ends at.
+fn tokens(query: &str) -> Vec<(Token, usize)> {
+    let mut tokens = Vec::new();
+    let mut word = String::new();
+    let mut quoted = false;
+    let mut had_quotes = false;
+    let mut closing = 0; // unquoted `)` the word ends with
+    for (at, c) in query.chars().enumerate() {
+        match c {
+            '"' => {
+                quoted = !quoted;
+                had_quotes = true;
+            }
+            c if c.is_whitespace() && !quoted => {
+                push_word(&mut tokens, &word, had_quotes, closing, at);
+                word.clear();
+                had_quotes = false;
+                closing = 0;
+            }
+            '(' if !quoted && word.is_empty() && !had_quotes => tokens.push((Token::Open, at)),
+            c => {
+                closing = if c == ')' && !quoted { closing + 1 } else { 0 };
+                word.push(c);
+            }
+        }
+    }
+    push_word(&mut tokens, &word, had_quotes, closing, query.chars().count());
+    tokens
+}
+
+/// `word`, which ends before column `end`.
+fn push_word(tokens: &mut Vec<(Token, usize)>, word: &str, had_quotes: bool, closing: usize, end: usize) {
+    let mut word = word;
+    let mut close = 0;
+    while close < closing && word.matches(')').count() > word.matches('(').count() {
+        word = &word[..word.len() - 1];
+        close += 1;
+    }
+    let token = match word {
+        "" => None,
+        "AND" if !had_quotes => Some(Token::And),
+        "OR" if !had_quotes => Some(Token::Or),
+        "NOT" if !had_quotes => Some(Token::Not),
+        w => Some(Token::Word(w.to_lowercase())),
+    };
+    tokens.extend(token.map(|t| (t, end - close - 1)));
+    tokens.extend((end - close..end).map(|at| (Token::Close, at)));
+}
+
+/// Recursive descent over the tokens; anything missing is left out rather
+/// than an error, as the filter is matched while it is typed.
+struct Parser {
+    tokens: Vec<Token>,
+    at: usize,
+}
+
+/// `parts` joined by `join`, or the one part alone.
+fn combine(mut parts: Vec<Expr>, join: fn(Vec<Expr>) -> Expr) -> Option<Expr> {
+    match parts.len() {
+        0 => None,
+        1 => parts.pop(),
+        _ => Some(join(parts)),
+    }
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.at)
+    }
+
+    fn eat(&mut self, token: &Token) -> bool {
+        let found = self.peek() == Some(token);
+        if found {
+            self.at += 1;
+        }
+        found
+    }
+
+    fn or(&mut self) -> Option<Expr> {
+        let mut parts: Vec<Expr> = self.and().into_iter().collect();
+        while self.eat(&Token::Or) {
+            parts.extend(self.and());
+        }
+        combine(parts, Expr::Or)
+    }
+
+    fn and(&mut self) -> Option<Expr> {
+        let mut parts = Vec::new();
+        loop {
+            match self.peek() {
+                None | Some(Token::Or) | Some(Token::Close) => break,
+                Some(Token::And) => self.at += 1,
+                Some(_) => parts.extend(self.unary()),
+            }
+        }
+        combine(parts, Expr::And)
+    }
+
+    fn unary(&mut self) -> Option<Expr> {
+        let token = self.peek()?.clone();
+        self.at += 1;
+        match token {
+            Token::Not => self.unary().map(|e| Expr::Not(Box::new(e))),
+            Token::Open => {
+                let inner = self.or();
+                self.eat(&Token::Close);
+                inner
+            }
+            Token::Word(first) => {
+                let mut words = vec![first];
+                while let Some(Token::Word(w)) = self.peek() {
+                    words.push(w.clone());
+                    self.at += 1;
+                }
+                Some(Expr::Terms(Terms::parse(words)))
+            }
+            Token::And | Token::Or | Token::Close => None,
+        }
+    }
+}
+
+impl Terms {
+    fn parse(words: Vec<String>) -> Self {
+        let mut fields = Vec::new();
+        let mut scores = Vec::new();
+        let mut times = Vec::new();
+        let mut text = Vec::new();
+        let mut unknown = Vec::new();
+        for word in words {
+            if let Some(bound) = score_bound(&word) {
+                scores.push(bound);
+                continue;
+            }
+            if let Some(bound) = time_bound(&word) {
+                times.push(bound);
+                continue;
+            }
+            match word.split_once(':').and_then(|(name, value)| Some((field(name)?, value))) {
+                Some((f, value)) if !value.is_empty() => fields.push((f, value.to_string())),
+                _ => {
+                    // Only something that reads like a field name can be a misspelt one.
+                    if let Some((name, value)) = word.split_once(':') {
+                        if !value.is_empty() && !name.is_empty() && name.chars().all(|c| c.is_alphanumeric() || c == '.' || c == '_') {
+                            unknown.push(name.to_string());
+                        }
+                    }
+                    text.push(word)
+                }
+            }
+        }
+        Self { fields, scores, times, text: text.join(" "), unknown }
+    }
+
+    fn narrows(&self, wider: &Terms) -> bool {
+        wider.fields.iter().all(|(f, want)| self.fields.iter().any(|(g, have)| f == g && have.contains(want.as_str())))
+            && wider.scores.iter().all(|bound| self.scores.contains(bound))
+            && wider.times.iter().all(|bound| self.times.contains(bound))
+            && self.text.contains(&wider.text)
+    }
+
+    fn matches(&self, nd: &NodeData) -> bool {
+        let has = |value: &str, want: &str| value.to_lowercase().contains(want);
+        self.fields.iter().all(|(f, want)| match f {
+            Field::Id => has(&nd.id, want),
+            Field::Label => has(&nd.label, want),
+            Field::Span => has(&nd.span, want),
+            Field::Ts => has(&nd.ts, want),
+            Field::Tag => nd.tags.iter().any(|t| has(t, want)),
+            Field::Source => nd.metadata.get(SOURCES_KEY).is_some_and(|v| has(v, want)),
+            Field::Meta(key) => nd.metadata.iter().any(|(k, v)| k.to_lowercase() == *key && has(v, want)),
+        }) && self.scores.iter().all(|&(cmp, bound)| nd.score.is_some_and(|score| cmp.holds(score, bound)))
+            && (self.times.is_empty() || timefmt::parse(&nd.ts).is_some_and(|ts| self.times.iter().all(|&(cmp, bound)| cmp.holds(ts, bound))))
+            && (self.text.is_empty() || nd.haystack().contains(&self.text))
+    }
+}
+
+impl Expr {
+    fn matches(&self, nd: &NodeData) -> bool {
+        match self {
+            Expr::Terms(terms) => terms.matches(nd),
+            Expr::Not(inner) => !inner.matches(nd),
+            Expr::And(parts) => parts.iter().all(|p| p.matches(nd)),
+            Expr::Or(parts) => parts.iter().any(|p| p.matches(nd)),
+        }
+    }
+
+    /// Whether every node this matches, `wider` does too. Sure answers only:
+    /// false may just mean it couldn't tell.
+    fn narrows(&self, wider: &Expr) -> bool {
+        if self == wider {
+            return true;
+        }
+        match (self, wider) {
+            (Expr::Terms(a), Expr::Terms(b)) => a.narrows(b),
+            (Expr::Not(a), Expr::Not(b)) => b.narrows(a),
+            (_, Expr::And(ws)) => ws.iter().all(|w| self.narrows(w)),
+            (Expr::Or(xs), _) => xs.iter().all(|x| x.narrows(wider)),
+            (Expr::And(xs), _) => xs.iter().any(|x| x.narrows(wider)),
+            (_, Expr::Or(ws)) => ws.iter().any(|w| self.narrows(w)),
+            _ => false,
+        }
+    }
+
+    fn terms<'a>(&'a self, out: &mut Vec<&'a Terms>) {
+        match self {
+            Expr::Terms(terms) => out.push(terms),
+            Expr::Not(inner) => inner.terms(out),
+            Expr::And(parts) | Expr::Or(parts) => parts.iter().for_each(|p| p.terms(out)),
+        }
+    }
+}
+
+impl Query {
+    pub fn parse(query: &str) -> Self {
+        if let Some(pattern) = query.trim_start().strip_prefix('~') {
+            let pattern = pattern.trim().to_lowercase();
+            return Self { expr: None, fuzzy: (!pattern.is_empty()).then_some(pattern), error: None };
+        }
+        // Pair the parentheses up; a `)` with nothing to close is passed over.
+        let mut open = Vec::new();
+        let mut stray = None;
+        let mut tokens = tokens(query);
+        tokens.retain(|(t, at)| match t {
+            Token::Open => {
+                open.push(*at);
+                true
+            }
+            Token::Close if open.is_empty() => {
+                stray = stray.or(Some(*at));
+                false
+            }
+            Token::Close => open.pop().is_some(),
+            _ => true,
+        });
+        let error = match (stray, open.first()) {
+            (Some(close), Some(&open)) if open < close => Some(format!("unmatched `(` at column {}", open + 1)),
+            (Some(close), _) => Some(format!("unmatched `)` at column {}", close + 1)),
+            (None, Some(&open)) => Some(format!("unmatched `(` at column {}", open + 1)),
+            (None, None) => None,
+        };
+        let tokens = tokens.into_iter().map(|(t, _)| t).collect();
+        Self { expr: Parser { tokens, at: 0 }.or(), fuzzy: None, error }
+    }
+
+    /// What is wrong with the query as written: an unmatched parenthesis
+    /// and its column. It still matches, leaving that parenthesis out.
+    pub fn error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.expr.is_none() && self.fuzzy.is_none()
+    }
+
+    /// A `~` query: matches are to be listed by `rank`.
+    pub fn is_fuzzy(&self) -> bool {
+        self.fuzzy.is_some()
+    }
+
+    /// How well a fuzzy query fits `nd`, its id or its label, whichever is
+    /// better; higher is better. `None` for a node it doesn't match, or for
+    /// a query that isn't fuzzy.
+    pub fn rank(&self, nd: &NodeData) -> Option<i64> {
+        let pattern = self.fuzzy.as_deref()?;
+        let score = |text: &str| fuzzy::best(pattern, text).map(|(score, _)| score);
+        score(&nd.id).max(score(&nd.label))
+    }
+
+    /// Whether every node this matches, `wider` matches too: each part of
+    /// `wider` is implied by one of these (`tag:err` by `tag:error`), as when
+    /// more of a query has been typed.
+    pub fn narrows(&self, wider: &Query) -> bool {
+        match (&self.fuzzy, &wider.fuzzy) {
+            // Every text the longer pattern is found in has the shorter one too.
+            (Some(narrow), Some(wider)) => return narrow.starts_with(wider.as_str()),
+            (None, None) => {}
+            _ => return false,
+        }
+        match (&self.expr, &wider.expr) {
+            (_, None) => true,
+            (None, Some(_)) => false,
+            (Some(narrow), Some(wider)) => narrow.narrows(wider),
+        }
+    }
+
+    pub fn matches(&self, nd: &NodeData) -> bool {
+        if self.fuzzy.is_some() {
+            return self.rank(nd).is_some();
+        }
+        self.expr.as_ref().is_none_or(|e| e.matches(nd))
+    }
+
+    fn terms(&self) -> Vec<&Terms> {
+        let mut out = Vec::new();
+        if let Some(e) = &self.expr {
+            e.terms(&mut out);
+        }
+        out
+    }
+}
+
+impl Query {
+    /// For a query `gm` has no match for: what was likely meant instead,
+    /// like "`tag:`" for `tga:` or the nearest id for an `id:` value.
+    pub fn did_you_mean(&self, gm: &GraphModel) -> Vec<String> {
+        let terms = self.terms();
+        let mut keys: BTreeSet<&str> = BTreeSet::new();
+        if terms.iter().any(|t| !t.unknown.is_empty() || t.fields.iter().any(|(f, _)| matches!(f, Field::Meta(_)))) {
+            keys.extend(gm.graph.node_weights().flat_map(|nd| nd.metadata.keys().map(String::as_str)));
+        }
+        let meta: Vec<String> = keys.iter().map(|k| format!("meta.{}", k.to_lowercase())).collect();
+        let ids = || gm.graph.node_weights().map(|nd| &*nd.id);
+        let mut out = Vec::new();
+        for t in terms {
+            for name in &t.unknown {
+                let names = FIELDS.iter().copied().chain(meta.iter().map(String::as_str));
+                if let Some(f) = suggest::closest(name, names) {
+                    out.push(format!("{}:", f));
+                }
+            }
+            for (f, value) in &t.fields {
+                match f {
+                    Field::Meta(key) if !keys.iter().any(|k| k.to_lowercase() == *key) => {
+                        if let Some(k) = suggest::closest(&format!("meta.{}", key), meta.iter().map(String::as_str)) {
+                            out.push(format!("{}:", k));
+                        }
+                    }
+                    Field::Id => out.extend(suggest::closest(value, ids()).map(|id| format!("id:{}", id))),
+                    _ => {}
+                }
+            }
+            // A lone word may be a whole id, mistyped.
+            if t.fields.is_empty() && !t.text.is_empty() && !t.text.contains(' ') {
+                out.extend(suggest::closest(&t.text, ids()).map(str::to_string));
+            }
+        }
+        out.dedup();
+        out
+    }
+}
+
+/// "did you mean `a` or `b`?", or nothing without suggestions.
+pub fn did_you_mean(suggestions: &[String]) -> Option<String> {
+    let quoted: Vec<String> = suggestions.iter().map(|s| format!("`{}`", s)).collect();
+    match quoted.as_slice() {
+        [] => None,
+        [one] => Some(format!("did you mean {}?", one)),
+        [rest @ .., last] => Some(format!("did you mean {} or {}?", rest.join(", "), last)),
+    }
+}
+
+/// `tag:NAME`, quoted when the tag has spaces.
+pub fn tag_query(tag: &str) -> String {
+    if tag.contains(char::is_whitespace) {
+        format!("tag:\"{}\"", tag)
+    } else {
+        format!("tag:{}", tag)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+
+    fn node(id: &str, label: &str, span: &str, tags: &[&str]) -> NodeData {
+        NodeData {
+            id: Arc::from(id),
+            label: label.to_string(),
+            span: Arc::from(span),
+            tags: tags.iter().map(|&t| Arc::from(t)).collect(),
+            ts: String::new(),
+            metadata: Default::default(),
+            score: None,
+            extra: Default::default(),
+            raw: Default::default(),
+        }
+    }
+
+    fn tag(t: &str) -> Expr {
+        Expr::Terms(Terms { fields: vec![(Field::Tag, t.to_string())], ..Terms::default() })
+    }
+
+    fn expr(query: &str) -> Option<Expr> {
+        Query::parse(query).expr
+    }
+
+    #[test]
+    fn and_binds_before_or() {
+        let want = Expr::Or(vec![tag("a"), Expr::And(vec![tag("b"), tag("c")])]);
+        assert_eq!(expr("tag:a OR tag:b AND tag:c"), Some(want));
+        let want = Expr::Or(vec![Expr::And(vec![tag("a"), tag("b")]), tag("c")]);
+        assert_eq!(expr("tag:a AND tag:b OR tag:c"), Some(want));
+    }
+
+    #[test]
+    fn parentheses_group_and_not_takes_the_next_group() {
+        let want = Expr::And(vec![Expr::Or(vec![tag("a"), tag("b")]), tag("c")]);
+        assert_eq!(expr("(tag:a OR tag:b) AND tag:c"), Some(want));
+        let both = Terms { fields: vec![(Field::Tag, "a".into()), (Field::Tag, "b".into())], ..Terms::default() };
+        assert_eq!(expr("NOT tag:a tag:b"), Some(Expr::Not(Box::new(Expr::Terms(both)))));
+        assert_eq!(expr("NOT tag:a OR tag:b"), Some(Expr::Or(vec![Expr::Not(Box::new(tag("a"))), tag("b")])));
+    }
+
+    #[test]
+    fn unfinished_operators_are_left_out() {
+        assert_eq!(expr("tag:a AND"), Some(tag("a")));
+        assert_eq!(expr("tag:a OR"), Some(tag("a")));
+        assert_eq!(expr("tag:a NOT"), Some(tag("a")));
+        assert_eq!(expr("(tag:a"), Some(tag("a")));
+        assert_eq!(expr("OR"), None);
+    }
+
+    #[test]
+    fn unmatched_parentheses_are_reported_with_their_column() {
+        let error = |q: &str| Query::parse(q).error;
+        assert_eq!(error("(tag:a"), Some("unmatched `(` at column 1".to_string()));
+        assert_eq!(error("tag:a AND (tag:b OR (tag:c)"), Some("unmatched `(` at column 11".to_string()));
+        assert_eq!(error("tag:a) OR tag:b"), Some("unmatched `)` at column 6".to_string()));
+        assert_eq!(error("(tag:a)) OR tag:b"), Some("unmatched `)` at column 8".to_string()));
+        assert_eq!(error(") ("), Some("unmatched `)` at column 1".to_string()));
+        assert_eq!(error("( )"), None);
+        assert_eq!(error("(tag:a) OR (tag:b)"), None);
+        assert_eq!(error("f(x) \"(\""), None);
+    }
+
+    #[test]
+    fn a_stray_close_is_passed_over() {
+        assert_eq!(expr("tag:a) OR tag:b"), expr("tag:a OR tag:b"));
+        assert_eq!(expr(") tag:a"), Some(tag("a")));
+        assert_eq!(expr("(tag:a)) OR tag:b"), expr("tag:a OR tag:b"));
+        // A `)` the word opens itself is part of it.
+        let call = Terms { text: "f(x)".into(), ..Terms::default() };
+        assert_eq!(expr("f(x)"), Some(Expr::Terms(call)));
+    }
+
+    #[test]
+    fn operators_are_words_when_quoted_or_lower_case() {
+        let text = |t: &str| Some(Expr::Terms(Terms { text: t.into(), ..Terms::default() }));
+        assert_eq!(expr("\"OR\""), text("or"));
+        assert_eq!(expr("this or that"), text("this or that"));
+    }
+
+    #[test]
+    fn narrowing_holds_for_typed_on_queries() {
+        let narrows = |a: &str, b: &str| Query::parse(a).narrows(&Query::parse(b));
+        assert!(narrows("tag:error", "tag:err"));
+        assert!(narrows("tag:err span:db", "tag:err"));
+        assert!(narrows("tag:a AND tag:b", "tag:a"));
+        assert!(narrows("tag:a", "tag:a OR tag:b"));
+        assert!(narrows("NOT tag:a", "NOT tag:ab"));
+        assert!(narrows("~plnr", "~pl"));
+        assert!(narrows("anything", ""));
+        assert!(!narrows("tag:a OR tag:b", "tag:a"));
+        assert!(!narrows("NOT tag:ab", "NOT tag:a"));
+        assert!(!narrows("NOT tag:a", "tag:a"));
+        assert!(!narrows("~pl", "tag:pl"));
+    }
+
+    /// Every pair `narrows` vouches for is checked against nodes that tell
+    /// the parts apart, so a wrong `true` shows up as a node lost.
+    #[test]
+    fn narrowing_never_loses_a_match() {
+        let queries = [
+            "", "tag:a", "tag:ab", "tag:b", "span:x", "tag:a span:x", "tag:a AND tag:b", "tag:a OR tag:b", "tag:ab OR tag:b",
+            "NOT tag:a", "NOT tag:ab", "NOT tag:a tag:b", "NOT (tag:a OR tag:b)", "NOT tag:a AND NOT tag:b", "(tag:a OR tag:b) span:x",
+            "tag:a OR (tag:b span:x)", "NOT NOT tag:a", "plan", "planner", "plan OR tag:a",
+        ];
+        let mut nodes = Vec::new();
+        for tags in [&[][..], &["a"], &["ab"], &["b"], &["a", "b"], &["ab", "b"]] {
+            for span in ["", "x"] {
+                for label in ["", "planner"] {
+                    nodes.push(node("n", label, span, tags));
+                }
+            }
+        }
+        for narrow in queries.map(Query::parse) {
+            for wider in queries.map(Query::parse) {
+                if narrow.narrows(&wider) {
+                    for nd in nodes.iter().filter(|nd| narrow.matches(nd)) {
+                        assert!(wider.matches(nd), "{:?} narrows {:?} but loses {:?}", narrow, wider, nd);
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,148 @@
+//! Redacted exports (`--redact`): the graph keeps its shape, but what the
+//! nodes say is replaced, so it can go into a public bug report.
+//!
+//! Set in the config's `[redact]` table; the values shown are the defaults:
+//!
+//! ```toml
+//! [redact]
+//! fields = ["label", "span", "metadata"] # also "id", "tags", "ts" and "meta.KEY"
+//! keep_metadata = []                     # metadata keys left as they are
+//! ```
+//!
+//! Fields of the producer's own beside the known ones count as metadata:
+//! `metadata` redacts them too, and `meta.KEY` and `keep_metadata` name
+//! them by their key.
+//!
+//! ```toml
+//! style = "hash"                         # or "placeholder"
+//! salt = ""
+//! ```
+//!
+//! A hash stands for the same text wherever it appears, so repeated labels
+//! still look alike; a placeholder hides even that. Ids, when redacted, are
+//! always hashed so edges still join the right nodes, and redacted
+//! timestamps are left out. Without a `salt`, short values can be guessed by
+//! hashing candidates, so set one before sharing.
+use std::{borrow::Cow, sync::Arc};
+
+use itertools::Itertools;
+
+use crate::{NodeData, Payload};
+
+/// What stands in for a redacted value under `style = "placeholder"`.
+const PLACEHOLDER: &str = "[redacted]";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Style {
+    #[default]
+    Hash,
+    Placeholder,
+}
+
+#[derive(Debug, Clone, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RedactConfig {
+    pub fields: Vec<String>,
+    pub keep_metadata: Vec<String>,
+    pub style: Style,
+    pub salt: String,
+}
+
+impl Default for RedactConfig {
+    fn default() -> Self {
+        Self {
+            fields: ["label", "span", "metadata"].map(String::from).to_vec(),
+            keep_metadata: Vec::new(),
+            style: Style::Hash,
+            salt: String::new(),
+        }
+    }
+}
+
+impl RedactConfig {
+    /// Reject the field names this doesn't know, so a typo doesn't leak data.
+    pub fn check(&self) -> Result<(), String> {
+        match self.fields.iter().find(|f| !matches!(f.as_str(), "id" | "label" | "span" | "tags" | "ts" | "metadata") && !f.starts_with("meta.")) {
+            Some(f) => Err(format!("redact: unknown field `{}` (id, label, span, tags, ts, metadata or meta.KEY)", f)),
+            None => Ok(()),
+        }
+    }
+
+    fn redacts(&self, field: &str) -> bool {
+        self.fields.iter().any(|f| f == field)
+    }
+
+    fn redacts_meta(&self, key: &str) -> bool {
+        !self.keep_metadata.iter().any(|k| k == key)
+            && (self.redacts("metadata") || self.fields.iter().any(|f| f.strip_prefix("meta.") == Some(key)))
+    }
+
+    /// FNV-1a over the salt and `value`, mixed so that similar values
+    /// don't share digits, as 16 hex digits.
+    fn hash(&self, value: &str) -> String {
+        let mut h: u64 = 0xcbf2_9ce4_8422_2325;
+        for b in self.salt.bytes().chain([0]).chain(value.bytes()) {
+            h ^= u64::from(b);
+            h = h.wrapping_mul(0x0100_0000_01b3);
+        }
+        h = (h ^ (h >> 33)).wrapping_mul(0xff51_afd7_ed55_8ccd);
+        h = (h ^ (h >> 33)).wrapping_mul(0xc4ce_b9fe_1a85_ec53);
+        format!("{:016x}", h ^ (h >> 33))
+    }
+
+    fn text(&self, value: &str) -> String {
+        match self.style {
+            _ if value.is_empty() => String::new(),
+            Style::Hash => self.hash(value),
+            Style::Placeholder => PLACEHOLDER.to_string(),
+        }
+    }
+
+    /// A node id as exported: hashed when ids are redacted.
+    pub fn id<'a>(&self, id: &'a str) -> Cow<'a, str> {
+        if self.redacts("id") {
+            Cow::Owned(self.hash(id))
+        } else {
+            Cow::Borrowed(id)
+        }
+    }
+
+    /// An edge's timestamp as exported: left out when timestamps are redacted.
+    pub fn edge_ts<'a>(&self, ts: &'a str) -> &'a str {
+        if self.redacts("ts") {
+            ""
+        } else {
+            ts
+        }
+    }
+
+    pub fn node(&self, nd: &NodeData) -> NodeData {
+        let field = |name: &str, value: &str| if self.redacts(name) { self.text(value) } else { value.to_string() };
+        NodeData {
+            id: Arc::from(self.id(&nd.id)),
+            label: field("label", &nd.label),
+            span: Arc::from(field("span", &nd.span)),
+            tags: if self.redacts("tags") { nd.tags.iter().map(|t| self.text(t)).unique().map(Arc::from).collect() } else { nd.tags.clone() },
+            ts: if self.redacts("ts") { String::new() } else { nd.ts.clone() },
+            metadata: nd
+                .metadata
+                .iter()
+                .map(|(k, v)| (k.clone(), if self.redacts_meta(k) { self.text(v) } else { v.clone() }))
+                .collect(),
+            score: nd.score,
+            // The producer's own fields are metadata by another name.
+            extra: nd
+                .extra
+                .iter()
+                .map(|(k, v)| match v {
+                    _ if !self.redacts_meta(k) => (k.clone(), v.clone()),
+                    serde_json::Value::String(s) => (k.clone(), self.text(s).into()),
+                    other => (k.clone(), self.text(&other.to_string()).into()),
+                })
+                .collect(),
+            // Unredacted, the event would give away what was redacted.
+            raw: Payload::default(),
+        }
+    }
+}
@@ -0,0 +1,80 @@
+//! Find and replace (`F`): a regex rewritten in every node's label and tags,
+//! say to scrub customer names before a graph leaves the team. The
+//! replacement can refer to groups (`$1`, `${name}`) as in
+//! `Regex::replace_all`. The nodes it would change are previewed while the
+//! pattern is typed; nothing changes until Enter.
+//!
+//! A tag replaced by nothing is dropped, and one that becomes a tag the node
+//! already has is merged into it.
+use std::sync::Arc;
+
+use regex::Regex;
+
+use crate::{intern, NodeData};
+
+/// Nodes the preview lists at most; the rest are only counted.
+pub const PREVIEW_LIMIT: usize = 200;
+
+#[derive(Debug, Default)]
+pub struct ReplacePrompt {
+    pub find: String,
+    pub with: String,
+    pub editing_with: bool, // Tab moves between the two fields
+}
+
+impl ReplacePrompt {
+    pub fn push(&mut self, ch: char) {
+        self.field().push(ch);
+    }
+
+    pub fn pop(&mut self) {
+        self.field().pop();
+    }
+
+    fn field(&mut self) -> &mut String {
+        if self.editing_with {
+            &mut self.with
+        } else {
+            &mut self.find
+        }
+    }
+
+    /// The compiled pattern; `None` while nothing is typed.
+    pub fn regex(&self) -> Result<Option<Regex>, String> {
+        if self.find.is_empty() {
+            return Ok(None);
+        }
+        Regex::new(&self.find).map(Some).map_err(|e| format!("invalid pattern: {}", e))
+    }
+}
+
+/// `nd` with `re` replaced in its label and tags, or `None` if nothing matched.
+pub fn rewrite(re: &Regex, with: &str, nd: &NodeData) -> Option<NodeData> {
+    let label = re.replace_all(&nd.label, with);
+    let mut tags: Vec<Arc<str>> = Vec::with_capacity(nd.tags.len());
+    for tag in &nd.tags {
+        let tag = re.replace_all(tag, with);
+        if !tag.is_empty() && !tags.iter().any(|t| **t == *tag) {
+            tags.push(intern::intern(&tag));
+        }
+    }
+    if label == nd.label && tags == nd.tags {
+        return None;
+    }
+    let mut new = nd.clone();
+    new.label = label.into_owned();
+    new.tags = tags;
+    Some(new)
+}
+
+/// What changes between `old` and `new`, a line per field, for the preview.
+pub fn describe(old: &NodeData, new: &NodeData) -> Vec<String> {
+    let mut lines = Vec::new();
+    if old.label != new.label {
+        lines.push(format!("{}  label: '{}' → '{}'", old.id, old.label, new.label));
+    }
+    if old.tags != new.tags {
+        lines.push(format!("{}  tags: {} → {}", old.id, old.tags.join(", "), new.tags.join(", ")));
+    }
+    lines
+}
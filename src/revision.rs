@@ -0,0 +1,89 @@
+//! Earlier versions of re-emitted nodes, and diffs between them (`v`).
+//!
+//! Whenever a redefinition changes a node, the version it replaces is kept
+//! (the last [`KEEP`] per node) with the time it was replaced. [`diff`]
+//! compares two versions line by line, the way `diff -u` would: the label
+//! (one line per line of text), span and ts, then one line per tag and per
+//! metadata entry, so a tag or key that came or went shows up on its own.
+use std::{
+    collections::{HashMap, VecDeque},
+    time::Instant,
+};
+
+use crate::NodeData;
+
+/// Versions remembered per node, besides the current one.
+const KEEP: usize = 32;
+
+#[derive(Debug, Clone)]
+pub struct Revision {
+    pub node: NodeData,
+    pub replaced: Instant,
+}
+
+#[derive(Debug, Default)]
+pub struct Revisions {
+    by_node: HashMap<String, VecDeque<Revision>>,
+}
+
+impl Revisions {
+    /// Keep `old`, which a redefinition just replaced.
+    pub fn record(&mut self, old: NodeData) {
+        let kept = self.by_node.entry(old.id.to_string()).or_default();
+        if kept.len() == KEEP {
+            kept.pop_front();
+        }
+        kept.push_back(Revision { node: old, replaced: Instant::now() });
+    }
+
+    /// The earlier versions of `id`, oldest first.
+    pub fn of(&self, id: &str) -> Vec<Revision> {
+        self.by_node.get(id).map_or_else(Vec::new, |kept| kept.iter().cloned().collect())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DiffLine {
+    Same(String),
+    Removed(String),
+    Added(String),
+}
+
+fn lines(nd: &NodeData) -> Vec<String> {
+    let mut out: Vec<String> = nd.label.lines().map(|l| format!("label: {}", l)).collect();
+    out.push(format!("span: {}", nd.span));
+    out.push(format!("ts: {}", nd.ts));
+    out.extend(nd.score.map(|s| format!("score: {}", s)));
+    out.extend(nd.tags.iter().map(|t| format!("tag: {}", t)));
+    out.extend(nd.metadata.iter().map(|(k, v)| format!("metadata.{}: {}", k, v)));
+    out.extend(nd.extra.iter().map(|(k, v)| format!("{}: {}", k, v)));
+    out
+}
+
+/// `old` against `new`, line by line (longest common subsequence).
+pub fn diff(old: &NodeData, new: &NodeData) -> Vec<DiffLine> {
+    let (a, b) = (lines(old), lines(new));
+    // common[i][j]: the longest common subsequence of a[i..] and b[j..].
+    let mut common = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            common[i][j] = if a[i] == b[j] { common[i + 1][j + 1] + 1 } else { common[i + 1][j].max(common[i][j + 1]) };
+        }
+    }
+    let (mut i, mut j) = (0, 0);
+    let mut out = Vec::new();
+    while i < a.len() || j < b.len() {
+        if i < a.len() && j < b.len() && a[i] == b[j] {
+            out.push(DiffLine::Same(a[i].clone()));
+            i += 1;
+            j += 1;
+        } else if i < a.len() && (j == b.len() || common[i + 1][j] >= common[i][j + 1]) {
+            out.push(DiffLine::Removed(a[i].clone()));
+            i += 1;
+        } else {
+            out.push(DiffLine::Added(b[j].clone()));
+            j += 1;
+        }
+    }
+    out
+}
@@ -0,0 +1,169 @@
+//! `--sample`: explore a representative subgraph of an enormous input.
+//!
+//! The whole graph is still loaded (so degrees and roots are exact), but
+//! each tab only displays `N` nodes picked by a strategy, plus whatever the
+//! user expanded with `x`:
+//!
+//! - `top-degree:N` — the best-connected nodes;
+//! - `random:N` — a uniform pick, stable as the graph grows (by id hash);
+//! - `bfs:N` — breadth-first from the roots, so the sample stays connected.
+//!
+//! The sample is rebuilt from the full graph whenever it changed, at most
+//! every [`RESAMPLE_EVERY`] while events are still flowing.
+use std::{
+    collections::{hash_map::DefaultHasher, HashSet, VecDeque},
+    fmt,
+    hash::{Hash, Hasher},
+    str::FromStr,
+    time::{Duration, Instant},
+};
+
+use petgraph::stable_graph::NodeIndex;
+use petgraph::Direction::Incoming;
+
+use crate::GraphModel;
+
+/// How often a sample is rebuilt while its source is still streaming.
+pub const RESAMPLE_EVERY: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Strategy {
+    TopDegree,
+    Random,
+    Bfs,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Sample {
+    pub strategy: Strategy,
+    pub limit: usize,
+}
+
+impl FromStr for Sample {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        let (name, limit) = s.split_once(':').ok_or_else(|| format!("expected STRATEGY:N, got `{}`", s))?;
+        let strategy = match name {
+            "top-degree" => Strategy::TopDegree,
+            "random" => Strategy::Random,
+            "bfs" => Strategy::Bfs,
+            other => return Err(format!("unknown sampling strategy `{}` (expected top-degree, random or bfs)", other)),
+        };
+        let limit: usize = limit.parse().map_err(|_| format!("invalid sample size `{}`", limit))?;
+        if limit == 0 {
+            return Err("sample size must be at least 1".to_string());
+        }
+        Ok(Self { strategy, limit })
+    }
+}
+
+impl fmt::Display for Sample {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self.strategy {
+            Strategy::TopDegree => "top-degree",
+            Strategy::Random => "random",
+            Strategy::Bfs => "bfs",
+        };
+        write!(f, "{}:{}", name, self.limit)
+    }
+}
+
+/// The full graph behind a sampled tab.
+#[derive(Debug)]
+pub struct Sampled {
+    pub sample: Sample,
+    pub full: GraphModel,
+    pub stale: bool,                   // `full` changed since the last rebuild
+    pub built: Option<Instant>,        // when the shown sample was last rebuilt
+    expanded: HashSet<String>,         // ids pulled in with `x`, shown regardless
+}
+
+impl Sampled {
+    pub fn new(sample: Sample, full: GraphModel) -> Self {
+        Self { sample, full, stale: true, built: None, expanded: HashSet::new() }
+    }
+
+    /// Add `id` and its neighbours in the full graph to the sample.
+    pub fn expand(&mut self, id: &str) {
+        let Some(&idx) = self.full.indices.get(id) else { return };
+        let around: Vec<NodeIndex> = self.full.parents_of(idx).into_iter().chain(self.full.children_of(idx)).collect();
+        self.expanded.insert(id.to_string());
+        self.expanded.extend(around.into_iter().map(|n| self.full.graph[n].id.to_string()));
+        self.stale = true;
+    }
+
+    /// The subgraph to display: the strategy's pick plus expanded nodes,
+    /// with every edge between them, in the full graph's order.
+    pub fn build(&mut self) -> GraphModel {
+        self.stale = false;
+        self.built = Some(Instant::now());
+        let g = &self.full.graph;
+        let limit = self.sample.limit;
+        let mut picked: Vec<NodeIndex> = match self.sample.strategy {
+            Strategy::TopDegree => {
+                let mut all: Vec<NodeIndex> = self.full.stable_order().collect();
+                if all.len() > limit {
+                    all.select_nth_unstable_by_key(limit, |&i| std::cmp::Reverse(g.neighbors_undirected(i).count()));
+                    all.truncate(limit);
+                }
+                all
+            }
+            Strategy::Random => {
+                let hash = |i: NodeIndex| {
+                    let mut h = DefaultHasher::new();
+                    g[i].id.hash(&mut h);
+                    h.finish()
+                };
+                let mut all: Vec<NodeIndex> = self.full.stable_order().collect();
+                if all.len() > limit {
+                    all.select_nth_unstable_by_key(limit, |&i| hash(i));
+                    all.truncate(limit);
+                }
+                all
+            }
+            Strategy::Bfs => {
+                let mut seen = HashSet::new();
+                let mut order = Vec::new();
+                // Roots first; nodes only reachable through cycles seed later walks.
+                let starts = self.full.stable_order().filter(|&i| g.neighbors_directed(i, Incoming).next().is_none()).chain(self.full.stable_order());
+                let mut queue = VecDeque::new();
+                for start in starts {
+                    if order.len() >= limit {
+                        break;
+                    }
+                    if seen.insert(start) {
+                        queue.push_back(start);
+                    }
+                    while let Some(n) = queue.pop_front() {
+                        order.push(n);
+                        if order.len() >= limit {
+                            break;
+                        }
+                        for next in self.full.children_of(n) {
+                            if seen.insert(next) {
+                                queue.push_back(next);
+                            }
+                        }
+                    }
+                }
+                order
+            }
+        };
+        picked.extend(self.expanded.iter().filter_map(|id| self.full.indices.get(id.as_str()).copied()));
+        picked.sort();
+        picked.dedup();
+        let kept: HashSet<NodeIndex> = picked.iter().copied().collect();
+
+        let mut gm = GraphModel::new();
+        for &idx in &picked {
+            gm.upsert_node(&g[idx].id, g[idx].clone());
+        }
+        for e in g.edge_indices() {
+            if let Some((a, b)) = g.edge_endpoints(e).filter(|(a, b)| kept.contains(a) && kept.contains(b)) {
+                gm.add_edge(&g[a].id, &g[b].id, g[e].clone());
+            }
+        }
+        gm
+    }
+}
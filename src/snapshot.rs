@@ -0,0 +1,109 @@
+//! Manual snapshots of a live graph, for looking back at what it was and
+//! at what has arrived since.
+//!
+//! `s` freezes a copy of the active tab's full graph. From the snapshots
+//! pane (`S`) the view can be rolled back to any of them while ingestion
+//! keeps applying to the live graph underneath, or the live graph can be
+//! diffed against one: nodes added or changed since are marked in the node
+//! list and the totals go to the status bar.
+use std::{
+    collections::HashMap,
+    time::Instant,
+};
+
+use crate::GraphModel;
+
+pub struct Snapshot {
+    pub taken: Instant,
+    pub graph: GraphModel, // nodes and edges only
+}
+
+/// How a node of the live graph differs from the snapshot it is diffed
+/// against (or, for `diff`, a file from the one before it).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Change {
+    Added,
+    Changed,
+    Removed, // only `diff` shows nodes that are gone
+}
+
+/// The live graph against one snapshot.
+#[derive(Debug, Default)]
+pub struct Diff {
+    pub against: usize, // index into the tab's snapshots
+    pub stale: bool,    // the live graph changed since the last `compute`
+    nodes: HashMap<String, Change>,
+    added: usize,
+    changed: usize,
+    removed: usize,
+    edges_added: usize,
+    edges_removed: usize,
+}
+
+/// Edges by endpoint ids and kind, with how many of each there are.
+pub fn edge_counts(gm: &GraphModel) -> HashMap<(&str, &str, &str), usize> {
+    let mut counts = HashMap::new();
+    for e in gm.graph.edge_indices() {
+        let (a, b) = gm.graph.edge_endpoints(e).expect("edge index is live");
+        *counts.entry((&*gm.graph[a].id, &*gm.graph[b].id, gm.graph[e].kind.as_str())).or_default() += 1;
+    }
+    counts
+}
+
+/// `12s ago`, `5m 3s ago`, `2h 10m ago`.
+pub fn ago(t: Instant) -> String {
+    let secs = t.elapsed().as_secs();
+    match secs {
+        0..=59 => format!("{}s ago", secs),
+        60..=3599 => format!("{}m {}s ago", secs / 60, secs % 60),
+        _ => format!("{}h {}m ago", secs / 3600, secs % 3600 / 60),
+    }
+}
+
+impl Snapshot {
+    pub fn new(gm: &GraphModel) -> Self {
+        Self { taken: Instant::now(), graph: gm.freeze() }
+    }
+}
+
+impl Diff {
+    pub fn new(against: usize) -> Self {
+        Self { against, stale: true, ..Self::default() }
+    }
+
+    pub fn compute(&mut self, before: &GraphModel, after: &GraphModel) {
+        self.stale = false;
+        self.nodes.clear();
+        for nd in after.graph.node_weights() {
+            let change = match before.indices.get(&nd.id) {
+                None => Change::Added,
+                Some(&idx) if !before.graph[idx].same_fields(nd) => Change::Changed,
+                Some(_) => continue,
+            };
+            self.nodes.insert(nd.id.to_string(), change);
+        }
+        self.added = self.nodes.values().filter(|&&c| c == Change::Added).count();
+        self.changed = self.nodes.len() - self.added;
+        self.removed = before.indices.keys().filter(|id| !after.indices.contains_key(*id)).count();
+        let (old, new) = (edge_counts(before), edge_counts(after));
+        self.edges_added = new.iter().map(|(k, &n)| n.saturating_sub(old.get(k).copied().unwrap_or(0))).sum();
+        self.edges_removed = old.iter().map(|(k, &n)| n.saturating_sub(new.get(k).copied().unwrap_or(0))).sum();
+    }
+
+    pub fn change(&self, id: &str) -> Option<Change> {
+        self.nodes.get(id).copied()
+    }
+
+    /// `since #2: +12 ~3 -0 nodes, +20 -0 edges`
+    pub fn summary(&self) -> String {
+        format!(
+            "since #{}: +{} ~{} -{} nodes, +{} -{} edges",
+            self.against + 1,
+            self.added,
+            self.changed,
+            self.removed,
+            self.edges_added,
+            self.edges_removed
+        )
+    }
+}
@@ -0,0 +1,106 @@
+//! `riff-dag-tui stats` and the stats pane (`I`): the shape of a graph at a
+//! glance, for when it is too big to take in from the canvas.
+//!
+//! Counted over the whole graph: nodes, edges, roots, leaves, the longest
+//! path from a root, the node with the most children, how many nodes there
+//! are of each type (as the canvas classifies them by tag) and how many
+//! carry each tag, most used first.
+use serde_json::json;
+
+use crate::{classify_node_type, metrics::Metrics, GraphModel, NodeType};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum StatsFormat {
+    Text,
+    Json,
+}
+
+#[derive(Debug, Default)]
+pub struct Stats {
+    pub nodes: usize,
+    pub edges: usize,
+    pub roots: usize,
+    pub leaves: usize,
+    pub max_depth: usize,                     // edges on the longest path from a root, cycles aside unless broken
+    pub max_fan_out: Option<(String, usize)>, // the node with the most children, and how many
+    pub types: Vec<(NodeType, usize)>,        // every type, in `NodeType::ALL` order
+    pub tags: Vec<(String, usize)>,           // most used first, then by name
+}
+
+/// A type's name as listed; untyped nodes have none of their own.
+fn type_name(t: NodeType) -> &'static str {
+    match t.name() {
+        "" => "other",
+        name => name,
+    }
+}
+
+impl Stats {
+    /// With `break_cycles`, depths run through cycles.
+    pub fn measure(gm: &GraphModel, break_cycles: bool) -> Self {
+        let m = Metrics::measure(gm, break_cycles);
+        let mut types: Vec<(NodeType, usize)> = NodeType::ALL.iter().map(|&t| (t, 0)).collect();
+        let mut tags = std::collections::HashMap::<&str, usize>::new();
+        for nd in gm.graph.node_weights() {
+            let t = classify_node_type(&nd.tags);
+            if let Some(entry) = types.iter_mut().find(|(u, _)| *u == t) {
+                entry.1 += 1;
+            }
+            for tag in &nd.tags {
+                *tags.entry(tag).or_default() += 1;
+            }
+        }
+        let mut tags: Vec<(String, usize)> = tags.into_iter().map(|(t, n)| (t.to_string(), n)).collect();
+        tags.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        // Ties go to the node listed first.
+        let max_fan_out = gm
+            .stable_order()
+            .map(|idx| (idx, gm.graph.neighbors_directed(idx, petgraph::Direction::Outgoing).count()))
+            .filter(|&(_, n)| n > 0)
+            .fold(None, |best: Option<(_, usize)>, (idx, n)| if best.is_some_and(|(_, m)| m >= n) { best } else { Some((idx, n)) })
+            .map(|(idx, n)| (gm.graph[idx].id.to_string(), n));
+        Self { nodes: m.nodes, edges: m.edges, roots: m.roots, leaves: m.leaves, max_depth: m.max_depth, max_fan_out, types, tags }
+    }
+
+    /// As the pane and `--format text` show it.
+    pub fn lines(&self) -> Vec<String> {
+        let mut out = vec![
+            format!("nodes        {}", self.nodes),
+            format!("edges        {}", self.edges),
+            format!("roots        {}", self.roots),
+            format!("leaves       {}", self.leaves),
+            format!("max depth    {}", self.max_depth),
+            match &self.max_fan_out {
+                Some((id, n)) => format!("max fan-out  {} ({})", n, id),
+                None => "max fan-out  0".to_string(),
+            },
+            String::new(),
+            "types".to_string(),
+        ];
+        out.extend(self.types.iter().map(|&(t, n)| format!("  {:<11}{}", type_name(t), n)));
+        out.push(String::new());
+        out.push(format!("tags ({} distinct)", self.tags.len()));
+        if self.tags.is_empty() {
+            out.push("  (none)".to_string());
+        }
+        let width = self.tags.iter().map(|(t, _)| t.chars().count()).max().unwrap_or(0).max(10) + 1;
+        out.extend(self.tags.iter().map(|(t, n)| format!("  {:<width$}{}", t, n, width = width)));
+        out
+    }
+
+    pub fn json(&self, input: &str) -> String {
+        json!({
+            "input": input,
+            "nodes": self.nodes,
+            "edges": self.edges,
+            "roots": self.roots,
+            "leaves": self.leaves,
+            "max_depth": self.max_depth,
+            "max_fan_out": self.max_fan_out.as_ref().map_or(json!(0), |(_, n)| json!(n)),
+            "max_fan_out_node": self.max_fan_out.as_ref().map(|(id, _)| id),
+            "types": self.types.iter().map(|&(t, n)| (type_name(t).to_string(), json!(n))).collect::<serde_json::Map<_, _>>(),
+            "tags": self.tags.iter().map(|(t, n)| (t.clone(), json!(n))).collect::<serde_json::Map<_, _>>(),
+        })
+        .to_string()
+    }
+}
@@ -0,0 +1,52 @@
+//! "Did you mean": the closest of some known words to one that matched
+//! nothing, by edit distance (Levenshtein, plus swapped neighbours). Node
+//! ids often differ by a character or two, so a typo would otherwise just
+//! leave the list empty.
+
+/// Edits (insertions, deletions, substitutions, swaps) a suggestion may be away.
+const MAX_EDITS: usize = 2;
+
+/// The edit distance between `a` and `b`, counting a swap of two
+/// neighbouring characters as one edit (`tga` is one from `tag`), or `None`
+/// once it is certainly more than `max`.
+fn distance(a: &str, b: &str, max: usize) -> Option<usize> {
+    let (a, b): (Vec<char>, Vec<char>) = (a.chars().collect(), b.chars().collect());
+    if a.len().abs_diff(b.len()) > max {
+        return None;
+    }
+    // d[i][j]: the distance between a[..i] and b[..j].
+    let mut d = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+    for (j, cell) in d[0].iter_mut().enumerate() {
+        *cell = j;
+    }
+    for i in 1..=a.len() {
+        d[i][0] = i;
+        for j in 1..=b.len() {
+            let mut best = (d[i - 1][j - 1] + usize::from(a[i - 1] != b[j - 1])).min(d[i - 1][j] + 1).min(d[i][j - 1] + 1);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                best = best.min(d[i - 2][j - 2] + 1);
+            }
+            d[i][j] = best;
+        }
+        if d[i].iter().min().is_some_and(|&m| m > max) {
+            return None;
+        }
+    }
+    Some(d[a.len()][b.len()]).filter(|&d| d <= max)
+}
+
+/// The candidate nearest `word` (ignoring case), if one is close enough
+/// and isn't `word` itself; the first listed wins a tie.
+pub fn closest<'a>(word: &str, candidates: impl IntoIterator<Item = &'a str>) -> Option<&'a str> {
+    let word = word.to_lowercase();
+    // A one-letter word is a typo of anything.
+    let max = MAX_EDITS.min(word.chars().count().saturating_sub(1));
+    let mut best: Option<(usize, &str)> = None;
+    for candidate in candidates {
+        let Some(d) = distance(&word, &candidate.to_lowercase(), max).filter(|&d| d > 0) else { continue };
+        if best.is_none_or(|(b, _)| d < b) {
+            best = Some((d, candidate));
+        }
+    }
+    best.map(|(_, c)| c)
+}
@@ -0,0 +1,70 @@
+//! The prompt for editing tags: `+` adds one to the selected node, `R` in
+//! the tags pane renames one on every node. Tab completes from the tags the
+//! graph already has, most used first; pressing it again moves on to the
+//! next match.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TagAction {
+    Add,
+    Rename(String), // the tag being renamed
+}
+
+#[derive(Debug)]
+pub struct TagPrompt {
+    pub action: TagAction,
+    pub input: String,
+    completing: Option<(String, usize)>, // what was typed, and which match Tab last filled in
+}
+
+/// Suggestions listed after the input.
+const SUGGESTIONS: usize = 5;
+
+/// Tags of `cloud` (most used first) starting with `prefix`.
+fn matches<'a>(cloud: &'a [(String, usize)], prefix: &'a str) -> impl Iterator<Item = &'a str> {
+    cloud.iter().map(|(tag, _)| tag.as_str()).filter(move |tag| tag.starts_with(prefix))
+}
+
+impl TagPrompt {
+    pub fn new(action: TagAction) -> Self {
+        let input = match &action {
+            TagAction::Add => String::new(),
+            TagAction::Rename(old) => old.clone(),
+        };
+        Self { action, input, completing: None }
+    }
+
+    pub fn push(&mut self, ch: char) {
+        self.completing = None;
+        self.input.push(ch);
+    }
+
+    pub fn pop(&mut self) {
+        self.completing = None;
+        self.input.pop();
+    }
+
+    /// Fill in the next tag of `cloud` that starts with what was typed.
+    pub fn complete(&mut self, cloud: &[(String, usize)]) {
+        let (typed, next) = match self.completing.take() {
+            Some((typed, i)) => (typed, i + 1),
+            None => (self.input.clone(), 0),
+        };
+        let found: Vec<&str> = matches(cloud, &typed).collect();
+        if found.is_empty() {
+            return;
+        }
+        let i = next % found.len();
+        self.input = found[i].to_string();
+        self.completing = Some((typed, i));
+    }
+
+    /// The first few tags Tab would fill in.
+    pub fn suggestions(&self, cloud: &[(String, usize)]) -> Vec<String> {
+        let typed = self.completing.as_ref().map_or(self.input.as_str(), |(typed, _)| typed.as_str());
+        matches(cloud, typed).take(SUGGESTIONS).map(str::to_string).collect()
+    }
+
+    /// The tag to apply: the input without surrounding whitespace.
+    pub fn tag(&self) -> Option<&str> {
+        Some(self.input.trim()).filter(|t| !t.is_empty())
+    }
+}
@@ -0,0 +1,160 @@
+//! `label_template`: how each node is named in the node list and the
+//! details pane.
+//!
+//! `{...}` placeholders are replaced by node fields and everything else is
+//! copied as is (`{{` and `}}` for literal braces). Fields are `id`, `label`,
+//! `span`, `ts`, `tags`, `type` (prompt, response, tool, error or event, from
+//! the tags, as on the canvas) and `meta.KEY`; `{label|id}` takes the first
+//! that isn't empty; `ts` is shown as `time_zone`/`time_format` say. A field
+//! can be shortened: `:short` keeps a timestamp's time of day (or its
+//! relative time) and the first 8 characters of anything else, `:N` at most N
+//! characters. A placeholder that comes out empty takes the space after it
+//! along, so `"{ts:short} {type} {label|id}"` leaves no gaps.
+use std::str::FromStr;
+
+use crate::{classify_node_type, timefmt::TimeDisplay, NodeData};
+
+#[derive(Debug, Clone)]
+enum Field {
+    Id,
+    Label,
+    Span,
+    Ts,
+    Tags,
+    Type,
+    Meta(String),
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Shorten {
+    Full,
+    Short,
+    Chars(usize),
+}
+
+#[derive(Debug, Clone)]
+enum Part {
+    Text(String),
+    Fields(Vec<(Field, Shorten)>), // alternatives, first non-empty wins
+}
+
+#[derive(Debug, Clone)]
+pub struct LabelTemplate {
+    parts: Vec<Part>,
+}
+
+fn parse_field(spec: &str) -> Result<(Field, Shorten), String> {
+    let (name, shorten) = match spec.split_once(':') {
+        Some((name, "short")) => (name, Shorten::Short),
+        Some((name, n)) => match n.parse() {
+            Ok(n) => (name, Shorten::Chars(n)),
+            Err(_) => return Err(format!("unknown modifier `{}` (expected short or a length)", n)),
+        },
+        None => (spec, Shorten::Full),
+    };
+    let field = match name.trim() {
+        "id" => Field::Id,
+        "label" => Field::Label,
+        "span" => Field::Span,
+        "ts" => Field::Ts,
+        "tags" => Field::Tags,
+        "type" => Field::Type,
+        other => match other.strip_prefix("meta.") {
+            Some(key) if !key.is_empty() => Field::Meta(key.to_string()),
+            _ => return Err(format!("unknown field `{}`", other)),
+        },
+    };
+    Ok((field, shorten))
+}
+
+impl FromStr for LabelTemplate {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        let mut parts = Vec::new();
+        let mut text = String::new();
+        let mut chars = s.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    text.push('{');
+                }
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    text.push('}');
+                }
+                '{' => {
+                    let spec: String = chars.by_ref().take_while(|&c| c != '}').collect();
+                    let alternatives = spec.split('|').map(parse_field).collect::<Result<Vec<_>, _>>()?;
+                    if !text.is_empty() {
+                        parts.push(Part::Text(std::mem::take(&mut text)));
+                    }
+                    parts.push(Part::Fields(alternatives));
+                }
+                '}' => return Err("unmatched `}` (write `}}` for a literal brace)".to_string()),
+                c => text.push(c),
+            }
+        }
+        if !text.is_empty() {
+            parts.push(Part::Text(text));
+        }
+        Ok(Self { parts })
+    }
+}
+
+/// `2025-05-22T10:00:03.120Z` -> `10:00:03`; anything without a time stays as is.
+fn time_of_day(ts: &str) -> &str {
+    let Some((_, time)) = ts.split_once(['T', ' ']) else { return ts };
+    let end = time.find(['.', 'Z', '+', '-']).unwrap_or(time.len());
+    &time[..end]
+}
+
+fn field_value(nd: &NodeData, field: &Field, time: &TimeDisplay) -> String {
+    match field {
+        Field::Id => nd.id.to_string(),
+        Field::Label => nd.label.clone(),
+        Field::Span => nd.span.to_string(),
+        Field::Ts => time.show(&nd.ts),
+        Field::Tags => nd.tags.join(","),
+        Field::Type => classify_node_type(&nd.tags).name().to_string(),
+        Field::Meta(key) => nd.metadata.get(key).cloned().unwrap_or_default(),
+    }
+}
+
+impl LabelTemplate {
+    pub fn render(&self, nd: &NodeData, time: &TimeDisplay) -> String {
+        let mut out = String::new();
+        let mut eat_space = false;
+        for part in &self.parts {
+            match part {
+                Part::Text(text) => {
+                    out.push_str(if eat_space { text.strip_prefix(' ').unwrap_or(text) } else { text });
+                    eat_space = false;
+                }
+                Part::Fields(alternatives) => {
+                    let value = alternatives.iter().find_map(|(field, shorten)| {
+                        let value = match (shorten, field) {
+                            (Shorten::Short, Field::Ts) => time.short(&nd.ts).unwrap_or_else(|| time_of_day(&nd.ts).to_string()),
+                            _ => field_value(nd, field, time),
+                        };
+                        let value = match (shorten, field) {
+                            (Shorten::Full, _) | (Shorten::Short, Field::Ts) => value,
+                            (Shorten::Short, _) => value.chars().take(8).collect(),
+                            (Shorten::Chars(n), _) => value.chars().take(*n).collect(),
+                        };
+                        (!value.is_empty()).then_some(value)
+                    });
+                    eat_space = value.is_none();
+                    out.push_str(&value.unwrap_or_default());
+                }
+            }
+        }
+        let out = out.trim_end();
+        if out.is_empty() {
+            nd.id.to_string()
+        } else {
+            out.to_string()
+        }
+    }
+}
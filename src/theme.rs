@@ -0,0 +1,218 @@
+//! Colours: one per node type (the canvas shapes) and one per role the rest
+//! of the UI draws with, so a light terminal or a solarized palette can be
+//! matched in one place.
+//!
+//! Pick a built-in with `theme` in the config (`dark`, the default, `light`
+//! or `solarized`) and override single colours in a `[colors]` table:
+//!
+//! ```toml
+//! theme = "light"
+//!
+//! [colors]
+//! error = "#d70000"
+//! highlight = "magenta"
+//! ```
+//!
+//! Colours are ratatui's names (`lightblue`, `darkgray`, ...), `#rrggbb` or
+//! a 256-colour index. Merged inputs coloured by source (`C`) and watch
+//! counters keep their own palettes.
+use ratatui::style::Color;
+
+use crate::{compat, NodeType};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Theme {
+    pub prompt: Color,
+    pub response: Color,
+    pub tool: Color,
+    pub error: Color,
+    pub event: Color,
+    pub unknown: Color,
+    pub highlight: Color,   // the selection, focused panes, warnings
+    pub accent: Color,      // headings, pending edges
+    pub muted: Color,       // the status bar, secondary text, light edges
+    pub faint: Color,       // text DAG connectors
+    pub good: Color,        // added lines and nodes, success notices
+    pub bad: Color,         // violations, cycles, removed lines and nodes, failures
+    pub changed: Color,     // nodes that differ from a snapshot or another file
+    pub found: Color,       // filter matches, when `H` highlights them instead of hiding the rest
+    pub alert: Color,       // structural alerts
+    pub conflict: Color,    // duplicate-id conflicts
+    pub path: Color,        // the shortest path found with `g`
+    pub group: Color,       // folded `[[group]]` containers
+    pub heavy_edge: Color,  // the heaviest third of the canvas edges
+    pub medium_edge: Color, // the middle third
+}
+
+pub const DARK: Theme = Theme {
+    prompt: Color::Cyan,
+    response: Color::Green,
+    tool: Color::Yellow,
+    error: Color::Red,
+    event: Color::Magenta,
+    unknown: Color::White,
+    highlight: Color::Yellow,
+    accent: Color::Cyan,
+    muted: Color::Gray,
+    faint: Color::DarkGray,
+    good: Color::Green,
+    bad: Color::Red,
+    changed: Color::Yellow,
+    found: Color::LightBlue,
+    alert: Color::LightRed,
+    conflict: Color::Magenta,
+    path: Color::LightGreen,
+    group: Color::LightMagenta,
+    heavy_edge: Color::LightCyan,
+    medium_edge: Color::White,
+};
+
+/// For terminals with a light background: nothing light on white.
+pub const LIGHT: Theme = Theme {
+    prompt: Color::Blue,
+    response: Color::Green,
+    tool: Color::Rgb(0xaf, 0x5f, 0x00),
+    error: Color::Red,
+    event: Color::Magenta,
+    unknown: Color::Black,
+    highlight: Color::Rgb(0xd7, 0x5f, 0x00),
+    accent: Color::Blue,
+    muted: Color::DarkGray,
+    faint: Color::Gray,
+    good: Color::Green,
+    bad: Color::Red,
+    changed: Color::Rgb(0xaf, 0x87, 0x00),
+    found: Color::Rgb(0x00, 0x5f, 0xd7),
+    alert: Color::Rgb(0xd7, 0x00, 0x5f),
+    conflict: Color::Magenta,
+    path: Color::Rgb(0x00, 0x87, 0x00),
+    group: Color::Rgb(0x87, 0x00, 0xaf),
+    heavy_edge: Color::Blue,
+    medium_edge: Color::Black,
+};
+
+/// Ethan Schoonover's solarized accents over its dark base.
+pub const SOLARIZED: Theme = Theme {
+    prompt: Color::Rgb(0x26, 0x8b, 0xd2),   // blue
+    response: Color::Rgb(0x85, 0x99, 0x00), // green
+    tool: Color::Rgb(0xb5, 0x89, 0x00),     // yellow
+    error: Color::Rgb(0xdc, 0x32, 0x2f),    // red
+    event: Color::Rgb(0xd3, 0x36, 0x82),    // magenta
+    unknown: Color::Rgb(0x93, 0xa1, 0xa1),  // base1
+    highlight: Color::Rgb(0xb5, 0x89, 0x00),
+    accent: Color::Rgb(0x2a, 0xa1, 0x98),   // cyan
+    muted: Color::Rgb(0x83, 0x94, 0x96),    // base0
+    faint: Color::Rgb(0x58, 0x6e, 0x75),    // base01
+    good: Color::Rgb(0x85, 0x99, 0x00),
+    bad: Color::Rgb(0xdc, 0x32, 0x2f),
+    changed: Color::Rgb(0xb5, 0x89, 0x00),
+    found: Color::Rgb(0x26, 0x8b, 0xd2),
+    alert: Color::Rgb(0xcb, 0x4b, 0x16),    // orange
+    conflict: Color::Rgb(0xd3, 0x36, 0x82),
+    path: Color::Rgb(0x6c, 0x71, 0xc4),     // violet
+    group: Color::Rgb(0x6c, 0x71, 0xc4),
+    heavy_edge: Color::Rgb(0x2a, 0xa1, 0x98),
+    medium_edge: Color::Rgb(0x93, 0xa1, 0xa1),
+};
+
+impl Default for Theme {
+    fn default() -> Self {
+        DARK
+    }
+}
+
+/// `[colors]` in the config: any of `Theme`'s colours, by field name.
+#[derive(Debug, Default, Clone, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ThemeColors {
+    prompt: Option<String>,
+    response: Option<String>,
+    tool: Option<String>,
+    error: Option<String>,
+    event: Option<String>,
+    unknown: Option<String>,
+    highlight: Option<String>,
+    accent: Option<String>,
+    muted: Option<String>,
+    faint: Option<String>,
+    good: Option<String>,
+    bad: Option<String>,
+    changed: Option<String>,
+    found: Option<String>,
+    alert: Option<String>,
+    conflict: Option<String>,
+    path: Option<String>,
+    group: Option<String>,
+    heavy_edge: Option<String>,
+    medium_edge: Option<String>,
+}
+
+impl Theme {
+    /// The built-in `name` (default `dark`) with `colors` laid over it.
+    pub fn new(name: Option<&str>, colors: &ThemeColors) -> Result<Self, String> {
+        let mut theme = match name.unwrap_or("dark") {
+            "dark" => DARK,
+            "light" => LIGHT,
+            "solarized" => SOLARIZED,
+            other => return Err(format!("unknown theme `{}` (dark, light or solarized)", other)),
+        };
+        let slots = [
+            (&mut theme.prompt, &colors.prompt, "prompt"),
+            (&mut theme.response, &colors.response, "response"),
+            (&mut theme.tool, &colors.tool, "tool"),
+            (&mut theme.error, &colors.error, "error"),
+            (&mut theme.event, &colors.event, "event"),
+            (&mut theme.unknown, &colors.unknown, "unknown"),
+            (&mut theme.highlight, &colors.highlight, "highlight"),
+            (&mut theme.accent, &colors.accent, "accent"),
+            (&mut theme.muted, &colors.muted, "muted"),
+            (&mut theme.faint, &colors.faint, "faint"),
+            (&mut theme.good, &colors.good, "good"),
+            (&mut theme.bad, &colors.bad, "bad"),
+            (&mut theme.changed, &colors.changed, "changed"),
+            (&mut theme.found, &colors.found, "found"),
+            (&mut theme.alert, &colors.alert, "alert"),
+            (&mut theme.conflict, &colors.conflict, "conflict"),
+            (&mut theme.path, &colors.path, "path"),
+            (&mut theme.group, &colors.group, "group"),
+            (&mut theme.heavy_edge, &colors.heavy_edge, "heavy_edge"),
+            (&mut theme.medium_edge, &colors.medium_edge, "medium_edge"),
+        ];
+        for (slot, value, key) in slots {
+            if let Some(value) = value {
+                *slot = value.parse().map_err(|_| format!("[colors] {}: `{}` is not a colour", key, value))?;
+            }
+        }
+        Ok(theme)
+    }
+
+    /// Red through `highlight` to green for a score from 0 to 1.
+    pub fn score(&self, score: f64) -> Color {
+        match score {
+            s if s >= 2.0 / 3.0 => self.good,
+            s if s >= 1.0 / 3.0 => self.highlight,
+            _ => self.bad,
+        }
+    }
+
+    /// The colour a node of this type is drawn in.
+    pub fn node(&self, node_type: NodeType) -> Color {
+        match node_type {
+            NodeType::Prompt => self.prompt,
+            NodeType::Response => self.response,
+            NodeType::Tool => self.tool,
+            NodeType::Error => self.error,
+            NodeType::Event => self.event,
+            NodeType::Unknown => self.unknown,
+        }
+    }
+}
+
+/// `color` darkened for a node scored `score` (0 to 1): a third as bright at
+/// 0, as is at 1.
+pub fn brightness(color: Color, score: f64) -> Color {
+    let Some((r, g, b)) = compat::rgb(color) else { return color };
+    let level = 1.0 / 3.0 + 2.0 / 3.0 * score.clamp(0.0, 1.0);
+    let scale = |v: u8| (v as f64 * level).round() as u8;
+    Color::Rgb(scale(r), scale(g), scale(b))
+}
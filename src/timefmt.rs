@@ -0,0 +1,598 @@
+//! How timestamps are shown (`--time-zone` / `--time-format`, or
+//! `time_zone` / `time_format` in the config).
+//!
+//! By default a `ts` is shown exactly as the emitter wrote it. Given a zone
+//! (`utc`, `local`, or a fixed offset like `+05:30`), RFC 3339 timestamps and
+//! Unix epochs (seconds, or milli/micro/nanoseconds by length) are converted
+//! and shown with the format: a strftime pattern (`%Y %m %d %H %M %S %y %j
+//! %b %a %z %Z %s %F %T`, `%3f`/`%6f`/`%9f` for the fraction) or `relative`
+//! (`5m ago`). The format defaults to `%Y-%m-%d %H:%M:%S %Z`; a format given
+//! without a zone uses local time. Anything that doesn't parse is shown as
+//! is, and only an explicit zone changes what exports write, always as RFC
+//! 3339 so the files still load.
+//!
+//! Local time comes from the zoneinfo file named by `$TZ` or from
+//! `/etc/localtime`, including its rule for years past the last listed
+//! transition; a system without one is taken to run on UTC.
+use std::{
+    fs,
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+const DEFAULT_FORMAT: &str = "%Y-%m-%d %H:%M:%S %Z";
+const MONTHS: [&str; 12] = ["Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec"];
+const WEEKDAYS: [&str; 7] = ["Thu", "Fri", "Sat", "Sun", "Mon", "Tue", "Wed"]; // from 1970-01-01
+
+/// Days since 1970-01-01 of a proleptic Gregorian date.
+fn days_from_civil(y: i64, m: u32, d: u32) -> i64 {
+    let y = if m <= 2 { y - 1 } else { y };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let mp = (m as i64 + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + d as i64 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146097 + doe - 719468
+}
+
+/// The date of a day count from 1970-01-01.
+fn civil_from_days(z: i64) -> (i64, u32, u32) {
+    let z = z + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let d = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let m = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    (if m <= 2 { yoe + era * 400 + 1 } else { yoe + era * 400 }, m, d)
+}
+
+fn is_leap(y: i64) -> bool {
+    (y % 4 == 0 && y % 100 != 0) || y % 400 == 0
+}
+
+fn digits(s: &str) -> Option<i64> {
+    (!s.is_empty() && s.bytes().all(|b| b.is_ascii_digit())).then(|| s.parse().ok()).flatten()
+}
+
+/// `+05:30`, `-0800`, `+02` as seconds east of UTC.
+fn parse_offset(s: &str) -> Option<i32> {
+    let sign = match s.as_bytes().first()? {
+        b'+' => 1,
+        b'-' => -1,
+        _ => return None,
+    };
+    let rest = s[1..].replace(':', "");
+    if !rest.is_ascii() {
+        return None;
+    }
+    let (h, m) = match rest.len() {
+        2 => (digits(&rest)?, 0),
+        4 => (digits(&rest[..2])?, digits(&rest[2..])?),
+        _ => return None,
+    };
+    (h < 24 && m < 60).then_some(sign * (h * 3600 + m * 60) as i32)
+}
+
+/// Seconds and nanoseconds since the epoch of an RFC 3339 timestamp (`T` or
+/// a space between date and time; no zone means UTC) or of a Unix epoch.
+pub fn parse(ts: &str) -> Option<(i64, u32)> {
+    let ts = ts.trim();
+    if let Some(n) = digits(ts) {
+        return match ts.len() {
+            1..=11 => Some((n, 0)),
+            13 => Some((n / 1_000, (n % 1_000) as u32 * 1_000_000)),
+            16 => Some((n / 1_000_000, (n % 1_000_000) as u32 * 1_000)),
+            19 => Some((n / 1_000_000_000, (n % 1_000_000_000) as u32)),
+            _ => None,
+        };
+    }
+    let b = ts.as_bytes();
+    // Checked first, so the slicing below is on character boundaries.
+    if b.len() < 19 || !b[..19].is_ascii() || b[4] != b'-' || b[7] != b'-' || !matches!(b[10], b'T' | b't' | b' ') || b[13] != b':' || b[16] != b':' {
+        return None;
+    }
+    let (y, mo, d) = (digits(&ts[0..4])?, digits(&ts[5..7])? as u32, digits(&ts[8..10])? as u32);
+    let (h, mi, s) = (digits(&ts[11..13])?, digits(&ts[14..16])?, digits(&ts[17..19])?);
+    if !(1..=12).contains(&mo) || !(1..=31).contains(&d) || h > 23 || mi > 59 || s > 60 {
+        return None;
+    }
+    let mut rest = &ts[19..];
+    let mut nanos = 0u32;
+    if let Some(frac) = rest.strip_prefix(['.', ',']) {
+        let end = frac.find(|c: char| !c.is_ascii_digit()).unwrap_or(frac.len());
+        if end == 0 {
+            return None;
+        }
+        let mut padded = frac[..end.min(9)].to_string();
+        while padded.len() < 9 {
+            padded.push('0');
+        }
+        nanos = padded.parse().ok()?;
+        rest = &frac[end..];
+    }
+    let offset = match rest {
+        "" | "Z" | "z" => 0,
+        other => parse_offset(other)?,
+    };
+    let secs = days_from_civil(y, mo, d) * 86400 + h * 3600 + mi * 60 + s - offset as i64;
+    Some((secs, nanos))
+}
+
+/// A timestamp as typed into a filter (`ts>2024-05-01T12:00`): like
+/// `parse`, but the seconds, or the whole time of day, may be left out.
+pub fn parse_bound(ts: &str) -> Option<(i64, u32)> {
+    let ts = ts.trim();
+    let b = ts.as_bytes();
+    if b.len() == 10 && b[4] == b'-' && b[7] == b'-' {
+        return parse(&format!("{}T00:00:00", ts));
+    }
+    if b.len() >= 16 && b[..16].is_ascii() && b[13] == b':' && b.get(16) != Some(&b':') {
+        return parse(&format!("{}:00{}", &ts[..16], &ts[16..]));
+    }
+    parse(ts)
+}
+
+/// Seconds and nanoseconds since the epoch as RFC 3339 in UTC, the
+/// fraction only as long as it needs to be.
+pub fn rfc3339(secs: i64, nanos: u32) -> String {
+    format!("{}{}Z", strftime("%Y-%m-%dT%H:%M:%S", secs, nanos, 0, ""), fraction(nanos))
+}
+
+fn fraction(nanos: u32) -> String {
+    match nanos {
+        0 => String::new(),
+        n if n % 1_000_000 == 0 => format!(".{:03}", n / 1_000_000),
+        n if n % 1_000 == 0 => format!(".{:06}", n / 1_000),
+        n => format!(".{:09}", n),
+    }
+}
+
+/// When in a year a POSIX TZ rule switches.
+#[derive(Debug, Clone, Copy)]
+enum RuleDay {
+    Julian(u32),            // Jn: day 1..=365, February 29 never counted
+    Zero(u32),              // n: day 0..=365
+    Month(u32, u32, u32),   // Mm.w.d: the w-th (5: last) weekday d of month m
+}
+
+#[derive(Debug, Clone)]
+struct Switch {
+    day: RuleDay,
+    time: i64, // seconds after local midnight
+}
+
+/// The POSIX TZ string at the end of a zoneinfo file (`CET-1CEST,M3.5.0,M10.5.0/3`).
+#[derive(Debug, Clone)]
+struct PosixRule {
+    std: (String, i32), // abbreviation, seconds east of UTC
+    dst: Option<((String, i32), Switch, Switch)>,
+}
+
+fn take_name(s: &str) -> Option<(String, &str)> {
+    if let Some(rest) = s.strip_prefix('<') {
+        let end = rest.find('>')?;
+        return Some((rest[..end].to_string(), &rest[end + 1..]));
+    }
+    let end = s.find(|c: char| !c.is_ascii_alphabetic()).unwrap_or(s.len());
+    (end >= 3).then(|| (s[..end].to_string(), &s[end..]))
+}
+
+/// `[+-]hh[:mm[:ss]]` as seconds, with the rest of the string.
+fn take_time(s: &str) -> Option<(i64, &str)> {
+    let (sign, s) = match s.as_bytes().first()? {
+        b'-' => (-1, &s[1..]),
+        b'+' => (1, &s[1..]),
+        _ => (1, s),
+    };
+    let end = s.find(|c: char| !c.is_ascii_digit() && c != ':').unwrap_or(s.len());
+    let mut total = 0;
+    for (i, part) in s[..end].split(':').enumerate() {
+        total += digits(part)? * [3600, 60, 1].get(i)?;
+    }
+    Some((sign * total, &s[end..]))
+}
+
+fn take_switch(s: &str) -> Option<(Switch, &str)> {
+    let (day, rest) = if let Some(m) = s.strip_prefix('M') {
+        let end = m.find(|c: char| !c.is_ascii_digit() && c != '.').unwrap_or(m.len());
+        let parts: Vec<u32> = m[..end].split('.').map(|p| p.parse().ok()).collect::<Option<_>>()?;
+        let [month, week, weekday] = parts[..] else { return None };
+        (RuleDay::Month(month, week, weekday), &m[end..])
+    } else if let Some(j) = s.strip_prefix('J') {
+        let end = j.find(|c: char| !c.is_ascii_digit()).unwrap_or(j.len());
+        (RuleDay::Julian(j[..end].parse().ok()?), &j[end..])
+    } else {
+        let end = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+        (RuleDay::Zero(s[..end].parse().ok()?), &s[end..])
+    };
+    let (time, rest) = match rest.strip_prefix('/') {
+        Some(t) => take_time(t)?,
+        None => (7200, rest),
+    };
+    Some((Switch { day, time }, rest))
+}
+
+impl PosixRule {
+    fn parse(s: &str) -> Option<Self> {
+        let (std_name, rest) = take_name(s)?;
+        let (std_off, rest) = take_time(rest)?;
+        // POSIX offsets count west of UTC.
+        let std = (std_name, -std_off as i32);
+        if rest.is_empty() {
+            return Some(Self { std, dst: None });
+        }
+        let (dst_name, rest) = take_name(rest)?;
+        let (dst_off, rest) = match rest.as_bytes().first() {
+            Some(b',') | None => (std.1 + 3600, rest),
+            _ => {
+                let (off, rest) = take_time(rest)?;
+                (-off as i32, rest)
+            }
+        };
+        let rest = rest.strip_prefix(',')?;
+        let (start, rest) = take_switch(rest)?;
+        let (end, rest) = take_switch(rest.strip_prefix(',')?)?;
+        rest.is_empty().then_some(Self { std, dst: Some(((dst_name, dst_off), start, end)) })
+    }
+
+    /// Seconds since the epoch at which `switch` happens in `year`, for a
+    /// clock running `offset` seconds east of UTC.
+    fn at(switch: &Switch, year: i64, offset: i32) -> i64 {
+        let jan1 = days_from_civil(year, 1, 1);
+        let day = match switch.day {
+            RuleDay::Julian(n) => jan1 + n as i64 - 1 + (is_leap(year) && n >= 60) as i64,
+            RuleDay::Zero(n) => jan1 + n as i64,
+            RuleDay::Month(m, w, d) => {
+                let first = days_from_civil(year, m, 1);
+                // 1970-01-01 was a Thursday (weekday 4).
+                let first_weekday = (first + 4).rem_euclid(7) as u32;
+                let mut day = first + ((d + 7 - first_weekday) % 7) as i64 + 7 * (w as i64 - 1);
+                let next_month = if m == 12 { days_from_civil(year + 1, 1, 1) } else { days_from_civil(year, m + 1, 1) };
+                while day >= next_month {
+                    day -= 7;
+                }
+                day
+            }
+        };
+        day * 86400 + switch.time - offset as i64
+    }
+
+    fn offset_at(&self, t: i64) -> (i32, &str) {
+        let Some((dst, start, end)) = &self.dst else { return (self.std.1, &self.std.0) };
+        let year = civil_from_days((t + self.std.1 as i64).div_euclid(86400)).0;
+        let (on, off) = (Self::at(start, year, self.std.1), Self::at(end, year, dst.1));
+        let in_dst = if on < off { on <= t && t < off } else { !(off <= t && t < on) };
+        if in_dst {
+            (dst.1, &dst.0)
+        } else {
+            (self.std.1, &self.std.0)
+        }
+    }
+}
+
+/// A zoneinfo (TZif) file: its transitions and the rule after the last.
+#[derive(Debug, Clone)]
+struct ZoneInfo {
+    transitions: Vec<(i64, usize)>, // when, index into types
+    types: Vec<(i32, String)>,      // seconds east of UTC, abbreviation
+    rule: Option<PosixRule>,
+}
+
+fn be32(b: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(b.get(at..at + 4)?.try_into().ok()?))
+}
+
+impl ZoneInfo {
+    fn parse(data: &[u8]) -> Option<Self> {
+        if data.get(..4)? != b"TZif" {
+            return None;
+        }
+        let counts = |at: usize| -> Option<[usize; 6]> {
+            let mut c = [0; 6];
+            for (i, n) in c.iter_mut().enumerate() {
+                *n = be32(data, at + 20 + 4 * i)? as usize;
+            }
+            Some(c)
+        };
+        let block_len = |c: [usize; 6], time: usize| {
+            let [isut, isstd, leap, timecnt, typecnt, charcnt] = c;
+            timecnt * time + timecnt + typecnt * 6 + charcnt + leap * (time + 4) + isstd + isut
+        };
+        let mut at = 0;
+        let mut time = 4;
+        if data[4] >= b'2' {
+            // Skip the 32-bit block for the 64-bit one after it.
+            at = 44 + block_len(counts(0)?, 4);
+            time = 8;
+        }
+        let c = counts(at)?;
+        let [_, _, _, timecnt, typecnt, charcnt] = c;
+        let mut p = at + 44;
+        let mut times = Vec::with_capacity(timecnt);
+        for i in 0..timecnt {
+            let bytes = data.get(p + i * time..p + (i + 1) * time)?;
+            times.push(if time == 8 { i64::from_be_bytes(bytes.try_into().ok()?) } else { i32::from_be_bytes(bytes.try_into().ok()?) as i64 });
+        }
+        p += timecnt * time;
+        let indices = data.get(p..p + timecnt)?;
+        p += timecnt;
+        let chars = data.get(p + typecnt * 6..p + typecnt * 6 + charcnt)?;
+        let mut types = Vec::with_capacity(typecnt);
+        for i in 0..typecnt {
+            let ttinfo = data.get(p + i * 6..p + i * 6 + 6)?;
+            let offset = i32::from_be_bytes(ttinfo[..4].try_into().ok()?);
+            let name = chars.get(ttinfo[5] as usize..)?;
+            let name = &name[..name.iter().position(|&b| b == 0).unwrap_or(name.len())];
+            types.push((offset, String::from_utf8_lossy(name).into_owned()));
+        }
+        if types.is_empty() {
+            return None;
+        }
+        let transitions = times.into_iter().zip(indices.iter().map(|&i| i as usize)).filter(|&(_, i)| i < types.len()).collect();
+        let footer_at = at + 44 + block_len(c, time);
+        let rule = (time == 8)
+            .then(|| data.get(footer_at..))
+            .flatten()
+            .and_then(|f| std::str::from_utf8(f).ok())
+            .and_then(|f| f.trim_matches('\n').lines().next().map(str::to_string))
+            .and_then(|f| PosixRule::parse(&f));
+        Some(Self { transitions, types, rule })
+    }
+
+    fn offset_at(&self, t: i64) -> (i32, &str) {
+        let after = self.transitions.partition_point(|&(when, _)| when <= t);
+        if after == self.transitions.len() {
+            if let Some(rule) = &self.rule {
+                return rule.offset_at(t);
+            }
+        }
+        let (offset, name) = match after.checked_sub(1) {
+            Some(i) => &self.types[self.transitions[i].1],
+            None => &self.types[0],
+        };
+        (*offset, name)
+    }
+
+    /// The system's zone: `$TZ` (a zone name, a path, or a POSIX rule),
+    /// else `/etc/localtime`.
+    fn local() -> Option<Self> {
+        let tz = std::env::var("TZ").ok().filter(|tz| !tz.is_empty());
+        let path = match tz.as_deref().map(|tz| tz.trim_start_matches(':')) {
+            Some(tz) if tz.starts_with('/') => tz.to_string(),
+            Some(tz) => format!("/usr/share/zoneinfo/{}", tz),
+            None => "/etc/localtime".to_string(),
+        };
+        if let Some(zone) = fs::read(Path::new(&path)).ok().and_then(|data| Self::parse(&data)) {
+            return Some(zone);
+        }
+        let rule = PosixRule::parse(tz?.as_str())?;
+        Some(Self { transitions: Vec::new(), types: vec![(rule.std.1, rule.std.0.clone())], rule: Some(rule) })
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Zone {
+    Utc,
+    Fixed(i32),
+    Local(Box<ZoneInfo>),
+}
+
+impl Zone {
+    fn offset_at(&self, t: i64) -> (i32, String) {
+        match self {
+            Zone::Utc => (0, "UTC".to_string()),
+            Zone::Fixed(offset) => (*offset, format_offset(*offset, true)),
+            Zone::Local(zone) => {
+                let (offset, name) = zone.offset_at(t);
+                (offset, name.to_string())
+            }
+        }
+    }
+}
+
+fn format_offset(offset: i32, colon: bool) -> String {
+    let sign = if offset < 0 { '-' } else { '+' };
+    let (h, m) = (offset.abs() / 3600, offset.abs() % 3600 / 60);
+    if colon {
+        format!("{}{:02}:{:02}", sign, h, m)
+    } else {
+        format!("{}{:02}{:02}", sign, h, m)
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Format {
+    Strftime(String),
+    Relative,
+}
+
+/// Check a strftime pattern against what `strftime` understands.
+fn check_pattern(pattern: &str) -> Result<(), String> {
+    let mut chars = pattern.chars();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            continue;
+        }
+        match chars.next() {
+            Some('Y' | 'm' | 'd' | 'H' | 'M' | 'S' | 'y' | 'j' | 'b' | 'a' | 'z' | 'Z' | 's' | 'F' | 'T' | '%') => {}
+            Some('3' | '6' | '9') if chars.next() == Some('f') => {}
+            Some(other) => return Err(format!("unsupported `%{}` in time format `{}`", other, pattern)),
+            None => return Err(format!("time format `{}` ends in a lone `%`", pattern)),
+        }
+    }
+    Ok(())
+}
+
+fn strftime(pattern: &str, secs: i64, nanos: u32, offset: i32, zone_name: &str) -> String {
+    let local = secs + offset as i64;
+    let days = local.div_euclid(86400);
+    let (y, m, d) = civil_from_days(days);
+    let tod = local.rem_euclid(86400);
+    let (hh, mm, ss) = (tod / 3600, tod % 3600 / 60, tod % 60);
+    let mut out = String::new();
+    let mut chars = pattern.chars();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('Y') => out.push_str(&y.to_string()),
+            Some('y') => out.push_str(&format!("{:02}", y.rem_euclid(100))),
+            Some('m') => out.push_str(&format!("{:02}", m)),
+            Some('d') => out.push_str(&format!("{:02}", d)),
+            Some('H') => out.push_str(&format!("{:02}", hh)),
+            Some('M') => out.push_str(&format!("{:02}", mm)),
+            Some('S') => out.push_str(&format!("{:02}", ss)),
+            Some('j') => out.push_str(&format!("{:03}", days - days_from_civil(y, 1, 1) + 1)),
+            Some('b') => out.push_str(MONTHS[m as usize - 1]),
+            Some('a') => out.push_str(WEEKDAYS[days.rem_euclid(7) as usize]),
+            Some('z') => out.push_str(&format_offset(offset, false)),
+            Some('Z') => out.push_str(zone_name),
+            Some('s') => out.push_str(&secs.to_string()),
+            Some('F') => out.push_str(&format!("{}-{:02}-{:02}", y, m, d)),
+            Some('T') => out.push_str(&format!("{:02}:{:02}:{:02}", hh, mm, ss)),
+            Some('3') => {
+                chars.next();
+                out.push_str(&format!("{:03}", nanos / 1_000_000));
+            }
+            Some('6') => {
+                chars.next();
+                out.push_str(&format!("{:06}", nanos / 1_000));
+            }
+            Some('9') => {
+                chars.next();
+                out.push_str(&format!("{:09}", nanos));
+            }
+            Some(other) => {
+                out.push('%');
+                out.push(other);
+            }
+            None => out.push('%'),
+        }
+    }
+    out
+}
+
+/// `12s ago`, `5m ago`, `3h ago`, `2d ago` (or `in 5m` for the future).
+fn relative(secs: i64) -> String {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs() as i64);
+    let diff = now - secs;
+    let n = diff.unsigned_abs();
+    let amount = match n {
+        0..=59 => format!("{}s", n),
+        60..=3599 => format!("{}m", n / 60),
+        3600..=86399 => format!("{}h", n / 3600),
+        _ => format!("{}d", n / 86400),
+    };
+    if diff < 0 {
+        format!("in {}", amount)
+    } else {
+        format!("{} ago", amount)
+    }
+}
+
+/// How timestamps are shown; see the module docs.
+#[derive(Debug, Clone, Default)]
+pub struct TimeDisplay {
+    zone: Option<Zone>, // None: as written
+    format: Option<Format>,
+    exports: bool, // a zone was asked for, so exports convert too
+}
+
+impl TimeDisplay {
+    pub fn new(zone: Option<&str>, format: Option<&str>) -> Result<Self, String> {
+        if zone.is_none() && format.is_none() {
+            return Ok(Self::default());
+        }
+        let parsed = match zone.unwrap_or("local") {
+            "utc" | "UTC" => Zone::Utc,
+            "local" => ZoneInfo::local().map_or(Zone::Utc, |z| Zone::Local(Box::new(z))),
+            other => Zone::Fixed(parse_offset(other).ok_or_else(|| format!("unknown time zone `{}` (expected utc, local or an offset like +05:30)", other))?),
+        };
+        let format = match format.unwrap_or(DEFAULT_FORMAT) {
+            "relative" => Format::Relative,
+            pattern => {
+                check_pattern(pattern)?;
+                Format::Strftime(pattern.to_string())
+            }
+        };
+        Ok(Self { zone: Some(parsed), format: Some(format), exports: zone.is_some() })
+    }
+
+    /// `ts` as the node list and details pane show it.
+    pub fn show(&self, ts: &str) -> String {
+        let (Some(zone), Some(format), Some((secs, nanos))) = (&self.zone, &self.format, parse(ts)) else {
+            return ts.to_string();
+        };
+        match format {
+            Format::Relative => relative(secs),
+            Format::Strftime(pattern) => {
+                let (offset, name) = zone.offset_at(secs);
+                strftime(pattern, secs, nanos, offset, &name)
+            }
+        }
+    }
+
+    /// `ts` as a label template's `{ts:short}`: the time of day, or the
+    /// relative time. `None` when timestamps are shown as written.
+    pub fn short(&self, ts: &str) -> Option<String> {
+        let (zone, format, (secs, nanos)) = (self.zone.as_ref()?, self.format.as_ref()?, parse(ts)?);
+        Some(match format {
+            Format::Relative => relative(secs),
+            Format::Strftime(_) => {
+                let (offset, name) = zone.offset_at(secs);
+                strftime("%T", secs, nanos, offset, &name)
+            }
+        })
+    }
+
+    /// `ts` as exports write it: RFC 3339 in the chosen zone, or as written.
+    pub fn export(&self, ts: &str) -> String {
+        let (true, Some(zone), Some((secs, nanos))) = (self.exports, &self.zone, parse(ts)) else {
+            return ts.to_string();
+        };
+        let (offset, _) = zone.offset_at(secs);
+        let zone = if matches!(zone, Zone::Utc) { "Z".to_string() } else { format_offset(offset, true) };
+        format!("{}{}{}", strftime("%Y-%m-%dT%H:%M:%S", secs, nanos, offset, ""), fraction(nanos), zone)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_rfc3339_and_epochs() {
+        assert_eq!(parse("1970-01-01T00:00:00Z"), Some((0, 0)));
+        assert_eq!(parse("1970-01-01 01:00:00+01:00"), Some((0, 0)));
+        assert_eq!(parse("1970-01-01T00:00:01.5Z"), Some((1, 500_000_000)));
+        assert_eq!(parse("1700000000123"), Some((1_700_000_000, 123_000_000)));
+    }
+
+    #[test]
+    fn malformed_timestamps_are_none() {
+        for ts in ["", "2024-05-01", "2024-13-01T00:00:00Z", "2024-05-01T25:00:00Z", "2024-05-01T12:00:00.Z", "2024-05-01T12:00:00+1"] {
+            assert_eq!(parse(ts), None, "{}", ts);
+        }
+    }
+
+    #[test]
+    fn non_ascii_timestamps_are_none_not_a_panic() {
+        for ts in ["2024-05-01T12:00:00+€1", "2024-05-01T12:00:0€", "2024-05-01T12:€0:00", "€€€€€€€€€€€€€€€€€€€€"] {
+            assert_eq!(parse(ts), None, "{}", ts);
+            assert_eq!(parse_bound(ts), None, "{}", ts);
+        }
+        assert_eq!(parse_offset("+€1"), None);
+        assert_eq!(parse_bound("2024-05-01T12:0€"), None);
+    }
+
+    #[test]
+    fn bounds_may_leave_out_the_seconds_or_the_time() {
+        assert_eq!(parse_bound("1970-01-02"), Some((86400, 0)));
+        assert_eq!(parse_bound("1970-01-01T00:01"), Some((60, 0)));
+        assert_eq!(parse_bound("1970-01-01T01:00+01:00"), Some((0, 0)));
+    }
+}
@@ -0,0 +1,68 @@
+//! TLS for socket sources.
+//!
+//! `--listen-tls` serves the certificate chain and key given with `--tls-cert`
+//! / `--tls-key`; `--connect-tls` verifies the peer against `--tls-ca` (PEM
+//! bundle) or, without it, the bundled web PKI roots. Handshakes run on the
+//! connection's worker thread, so a stalled client never blocks `accept`.
+use std::sync::Arc;
+
+use color_eyre::eyre::{eyre, Result, WrapErr};
+use rustls::{ClientConfig, ClientConnection, RootCertStore, ServerConfig, ServerConnection};
+use rustls_pki_types::{pem::PemObject, CertificateDer, PrivateKeyDer, ServerName};
+
+fn provider() -> Arc<rustls::crypto::CryptoProvider> {
+    Arc::new(rustls::crypto::ring::default_provider())
+}
+
+/// Server side of `--listen-tls`.
+pub fn server_config(cert: &str, key: &str) -> Result<Arc<ServerConfig>> {
+    let chain = CertificateDer::pem_file_iter(cert)
+        .and_then(|certs| certs.collect::<Result<Vec<_>, _>>())
+        .map_err(|err| eyre!("failed to read certificates from {}: {}", cert, err))?;
+    if chain.is_empty() {
+        return Err(eyre!("no certificates found in {}", cert));
+    }
+    let key = PrivateKeyDer::from_pem_file(key).map_err(|err| eyre!("failed to read private key {}: {}", key, err))?;
+    let config = ServerConfig::builder_with_provider(provider())
+        .with_safe_default_protocol_versions()?
+        .with_no_client_auth()
+        .with_single_cert(chain, key)
+        .wrap_err("invalid TLS certificate or key")?;
+    Ok(Arc::new(config))
+}
+
+/// Client side of `--connect-tls`, trusting `ca` if given, else the web PKI roots.
+pub fn client_config(ca: Option<&str>) -> Result<Arc<ClientConfig>> {
+    let mut roots = RootCertStore::empty();
+    match ca {
+        Some(ca) => {
+            let certs = CertificateDer::pem_file_iter(ca)
+                .and_then(|certs| certs.collect::<Result<Vec<_>, _>>())
+                .map_err(|err| eyre!("failed to read CA bundle {}: {}", ca, err))?;
+            let (added, _) = roots.add_parsable_certificates(certs);
+            if added == 0 {
+                return Err(eyre!("no usable CA certificates in {}", ca));
+            }
+        }
+        None => roots.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned()),
+    }
+    let config = ClientConfig::builder_with_provider(provider())
+        .with_safe_default_protocol_versions()?
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+    Ok(Arc::new(config))
+}
+
+pub fn accept(config: &Arc<ServerConfig>) -> Result<ServerConnection> {
+    ServerConnection::new(config.clone()).wrap_err("failed to start TLS session")
+}
+
+/// Start a client session for `addr` (`host:port`), verifying the certificate against `host`.
+pub fn connect(config: &Arc<ClientConfig>, addr: &str) -> Result<ClientConnection> {
+    let host = match addr.rsplit_once(':') {
+        Some((host, _)) => host.trim_start_matches('[').trim_end_matches(']'),
+        None => addr,
+    };
+    let name = ServerName::try_from(host.to_string()).map_err(|_| eyre!("invalid TLS server name {}", host))?;
+    ClientConnection::new(config.clone(), name).wrap_err("failed to start TLS session")
+}
@@ -0,0 +1,149 @@
+//! `riff-dag-tui tutorial`: a guided first session for new teammates.
+//!
+//! One of a few bundled graphs (an agent run, a build, a request trace) is
+//! opened with a box in the corner walking through the features used day to
+//! day: moving around, filtering, narrowing to a node's lineage, the canvas
+//! and exporting. Each step names the keys to press and moves on by itself
+//! once the app shows they were, so it is the real UI being learned, not a
+//! slideshow; Ctrl-n skips a step, or closes the box after the last.
+use crate::{App, DagViewMode, Mode};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Scenario {
+    /// An LLM agent answering with tool calls, one of which fails
+    Agent,
+    /// A build's compile and test steps, with a failing test
+    Build,
+    /// A request traced across services, with a slow query
+    Trace,
+}
+
+/// What a step asks for, checked against the app after every key.
+#[derive(Debug, Clone, Copy)]
+enum Goal {
+    Move,                 // the selection leaves the node it started on
+    Filter(&'static str), // this query is applied and the prompt closed
+    Lineage,              // `f` narrows to the selected node's ancestors and descendants
+    Canvas,               // the canvas is shown
+    Export,               // a file was written
+    Done,                 // nothing: the last step stays until closed
+}
+
+impl Goal {
+    fn met(self, app: &App) -> bool {
+        let tab = app.tab();
+        match self {
+            Goal::Move => {
+                let gm = tab.full_graph();
+                let start = gm.roots().first().map(|&idx| gm.graph[idx].id.clone());
+                tab.selected().is_some_and(|idx| Some(&tab.gm.graph[idx].id) != start.as_ref())
+            }
+            Goal::Filter(q) => app.mode == Mode::Normal && tab.filter_text.to_lowercase().contains(q),
+            Goal::Lineage => tab.closure_of.is_some(),
+            Goal::Canvas => app.dag_view_mode == DagViewMode::Canvas,
+            Goal::Export => app.notice.as_ref().is_some_and(|(n, failed)| !failed && n.starts_with("wrote")),
+            Goal::Done => false,
+        }
+    }
+}
+
+impl Scenario {
+    pub fn title(self) -> &'static str {
+        match self {
+            Scenario::Agent => "tutorial: agent run",
+            Scenario::Build => "tutorial: build graph",
+            Scenario::Trace => "tutorial: request trace",
+        }
+    }
+
+    /// The graph, as JSONL (the same as assets/tutorial/).
+    pub fn events(self) -> &'static str {
+        match self {
+            Scenario::Agent => include_str!("../assets/tutorial/agent.jsonl"),
+            Scenario::Build => include_str!("../assets/tutorial/build.jsonl"),
+            Scenario::Trace => include_str!("../assets/tutorial/trace.jsonl"),
+        }
+    }
+
+    /// The node the filter step finds, and what to say about it.
+    fn target(self) -> (&'static str, &'static str) {
+        match self {
+            Scenario::Agent => ("tag:error", "One of the agent's tool calls failed."),
+            Scenario::Build => ("tag:error", "One of the test steps failed."),
+            Scenario::Trace => ("tag:slow", "Most of this request went on one slow query."),
+        }
+    }
+
+    fn steps(self) -> Vec<(String, Goal)> {
+        let (query, story) = self.target();
+        vec![
+            (
+                "The left pane lists every node, the right one shows the graph and the selected node's details. \
+                 Move the selection with j/k or the arrow keys."
+                    .to_string(),
+                Goal::Move,
+            ),
+            (format!("{} Press /, type {} and press Enter to list only the nodes that match.", story, query), Goal::Filter(query)),
+            (
+                "Press f to narrow to the selected node's lineage: everything that led to it and everything after it. \
+                 f again widens back out."
+                    .to_string(),
+                Goal::Lineage,
+            ),
+            ("Press Tab to draw the graph on the canvas instead of as text. Tab switches back.".to_string(), Goal::Canvas),
+            ("Press X, then Enter, to write the listed nodes and their edges to the file named.".to_string(), Goal::Export),
+            (
+                "That's the tour. c clears the filter, ? lists every key, and `riff-dag-tui FILE` opens your own graph. \
+                 Ctrl-n closes this box."
+                    .to_string(),
+                Goal::Done,
+            ),
+        ]
+    }
+}
+
+#[derive(Debug)]
+pub struct Tutorial {
+    scenario: Scenario,
+    steps: Vec<(String, Goal)>,
+    step: usize, // index into `steps` of the one shown
+}
+
+impl Tutorial {
+    pub fn new(scenario: Scenario) -> Self {
+        Self { scenario, steps: scenario.steps(), step: 0 }
+    }
+
+    /// Where the export step writes, so it doesn't litter the working directory.
+    pub fn export_path() -> String {
+        std::env::temp_dir().join("riff-dag-tui-tutorial.jsonl").display().to_string()
+    }
+
+    /// Go past every step `app` now shows done.
+    pub fn advance(app: &mut App) {
+        let Some(t) = &app.tutorial else { return };
+        let mut step = t.step;
+        while t.steps.get(step).is_some_and(|&(_, goal)| goal.met(app)) {
+            step += 1;
+        }
+        if let Some(t) = &mut app.tutorial {
+            t.step = step.min(t.steps.len() - 1);
+        }
+    }
+
+    /// Ctrl-n: on to the next step; false once past the last.
+    pub fn skip(&mut self) -> bool {
+        self.step += 1;
+        self.step < self.steps.len()
+    }
+
+    /// ` agent run — step 2 of 6 `
+    pub fn heading(&self) -> String {
+        let name = self.scenario.title().trim_start_matches("tutorial: ");
+        format!(" {} — step {} of {} ", name, self.step + 1, self.steps.len())
+    }
+
+    pub fn text(&self) -> &str {
+        &self.steps[self.step].0
+    }
+}
@@ -0,0 +1,87 @@
+//! Which nodes a filter lists and in what order, as the viewer's node list
+//! has them: the nodes a [`Query`] matches, in a [`SortOrder`], fuzzy
+//! matches best first whatever the order.
+//!
+//! ```no_run
+//! use riff_dag_tui::{query::Query, view::{self, SortOrder}};
+//! # let gm = riff_dag_tui::GraphModel::new();
+//! let errors = view::matching(&gm, &Query::parse("tag:error"), SortOrder::Time, |_| true);
+//! ```
+use petgraph::stable_graph::NodeIndex;
+
+use crate::{query::Query, timefmt, GraphModel};
+
+/// How the node list is ordered (`=` cycles through them).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SortOrder {
+    #[default]
+    Stable,    // `GraphModel::stable_order`: by `ts` as written, then id
+    Time,      // by `ts`, earliest first; untimestamped nodes last
+    Id,
+    Label,     // case-insensitively; unlabelled nodes last
+    InDegree,  // most parents first
+    OutDegree, // most children first
+}
+
+impl SortOrder {
+    pub fn next(self) -> Self {
+        match self {
+            SortOrder::Stable => SortOrder::Time,
+            SortOrder::Time => SortOrder::Id,
+            SortOrder::Id => SortOrder::Label,
+            SortOrder::Label => SortOrder::InDegree,
+            SortOrder::InDegree => SortOrder::OutDegree,
+            SortOrder::OutDegree => SortOrder::Stable,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            SortOrder::Stable => "default",
+            SortOrder::Time => "ts",
+            SortOrder::Id => "id",
+            SortOrder::Label => "label",
+            SortOrder::InDegree => "in-degree",
+            SortOrder::OutDegree => "out-degree",
+        }
+    }
+
+    /// Put `nodes`, listed in the stable order, in this order; ties keep it.
+    pub fn sort(self, gm: &GraphModel, nodes: &mut [NodeIndex]) {
+        match self {
+            SortOrder::Stable => {}
+            SortOrder::Time => nodes.sort_by_cached_key(|&idx| timefmt::parse(&gm.graph[idx].ts).map_or((true, (0, 0)), |t| (false, t))),
+            SortOrder::Id => nodes.sort_by(|&a, &b| gm.graph[a].id.cmp(&gm.graph[b].id)),
+            SortOrder::Label => nodes.sort_by_cached_key(|&idx| {
+                let label = &gm.graph[idx].label;
+                (label.is_empty(), label.to_lowercase())
+            }),
+            SortOrder::InDegree => nodes.sort_by_cached_key(|&idx| std::cmp::Reverse(gm.degree(idx).0)),
+            SortOrder::OutDegree => nodes.sort_by_cached_key(|&idx| std::cmp::Reverse(gm.degree(idx).1)),
+        }
+    }
+}
+
+/// The nodes `query` matches among those `within` admits, in `sort` order.
+pub fn matching(gm: &GraphModel, query: &Query, sort: SortOrder, within: impl Fn(NodeIndex) -> bool) -> Vec<NodeIndex> {
+    let mut matched: Vec<NodeIndex> = gm.stable_order().filter(|&idx| within(idx) && (query.is_empty() || query.matches(&gm.graph[idx]))).collect();
+    sort.sort(gm, &mut matched);
+    ranked(gm, query, matched)
+}
+
+/// `matching` for a query that [narrows](Query::narrows) the one `last`
+/// came from: only those are checked again, and they keep their order.
+pub fn rematch(gm: &GraphModel, query: &Query, last: Vec<NodeIndex>) -> Vec<NodeIndex> {
+    let matched = last.into_iter().filter(|&idx| query.is_empty() || query.matches(&gm.graph[idx])).collect();
+    ranked(gm, query, matched)
+}
+
+/// Fuzzy matches best first; anything else as it is.
+fn ranked(gm: &GraphModel, query: &Query, matched: Vec<NodeIndex>) -> Vec<NodeIndex> {
+    if !query.is_fuzzy() {
+        return matched;
+    }
+    let mut ranked: Vec<(i64, NodeIndex)> = matched.into_iter().filter_map(|idx| Some((query.rank(&gm.graph[idx])?, idx))).collect();
+    ranked.sort_by_key(|&(score, _)| std::cmp::Reverse(score));
+    ranked.into_iter().map(|(_, idx)| idx).collect()
+}
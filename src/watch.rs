@@ -0,0 +1,121 @@
+//! Watch expressions: filters pinned to the status bar.
+//!
+//! Each watch counts the nodes of the active tab that match it (the same
+//! match as `/`), over the full graph even when the tab is sampled, and is
+//! recounted as events arrive. A counter that just moved is highlighted for
+//! [`ALERT_FOR`], so a change during live ingestion catches the eye.
+use std::time::{Duration, Instant};
+
+use ratatui::{
+    style::{Color, Modifier, Style},
+    text::Span,
+};
+
+use crate::{query::Query, GraphModel};
+
+/// How often counters are refreshed while events are still flowing.
+pub const RECOUNT_EVERY: Duration = Duration::from_secs(1);
+/// How long a counter stays highlighted after its count changed.
+pub const ALERT_FOR: Duration = Duration::from_secs(3);
+
+const COLORS: [Color; 6] = [Color::Cyan, Color::Magenta, Color::Green, Color::LightBlue, Color::LightRed, Color::LightYellow];
+
+#[derive(Debug)]
+struct Watch {
+    query: String,             // as typed, like the `/` filter
+    count: Option<usize>,      // `None` until counted on the current tab
+    changed: Option<Instant>,  // when `count` last moved
+}
+
+#[derive(Debug, Default)]
+pub struct Watches {
+    list: Vec<Watch>,
+    pub stale: bool,           // the graph changed since the last count
+    counted: Option<Instant>,
+}
+
+impl Watches {
+    pub fn new(queries: &[String]) -> Self {
+        let mut watches = Self::default();
+        for q in queries {
+            watches.toggle(q);
+        }
+        watches
+    }
+
+    /// Pin `query`, or unpin it if it is already watched.
+    pub fn toggle(&mut self, query: &str) {
+        let q = query.trim().to_string();
+        if q.is_empty() {
+            return;
+        }
+        match self.list.iter().position(|w| w.query == q) {
+            Some(i) => {
+                self.list.remove(i);
+            }
+            None => self.list.push(Watch { query: q, count: None, changed: None }),
+        }
+        self.stale = true;
+    }
+
+    /// Forget the counts (another tab became active) without raising alerts.
+    pub fn reset(&mut self) {
+        for w in &mut self.list {
+            w.count = None;
+            w.changed = None;
+        }
+        self.stale = true;
+    }
+
+    /// Recount a stale graph, at most every `RECOUNT_EVERY` while `busy`.
+    pub fn recount(&mut self, gm: &GraphModel, busy: bool) {
+        if self.list.is_empty() || !self.stale {
+            return;
+        }
+        if busy && self.counted.is_some_and(|t| t.elapsed() < RECOUNT_EVERY) {
+            return;
+        }
+        self.stale = false;
+        self.counted = Some(Instant::now());
+        let queries: Vec<Query> = self.list.iter().map(|w| Query::parse(&w.query)).collect();
+        let mut counts = vec![0; self.list.len()];
+        for nd in gm.graph.node_weights() {
+            for (count, q) in counts.iter_mut().zip(&queries) {
+                if q.matches(nd) {
+                    *count += 1;
+                }
+            }
+        }
+        for (w, count) in self.list.iter_mut().zip(counts) {
+            if w.count.is_some_and(|old| old != count) {
+                w.changed = Some(Instant::now());
+            }
+            w.count = Some(count);
+        }
+    }
+
+    /// Each watch's query and its count, as of the last recount.
+    pub fn counts(&self) -> impl Iterator<Item = (&str, Option<usize>)> {
+        self.list.iter().map(|w| (w.query.as_str(), w.count))
+    }
+
+    /// Whether a highlight is showing, so the screen must be redrawn when it ends.
+    pub fn alerting(&self) -> bool {
+        self.list.iter().any(|w| w.changed.is_some_and(|t| t.elapsed() < ALERT_FOR))
+    }
+
+    /// ` | error: 12` per watch, each in its own colour; recent changes reversed.
+    pub fn spans(&self) -> Vec<Span<'static>> {
+        let mut spans = Vec::new();
+        for (i, w) in self.list.iter().enumerate() {
+            let mut style = Style::default().fg(COLORS[i % COLORS.len()]);
+            if w.changed.is_some_and(|t| t.elapsed() < ALERT_FOR) {
+                style = style.add_modifier(Modifier::REVERSED | Modifier::BOLD);
+            }
+            let count = w.count.map_or("…".to_string(), |c| c.to_string());
+            spans.push(Span::raw(" | "));
+            spans.push(Span::styled(format!("{}: {}", w.query, count), style));
+        }
+        spans
+    }
+}